@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+///
+/// Describes the span of frames to render. Frames are numbered from 1 to
+/// `frame_count`, and a render may be limited to a sub-range of those (e.g.
+/// to split an animation across several machines).
+///
+#[derive(Debug, PartialEq)]
+pub struct Animation {
+    frame_count: u32,
+    start_frame: u32,
+    end_frame: u32,
+}
+
+impl Animation {
+    pub fn new(
+        frame_count: u32,
+        start_frame: u32,
+        end_frame: Option<u32>,
+    ) -> Result<Animation, String> {
+        let end_frame = end_frame.unwrap_or(frame_count);
+
+        if frame_count == 0 {
+            return Err("Frame count must be at least 1".to_owned());
+        }
+
+        if start_frame == 0 || start_frame > end_frame || end_frame > frame_count {
+            return Err(format!(
+                "Invalid frame range {}..{} (expected 1 <= start <= end <= {})",
+                start_frame, end_frame, frame_count
+            ));
+        }
+
+        Ok(Animation {
+            frame_count,
+            start_frame,
+            end_frame,
+        })
+    }
+
+    /// Is this a multi-frame render, or just a single still image?
+    pub fn is_animated(&self) -> bool {
+        self.frame_count > 1
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = Frame> {
+        let count = self.frame_count;
+        (self.start_frame..=self.end_frame).map(move |number| Frame { number, count })
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Animation {
+        Animation {
+            frame_count: 1,
+            start_frame: 1,
+            end_frame: 1,
+        }
+    }
+}
+
+///
+/// A single frame in an animation
+///
+#[derive(Debug, PartialEq)]
+pub struct Frame {
+    pub number: u32,
+    pub count: u32,
+}
+
+impl Frame {
+    ///
+    /// The animation clock, running from 0.0 on the first frame to 1.0 on the
+    /// last.
+    ///
+    pub fn clock(&self) -> f64 {
+        if self.count > 1 {
+            f64::from(self.number - 1) / f64::from(self.count - 1)
+        } else {
+            0.0
+        }
+    }
+
+    /// The values exposed to the scene template when rendering this frame
+    pub fn globals(&self) -> liquid::Object {
+        liquid::object!({
+            "clock": self.clock(),
+            "frame": self.number as i32,
+            "frame_count": self.count as i32,
+        })
+    }
+
+    ///
+    /// Generates the numbered output filename for this frame, e.g.
+    /// `render.png` becomes `render_0001.png` for frame 1.
+    ///
+    pub fn output_file(&self, base: &Path) -> PathBuf {
+        let digits = std::cmp::max(4, self.count.to_string().len());
+        let stem = base
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut name = format!("{}_{:0width$}", stem, self.number, width = digits);
+        if let Some(ext) = base.extension() {
+            name.push('.');
+            name.push_str(&ext.to_string_lossy());
+        }
+        base.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_is_a_single_still() {
+        let a = Animation::default();
+        assert!(!a.is_animated());
+
+        let frames: Vec<Frame> = a.frames().collect();
+        assert_eq!(
+            frames,
+            vec![Frame {
+                number: 1,
+                count: 1
+            }]
+        );
+        assert_eq!(frames[0].clock(), 0.0);
+    }
+
+    #[test]
+    fn clock_runs_from_zero_to_one() {
+        let a = Animation::new(5, 1, None).unwrap();
+        let clocks: Vec<f64> = a.frames().map(|f| f.clock()).collect();
+        assert_eq!(clocks, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn sub_range_keeps_clock_relative_to_whole_animation() {
+        let a = Animation::new(5, 2, Some(3)).unwrap();
+        let frames: Vec<(u32, f64)> = a.frames().map(|f| (f.number, f.clock())).collect();
+        assert_eq!(frames, vec![(2, 0.25), (3, 0.5)]);
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        assert!(Animation::new(0, 1, None).is_err());
+        assert!(Animation::new(10, 0, None).is_err());
+        assert!(Animation::new(10, 5, Some(4)).is_err());
+        assert!(Animation::new(10, 1, Some(11)).is_err());
+    }
+
+    #[test]
+    fn output_files_are_numbered() {
+        let f = Frame {
+            number: 7,
+            count: 24,
+        };
+        assert_eq!(
+            f.output_file(Path::new("out/render.png")),
+            PathBuf::from("out/render_0007.png")
+        );
+
+        let f = Frame {
+            number: 42,
+            count: 12000,
+        };
+        assert_eq!(
+            f.output_file(Path::new("render.png")),
+            PathBuf::from("render_00042.png")
+        );
+    }
+}
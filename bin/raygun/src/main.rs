@@ -6,9 +6,12 @@ use std::path::PathBuf;
 use log::{self, debug, error, info};
 use simplelog::{Config, LevelFilter, TermLogger, TerminalMode};
 
+mod animation;
 mod render;
+mod variables;
 
-use raygun_scenefile::{load_scene, SceneError};
+use animation::Animation;
+use raygun_scenefile::{import_gltf, load_scene_with_options, LoadOptions, SceneError};
 
 #[cfg(not(test))]
 fn main() {
@@ -24,58 +27,135 @@ fn main() {
     let args = parse_args();
     info!("Dimensions {} x {}", args.width, args.height);
 
-    let s = load_scene(args.scene_file).unwrap_or_else(|err| {
-        error!("Scene file loading failed:");
-        match err {
-            SceneError::FileError(e) => {
-                error!("File IO error: {:?}", e);
-            }
-            SceneError::Template(s) => {
-                error!("Template parse error: {}", s);
-            }
-            SceneError::Scene(errs) => {
-                for e in errs {
-                    error!("{}", e)
-                }
-            }
+    let animation =
+        Animation::new(args.frames, args.start_frame, args.end_frame).unwrap_or_else(|msg| {
+            error!("{}", msg);
+            exit(1);
+        });
+
+    let user_globals = variables::user_globals(args.vars_file.as_deref(), &args.defines)
+        .unwrap_or_else(|msg| {
+            error!("{}", msg);
+            exit(1);
+        });
+
+    for frame in animation.frames() {
+        let output_file = if animation.is_animated() {
+            frame.output_file(&args.output_file)
+        } else {
+            args.output_file.clone()
         };
-        exit(1);
-    });
 
-    let options = render::RenderOptions {
-        width: args.width,
-        height: args.height,
-    };
+        if animation.is_animated() && output_file.exists() {
+            info!(
+                "Skipping frame {}: {:?} already exists",
+                frame.number, output_file
+            );
+            continue;
+        }
+
+        if animation.is_animated() {
+            info!(
+                "Rendering frame {} of {} (clock = {})...",
+                frame.number,
+                frame.count,
+                frame.clock()
+            );
+        }
+
+        let mut load_options = LoadOptions {
+            globals: user_globals.clone(),
+            include_paths: args.include_paths.clone(),
+        };
+        load_options.globals.extend(frame.globals());
+
+        // glTF files are imported as they are, everything else is a scene file
+        let is_gltf = match args.scene_file.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.eq_ignore_ascii_case("gltf") || ext.eq_ignore_ascii_case("glb"),
+            None => false,
+        };
+        let loaded = if is_gltf {
+            import_gltf(&args.scene_file)
+        } else {
+            load_scene_with_options(&args.scene_file, &load_options)
+        };
+        let s = loaded.unwrap_or_else(|err| {
+            report_scene_error(err);
+            exit(1);
+        });
+
+        let options = render::RenderOptions {
+            width: args.width,
+            height: args.height,
+        };
 
-    info!("Starting render...");
-    if let Some(img) = render::render(&s, options) {
-        info!("Saving to {:?}...", args.output_file);
-        match img.save(args.output_file) {
-            Ok(_) => {}
-            Err(_) => {}
+        info!("Starting render...");
+        if let Some(img) = render::render(&s, options) {
+            info!("Saving to {:?}...", output_file);
+            if let Err(e) = img.save(&output_file) {
+                error!("Failed to save {:?}: {}", output_file, e);
+                exit(1);
+            }
         }
     }
 }
 
+fn report_scene_error(err: SceneError) {
+    error!("Scene file loading failed:");
+    match err {
+        SceneError::FileError(e) => {
+            error!("File IO error: {:?}", e);
+        }
+        SceneError::Template(s) => {
+            error!("Template parse error: {}", s);
+        }
+        SceneError::Include(s) => {
+            error!("Include error: {}", s);
+        }
+        SceneError::Import(s) => {
+            error!("glTF import error: {}", s);
+        }
+        SceneError::Scene(errs) => {
+            for e in errs {
+                error!("{}", e)
+            }
+        }
+    };
+}
+
 struct Args {
     width: isize,
     height: isize,
     scene_file: PathBuf,
     output_file: PathBuf,
+    frames: u32,
+    start_frame: u32,
+    end_frame: Option<u32>,
+    defines: Vec<String>,
+    vars_file: Option<PathBuf>,
+    include_paths: Vec<PathBuf>,
 }
 
 fn parse_args() -> Args {
-    use argparse::{ArgumentParser, Store};
+    use argparse::{ArgumentParser, Collect, Store, StoreOption};
 
     let mut result = Args {
         width: 640,
         height: 480,
         scene_file: PathBuf::default(),
         output_file: PathBuf::default(),
+        frames: 1,
+        start_frame: 1,
+        end_frame: None,
+        defines: Vec::new(),
+        vars_file: None,
+        include_paths: Vec::new(),
     };
 
     let mut scene_file = String::new();
     let mut image_file = String::from("render.png");
+    let mut vars_file: Option<String> = None;
+    let mut include_paths: Vec<String> = Vec::new();
 
     /* Artificial scope to limit borrows */
     {
@@ -96,9 +176,63 @@ fn parse_args() -> Args {
             .add_option(&["-o", "--output"], Store, "Output image file")
             .metavar("FILE");
 
+        parser
+            .refer(&mut result.frames)
+            .add_option(
+                &["--frames"],
+                Store,
+                "Number of frames in the animation. Defaults to 1.",
+            )
+            .metavar("INT");
+
+        parser
+            .refer(&mut result.start_frame)
+            .add_option(
+                &["--start-frame"],
+                Store,
+                "First frame to render. Defaults to 1.",
+            )
+            .metavar("INT");
+
+        parser
+            .refer(&mut result.end_frame)
+            .add_option(
+                &["--end-frame"],
+                StoreOption,
+                "Last frame to render. Defaults to the final frame.",
+            )
+            .metavar("INT");
+
+        parser
+            .refer(&mut result.defines)
+            .add_option(
+                &["-D", "--define"],
+                Collect,
+                "Define a scene template variable. May be repeated.",
+            )
+            .metavar("NAME=VALUE");
+
+        parser
+            .refer(&mut vars_file)
+            .add_option(
+                &["--vars"],
+                StoreOption,
+                "A TOML or JSON file of scene template variables",
+            )
+            .metavar("FILE");
+
+        parser
+            .refer(&mut include_paths)
+            .add_option(
+                &["-I", "--include-path"],
+                Collect,
+                "Directory to search for included scene files. May be repeated.",
+            )
+            .metavar("DIR");
+
         parser
             .refer(&mut scene_file)
-            .add_argument("FILE", Store, "The scene file")
+            .add_argument("FILE", Store, "The scene file, or a glTF file to import")
             .required()
             .metavar("FILE");
 
@@ -108,5 +242,7 @@ fn parse_args() -> Args {
     // repack the values that argparse won't pick up for us
     result.scene_file = PathBuf::from(scene_file);
     result.output_file = PathBuf::from(image_file);
+    result.vars_file = vars_file.map(PathBuf::from);
+    result.include_paths = include_paths.into_iter().map(PathBuf::from).collect();
     result
 }
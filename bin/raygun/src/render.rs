@@ -4,8 +4,8 @@ use image::{Rgba, RgbaImage};
 use log::{debug, error};
 
 use raygun_material::{Colour, Finish, COLOUR_BLACK};
-use raygun_math::{point, Point, Ray, UnitVector, Vector};
-use raygun_primitives::Object;
+use raygun_math::{spawn_ray, Float, Normal3, Point3, Ray, RayPacket, Vector3, LANES};
+use raygun_primitives::{Hit, Object, SurfaceInfo};
 use raygun_scene::{LightInfo, Scene};
 
 pub struct RenderOptions {
@@ -16,6 +16,10 @@ pub struct RenderOptions {
 pub fn render(scene: &Scene, options: RenderOptions) -> Option<RgbaImage> {
     use std::sync::mpsc::channel;
 
+    // only the leaves of the object tree are intersected, so hoist the
+    // contents of any unions up to the top level
+    let scene = &scene.flattened();
+
     debug!("Collecting lights...");
 
     let lights = &scene.lights();
@@ -34,13 +38,26 @@ pub fn render(scene: &Scene, options: RenderOptions) -> Option<RgbaImage> {
 
         debug!("Spawning render tasks...");
 
+        // primary rays are traced in packets of horizontally adjacent pixels.
+        // Rows that don't divide evenly into packets have the last ray
+        // repeated to fill out the final packet.
+        let width = options.width as u32;
         for y in 0..(options.height as u32) {
-            for x in 0..(options.width as u32) {
-                let ray = projection.ray_for(x, y);
+            for x0 in (0..width).step_by(LANES) {
+                let mut rays = [projection.ray_for(x0, y); LANES];
+                for (n, r) in rays.iter_mut().enumerate() {
+                    *r = projection.ray_for(u32::min(x0 + n as u32, width - 1), y);
+                }
+
                 let sender = tx.clone();
                 s.spawn(move |_| {
-                    let c = trace(ray, scene, lights);
-                    sender.send((x, y, c)).unwrap();
+                    let colours = trace_packet(&rays, scene, lights);
+                    for (n, c) in colours.iter().enumerate() {
+                        let x = x0 + n as u32;
+                        if x < width {
+                            sender.send((x, y, *c)).unwrap();
+                        }
+                    }
                 })
             }
         }
@@ -60,13 +77,6 @@ pub fn render(scene: &Scene, options: RenderOptions) -> Option<RgbaImage> {
     Some(img)
 }
 
-fn distance(a: Point, b: Point) -> f64 {
-    let dx = b.x - a.x;
-    let dy = b.y - a.y;
-    let dz = b.z - a.z;
-    ((dx * dx) + (dy * dy) + (dz * dz)).sqrt()
-}
-
 fn pack_pixel(c: Colour) -> Rgba<u8> {
     Rgba([
         (255.0 * c.r).min(255.0) as u8,
@@ -81,8 +91,13 @@ fn pack_pixel(c: Colour) -> Rgba<u8> {
 ///
 pub struct Intersection<'a> {
     obj: &'a Object,
-    dist: f64,
-    point: Point,
+    hit: Hit,
+}
+
+impl<'a> Intersection<'a> {
+    fn new(obj: &'a Object, hit: Hit) -> Intersection<'a> {
+        Intersection { obj, hit }
+    }
 }
 
 ///
@@ -93,45 +108,63 @@ pub struct Intersection<'a> {
 /// tests on objects tht could possibly intersect the ray.
 ///
 fn closest_intersecting_object<'a>(r: Ray, scene: &'a Scene) -> Option<Intersection<'a>> {
-    use std::f64;
+    let mut result: Option<Intersection> = None;
+
+    for obj in scene.objects.iter() {
+        if let Some(hit) = obj.intersects(r) {
+            let closer = match &result {
+                Some(ix) => hit.t < ix.hit.t,
+                None => true,
+            };
+            if closer {
+                result = Some(Intersection::new(obj, hit));
+            }
+        }
+    }
+    result
+}
 
-    let mut min_dist = f64::INFINITY;
-    let mut intersecting_obj = None;
-    let mut intersection_point = point(f64::NAN, f64::NAN, f64::NAN);
+///
+/// Finds the closest intersection for each ray in a packet
+///
+fn closest_intersecting_objects<'a>(
+    p: &RayPacket,
+    scene: &'a Scene,
+) -> [Option<Intersection<'a>>; LANES] {
+    let mut result: [Option<Intersection>; LANES] = Default::default();
 
     for obj in scene.objects.iter() {
-        if let Some(pt) = obj.intersects(r) {
-            let n = distance(r.src, pt);
-            if n < min_dist {
-                min_dist = n;
-                intersecting_obj = Some(obj);
-                intersection_point = pt;
+        for (n, hit) in obj.intersects_packet(p).iter().enumerate() {
+            if let Some(hit) = *hit {
+                let closer = match &result[n] {
+                    Some(ix) => hit.t < ix.hit.t,
+                    None => true,
+                };
+                if closer {
+                    result[n] = Some(Intersection::new(obj, hit));
+                }
             }
         }
     }
-    intersecting_obj.map(|o| Intersection {
-        obj: &(*o),
-        dist: min_dist,
-        point: intersection_point,
-    })
+    result
 }
 
 ///
 /// Specular highlights using the blinn-phong shading model
 ///
 fn blinn_phong_highlight(
-    viewdir: UnitVector,
+    viewdir: Vector3,
     light_ray: Ray,
-    surface_normal: UnitVector,
+    surface_normal: Normal3,
     light_colour: Colour,
     finish: &Finish,
 ) -> Colour {
     if !finish.highlight_hardness.is_infinite() {
         let half_vector = (light_ray.dir - viewdir).normalize();
-        let intensity = half_vector
-            .dot(surface_normal)
+        let intensity = surface_normal
+            .dot(half_vector)
             .max(0.0)
-            .powf(finish.highlight_hardness);
+            .powf(finish.highlight_hardness as Float);
 
         light_colour * intensity
     } else {
@@ -139,13 +172,78 @@ fn blinn_phong_highlight(
     }
 }
 
+///
+/// A ray pointing from the surface to a light source, along with the distance
+/// to the light and the colour of the light falling on the surface.
+///
+struct LightRay {
+    ray: Ray,
+    distance: Float,
+    colour: Colour,
+}
+
+///
+/// Finds the ray from a point on a surface to a light, if the light can
+/// illuminate that point at all. Shadows are not considered.
+///
+fn light_ray(
+    light_info: &LightInfo,
+    surface_pt: Point3,
+    surface_error: Vector3,
+    surface_normal: Normal3,
+) -> Option<LightRay> {
+    let light = light_info.light.as_light().unwrap();
+    let point_in_light_space = light_info.transform.inverse * surface_pt;
+    let light_colour = light.illuminates(point_in_light_space)?;
+    let light_beam = (light_info.transform.matrix * light.src()) - surface_pt;
+
+    // if the light beam is behind the point we're trying to light, there's
+    // no point going any further
+    if surface_normal.dot(light_beam) <= 0.0 {
+        return None;
+    }
+
+    Some(LightRay {
+        ray: spawn_ray(surface_pt, surface_error, surface_normal, light_beam),
+        distance: light_beam.length(),
+        colour: light_colour,
+    })
+}
+
+///
+/// The diffuse and specular light reflected from a surface by a single,
+/// unshadowed light
+///
+fn direct_light(
+    viewdir: Vector3,
+    light: &LightRay,
+    surface_normal: Normal3,
+    surface_colour: Colour,
+    surface_finish: &Finish,
+) -> Colour {
+    // compute the diffuse lighting
+    let lambert_coeff = surface_normal.dot(light.ray.dir);
+    let diffuse = surface_finish.diffuse * surface_colour * light.colour * lambert_coeff;
+
+    // compute the specular highlight
+    let specular = blinn_phong_highlight(
+        viewdir,
+        light.ray,
+        surface_normal,
+        light.colour,
+        surface_finish,
+    );
+    diffuse + specular
+}
+
 ///
 /// Calculates the light falling on the given point, from all lights in the scene
 ///
 fn light_surface(
-    viewdir: UnitVector,
-    surface_pt: Point,
-    surface_normal: UnitVector,
+    viewdir: Vector3,
+    surface_pt: Point3,
+    surface_error: Vector3,
+    surface_normal: Normal3,
     surface_colour: Colour,
     surface_finish: &Finish,
     scene: &Scene,
@@ -153,82 +251,100 @@ fn light_surface(
 ) -> Colour {
     let mut result = surface_colour * surface_finish.ambient;
     for light_info in lights.iter() {
-        let light = light_info.light.as_light().unwrap();
-        let point_in_light_space = light_info.transform.matrix * surface_pt;
-
-        if let Some(light_colour) = light.illuminates(point_in_light_space) {
-            let light_beam = light.src() - surface_pt;
-
-            // if the light beam is not behind the point we're trying to light...
-            if light_beam.dot(surface_normal) > 0.0 {
-                // define a ray pointing from the surface to the light source
-                let pp = surface_pt + (1e-6 * surface_normal);
-                let light_ray = Ray::new(pp, light_beam.normalize());
-
-                if !is_shadowed(light_ray, light_beam.length(), scene) {
-                    // compute the diffuse lighting
-                    let lambert_coeff = light_ray.dir.dot(surface_normal);
-                    let diffuse =
-                        surface_finish.diffuse * surface_colour * light_colour * lambert_coeff;
-
-                    // compute the specular highlight
-                    let specular = blinn_phong_highlight(
+        if let Some(light) = light_ray(light_info, surface_pt, surface_error, surface_normal) {
+            if !is_shadowed(light.ray, light.distance, scene) {
+                result = result
+                    + direct_light(
                         viewdir,
-                        light_ray,
+                        &light,
                         surface_normal,
-                        light_colour,
+                        surface_colour,
                         surface_finish,
                     );
-                    result = result + diffuse + specular;
-                }
             }
         }
     }
     result
 }
 
-fn is_shadowed(light_ray: Ray, light_distance: f64, scene: &Scene) -> bool {
+fn is_shadowed(light_ray: Ray, light_distance: Float, scene: &Scene) -> bool {
     if let Some(ix) = closest_intersecting_object(light_ray, scene) {
-        ix.dist < light_distance
+        ix.hit.t < light_distance
     } else {
         false
     }
 }
 
-/// Reflect the incoming ray at the point of intersection, moving it
-/// infinitesimally back along the incoming ray so as not to immediately
-/// find the same point on the same object again.
-fn reflect(inbound: Ray, pt: Point, normal: Vector) -> Ray {
-    let reflected = inbound.reflect(normal, pt);
-    let offset = normal * 1e-12;
-    Ray {
-        src: reflected.src + offset,
-        dir: reflected.dir,
+///
+/// The packet equivalent of `is_shadowed`. Lanes with no light ray are never
+/// shadowed.
+///
+fn is_shadowed_packet(light_rays: &[Option<LightRay>; LANES], scene: &Scene) -> [bool; LANES] {
+    let mut shadowed = [false; LANES];
+
+    // unused lanes are filled with a copy of a live ray
+    let first = match light_rays.iter().flatten().next() {
+        Some(l) => l.ray,
+        None => return shadowed,
+    };
+    let mut rays = [first; LANES];
+    for (r, l) in rays.iter_mut().zip(light_rays.iter()) {
+        if let Some(l) = l {
+            *r = l.ray;
+        }
     }
+
+    let hits = closest_intersecting_objects(&RayPacket::new(&rays), scene);
+    for n in 0..LANES {
+        if let (Some(l), Some(ix)) = (&light_rays[n], &hits[n]) {
+            shadowed[n] = ix.hit.t < l.distance;
+        }
+    }
+    shadowed
 }
 
+/// Reflect the incoming ray at the point of intersection, moving it just
+/// off the surface so as not to immediately find the same point on the same
+/// object again.
+fn reflect(inbound: Ray, pt: Point3, error: Vector3, normal: Normal3) -> Ray {
+    let reflected = inbound.reflect(normal, pt);
+    spawn_ray(pt, error, normal, reflected.dir)
+}
+
+///
+/// Reflected rays contributing less than this to the final colour are not
+/// traced
+///
+const REFLECTION_THRESHOLD: f64 = 1e-12;
+
 ///
 /// Traces a ray from the ray source through the scene
 ///
 fn trace(inbound_ray: Ray, scene: &Scene, lights: &Vec<LightInfo>) -> Colour {
-    use std::collections::VecDeque;
+    trace_weighted(inbound_ray, 1.0, scene, lights)
+}
 
-    const THRESHOLD: f64 = 1e-12;
+///
+/// Traces a ray whose contribution to the final colour is scaled by `weight`
+///
+fn trace_weighted(inbound_ray: Ray, weight: f64, scene: &Scene, lights: &Vec<LightInfo>) -> Colour {
+    use std::collections::VecDeque;
 
     let mut contribs = Vec::new();
     let mut rays = VecDeque::new();
-    rays.push_back((inbound_ray, 1.0));
+    rays.push_back((inbound_ray, weight));
 
     while !rays.is_empty() {
         let (ray, weight) = rays.pop_front().unwrap();
         let intersection = closest_intersecting_object(ray, scene);
         let contrib = match intersection {
             Some(ix) => {
-                let surface_point = ix.point;
-                let surface = ix.obj.surface_at(surface_point);
+                let surface_point = ix.hit.point;
+                let surface = ix.obj.surface_at(&ix.hit);
                 let colour = light_surface(
                     ray.dir,
                     surface_point,
+                    ix.hit.error,
                     surface.normal,
                     surface.colour,
                     &surface.finish,
@@ -238,8 +354,8 @@ fn trace(inbound_ray: Ray, scene: &Scene, lights: &Vec<LightInfo>) -> Colour {
 
                 if surface.finish.reflection > 0.0 {
                     let new_weight = weight * surface.finish.reflection;
-                    if new_weight > THRESHOLD {
-                        let new_ray = reflect(ray, surface_point, surface.normal);
+                    if new_weight > REFLECTION_THRESHOLD {
+                        let new_ray = reflect(ray, surface_point, ix.hit.error, surface.normal);
                         rays.push_back((new_ray, new_weight));
                     }
                 }
@@ -255,11 +371,70 @@ fn trace(inbound_ray: Ray, scene: &Scene, lights: &Vec<LightInfo>) -> Colour {
     contribs.iter().fold(COLOUR_BLACK, |sum, b| sum + (*b))
 }
 
+///
+/// Traces a packet of coherent rays through the scene. The primary and shadow
+/// rays are traced as packets, but reflected rays are likely to diverge, so
+/// they are traced individually.
+///
+fn trace_packet(rays: &[Ray; LANES], scene: &Scene, lights: &Vec<LightInfo>) -> [Colour; LANES] {
+    let hits = closest_intersecting_objects(&RayPacket::new(rays), scene);
+
+    let mut surfaces: [Option<(&Intersection, SurfaceInfo)>; LANES] = Default::default();
+    let mut colours = [COLOUR_BLACK; LANES];
+    for n in 0..LANES {
+        match &hits[n] {
+            Some(ix) => {
+                let surface = ix.obj.surface_at(&ix.hit);
+                colours[n] = surface.colour * surface.finish.ambient;
+                surfaces[n] = Some((ix, surface));
+            }
+            None => colours[n] = scene.sky(rays[n]),
+        }
+    }
+
+    for light_info in lights.iter() {
+        let mut light_rays: [Option<LightRay>; LANES] = Default::default();
+        for (l, surface) in light_rays.iter_mut().zip(surfaces.iter()) {
+            if let Some((ix, surface)) = surface {
+                *l = light_ray(light_info, ix.hit.point, ix.hit.error, surface.normal);
+            }
+        }
+
+        let shadowed = is_shadowed_packet(&light_rays, scene);
+        for n in 0..LANES {
+            if let (Some((_, surface)), Some(light)) = (&surfaces[n], &light_rays[n]) {
+                if !shadowed[n] {
+                    colours[n] = colours[n]
+                        + direct_light(
+                            rays[n].dir,
+                            light,
+                            surface.normal,
+                            surface.colour,
+                            surface.finish,
+                        );
+                }
+            }
+        }
+    }
+
+    for n in 0..LANES {
+        if let Some((ix, surface)) = &surfaces[n] {
+            let weight = surface.finish.reflection;
+            if weight > REFLECTION_THRESHOLD {
+                let new_ray = reflect(rays[n], ix.hit.point, ix.hit.error, surface.normal);
+                colours[n] = colours[n] + trace_weighted(new_ray, weight, scene, lights);
+            }
+        }
+    }
+
+    colours
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use raygun_material::Colour;
-    use raygun_math::{point, vector, Ray, Vector};
+    use raygun_math::{point, vector, Ray, Vector3};
     use raygun_primitives::{Object, PointLight, Primitive, Sphere};
     use raygun_scene::Scene;
     use std::sync::Arc;
@@ -281,7 +456,7 @@ mod test {
         s
     }
 
-    fn floats_are_close(a: f64, b: f64, epsilon: f64) -> bool {
+    fn floats_are_close(a: Float, b: Float, epsilon: Float) -> bool {
         (a - b).abs() < epsilon
     }
 
@@ -290,7 +465,7 @@ mod test {
         let s = test_scene();
         let r = Ray::new(point(0.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
         if let Some(i) = super::closest_intersecting_object(r, &s) {
-            assert!(floats_are_close(9.0, i.dist, 1e-6))
+            assert!(floats_are_close(9.0, i.hit.t, 1e-6))
         } else {
             panic!("Expected an intersecting object")
         }
@@ -312,7 +487,7 @@ mod test {
         let light_loc = point(100.0, 100.0, 100.0);
 
         let surface_pt = point(0.0, 0.0, 0.0);
-        let light_beam = Vector::between(surface_pt, light_loc);
+        let light_beam = Vector3::between(surface_pt, light_loc);
         let light_ray = Ray::new(surface_pt, light_beam.normalize());
 
         assert!(!super::is_shadowed(light_ray, light_beam.length(), &s))
@@ -325,7 +500,7 @@ mod test {
         s.add_object(to_obj(Sphere::new(point(90.0, 90.0, 90.0), 2.0)));
 
         let surface_pt = point(0.0, 0.0, 0.0);
-        let light_beam = Vector::between(surface_pt, light_loc);
+        let light_beam = Vector3::between(surface_pt, light_loc);
         let light_ray = Ray::new(surface_pt, light_beam.normalize());
 
         assert!(super::is_shadowed(light_ray, light_beam.length(), &s))
@@ -339,9 +514,149 @@ mod test {
         s.add_object(to_obj(Sphere::new(point(110.0, 110.0, 11.0), 2.0)));
 
         let surface_pt = point(0.0, 0.0, 0.0);
-        let light_beam = Vector::between(surface_pt, light_loc);
+        let light_beam = Vector3::between(surface_pt, light_loc);
         let light_ray = Ray::new(surface_pt, light_beam.normalize());
 
         assert!(!super::is_shadowed(light_ray, light_beam.length(), &s))
     }
+
+    #[test]
+    fn lights_follow_their_transforms() {
+        use raygun_math::{Normal3, Transform};
+        use raygun_primitives::{PointLight, Union};
+
+        // a light nudged along x, tucked away inside a union raised above
+        // the origin
+        let mut light = to_obj(PointLight::new(
+            point(0.0, 0.0, 0.0),
+            Colour::new(1.0, 1.0, 1.0),
+        ));
+        light.transform = Some(Box::new(Transform::for_translation(3.0, 0.0, 0.0)));
+        let mut group = to_obj(Union {
+            children: vec![Arc::new(light)],
+        });
+        group.transform = Some(Box::new(Transform::for_translation(0.0, 10.0, 0.0)));
+
+        let mut s = Scene::new();
+        s.add_object(group);
+        let lights = s.lights();
+
+        let up = Normal3::new(0.0, 1.0, 0.0);
+        let error = Vector3::new(0.0, 0.0, 0.0);
+        let lit = super::light_ray(&lights[0], point(3.0, 0.0, 0.0), error, up).unwrap();
+        assert!((lit.distance - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn spot_lights_shine_along_their_transforms() {
+        use raygun_math::{Normal3, Transform};
+        use raygun_primitives::{SpotLight, Union};
+
+        // a spot light pointing straight down from above the origin, tucked
+        // away inside a union
+        let mut light = to_obj(SpotLight::new(Colour::new(1.0, 1.0, 1.0), 0.1, 0.2));
+        light.transform = Some(Box::new(Transform::for_rotation(
+            raygun_math::degrees(90.0).radians(),
+            raygun_math::degrees(0.0).radians(),
+            raygun_math::degrees(0.0).radians(),
+        )));
+        let mut group = to_obj(Union {
+            children: vec![Arc::new(light)],
+        });
+        group.transform = Some(Box::new(Transform::for_translation(0.0, 10.0, 0.0)));
+
+        let mut s = Scene::new();
+        s.add_object(group);
+        let lights = s.lights();
+
+        let up = Normal3::new(0.0, 1.0, 0.0);
+        let error = Vector3::new(0.0, 0.0, 0.0);
+        let lit = super::light_ray(&lights[0], point(0.0, 0.0, 0.0), error, up).unwrap();
+        assert!((lit.distance - 10.0).abs() < 1e-6);
+
+        // outside the cone it's dark
+        assert!(super::light_ray(&lights[0], point(5.0, 0.0, 0.0), error, up).is_none());
+    }
+
+    #[test]
+    fn large_scaled_objects_do_not_shadow_themselves() {
+        use raygun_math::Transform;
+
+        let mut s = Scene::new();
+        let mut sphere = to_obj(Sphere::default());
+        sphere.transform = Some(Box::new(
+            Transform::for_scale(1e5, 1e5, 1e5).translate(1e6, 0.0, 0.0),
+        ));
+        s.add_object(sphere);
+        s.add_object(to_obj(PointLight::new(
+            point(1e6, 1e7, -1e7),
+            Colour::new(1.0, 1.0, 1.0),
+        )));
+        let lights = s.lights();
+
+        let eye = point(1e6, 0.0, -1e6);
+        for y in -10..=10 {
+            for x in -10..=10 {
+                let dir = vector(x as Float * 0.005, y as Float * 0.005, 1.0);
+                let ix = match super::closest_intersecting_object(Ray::new(eye, dir), &s) {
+                    Some(ix) => ix,
+                    None => continue,
+                };
+                let n = ix.obj.surface_at(&ix.hit).normal;
+                if let Some(l) = super::light_ray(&lights[0], ix.hit.point, ix.hit.error, n) {
+                    assert!(
+                        !super::is_shadowed(l.ray, l.distance, &s),
+                        "{:?} shadowed by itself",
+                        ix.hit.point
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packets_match_single_rays() {
+        use raygun_material::{Finish, Material, Pigment};
+        use raygun_primitives::Plane;
+
+        let mut s = test_scene();
+        let mut mirror = to_obj(Plane::default());
+        mirror.material = Material {
+            pigment: Pigment::Solid(Colour::new(0.2, 0.4, 0.6)),
+            finish: Finish {
+                reflection: 0.5,
+                ..Finish::default()
+            },
+        };
+        s.add_object(to_obj(PointLight::new(
+            point(10.0, 10.0, -10.0),
+            Colour::new(1.0, 1.0, 1.0),
+        )));
+        s.add_object(mirror);
+        let lights = s.lights();
+
+        let src = point(0.0, 3.0, -10.0);
+        let rays = [
+            Ray::new(src, vector(0.0, -0.3, 1.0)),
+            Ray::new(src, vector(0.1, -0.5, 1.0)),
+            Ray::new(src, vector(0.0, 1.0, 1.0)),
+            Ray::new(src, vector(-0.3, -1.0, 1.0)),
+        ];
+
+        // colours are always computed in f64, whatever the geometry uses
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        let colours = super::trace_packet(&rays, &s, &lights);
+        for (n, r) in rays.iter().enumerate() {
+            let expected = super::trace(*r, &s, &lights);
+            let c = colours[n];
+            assert!(
+                close(c.r, expected.r) && close(c.g, expected.g) && close(c.b, expected.b),
+                "lane {}: expected {:?}, got {:?}",
+                n,
+                expected,
+                c
+            );
+        }
+    }
 }
@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+
+use liquid::model::Value;
+
+///
+/// Builds the user-supplied template globals, first loading any variables
+/// file and then applying the individual `name=value` definitions on top
+/// of it.
+///
+pub fn user_globals(
+    vars_file: Option<&Path>,
+    defines: &[String],
+) -> Result<liquid::Object, String> {
+    let mut globals = match vars_file {
+        Some(path) => load_vars_file(path)?,
+        None => liquid::Object::new(),
+    };
+
+    for def in defines {
+        let (name, value) = parse_define(def)?;
+        globals.insert(name.into(), value);
+    }
+
+    Ok(globals)
+}
+
+///
+/// Loads a TOML or JSON file (selected by extension) whose top level is a
+/// table of variables.
+///
+pub fn load_vars_file(path: &Path) -> Result<liquid::Object, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read variables file {:?}: {}", path, e))?;
+
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "toml" => {
+            let table: toml::value::Table =
+                toml::from_str(&text).map_err(|e| format!("Invalid TOML in {:?}: {}", path, e))?;
+            liquid::to_object(&table).map_err(|e| e.to_string())
+        }
+        "json" => {
+            let value: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| format!("Invalid JSON in {:?}: {}", path, e))?;
+            if !value.is_object() {
+                return Err(format!("Expected a JSON object at the top of {:?}", path));
+            }
+            liquid::to_object(&value).map_err(|e| e.to_string())
+        }
+        _ => Err(format!(
+            "Unknown variables file type {:?}, expected .toml or .json",
+            path
+        )),
+    }
+}
+
+///
+/// Parses a single `name=value` definition. The value is interpreted as a
+/// TOML value, so numbers, booleans, quoted strings and arrays keep their
+/// type. Anything that isn't valid TOML is taken as a bare string.
+///
+pub fn parse_define(def: &str) -> Result<(String, Value), String> {
+    let mut parts = def.splitn(2, '=');
+    let name = parts.next().unwrap_or_default().trim();
+    let raw = parts
+        .next()
+        .ok_or_else(|| format!("Invalid definition {:?}, expected NAME=VALUE", def))?;
+
+    if name.is_empty() {
+        return Err(format!("Invalid definition {:?}, missing name", def));
+    }
+
+    let value = match toml::from_str::<toml::value::Table>(&format!("value = {}", raw)) {
+        Ok(table) => liquid::model::to_value(&table["value"]).map_err(|e| e.to_string())?,
+        Err(_) => Value::scalar(raw.to_owned()),
+    };
+
+    Ok((name.to_owned(), value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use liquid::model::ValueView;
+    use std::path::PathBuf;
+
+    ///
+    /// An empty temporary directory, unique to the test that made it, which
+    /// is removed along with its contents when dropped
+    ///
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> ScratchDir {
+            let dir = std::env::temp_dir().join(format!("raygun-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            ScratchDir(dir)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn render(globals: &liquid::Object, text: &str) -> String {
+        liquid::ParserBuilder::with_stdlib()
+            .build()
+            .unwrap()
+            .parse(text)
+            .unwrap()
+            .render(globals)
+            .unwrap()
+    }
+
+    #[test]
+    fn defines_are_typed() {
+        let (name, value) = parse_define("radius=2.5").unwrap();
+        assert_eq!(name, "radius");
+        assert_eq!(value.as_scalar().unwrap().to_float(), Some(2.5));
+
+        let (_, value) = parse_define("count=3").unwrap();
+        assert_eq!(value.as_scalar().unwrap().to_integer(), Some(3));
+
+        let (_, value) = parse_define("colour=[1, 0.5, 0]").unwrap();
+        assert_eq!(value.as_array().unwrap().size(), 3);
+
+        let (_, value) = parse_define(r#"title="Hello, world""#).unwrap();
+        assert_eq!(value.to_kstr(), "Hello, world");
+    }
+
+    #[test]
+    fn unquoted_text_is_a_string() {
+        let (name, value) = parse_define("quality=high").unwrap();
+        assert_eq!(name, "quality");
+        assert_eq!(value.to_kstr(), "high");
+    }
+
+    #[test]
+    fn malformed_defines_are_rejected() {
+        assert!(parse_define("radius").is_err());
+        assert!(parse_define("=2").is_err());
+    }
+
+    #[test]
+    fn later_defines_override_earlier_ones() {
+        let defines = vec!["x=1".to_owned(), "x=2".to_owned()];
+        let globals = user_globals(None, &defines).unwrap();
+        assert_eq!(render(&globals, "{{ x | plus: 1 }}"), "3");
+    }
+
+    #[test]
+    fn vars_files_are_loaded_by_extension() {
+        let dir = ScratchDir::new("vars");
+
+        let toml_file = dir.0.join("vars.toml");
+        fs::write(&toml_file, "radius = 2\nname = \"ball\"\n").unwrap();
+        let globals = user_globals(Some(&toml_file), &["radius=3".to_owned()]).unwrap();
+        assert_eq!(render(&globals, "{{ name }} {{ radius }}"), "ball 3");
+
+        let json_file = dir.0.join("vars.json");
+        fs::write(&json_file, r#"{ "sizes": [1, 2] }"#).unwrap();
+        let globals = load_vars_file(&json_file).unwrap();
+        assert_eq!(render(&globals, "{{ sizes | last }}"), "2");
+
+        let bad_file = dir.0.join("vars.ini");
+        fs::write(&bad_file, "x = 1").unwrap();
+        assert!(load_vars_file(&bad_file).is_err());
+    }
+
+    #[test]
+    fn array_values_are_usable_in_templates() {
+        let defines = vec!["xs=[1, 2, 3]".to_owned()];
+        let globals = user_globals(None, &defines).unwrap();
+        assert_eq!(
+            render(&globals, "{% for x in xs %}{{x}};{% endfor %}"),
+            "1;2;3;"
+        );
+    }
+}
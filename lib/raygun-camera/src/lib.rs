@@ -2,12 +2,12 @@ use raygun_math::*;
 
 use log::debug;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Camera {
-    pub loc: Point,
-    pub dir: Vector,
-    pub up: Vector,
-    pub right: Vector,
+    pub loc: Point3,
+    pub dir: Vector3,
+    pub up: Vector3,
+    pub right: Vector3,
     pub hfov: Angle<Radians>,
     pub vfov: Angle<Radians>,
 }
@@ -30,14 +30,14 @@ impl Default for Camera {
 }
 
 impl Camera {
-    pub fn with_loc(&self, x: f64, y: f64, z: f64) -> Camera {
+    pub fn with_loc(&self, x: Float, y: Float, z: Float) -> Camera {
         Camera {
             loc: point(x, y, z),
             ..*self
         }
     }
 
-    pub fn with_dir(&self, x: f64, y: f64, z: f64) -> Camera {
+    pub fn with_dir(&self, x: Float, y: Float, z: Float) -> Camera {
         Camera {
             dir: vector(x, y, z),
             ..*self
@@ -84,16 +84,16 @@ impl Camera {
 }
 
 pub struct Projection {
-    topleft: Point,
-    dx: Vector,
-    dy: Vector,
-    src: Point,
+    topleft: Point3,
+    dx: Vector3,
+    dy: Vector3,
+    src: Point3,
 }
 
 impl Projection {
     pub fn ray_for(&self, x: u32, y: u32) -> Ray {
         let pixel_pos = self.topleft + (x * self.dx) + (y * self.dy);
-        let v = Vector::between(self.src, pixel_pos).normalize();
+        let v = (pixel_pos - self.src).normalize();
         Ray::new(self.src, v)
     }
 }
@@ -1,7 +1,7 @@
 use crate::Colour;
-use raygun_math::Point;
+use raygun_math::{Float, Point3};
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Finish {
     pub opacity: f64,
     pub reflection: f64,
@@ -22,9 +22,13 @@ impl Default for Finish {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Pigment {
     Solid(Colour),
+
+    /// Takes the colour from the primitive's own per-vertex colours, for
+    /// primitives that have them, and the given colour elsewhere
+    Vertex(Colour),
 }
 
 impl Default for Pigment {
@@ -33,7 +37,7 @@ impl Default for Pigment {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Material {
     // transforms, etc
     pub finish: Finish,
@@ -41,9 +45,14 @@ pub struct Material {
 }
 
 impl Material {
-    pub fn sample<'a>(&'a self, _p: Point) -> (Colour, &'a Finish) {
+    ///
+    /// Samples the material at a point on a surface, given both as the point
+    /// in object space and as the surface's own `(u, v)` parameterisation of
+    /// that point.
+    ///
+    pub fn sample<'a>(&'a self, _p: Point3, _uv: (Float, Float)) -> (Colour, &'a Finish) {
         match self.pigment {
-            Pigment::Solid(c) => (c, &self.finish),
+            Pigment::Solid(c) | Pigment::Vertex(c) => (c, &self.finish),
         }
     }
 }
@@ -0,0 +1,42 @@
+//! The floating point type used for all geometry.
+//!
+//! Geometry is computed in `f64` by default. Building with the `f32` feature
+//! switches everything over to single precision, which halves the memory
+//! used by large meshes and doubles the number of lanes that fit in a SIMD
+//! register, at the cost of needing more care around self-intersection.
+
+#[cfg(not(feature = "f32"))]
+mod precision {
+    pub type Float = f64;
+    pub use std::f64::consts;
+
+    /// Tolerance used when comparing geometry for approximate equality
+    pub const APPROX_EPSILON: Float = 1e-10;
+}
+
+#[cfg(feature = "f32")]
+mod precision {
+    pub type Float = f32;
+    pub use std::f32::consts;
+
+    /// Tolerance used when comparing geometry for approximate equality
+    pub const APPROX_EPSILON: Float = 1e-5;
+}
+
+pub use self::precision::{consts, Float, APPROX_EPSILON};
+
+///
+/// The largest relative error introduced by rounding the result of a single
+/// floating point operation
+///
+pub const MACHINE_EPSILON: Float = Float::EPSILON * 0.5;
+
+///
+/// A conservative bound on the relative error accumulated over `n`
+/// successive floating point operations, i.e. γₙ as defined by Higham.
+///
+#[inline]
+pub fn gamma(n: i32) -> Float {
+    let n = n as Float * MACHINE_EPSILON;
+    n / (1.0 - n)
+}
@@ -1,10 +1,19 @@
+mod float;
 mod matrix;
+mod normal;
+mod packet;
+mod point;
+mod quaternion;
 mod ray;
+mod roots;
 mod transform;
 mod units;
 mod vector;
 
-pub use self::{matrix::*, ray::*, transform::*, units::*, vector::unit_vectors, vector::*};
+pub use self::{
+    float::*, matrix::*, normal::*, packet::*, point::*, quaternion::*, ray::*, roots::*,
+    transform::*, units::*, vector::unit_vectors, vector::*,
+};
 
 #[inline]
 pub fn min<T: PartialOrd>(a: T, b: T) -> T {
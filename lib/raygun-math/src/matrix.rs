@@ -4,7 +4,7 @@ use std::cmp;
 use std::fmt;
 use std::ops;
 
-use super::Vector;
+use super::{gamma, Float, Floatx4, Normal3, Point3, Vector3};
 use crate::units::{Angle, Radians};
 
 macro_rules! idx {
@@ -19,7 +19,7 @@ macro_rules! idx {
 /// on them generate a new matrix.
 ///
 #[derive(Copy, Clone)]
-pub struct Matrix([f64; 16]);
+pub struct Matrix([Float; 16]);
 
 ///
 /// The identity matrix
@@ -35,13 +35,13 @@ pub static ZERO: Matrix = Matrix([
     0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
 ]);
 
-pub fn translation_matrix(x: f64, y: f64, z: f64) -> Matrix {
+pub fn translation_matrix(x: Float, y: Float, z: Float) -> Matrix {
     Matrix([
         1.0, 0.0, 0.0, x, 0.0, 1.0, 0.0, y, 0.0, 0.0, 1.0, z, 0.0, 0.0, 0.0, 1.0,
     ])
 }
 
-pub fn scaling_matrix(x: f64, y: f64, z: f64) -> Matrix {
+pub fn scaling_matrix(x: Float, y: Float, z: Float) -> Matrix {
     Matrix([
         x, 0.0, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, 0.0, z, 0.0, 0.0, 0.0, 0.0, 1.0,
     ])
@@ -71,7 +71,122 @@ pub fn z_rotation_matrix(theta: Angle<Radians>) -> Matrix {
     ])
 }
 
+///
+/// A shear, where each co-ordinate is offset in proportion to the others, e.g.
+/// `xy` is the amount `x` moves for each unit of `y`.
+///
+pub fn shear_matrix(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix {
+    Matrix([
+        1.0, xy, xz, 0.0, yx, 1.0, yz, 0.0, zx, zy, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+    ])
+}
+
+///
+/// Determinants smaller than this, relative to the product of the lengths of
+/// the matrix's rows, are treated as zero when inverting. That product bounds
+/// the determinant, so the test only depends on how close the rows are to
+/// being linearly dependent and not on the matrix's overall scale.
+///
+const SINGULAR_EPSILON: Float = 64.0 * Float::EPSILON;
+
+#[inline]
+fn combine_sub_determinants(s: &[Float; 6], c: &[Float; 6]) -> Float {
+    (s[0] * c[5]) - (s[1] * c[4]) + (s[2] * c[3]) + (s[3] * c[2]) - (s[4] * c[1]) + (s[5] * c[0])
+}
+
 impl Matrix {
+    ///
+    /// Creates a matrix from its values, in row-major order
+    ///
+    pub fn new(values: [Float; 16]) -> Matrix {
+        Matrix(values)
+    }
+
+    ///
+    /// The determinant, computed from the 2x2 sub-determinants of the upper
+    /// and lower halves of the matrix.
+    ///
+    pub fn determinant(&self) -> Float {
+        let (s, c) = self.sub_determinants();
+        combine_sub_determinants(&s, &c)
+    }
+
+    ///
+    /// Computes the inverse of the matrix, or `None` if the matrix is
+    /// singular (or close enough to it that the inverse would be garbage).
+    ///
+    pub fn inverse(&self) -> Option<Matrix> {
+        let Matrix(a) = self;
+        let (s, c) = self.sub_determinants();
+
+        let det = combine_sub_determinants(&s, &c);
+        if !det.is_finite() || det.abs() <= SINGULAR_EPSILON * self.row_length_product() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let b = [
+            (a[5] * c[5]) - (a[6] * c[4]) + (a[7] * c[3]),
+            -(a[1] * c[5]) + (a[2] * c[4]) - (a[3] * c[3]),
+            (a[13] * s[5]) - (a[14] * s[4]) + (a[15] * s[3]),
+            -(a[9] * s[5]) + (a[10] * s[4]) - (a[11] * s[3]),
+            -(a[4] * c[5]) + (a[6] * c[2]) - (a[7] * c[1]),
+            (a[0] * c[5]) - (a[2] * c[2]) + (a[3] * c[1]),
+            -(a[12] * s[5]) + (a[14] * s[2]) - (a[15] * s[1]),
+            (a[8] * s[5]) - (a[10] * s[2]) + (a[11] * s[1]),
+            (a[4] * c[4]) - (a[5] * c[2]) + (a[7] * c[0]),
+            -(a[0] * c[4]) + (a[1] * c[2]) - (a[3] * c[0]),
+            (a[12] * s[4]) - (a[13] * s[2]) + (a[15] * s[0]),
+            -(a[8] * s[4]) + (a[9] * s[2]) - (a[11] * s[0]),
+            -(a[4] * c[3]) + (a[5] * c[1]) - (a[6] * c[0]),
+            (a[0] * c[3]) - (a[1] * c[1]) + (a[2] * c[0]),
+            -(a[12] * s[3]) + (a[13] * s[1]) - (a[14] * s[0]),
+            (a[8] * s[3]) - (a[9] * s[1]) + (a[10] * s[0]),
+        ];
+
+        let mut result = [0.0; 16];
+        for (r, v) in result.iter_mut().zip(b.iter()) {
+            *r = v * inv_det;
+        }
+        Some(Matrix(result))
+    }
+
+    ///
+    /// The product of the lengths of the rows, which is an upper bound on the
+    /// size of the determinant.
+    ///
+    fn row_length_product(&self) -> Float {
+        let Matrix(a) = self;
+        a.chunks(4)
+            .map(|row| row.iter().map(|v| v * v).sum::<Float>().sqrt())
+            .product()
+    }
+
+    ///
+    /// The 2x2 determinants of the top two rows (`s`) and bottom two rows
+    /// (`c`), shared by the determinant and inverse calculations.
+    ///
+    fn sub_determinants(&self) -> ([Float; 6], [Float; 6]) {
+        let Matrix(a) = self;
+        let s = [
+            (a[0] * a[5]) - (a[4] * a[1]),
+            (a[0] * a[6]) - (a[4] * a[2]),
+            (a[0] * a[7]) - (a[4] * a[3]),
+            (a[1] * a[6]) - (a[5] * a[2]),
+            (a[1] * a[7]) - (a[5] * a[3]),
+            (a[2] * a[7]) - (a[6] * a[3]),
+        ];
+        let c = [
+            (a[8] * a[13]) - (a[12] * a[9]),
+            (a[8] * a[14]) - (a[12] * a[10]),
+            (a[8] * a[15]) - (a[12] * a[11]),
+            (a[9] * a[14]) - (a[13] * a[10]),
+            (a[9] * a[15]) - (a[13] * a[11]),
+            (a[10] * a[15]) - (a[14] * a[11]),
+        ];
+        (s, c)
+    }
+
     pub fn transpose(&self) -> Matrix {
         let &Matrix(ref m) = self;
         let Matrix(mut result) = ZERO;
@@ -136,9 +251,9 @@ impl cmp::PartialEq for Matrix {
 impl Eq for Matrix {}
 
 impl ops::Index<(usize, usize)> for Matrix {
-    type Output = f64;
+    type Output = Float;
 
-    fn index<'a>(&'a self, idx: (usize, usize)) -> &'a f64 {
+    fn index<'a>(&'a self, idx: (usize, usize)) -> &'a Float {
         let &Matrix(ref values) = self;
         let (i, j) = idx;
         &values[(j * 4) + i]
@@ -149,7 +264,7 @@ impl ops::Index<(usize, usize)> for Matrix {
 /// Implements m[(i,j)] = x.
 //
 impl ops::IndexMut<(usize, usize)> for Matrix {
-    fn index_mut<'a>(&'a mut self, idx: (usize, usize)) -> &'a mut f64 {
+    fn index_mut<'a>(&'a mut self, idx: (usize, usize)) -> &'a mut Float {
         let Matrix(ref mut values) = *self;
         let (i, j) = idx;
         &mut values[(j * 4) + i]
@@ -161,7 +276,7 @@ impl ops::IndexMut<(usize, usize)> for Matrix {
 /// matrix multiplication.
 ///
 #[inline]
-fn row_col_dot_product(lhs: &Matrix, rhs: &Matrix, i: usize, j: usize) -> f64 {
+fn row_col_dot_product(lhs: &Matrix, rhs: &Matrix, i: usize, j: usize) -> Float {
     let &Matrix(ref a) = lhs;
     let &Matrix(ref b) = rhs;
 
@@ -188,55 +303,128 @@ impl ops::Mul<Matrix> for Matrix {
     }
 }
 
-impl ops::Mul<Vector> for Matrix {
-    type Output = Vector;
-
+impl Matrix {
+    ///
+    /// Multiplies the homogeneous column vector (x, y, z, w) by this matrix
+    ///
     #[inline]
-    fn mul(self, rhs: Vector) -> Vector {
-        &self * &rhs
+    fn mul_homogeneous(
+        &self,
+        x: Float,
+        y: Float,
+        z: Float,
+        w: Float,
+    ) -> (Float, Float, Float, Float) {
+        let &Matrix(ref m) = self;
+        let row = |r: usize| {
+            (m[idx!(r, 0)] * x) + (m[idx!(r, 1)] * y) + (m[idx!(r, 2)] * z) + (m[idx!(r, 3)] * w)
+        };
+        (row(0), row(1), row(2), row(3))
     }
-}
-
-impl<'a> ops::Mul<Vector> for &'a Matrix {
-    type Output = Vector;
 
+    ///
+    /// `mul_homogeneous` for a packet of vectors, with the operations done in
+    /// the same order so that every lane gets exactly the same answer
+    ///
     #[inline]
-    fn mul(self, rhs: Vector) -> Vector {
-        self * &rhs
+    pub(crate) fn mul_homogeneous_x4(
+        &self,
+        x: Floatx4,
+        y: Floatx4,
+        z: Floatx4,
+        w: Floatx4,
+    ) -> (Floatx4, Floatx4, Floatx4, Floatx4) {
+        let Matrix(m) = self;
+        let e = |r: usize, c: usize| Floatx4::splat(m[idx!(r, c)]);
+        let row = |r: usize| (e(r, 0) * x) + (e(r, 1) * y) + (e(r, 2) * z) + (e(r, 3) * w);
+        (row(0), row(1), row(2), row(3))
     }
-}
 
-impl<'a, 'b> ops::Mul<&'b Vector> for &'a Matrix {
-    type Output = Vector;
+    ///
+    /// Transforms a point whose coordinates are only known to within `error`,
+    /// returning the transformed point along with a conservative bound on the
+    /// error in its coordinates. This accounts for both the error carried in
+    /// and the rounding error in the transform itself, and assumes the matrix
+    /// is affine.
+    ///
+    pub fn transform_with_error(&self, p: Point3, error: Vector3) -> (Point3, Vector3) {
+        let Matrix(m) = self;
+        let bound = |r: usize| {
+            let rounding = (m[idx!(r, 0)] * p.x).abs()
+                + (m[idx!(r, 1)] * p.y).abs()
+                + (m[idx!(r, 2)] * p.z).abs()
+                + m[idx!(r, 3)].abs();
+            let carried = (m[idx!(r, 0)].abs() * error.x)
+                + (m[idx!(r, 1)].abs() * error.y)
+                + (m[idx!(r, 2)].abs() * error.z);
+            ((gamma(3) + 1.0) * carried) + (gamma(3) * rounding)
+        };
+
+        (self * p, Vector3::new(bound(0), bound(1), bound(2)))
+    }
+}
 
-    fn mul(self, rhs: &'b Vector) -> Vector {
-        let &Matrix(ref m) = self;
-        let x = (m[idx!(0, 0)] * rhs.x)
-            + (m[idx!(0, 1)] * rhs.y)
-            + (m[idx!(0, 2)] * rhs.z)
-            + m[idx!(0, 3)];
+///
+/// Implements `Matrix * T`, `&Matrix * T` and `&Matrix * &T` in terms of a
+/// function transforming a `T` by a `&Matrix`.
+///
+macro_rules! matrix_mul_impl {
+    ($t:ident, $f:expr) => {
+        impl ops::Mul<$t> for Matrix {
+            type Output = $t;
+
+            #[inline]
+            fn mul(self, rhs: $t) -> $t {
+                $f(&self, &rhs)
+            }
+        }
 
-        let y = (m[idx!(1, 0)] * rhs.x)
-            + (m[idx!(1, 1)] * rhs.y)
-            + (m[idx!(1, 2)] * rhs.z)
-            + m[idx!(1, 3)];
+        impl<'a> ops::Mul<$t> for &'a Matrix {
+            type Output = $t;
 
-        let z = (m[idx!(2, 0)] * rhs.x)
-            + (m[idx!(2, 1)] * rhs.y)
-            + (m[idx!(2, 2)] * rhs.z)
-            + m[idx!(2, 3)];
+            #[inline]
+            fn mul(self, rhs: $t) -> $t {
+                $f(self, &rhs)
+            }
+        }
 
-        let w = m[idx!(3, 3)];
-        let inv_w = 1.0 / w;
+        impl<'a, 'b> ops::Mul<&'b $t> for &'a Matrix {
+            type Output = $t;
 
-        Vector::new(x * inv_w, y * inv_w, z * inv_w)
-    }
+            #[inline]
+            fn mul(self, rhs: &'b $t) -> $t {
+                $f(self, rhs)
+            }
+        }
+    };
 }
 
+// Points are affected by translation (w = 1), and may need the perspective
+// divide
+matrix_mul_impl!(Point3, |m: &Matrix, p: &Point3| {
+    let (x, y, z, w) = m.mul_homogeneous(p.x, p.y, p.z, 1.0);
+    let inv_w = 1.0 / w;
+    Point3::new(x * inv_w, y * inv_w, z * inv_w)
+});
+
+// Vectors are directions, and so unaffected by translation (w = 0)
+matrix_mul_impl!(Vector3, |m: &Matrix, v: &Vector3| {
+    let (x, y, z, _) = m.mul_homogeneous(v.x, v.y, v.z, 0.0);
+    Vector3::new(x, y, z)
+});
+
+// Normals are also directions. Note that a normal should be transformed by
+// the inverse transpose of the matrix used for points.
+matrix_mul_impl!(Normal3, |m: &Matrix, n: &Normal3| {
+    let (x, y, z, _) = m.mul_homogeneous(n.x, n.y, n.z, 0.0);
+    Normal3::new(x, y, z)
+});
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::units::degrees;
+    use crate::APPROX_EPSILON;
 
     #[test]
     fn matrix_transpose() -> () {
@@ -275,6 +463,92 @@ mod test {
         assert_eq!(m, IDENTITY);
     }
 
+    #[test]
+    fn points_are_translated_but_directions_are_not() {
+        let m = translation_matrix(1.0, 2.0, 3.0);
+        assert_eq!(m * Point3::new(1.0, 1.0, 1.0), Point3::new(2.0, 3.0, 4.0));
+        assert_eq!(m * Vector3::new(1.0, 1.0, 1.0), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(m * Normal3::new(0.0, 1.0, 0.0), Normal3::new(0.0, 1.0, 0.0));
+    }
+
+    fn assert_matrix_approx_eq(actual: Matrix, expected: Matrix) {
+        let Matrix(a) = actual;
+        let Matrix(e) = expected;
+        for n in 0..16 {
+            assert!(
+                (a[n] - e[n]).abs() < APPROX_EPSILON,
+                "Expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn matrix_determinant() {
+        assert_eq!(IDENTITY.determinant(), 1.0);
+        assert_eq!(ZERO.determinant(), 0.0);
+        assert_eq!(scaling_matrix(2.0, 3.0, 4.0).determinant(), 24.0);
+        assert_eq!(translation_matrix(2.0, 3.0, 4.0).determinant(), 1.0);
+    }
+
+    #[test]
+    fn matrix_inverse() {
+        let m = translation_matrix(1.0, -2.0, 3.0)
+            * x_rotation_matrix(degrees(30.0).radians())
+            * shear_matrix(0.5, 0.0, 0.0, 0.25, 1.0, 0.0)
+            * scaling_matrix(2.0, 3.0, 0.5);
+
+        let inv = m.inverse().unwrap();
+        assert_matrix_approx_eq(m * inv, IDENTITY);
+        assert_matrix_approx_eq(inv * m, IDENTITY);
+        assert_matrix_approx_eq(
+            translation_matrix(1.0, 2.0, 3.0).inverse().unwrap(),
+            translation_matrix(-1.0, -2.0, -3.0),
+        );
+    }
+
+    #[test]
+    fn transformed_error_bounds() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+
+        // exact inputs still pick up rounding error from the transform
+        let (q, e) = translation_matrix(1.0, 1.0, 1.0).transform_with_error(p, Vector3::default());
+        assert_eq!(q, Point3::new(2.0, 3.0, 4.0));
+        assert!(e.x > 0.0 && e.y > 0.0 && e.z > 0.0);
+        assert!(e.x < 1e-3 && e.y < 1e-3 && e.z < 1e-3);
+
+        // error carried in is scaled along with the point
+        let error = Vector3::new(0.1, 0.1, 0.1);
+        let (_, e) = scaling_matrix(2.0, 3.0, 4.0).transform_with_error(p, error);
+        assert!(e.x >= 0.2 && e.y >= 0.3 && e.z >= 0.4, "{:?}", e);
+        assert!(e.x < 0.21 && e.y < 0.31 && e.z < 0.41, "{:?}", e);
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert_eq!(ZERO.inverse(), None);
+        assert_eq!(scaling_matrix(1.0, 0.0, 1.0).inverse(), None);
+        assert_eq!(shear_matrix(1.0, 0.0, 1.0, 0.0, 0.0, 0.0).inverse(), None);
+    }
+
+    #[test]
+    fn small_scales_are_invertible() {
+        let m = scaling_matrix(1e-5, 1e-5, 1e-5);
+        let inv = m.inverse().expect("a uniform scale is never singular");
+        let p = Point3::new(1.0, 2.0, 3.0);
+        let q = inv * (m * p);
+        assert!((q - p).length() < 1e-3, "{:?}", q);
+
+        // scaling the rows of a singular matrix up or down doesn't change
+        // whether it's singular
+        assert_eq!(
+            (shear_matrix(1.0, 0.0, 1.0, 0.0, 0.0, 0.0) * scaling_matrix(1e-5, 1e-5, 1e-5))
+                .inverse(),
+            None
+        );
+    }
+
     #[test]
     fn rotation_about_x_matrix() {
         use float_cmp::*;
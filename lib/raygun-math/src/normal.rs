@@ -0,0 +1,125 @@
+use std::{cmp, fmt, ops};
+
+use super::{Float, Matrix, Vector3};
+
+///
+/// Defines a surface normal. Normals are directions like vectors, but they
+/// don't transform like vectors do, and so are kept as a distinct type.
+///
+#[derive(Default, Clone, Copy)]
+pub struct Normal3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+///
+/// Normal constructor function. No normalisation is performed.
+///
+pub fn normal(x: Float, y: Float, z: Float) -> Normal3 {
+    Normal3::new(x, y, z)
+}
+
+impl Normal3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Normal3 {
+        Normal3 { x, y, z }
+    }
+
+    pub fn dot(&self, v: Vector3) -> Float {
+        (self.x * v.x) + (self.y * v.y) + (self.z * v.z)
+    }
+
+    pub fn length(&self) -> Float {
+        Vector3::from(*self).length()
+    }
+
+    /// Scales the normal such that |self| == 1.0
+    pub fn normalize(&self) -> Normal3 {
+        Normal3::from(Vector3::from(*self).normalize())
+    }
+
+    ///
+    /// Tests that the normal is approximately equal to another normal.
+    ///
+    pub fn approx_eq(&self, other: Normal3) -> bool {
+        Vector3::from(*self).approx_eq(Vector3::from(other))
+    }
+
+    ///
+    /// Transforms the normal by `t`, which must already be the appropriate
+    /// matrix for transforming normals (i.e. the inverse transpose of the
+    /// matrix used to transform points). The result is not renormalised.
+    ///
+    pub fn transform(&self, t: &Matrix) -> Normal3 {
+        t * self
+    }
+}
+
+impl fmt::Debug for Normal3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl cmp::PartialEq for Normal3 {
+    fn eq(&self, other: &Normal3) -> bool {
+        self.x == other.x && self.y == other.y && self.z == other.z
+    }
+}
+
+impl cmp::Eq for Normal3 {}
+
+impl From<Vector3> for Normal3 {
+    fn from(v: Vector3) -> Normal3 {
+        normal(v.x, v.y, v.z)
+    }
+}
+
+impl From<Normal3> for Vector3 {
+    fn from(n: Normal3) -> Vector3 {
+        Vector3::new(n.x, n.y, n.z)
+    }
+}
+
+impl ops::Neg for Normal3 {
+    type Output = Normal3;
+
+    fn neg(self) -> Normal3 {
+        normal(-self.x, -self.y, -self.z)
+    }
+}
+
+impl ops::Mul<Float> for Normal3 {
+    type Output = Vector3;
+
+    fn mul(self, s: Float) -> Vector3 {
+        Vector3::from(self) * s
+    }
+}
+
+impl ops::Mul<Normal3> for Float {
+    type Output = Vector3;
+
+    fn mul(self, n: Normal3) -> Vector3 {
+        Vector3::from(n) * self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vector;
+
+    #[test]
+    fn normal_dot_product() {
+        let n = normal(0.0, 1.0, 0.0);
+        assert_eq!(n.dot(vector(3.0, 4.0, 5.0)), 4.0);
+    }
+
+    #[test]
+    fn scaled_normal_is_a_vector() {
+        let n = normal(0.0, 1.0, 0.0);
+        assert_eq!(2.0 * n, vector(0.0, 2.0, 0.0));
+        assert_eq!(n * 2.0, vector(0.0, 2.0, 0.0));
+    }
+}
@@ -0,0 +1,293 @@
+//! Ray packets for tracing several coherent rays at once.
+//!
+//! Packets are stored as a structure of arrays, so that each operation is
+//! applied to every lane at once. The lanes are held in `wide`'s SIMD types,
+//! which use SSE, AVX or NEON instructions where the target supports them and
+//! fall back to plain arrays where it doesn't.
+
+use std::ops;
+
+use wide::{CmpGt, CmpLt};
+
+use super::{Float, Matrix, Point3, Ray, Vector3};
+
+///
+/// The number of rays in a packet
+///
+pub const LANES: usize = 4;
+
+///
+/// A mask selecting some subset of the lanes in a packet
+///
+pub type Mask = [bool; LANES];
+
+#[cfg(not(feature = "f32"))]
+type Lanes = wide::f64x4;
+
+#[cfg(feature = "f32")]
+type Lanes = wide::f32x4;
+
+///
+/// A packet of `LANES` values, operated on lane-wise
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Floatx4(Lanes);
+
+impl Floatx4 {
+    /// A packet holding the given values, one per lane
+    #[inline]
+    pub fn new(values: [Float; LANES]) -> Floatx4 {
+        Floatx4(Lanes::new(values))
+    }
+
+    /// A packet with the same value in every lane
+    #[inline]
+    pub fn splat(n: Float) -> Floatx4 {
+        Floatx4(Lanes::splat(n))
+    }
+
+    #[inline]
+    pub fn sqrt(self) -> Floatx4 {
+        Floatx4(self.0.sqrt())
+    }
+
+    /// The lane-wise minimum. Unlike `Float::min`, the result is unspecified
+    /// in lanes where either side is NaN, so check with `any_nan` first if
+    /// they can turn up.
+    #[inline]
+    pub fn min(self, other: Floatx4) -> Floatx4 {
+        Floatx4(self.0.fast_min(other.0))
+    }
+
+    /// The lane-wise maximum, with NaNs left unspecified as for `min`
+    #[inline]
+    pub fn max(self, other: Floatx4) -> Floatx4 {
+        Floatx4(self.0.fast_max(other.0))
+    }
+
+    /// Whether any lane of any of the packets is NaN
+    #[inline]
+    pub fn any_nan(packets: &[Floatx4]) -> bool {
+        packets
+            .iter()
+            .fold(Lanes::splat(0.0), |nans, p| nans | p.0.is_nan())
+            .any()
+    }
+
+    /// The lanes where `self < other`
+    #[inline]
+    pub fn lt(self, other: Floatx4) -> Mask {
+        to_mask(self.0.cmp_lt(other.0))
+    }
+
+    /// The lanes where `self > other`
+    #[inline]
+    pub fn gt(self, other: Floatx4) -> Mask {
+        to_mask(self.0.cmp_gt(other.0))
+    }
+
+    #[inline]
+    pub fn lane(&self, n: usize) -> Float {
+        self.0.as_array_ref()[n]
+    }
+}
+
+///
+/// Converts the all-ones or all-zeros lanes of a SIMD comparison into a mask
+///
+#[inline]
+fn to_mask(lanes: Lanes) -> Mask {
+    let bits = lanes.move_mask();
+    let mut mask = [false; LANES];
+    for (n, m) in mask.iter_mut().enumerate() {
+        *m = bits & (1 << n) != 0;
+    }
+    mask
+}
+
+macro_rules! floatx4_op_impl {
+    ($trait:ident, $fn:ident, $op:tt) => {
+        impl ops::$trait<Floatx4> for Floatx4 {
+            type Output = Floatx4;
+
+            #[inline]
+            fn $fn(self, rhs: Floatx4) -> Floatx4 {
+                Floatx4(self.0 $op rhs.0)
+            }
+        }
+    };
+}
+
+floatx4_op_impl!(Add, add, +);
+floatx4_op_impl!(Sub, sub, -);
+floatx4_op_impl!(Mul, mul, *);
+floatx4_op_impl!(Div, div, /);
+
+///
+/// A packet of `LANES` points or vectors
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector3x4 {
+    pub x: Floatx4,
+    pub y: Floatx4,
+    pub z: Floatx4,
+}
+
+impl Vector3x4 {
+    /// The same vector in every lane
+    #[inline]
+    pub fn splat(x: Float, y: Float, z: Float) -> Vector3x4 {
+        Vector3x4 {
+            x: Floatx4::splat(x),
+            y: Floatx4::splat(y),
+            z: Floatx4::splat(z),
+        }
+    }
+
+    #[inline]
+    pub fn dot(&self, other: &Vector3x4) -> Floatx4 {
+        (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
+    }
+
+    /// Scales every lane to unit length, as `Vector3::normalize` does
+    #[inline]
+    pub fn normalize(&self) -> Vector3x4 {
+        let length = ((self.x * self.x) + (self.y * self.y) + (self.z * self.z)).sqrt();
+        let inv_len = Floatx4::splat(1.0) / length;
+        Vector3x4 {
+            x: self.x * inv_len,
+            y: self.y * inv_len,
+            z: self.z * inv_len,
+        }
+    }
+}
+
+impl ops::Sub<Vector3x4> for Vector3x4 {
+    type Output = Vector3x4;
+
+    #[inline]
+    fn sub(self, rhs: Vector3x4) -> Vector3x4 {
+        Vector3x4 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+///
+/// A packet of `LANES` rays, in structure-of-arrays form
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayPacket {
+    pub src: Vector3x4,
+    pub dir: Vector3x4,
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Ray; LANES]) -> RayPacket {
+        let lanes = |f: &dyn Fn(&Ray) -> Float| {
+            let mut values = [0.0; LANES];
+            for (v, r) in values.iter_mut().zip(rays.iter()) {
+                *v = f(r);
+            }
+            Floatx4::new(values)
+        };
+
+        RayPacket {
+            src: Vector3x4 {
+                x: lanes(&|r| r.src.x),
+                y: lanes(&|r| r.src.y),
+                z: lanes(&|r| r.src.z),
+            },
+            dir: Vector3x4 {
+                x: lanes(&|r| r.dir.x),
+                y: lanes(&|r| r.dir.y),
+                z: lanes(&|r| r.dir.z),
+            },
+        }
+    }
+
+    /// Extracts the ray in lane `n`
+    #[inline]
+    pub fn ray(&self, n: usize) -> Ray {
+        Ray {
+            src: Point3::new(self.src.x.lane(n), self.src.y.lane(n), self.src.z.lane(n)),
+            dir: Vector3::new(self.dir.x.lane(n), self.dir.y.lane(n), self.dir.z.lane(n)),
+        }
+    }
+
+    pub fn rays(&self) -> [Ray; LANES] {
+        let mut rays = [self.ray(0); LANES];
+        for (n, r) in rays.iter_mut().enumerate().skip(1) {
+            *r = self.ray(n);
+        }
+        rays
+    }
+
+    ///
+    /// Transforms every ray in the packet. The arithmetic follows
+    /// `Ray::transform` step for step, so that the results are identical to
+    /// transforming the rays individually.
+    ///
+    pub fn transform(&self, t: &Matrix) -> RayPacket {
+        let (zero, one) = (Floatx4::splat(0.0), Floatx4::splat(1.0));
+
+        let (x, y, z, w) = t.mul_homogeneous_x4(self.src.x, self.src.y, self.src.z, one);
+        let inv_w = one / w;
+        let src = Vector3x4 {
+            x: x * inv_w,
+            y: y * inv_w,
+            z: z * inv_w,
+        };
+
+        let (x, y, z, _) = t.mul_homogeneous_x4(self.dir.x, self.dir.y, self.dir.z, zero);
+        // normalised twice, as `Ray::transform` normalises the direction and
+        // then `Ray::new` does it again
+        let dir = Vector3x4 { x, y, z }.normalize().normalize();
+
+        RayPacket { src, dir }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{degrees, point, translation_matrix, vector, Transform};
+
+    #[test]
+    fn lanewise_arithmetic() {
+        let a = Floatx4::new([1.0, 2.0, 3.0, 4.0]);
+        let b = Floatx4::new([4.0, 3.0, 2.0, 1.0]);
+
+        assert_eq!(a + b, Floatx4::splat(5.0));
+        assert_eq!(a * b, Floatx4::new([4.0, 6.0, 6.0, 4.0]));
+        assert_eq!(a.min(b), Floatx4::new([1.0, 2.0, 2.0, 1.0]));
+        assert_eq!(a.lt(b), [true, true, false, false]);
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let rays = [
+            Ray::new(point(0.0, 0.0, 0.0), vector(1.0, 0.0, 0.0)),
+            Ray::new(point(1.0, 2.0, 3.0), vector(0.0, 1.0, 0.0)),
+            Ray::new(point(4.0, 5.0, 6.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(7.0, 8.0, 9.0), vector(1.0, 1.0, 1.0)),
+        ];
+        let packet = RayPacket::new(&rays);
+        assert_eq!(packet.rays(), rays);
+
+        let rotated = Transform::for_rotation(
+            degrees(30.0).radians(),
+            degrees(45.0).radians(),
+            degrees(60.0).radians(),
+        )
+        .scale(2.0, 3.0, 4.0);
+        for t in &[translation_matrix(1.0, 1.0, 1.0), rotated.inverse] {
+            let moved = packet.transform(t);
+            for (n, r) in rays.iter().enumerate() {
+                assert_eq!(moved.ray(n), r.transform(t));
+            }
+        }
+    }
+}
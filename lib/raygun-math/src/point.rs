@@ -0,0 +1,112 @@
+use std::{cmp, fmt, ops};
+
+use super::{Float, Vector3, APPROX_EPSILON};
+
+///
+/// Defines an immutable location in 3D space. Points can be offset by a
+/// vector, and the difference between two points is a vector, but points
+/// can't be added to each other or scaled.
+///
+#[derive(Default, Clone, Copy)]
+pub struct Point3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+///
+/// Point constructor function
+///
+pub fn point(x: Float, y: Float, z: Float) -> Point3 {
+    Point3::new(x, y, z)
+}
+
+impl Point3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Point3 {
+        Point3 { x, y, z }
+    }
+
+    ///
+    /// Tests that the point is approximately equal to another point.
+    ///
+    pub fn approx_eq(&self, other: Point3) -> bool {
+        (self.x - other.x).abs() < APPROX_EPSILON
+            && (self.y - other.y).abs() < APPROX_EPSILON
+            && (self.z - other.z).abs() < APPROX_EPSILON
+    }
+
+    /// The absolute value of each of the point's coordinates
+    pub fn abs(&self) -> Vector3 {
+        Vector3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+}
+
+impl fmt::Debug for Point3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl cmp::PartialEq for Point3 {
+    fn eq(&self, other: &Point3) -> bool {
+        self.x == other.x && self.y == other.y && self.z == other.z
+    }
+}
+
+impl cmp::Eq for Point3 {}
+
+impl From<Vector3> for Point3 {
+    /// The point reached by displacing the origin by `v`
+    fn from(v: Vector3) -> Point3 {
+        point(v.x, v.y, v.z)
+    }
+}
+
+impl ops::Add<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, v: Vector3) -> Point3 {
+        point(self.x + v.x, self.y + v.y, self.z + v.z)
+    }
+}
+
+impl ops::Sub<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, v: Vector3) -> Point3 {
+        point(self.x - v.x, self.y - v.y, self.z - v.z)
+    }
+}
+
+impl ops::Sub<Point3> for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Point3) -> Vector3 {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vector;
+
+    #[test]
+    fn point_construction() {
+        let p = point(2.0, 4.0, 6.0);
+        assert_eq!(p.x, 2.0);
+        assert_eq!(p.y, 4.0);
+        assert_eq!(p.z, 6.0);
+    }
+
+    #[test]
+    fn point_arithmetic() {
+        let a = point(1.0, 2.0, 3.0);
+        let b = point(5.0, 7.0, 9.0);
+        let v = vector(4.0, 5.0, 6.0);
+
+        assert_eq!(b - a, v);
+        assert_eq!(a + v, b);
+        assert_eq!(b - v, a);
+    }
+}
@@ -0,0 +1,311 @@
+use std::{fmt, ops};
+
+use super::{Float, Matrix, Vector3, APPROX_EPSILON};
+use crate::units::{Angle, Radians};
+
+///
+/// A quaternion `w + xi + yj + zk`. Unit quaternions represent rotations,
+/// and unlike Euler angles can be composed and interpolated without gimbal
+/// lock.
+///
+#[derive(Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    /// The quaternion representing no rotation at all
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    ///
+    /// A rotation of `angle` about `axis`, following the right-hand rule.
+    /// The axis need not be normalised, but must not be zero.
+    ///
+    pub fn from_axis_angle(axis: Vector3, angle: Angle<Radians>) -> Quaternion {
+        let a = axis.normalize();
+        let half = angle.get() / 2.0;
+        let s = half.sin();
+        Quaternion::new(half.cos(), a.x * s, a.y * s, a.z * s)
+    }
+
+    ///
+    /// Extracts the rotation from the upper 3x3 part of a matrix, which is
+    /// assumed to be a pure rotation.
+    ///
+    pub fn from_matrix(m: &Matrix) -> Quaternion {
+        // Matrix indexing is (column, row)
+        let at = |r: usize, c: usize| m[(c, r)];
+        let trace = at(0, 0) + at(1, 1) + at(2, 2);
+
+        // pick the largest of the diagonal terms to divide by, for accuracy
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(
+                0.25 * s,
+                (at(2, 1) - at(1, 2)) / s,
+                (at(0, 2) - at(2, 0)) / s,
+                (at(1, 0) - at(0, 1)) / s,
+            )
+        } else if at(0, 0) > at(1, 1) && at(0, 0) > at(2, 2) {
+            let s = (1.0 + at(0, 0) - at(1, 1) - at(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (at(2, 1) - at(1, 2)) / s,
+                0.25 * s,
+                (at(0, 1) + at(1, 0)) / s,
+                (at(0, 2) + at(2, 0)) / s,
+            )
+        } else if at(1, 1) > at(2, 2) {
+            let s = (1.0 + at(1, 1) - at(0, 0) - at(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (at(0, 2) - at(2, 0)) / s,
+                (at(0, 1) + at(1, 0)) / s,
+                0.25 * s,
+                (at(1, 2) + at(2, 1)) / s,
+            )
+        } else {
+            let s = (1.0 + at(2, 2) - at(0, 0) - at(1, 1)).sqrt() * 2.0;
+            Quaternion::new(
+                (at(1, 0) - at(0, 1)) / s,
+                (at(0, 2) + at(2, 0)) / s,
+                (at(1, 2) + at(2, 1)) / s,
+                0.25 * s,
+            )
+        };
+
+        q.normalize()
+    }
+
+    ///
+    /// The rotation matrix equivalent to this quaternion, which must be a
+    /// unit quaternion.
+    ///
+    pub fn to_matrix(&self) -> Matrix {
+        let Quaternion { w, x, y, z } = *self;
+        Matrix::new([
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+            0.0,
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+            0.0,
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ])
+    }
+
+    pub fn dot(&self, other: Quaternion) -> Float {
+        (self.w * other.w) + (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
+    }
+
+    pub fn length(&self) -> Float {
+        self.dot(*self).sqrt()
+    }
+
+    /// Scales the quaternion such that |self| == 1.0
+    pub fn normalize(&self) -> Quaternion {
+        let inv_len = 1.0 / self.length();
+        Quaternion::new(
+            self.w * inv_len,
+            self.x * inv_len,
+            self.y * inv_len,
+            self.z * inv_len,
+        )
+    }
+
+    /// For a unit quaternion, the conjugate is the opposite rotation
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotates `v` by this (unit) quaternion
+    pub fn rotate(&self, v: Vector3) -> Vector3 {
+        let p = Quaternion::new(0.0, v.x, v.y, v.z);
+        let Quaternion { x, y, z, .. } = *self * p * self.conjugate();
+        Vector3::new(x, y, z)
+    }
+
+    ///
+    /// Spherical linear interpolation between two rotations, where `t` = 0
+    /// gives `self` and `t` = 1 gives `other`. Always takes the shortest path
+    /// between the two.
+    ///
+    pub fn slerp(&self, other: Quaternion, t: Float) -> Quaternion {
+        let mut other = other;
+        let mut cos = self.dot(other);
+
+        // q and -q are the same rotation; pick whichever is closer
+        if cos < 0.0 {
+            other = -other;
+            cos = -cos;
+        }
+
+        // for very close rotations, fall back to linear interpolation to
+        // avoid dividing by sin(θ) ≈ 0
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        Quaternion::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalize()
+    }
+
+    ///
+    /// Tests that the quaternion is approximately equal to another
+    ///
+    pub fn approx_eq(&self, other: Quaternion) -> bool {
+        (self.w - other.w).abs() < APPROX_EPSILON
+            && (self.x - other.x).abs() < APPROX_EPSILON
+            && (self.y - other.y).abs() < APPROX_EPSILON
+            && (self.z - other.z).abs() < APPROX_EPSILON
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Quaternion {
+        Quaternion::identity()
+    }
+}
+
+impl fmt::Debug for Quaternion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}i, {}j, {}k)", self.w, self.x, self.y, self.z)
+    }
+}
+
+impl ops::Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
+
+///
+/// The Hamilton product. `a * b` is the rotation `b` followed by `a`.
+///
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        let (a, b) = (self, rhs);
+        Quaternion::new(
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{degrees, vector, x_rotation_matrix, y_rotation_matrix, z_rotation_matrix};
+
+    fn matrix_approx_eq(a: Matrix, b: Matrix) -> bool {
+        (0..4).all(|i| (0..4).all(|j| (a[(i, j)] - b[(i, j)]).abs() < APPROX_EPSILON))
+    }
+
+    #[test]
+    fn axis_angle_matches_rotation_matrices() {
+        let angle = degrees(30.0).radians();
+        let cases = [
+            (vector(1.0, 0.0, 0.0), x_rotation_matrix(angle)),
+            (vector(0.0, 2.0, 0.0), y_rotation_matrix(angle)),
+            (vector(0.0, 0.0, 1.0), z_rotation_matrix(angle)),
+        ];
+
+        for &(axis, expected) in cases.iter() {
+            let m = Quaternion::from_axis_angle(axis, angle).to_matrix();
+            assert!(
+                matrix_approx_eq(m, expected),
+                "Axis {:?}: expected {:?}, got {:?}",
+                axis,
+                expected,
+                m
+            );
+        }
+    }
+
+    #[test]
+    fn rotate_vector() {
+        let q = Quaternion::from_axis_angle(vector(0.0, 0.0, 1.0), degrees(90.0).radians());
+        let v = q.rotate(vector(1.0, 0.0, 0.0));
+        assert!(v.approx_eq(vector(0.0, 1.0, 0.0)), "got {:?}", v);
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        let axes = [
+            vector(1.0, 2.0, 3.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(0.0, 1.0, -1.0),
+        ];
+        let angles = [10.0, 90.0, 179.0, 270.0];
+
+        for &axis in axes.iter() {
+            for &angle in angles.iter() {
+                let q = Quaternion::from_axis_angle(axis, degrees(angle).radians());
+                let back = Quaternion::from_matrix(&q.to_matrix());
+
+                // q and -q are the same rotation
+                assert!(
+                    back.approx_eq(q) || back.approx_eq(-q),
+                    "Expected {:?}, got {:?}",
+                    q,
+                    back
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn composition() {
+        let a = Quaternion::from_axis_angle(vector(1.0, 0.0, 0.0), degrees(90.0).radians());
+        let b = Quaternion::from_axis_angle(vector(0.0, 1.0, 0.0), degrees(90.0).radians());
+
+        let v = vector(0.0, 0.0, 1.0);
+        assert!((b * a).rotate(v).approx_eq(b.rotate(a.rotate(v))));
+    }
+
+    #[test]
+    fn slerp() {
+        let axis = vector(0.0, 1.0, 0.0);
+        let a = Quaternion::from_axis_angle(axis, degrees(0.0).radians());
+        let b = Quaternion::from_axis_angle(axis, degrees(90.0).radians());
+        let half = Quaternion::from_axis_angle(axis, degrees(45.0).radians());
+
+        assert!(a.slerp(b, 0.0).approx_eq(a));
+        assert!(a.slerp(b, 1.0).approx_eq(b));
+        assert!(a.slerp(b, 0.5).approx_eq(half));
+
+        // takes the short way round, even if the signs disagree
+        assert!(a.slerp(-b, 0.5).approx_eq(half));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Matrix, Point, Vector};
+use crate::{Float, Matrix, Normal3, Point3, Vector3};
 
 ///
 /// Represents a ray through the scene, starting at `src` and heading along
@@ -6,14 +6,14 @@ use crate::{Matrix, Point, Vector};
 ///
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Ray {
-    pub src: Point,
-    pub dir: Vector,
+    pub src: Point3,
+    pub dir: Vector3,
 }
 
 impl Ray {
     /// Initialises a new `Ray` instance, normalising the supplied vector
     /// during construction.
-    pub fn new(src: Point, dir: Vector) -> Ray {
+    pub fn new(src: Point3, dir: Vector3) -> Ray {
         Ray {
             src: src,
             dir: dir.normalize(),
@@ -21,14 +21,14 @@ impl Ray {
     }
 
     /// Calculates the point `len` units along the ray
-    pub fn extend(&self, len: f64) -> Point {
+    pub fn extend(&self, len: Float) -> Point3 {
         self.src + (self.dir * len)
     }
 
     /// Reflect the ray vector through the surface normal.
     /// http://www.3dkingdoms.com/weekly/weekly.php?a=2
-    pub fn reflect(&self, normal: Vector, surface: Point) -> Ray {
-        let dir = (-2.0 * self.dir.dot(normal) * normal) + self.dir;
+    pub fn reflect(&self, normal: Normal3, surface: Point3) -> Ray {
+        let dir = (-2.0 * normal.dot(self.dir) * normal) + self.dir;
         Ray {
             src: surface,
             dir: dir.normalize(),
@@ -37,11 +37,47 @@ impl Ray {
 
     pub fn transform(&self, t: &Matrix) -> Ray {
         let s = t * self.src;
-        let d = self.dir.transform(t).normalize();
+        let d = (t * self.dir).normalize();
         Ray::new(s, d)
     }
 }
 
+///
+/// Spawns a ray leaving a surface at `pt`, heading along `dir`. The origin is
+/// pushed along the surface normal `n` (which must be normalised) by just
+/// enough to escape `error`, the bound on the error in `pt`'s coordinates,
+/// and onto the side of the surface the ray is heading towards. This keeps
+/// the ray from re-intersecting the surface it started on, no matter the
+/// scale of the scene.
+///
+pub fn spawn_ray(pt: Point3, error: Vector3, n: Normal3, dir: Vector3) -> Ray {
+    let n = Vector3::from(n);
+    let d = n.abs().dot(error);
+    let offset = if dir.dot(n) < 0.0 { n * -d } else { n * d };
+    let src = pt + offset;
+
+    // the addition above may have rounded back towards the surface, so step
+    // one more representable value away from it
+    let away = |x: Float, o: Float| {
+        if o > 0.0 {
+            x.next_up()
+        } else if o < 0.0 {
+            x.next_down()
+        } else {
+            x
+        }
+    };
+
+    Ray::new(
+        Point3::new(
+            away(src.x, offset.x),
+            away(src.y, offset.y),
+            away(src.z, offset.z),
+        ),
+        dir,
+    )
+}
+
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -69,7 +105,7 @@ mod test {
             dir: vector(0.0, -1.0, 1.0).normalize(),
         };
 
-        let outbound = inbound.reflect(vector(0.0, 1.0, 0.0), origin);
+        let outbound = inbound.reflect(normal(0.0, 1.0, 0.0), origin);
 
         assert_eq!(outbound.src, origin);
         let expected = vector(0.0, 1.0, 1.0).normalize();
@@ -79,7 +115,7 @@ mod test {
     #[test]
     fn translation() {
         let m = IDENTITY * translation_matrix(0.0, 1.0, 0.0);
-        let r = Ray::new(point(0.0, 0.0, -1.0), point(0.0, 0.0, 1.0));
+        let r = Ray::new(point(0.0, 0.0, -1.0), vector(0.0, 0.0, 1.0));
         let rt = r.transform(&m);
 
         let expected_src = point(0.0, 1.0, -1.0);
@@ -98,4 +134,19 @@ mod test {
             rt.dir
         );
     }
+
+    #[test]
+    fn spawned_rays_leave_the_surface() {
+        let pt = point(1.0, 0.0, 0.0);
+        let error = vector(1e-6, 1e-6, 1e-6);
+        let n = normal(1.0, 0.0, 0.0);
+
+        let out = spawn_ray(pt, error, n, vector(1.0, 1.0, 0.0));
+        assert!(out.src.x > pt.x + 1e-6, "{:?}", out.src);
+        assert_eq!((out.src.y, out.src.z), (0.0, 0.0));
+
+        // rays heading into the surface start on the other side
+        let into = spawn_ray(pt, error, n, vector(-1.0, 1.0, 0.0));
+        assert!(into.src.x < pt.x - 1e-6, "{:?}", into.src);
+    }
 }
@@ -0,0 +1,407 @@
+//! Finding the real roots of the polynomials that come out of intersecting
+//! rays with implicit surfaces.
+
+use std::ops;
+
+use super::{consts::PI, Float};
+
+///
+/// The real roots of a polynomial of degree four or less, in ascending
+/// order. Repeated roots may appear more than once.
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Roots {
+    values: [Float; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn push(&mut self, x: Float) {
+        self.values[self.len] = x;
+        self.len += 1;
+    }
+
+    fn sorted(mut self) -> Roots {
+        let n = self.len;
+        self.values[..n].sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        self
+    }
+}
+
+impl ops::Deref for Roots {
+    type Target = [Float];
+
+    fn deref(&self) -> &[Float] {
+        &self.values[..self.len]
+    }
+}
+
+///
+/// Finds the real roots of `ax² + bx + c = 0`, in ascending order. A repeated
+/// root is returned twice. Returns `None` if there are no real roots, or if
+/// the equation is degenerate (i.e. `a` is zero).
+///
+pub fn solve_quadratic(a: Float, b: Float, c: Float) -> Option<(Float, Float)> {
+    if a == 0.0 {
+        return None;
+    }
+
+    let discriminant = (b * b) - (4.0 * a * c);
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // avoid the catastrophic cancellation in the textbook formula by never
+    // subtracting two numbers of the same sign
+    let q = if b < 0.0 {
+        -0.5 * (b - discriminant.sqrt())
+    } else {
+        -0.5 * (b + discriminant.sqrt())
+    };
+
+    if q == 0.0 {
+        // b and c are both zero
+        return Some((0.0, 0.0));
+    }
+
+    Some(super::sort(q / a, c / q))
+}
+
+///
+/// Finds the real roots of `ax³ + bx² + cx + d = 0`, falling back to the
+/// quadratic if `a` is zero.
+///
+pub fn solve_cubic(a: Float, b: Float, c: Float, d: Float) -> Roots {
+    let mut roots = Roots::default();
+    if a == 0.0 {
+        if let Some((x0, x1)) = solve_quadratic(b, c, d) {
+            roots.push(x0);
+            roots.push(x1);
+        } else if b == 0.0 && c != 0.0 {
+            roots.push(-d / c);
+        }
+        return roots;
+    }
+
+    // normalise to x³ + ax² + bx + c, and use the trigonometric solution
+    // when there are three real roots and Cardano's otherwise
+    let (a, b, c) = (b / a, c / a, d / a);
+    let q = ((a * a) - (3.0 * b)) / 9.0;
+    let r = ((2.0 * a * a * a) - (9.0 * a * b) + (27.0 * c)) / 54.0;
+    let q3 = q * q * q;
+
+    if r * r < q3 {
+        let theta = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+        let m = -2.0 * q.sqrt();
+        roots.push((m * (theta / 3.0).cos()) - (a / 3.0));
+        roots.push((m * ((theta + 2.0 * PI) / 3.0).cos()) - (a / 3.0));
+        roots.push((m * ((theta - 2.0 * PI) / 3.0).cos()) - (a / 3.0));
+    } else {
+        let big_a = -r.signum() * (r.abs() + ((r * r) - q3).sqrt()).cbrt();
+        let big_b = if big_a == 0.0 { 0.0 } else { q / big_a };
+        roots.push((big_a + big_b) - (a / 3.0));
+    }
+
+    roots.sorted()
+}
+
+///
+/// Finds the real roots of `ax⁴ + bx³ + cx² + dx + e = 0`, falling back to the
+/// cubic if `a` is zero.
+///
+/// The roots are found with Ferrari's method, which on its own can lose a
+/// lot of precision when the coefficients vary wildly in scale, as they do
+/// for rays starting far from a surface. Each root is therefore polished
+/// with a few rounds of Newton's method against the original polynomial.
+///
+pub fn solve_quartic(a: Float, b: Float, c: Float, d: Float, e: Float) -> Roots {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // normalise to x⁴ + ax³ + bx² + cx + d, then substitute x = y - a/4 to
+    // remove the cubic term, leaving y⁴ + py² + qy + r
+    let (a, b, c, d) = (b / a, c / a, d / a, e / a);
+    let a2 = a * a;
+    let p = b - (3.0 * a2 / 8.0);
+    let q = c - (a * b / 2.0) + (a2 * a / 8.0);
+    let r = d - (a * c / 4.0) + (a2 * b / 16.0) - (3.0 * a2 * a2 / 256.0);
+
+    // the resolvent cubic always has a positive root m when q != 0, which
+    // splits the quartic into two quadratics
+    let scale = 1.0 + p.abs() + r.abs();
+    let m = if q.abs() < Float::EPSILON * scale {
+        0.0
+    } else {
+        let resolvent = solve_cubic(1.0, p, (p * p / 4.0) - r, -q * q / 8.0);
+        resolvent.iter().cloned().fold(0.0, Float::max)
+    };
+
+    let mut ys = Roots::default();
+    if m <= Float::EPSILON * scale {
+        // q is negligible, and dividing by a vanishing m would throw the
+        // roots away, so treat it as biquadratic and solve for y². The
+        // polishing below takes care of the q we ignored.
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            for &z in [z0, z1].iter().filter(|&&z| z >= 0.0) {
+                ys.push(z.sqrt());
+                ys.push(-z.sqrt());
+            }
+        }
+    } else {
+        let s = (2.0 * m).sqrt();
+        let h = q / (2.0 * s);
+        for &(sign, shift) in [(-1.0, h), (1.0, -h)].iter() {
+            if let Some((y0, y1)) = solve_quadratic(1.0, sign * s, (p / 2.0) + m + shift) {
+                ys.push(y0);
+                ys.push(y1);
+            }
+        }
+    }
+
+    let f = |x: Float| (((x + a) * x + b) * x + c) * x + d;
+    let df = |x: Float| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+
+    let mut roots = Roots::default();
+    for &y in ys.iter() {
+        let mut x = y - (a / 4.0);
+        for _ in 0..4 {
+            let slope = df(x);
+            if slope == 0.0 {
+                break;
+            }
+            let next = x - f(x) / slope;
+            if !next.is_finite() || f(next).abs() >= f(x).abs() {
+                break;
+            }
+            x = next;
+        }
+        roots.push(x);
+    }
+
+    roots.sorted()
+}
+
+///
+/// Finds the real roots of a polynomial of any degree that lie between
+/// `lower` and `upper`, in ascending order. The coefficients are given
+/// lowest power first, so `[c, b, a]` is `ax² + bx + c`.
+///
+/// The roots of the derivative split the range into pieces over which the
+/// polynomial is monotonic, each holding at most one root, which is then
+/// found by Newton's method safeguarded by bisection. Only roots where the
+/// polynomial changes sign are found, so a repeated root of even order is
+/// missed unless it lands exactly on zero.
+///
+pub fn solve_polynomial(coefficients: &[Float], lower: Float, upper: Float) -> Vec<Float> {
+    let degree = match coefficients.iter().rposition(|&c| c != 0.0) {
+        Some(n) if n > 0 => n,
+        _ => return Vec::new(),
+    };
+    let coefficients = &coefficients[..=degree];
+
+    let f = |x: Float| coefficients.iter().rev().fold(0.0, |acc, &c| (acc * x) + c);
+    let derivative: Vec<Float> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(n, &c)| c * n as Float)
+        .collect();
+    let df = |x: Float| derivative.iter().rev().fold(0.0, |acc, &c| (acc * x) + c);
+
+    let mut bounds = vec![lower];
+    if degree > 1 {
+        bounds.extend(solve_polynomial(&derivative, lower, upper));
+    }
+    bounds.push(upper);
+
+    let mut roots: Vec<Float> = Vec::new();
+    for w in bounds.windows(2) {
+        let (mut lo, mut hi) = (w[0], w[1]);
+        let (f_lo, f_hi) = (f(lo), f(hi));
+        if f_lo == 0.0 {
+            if roots.last() != Some(&lo) {
+                roots.push(lo);
+            }
+            continue;
+        }
+        if f_hi == 0.0 {
+            roots.push(hi);
+            continue;
+        }
+        if (f_lo < 0.0) == (f_hi < 0.0) {
+            continue;
+        }
+
+        // keep the root bracketed, with `lo` always on the same side of it
+        // as the original lower bound
+        let sign = f_lo.signum();
+        let mut x = 0.5 * (lo + hi);
+        for _ in 0..100 {
+            let fx = f(x) * sign;
+            if fx == 0.0 {
+                break;
+            }
+            if fx > 0.0 {
+                lo = x;
+            } else {
+                hi = x;
+            }
+            if (hi - lo).abs() <= Float::EPSILON * (lo.abs() + hi.abs()) {
+                break;
+            }
+
+            let newton = x - (f(x) / df(x));
+            x = if newton > lo.min(hi) && newton < lo.max(hi) {
+                newton
+            } else {
+                0.5 * (lo + hi)
+            };
+        }
+        roots.push(x);
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::APPROX_EPSILON;
+
+    fn assert_roots(actual: Roots, expected: &[Float]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(
+                (a - e).abs() < APPROX_EPSILON * (1.0 + e.abs()),
+                "Expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn quadratic_roots() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), Some((1.0, 2.0)));
+        assert_eq!(solve_quadratic(2.0, 0.0, -8.0), Some((-2.0, 2.0)));
+        assert_eq!(solve_quadratic(1.0, 2.0, 1.0), Some((-1.0, -1.0)));
+        assert_eq!(solve_quadratic(1.0, 0.0, 0.0), Some((0.0, 0.0)));
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+        assert_eq!(solve_quadratic(0.0, 1.0, 1.0), None);
+    }
+
+    #[test]
+    fn quadratic_roots_are_accurate_when_b_dominates() {
+        // the roots are roughly -1e-6 and -1e6; the textbook formula loses
+        // nearly all the precision of the small one
+        let (r1, r2) = solve_quadratic(1.0, 1e6, 1.0).unwrap();
+        assert!((r1 + 1e6).abs() / 1e6 < APPROX_EPSILON);
+        assert!((r2 + 1e-6).abs() / 1e-6 < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn cubic_roots() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+
+        // (x - 2)(x² + 1)
+        assert_roots(solve_cubic(2.0, -4.0, 2.0, -4.0), &[2.0]);
+
+        // degenerate
+        assert_roots(solve_cubic(0.0, 1.0, -3.0, 2.0), &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+
+        // (x² - 1)(x² - 4), which is biquadratic
+        assert_roots(
+            solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0),
+            &[-2.0, -1.0, 1.0, 2.0],
+        );
+
+        // (x - 1)(x + 2)(x² + 1)
+        assert_roots(solve_quartic(3.0, 3.0, -3.0, 3.0, -6.0), &[-2.0, 1.0]);
+
+        // no real roots at all
+        assert_roots(solve_quartic(1.0, 0.0, 2.0, 0.0, 1.0), &[]);
+    }
+
+    #[test]
+    fn quartic_roots_with_wide_ranging_coefficients() {
+        // (x - 0.001)(x - 1)(x - 100)(x - 1000), as from a ray starting far
+        // from a surface
+        let roots = [0.001, 1.0, 100.0, 1000.0];
+        let (a, b, c, d) = (roots[0], roots[1], roots[2], roots[3]);
+        let actual = solve_quartic(
+            1.0,
+            -(a + b + c + d),
+            (a * b) + (a * c) + (a * d) + (b * c) + (b * d) + (c * d),
+            -((a * b * c) + (a * b * d) + (a * c * d) + (b * c * d)),
+            a * b * c * d,
+        );
+        assert_roots(actual, &roots);
+    }
+
+    #[test]
+    // the coefficients are as captured in double precision, where rounding
+    // is what triggers the problem
+    #[allow(clippy::excessive_precision)]
+    fn nearly_biquadratic_quartics() {
+        // the density along a ray grazing a metaball, less the threshold.
+        // Rounding leaves a tiny cubic term after the substitution, whose
+        // resolvent root is too small to split the quartic with.
+        let (a, b, c, d, e) = (
+            0.0625,
+            -0.19685019685029603,
+            0.1550000000000018,
+            -8.881784197001252e-16,
+            -0.25,
+        );
+        let roots = solve_quartic(a, b, c, d, e);
+        assert_eq!(roots.len(), 2, "{:?}", roots);
+        for &x in roots.iter() {
+            let f = (((a * x + b) * x + c) * x + d) * x + e;
+            assert!(f.abs() < APPROX_EPSILON, "f({}) = {}", x, f);
+        }
+    }
+
+    #[test]
+    fn polynomial_roots_in_a_range() {
+        // (x - 0.1)(x - 0.2)(x - 0.5)(x - 0.7)(x - 0.9)(x - 2), expanded
+        let roots: [Float; 6] = [0.1, 0.2, 0.5, 0.7, 0.9, 2.0];
+        let mut coefficients = vec![1.0];
+        for &r in roots.iter() {
+            let mut next = vec![0.0; coefficients.len() + 1];
+            for (n, &c) in coefficients.iter().enumerate() {
+                next[n] -= c * r;
+                next[n + 1] += c;
+            }
+            coefficients = next;
+        }
+
+        let found = solve_polynomial(&coefficients, 0.0, 1.0);
+        assert_eq!(found.len(), 5, "{:?}", found);
+        for (a, e) in found.iter().zip(roots.iter()) {
+            assert!((a - e).abs() < APPROX_EPSILON, "{:?}", found);
+        }
+    }
+
+    #[test]
+    fn polynomial_roots_of_low_degree() {
+        assert_eq!(solve_polynomial(&[-1.0, 2.0], 0.0, 1.0), vec![0.5]);
+        assert_eq!(solve_polynomial(&[1.0, 0.0, 1.0], -10.0, 10.0), vec![]);
+        assert_eq!(solve_polynomial(&[1.0, 0.0, 0.0], -10.0, 10.0), vec![]);
+
+        // trailing zeroes don't count towards the degree
+        let found = solve_polynomial(&[-4.0, 0.0, 1.0, 0.0], 0.0, 10.0);
+        assert_eq!(found.len(), 1);
+        assert!((found[0] - 2.0).abs() < APPROX_EPSILON);
+    }
+}
@@ -1,4 +1,6 @@
-use super::{translation_matrix, Matrix};
+use super::{
+    translation_matrix, Float, Matrix, Normal3, Point3, Quaternion, Vector3, APPROX_EPSILON,
+};
 
 use crate::units::{Angle, Radians};
 
@@ -17,7 +19,15 @@ impl Transform {
         Transform::default()
     }
 
-    pub fn for_translation(x: f64, y: f64, z: f64) -> Transform {
+    ///
+    /// Creates a transform from an arbitrary matrix, or `None` if the matrix
+    /// can't be inverted.
+    ///
+    pub fn from_matrix(m: Matrix) -> Option<Transform> {
+        m.inverse().map(|inverse| Transform { matrix: m, inverse })
+    }
+
+    pub fn for_translation(x: Float, y: Float, z: Float) -> Transform {
         let fwd = translation_matrix(x, y, z);
         let rev = translation_matrix(-x, -y, -z);
         Transform {
@@ -26,7 +36,7 @@ impl Transform {
         }
     }
 
-    pub fn translate(&self, x: f64, y: f64, z: f64) -> Transform {
+    pub fn translate(&self, x: Float, y: Float, z: Float) -> Transform {
         self.apply(&Transform::for_translation(x, y, z))
     }
 
@@ -60,7 +70,35 @@ impl Transform {
         self.apply(&Transform::for_rotation(x, y, z))
     }
 
-    pub fn for_scale(x: f64, y: f64, z: f64) -> Transform {
+    ///
+    /// The rotation described by a quaternion, which is normalised first
+    ///
+    pub fn for_quaternion(q: Quaternion) -> Transform {
+        let fwd = q.normalize().to_matrix();
+
+        // rotations are orthogonal, so the inverse is just the transpose
+        Transform {
+            matrix: fwd,
+            inverse: fwd.transpose(),
+        }
+    }
+
+    pub fn rotate_quaternion(&self, q: Quaternion) -> Transform {
+        self.apply(&Transform::for_quaternion(q))
+    }
+
+    ///
+    /// A rotation of `angle` about an arbitrary `axis`, which must not be zero
+    ///
+    pub fn for_axis_rotation(axis: Vector3, angle: Angle<Radians>) -> Transform {
+        Transform::for_quaternion(Quaternion::from_axis_angle(axis, angle))
+    }
+
+    pub fn rotate_axis(&self, axis: Vector3, angle: Angle<Radians>) -> Transform {
+        self.apply(&Transform::for_axis_rotation(axis, angle))
+    }
+
+    pub fn for_scale(x: Float, y: Float, z: Float) -> Transform {
         let fwd = super::scaling_matrix(x, y, z);
         let rev = super::scaling_matrix(1.0 / x, 1.0 / y, 1.0 / z);
         Transform {
@@ -69,10 +107,142 @@ impl Transform {
         }
     }
 
-    pub fn scale(&self, x: f64, y: f64, z: f64) -> Transform {
+    pub fn scale(&self, x: Float, y: Float, z: Float) -> Transform {
         self.apply(&Transform::for_scale(x, y, z))
     }
 
+    ///
+    /// A shear transform (see `shear_matrix`), or `None` if the shear
+    /// collapses space onto a plane and so can't be undone.
+    ///
+    pub fn for_shear(
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Option<Transform> {
+        Transform::from_matrix(super::shear_matrix(xy, xz, yx, yz, zx, zy))
+    }
+
+    pub fn shear(
+        &self,
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Option<Transform> {
+        Transform::for_shear(xy, xz, yx, yz, zx, zy).map(|t| self.apply(&t))
+    }
+
+    ///
+    /// A transform that moves the origin to `from` and turns the +z axis to
+    /// point at `to`, keeping the +y axis as close to `up` as possible.
+    /// Returns `None` if `from` and `to` coincide, or if `up` is parallel to
+    /// the line between them.
+    ///
+    pub fn look_at(from: Point3, to: Point3, up: Vector3) -> Option<Transform> {
+        let dir = to - from;
+        let right = up.cross(dir);
+        if dir.length() < EPSILON || right.length() < EPSILON {
+            return None;
+        }
+
+        let dir = dir.normalize();
+        let right = right.normalize();
+        let up = dir.cross(right);
+
+        Transform::from_matrix(Matrix::new([
+            right.x, up.x, dir.x, from.x, right.y, up.y, dir.y, from.y, right.z, up.z, dir.z,
+            from.z, 0.0, 0.0, 0.0, 1.0,
+        ]))
+    }
+
+    ///
+    /// The rotation that turns the direction `from` onto the direction `to`,
+    /// by the smallest possible angle. Returns `None` if either vector has no
+    /// length.
+    ///
+    pub fn align_to_vector(from: Vector3, to: Vector3) -> Option<Transform> {
+        if from.length() < EPSILON || to.length() < EPSILON {
+            return None;
+        }
+
+        let a = from.normalize();
+        let b = to.normalize();
+        let cos = a.dot(b);
+
+        let rotation = if cos < -1.0 + EPSILON {
+            // the vectors are opposed, so any axis perpendicular to them will
+            // do for a half turn
+            let other = if a.x.abs() < 0.9 {
+                Vector3::new(1.0, 0.0, 0.0)
+            } else {
+                Vector3::new(0.0, 1.0, 0.0)
+            };
+            let p = a.cross(other).normalize();
+            Matrix::new([
+                2.0 * p.x * p.x - 1.0,
+                2.0 * p.x * p.y,
+                2.0 * p.x * p.z,
+                0.0,
+                2.0 * p.y * p.x,
+                2.0 * p.y * p.y - 1.0,
+                2.0 * p.y * p.z,
+                0.0,
+                2.0 * p.z * p.x,
+                2.0 * p.z * p.y,
+                2.0 * p.z * p.z - 1.0,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ])
+        } else {
+            // Rodrigues' rotation formula, with the axis scaled by sin θ
+            let v = a.cross(b);
+            let k = 1.0 / (1.0 + cos);
+            Matrix::new([
+                cos + v.x * v.x * k,
+                v.x * v.y * k - v.z,
+                v.x * v.z * k + v.y,
+                0.0,
+                v.y * v.x * k + v.z,
+                cos + v.y * v.y * k,
+                v.y * v.z * k - v.x,
+                0.0,
+                v.z * v.x * k - v.y,
+                v.z * v.y * k + v.x,
+                cos + v.z * v.z * k,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ])
+        };
+
+        // rotations are orthogonal, so the inverse is just the transpose
+        Some(Transform {
+            matrix: rotation,
+            inverse: rotation.transpose(),
+        })
+    }
+
+    ///
+    /// Transforms a surface normal from object space into world space.
+    /// Normals have to stay perpendicular to the transformed surface, so they
+    /// are multiplied by the inverse transpose of the transform matrix rather
+    /// than the matrix itself. The result is renormalised.
+    ///
+    pub fn normal_to_world(&self, n: Normal3) -> Normal3 {
+        n.transform(&self.inverse.transpose()).normalize()
+    }
+
     pub fn apply(&self, other: &Transform) -> Transform {
         let fwd = self.matrix * other.matrix;
         let rev = other.inverse * self.inverse;
@@ -83,6 +253,8 @@ impl Transform {
     }
 }
 
+const EPSILON: Float = APPROX_EPSILON;
+
 impl Default for Transform {
     fn default() -> Transform {
         Transform {
@@ -178,6 +350,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn axis_rotation() {
+        let t = Transform::default().rotate_axis(vector(1.0, 1.0, 0.0), degrees(180.0).radians());
+        let p = point(1.0, 0.0, 0.0);
+        let new_p = t.matrix * p;
+
+        assert!(new_p.approx_eq(point(0.0, 1.0, 0.0)), "got {:?}", new_p);
+        assert!((t.inverse * new_p).approx_eq(p));
+
+        // a rotation about a major axis is the same as the Euler equivalent
+        let euler = Transform::default().rotate(
+            degrees(0.0).radians(),
+            degrees(35.0).radians(),
+            degrees(0.0).radians(),
+        );
+        let axis = Transform::default().rotate_axis(vector(0.0, 1.0, 0.0), degrees(35.0).radians());
+        let p = point(1.0, 2.0, 3.0);
+        assert!((euler.matrix * p).approx_eq(axis.matrix * p));
+    }
+
     #[test]
     fn scale() {
         let test_cases = [
@@ -217,6 +409,100 @@ mod test {
         }
     }
 
+    #[test]
+    fn from_matrix() {
+        let m = scaling_matrix(2.0, 2.0, 2.0) * translation_matrix(1.0, 2.0, 3.0);
+        let t = Transform::from_matrix(m).unwrap();
+        let p = point(1.0, 1.0, 1.0);
+
+        assert!((t.matrix * p).approx_eq(point(3.0, 4.0, 5.0)));
+        assert!((t.inverse * (t.matrix * p)).approx_eq(p));
+        assert_eq!(Transform::from_matrix(scaling_matrix(1.0, 1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn shear() {
+        let t = Transform::default()
+            .shear(2.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+            .unwrap();
+        let p = point(1.0, 1.0, 1.0);
+        let new_p = t.matrix * p;
+
+        assert!(new_p.approx_eq(point(3.0, 1.0, 1.0)), "got {:?}", new_p);
+        assert!((t.inverse * new_p).approx_eq(p));
+    }
+
+    #[test]
+    fn look_at() {
+        let from = point(1.0, 2.0, 3.0);
+        let to = point(1.0, 2.0, 13.0);
+        let t = Transform::look_at(from, to, vector(0.0, 1.0, 0.0)).unwrap();
+
+        assert!((t.matrix * point(0.0, 0.0, 0.0)).approx_eq(from));
+        assert!((t.matrix * point(0.0, 0.0, 10.0)).approx_eq(to));
+        assert!((t.matrix * vector(0.0, 1.0, 0.0)).approx_eq(vector(0.0, 1.0, 0.0)));
+
+        let t = Transform::look_at(from, point(11.0, 2.0, 3.0), vector(0.0, 1.0, 0.0)).unwrap();
+        assert!((t.matrix * vector(0.0, 0.0, 1.0)).approx_eq(vector(1.0, 0.0, 0.0)));
+        assert!((t.inverse * point(11.0, 2.0, 3.0)).approx_eq(point(0.0, 0.0, 10.0)));
+
+        assert_eq!(Transform::look_at(from, from, vector(0.0, 1.0, 0.0)), None);
+        assert_eq!(Transform::look_at(from, to, vector(0.0, 0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn align_to_vector() {
+        let test_cases = [
+            (vector(0.0, 0.0, 1.0), vector(1.0, 0.0, 0.0)),
+            (vector(1.0, 2.0, 3.0), vector(-3.0, 0.5, 1.0)),
+            (vector(0.0, 1.0, 0.0), vector(0.0, 5.0, 0.0)),
+            (vector(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            (vector(1.0, 1.0, 0.0), vector(-1.0, -1.0, 0.0)),
+        ];
+
+        for &(from, to) in test_cases.iter() {
+            let t = Transform::align_to_vector(from, to).unwrap();
+            let aligned = t.matrix * from.normalize();
+            assert!(
+                aligned.approx_eq(to.normalize()),
+                "Aligning {:?} to {:?}, got {:?}",
+                from,
+                to,
+                aligned
+            );
+            assert!((t.inverse * aligned).approx_eq(from.normalize()));
+        }
+
+        assert_eq!(
+            Transform::align_to_vector(vector(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_scale() {
+        let t = Transform::default().scale(2.0, 1.0, 1.0);
+
+        // the surface of a plane x + y = 0 contains the direction (1, -1, 0)
+        let tangent = t.matrix * vector(1.0, -1.0, 0.0);
+        let n = t.normal_to_world(normal(1.0, 1.0, 0.0));
+
+        assert!(
+            n.dot(tangent).abs() < APPROX_EPSILON,
+            "{:?} . {:?} != 0",
+            n,
+            tangent
+        );
+        assert!((n.length() - 1.0).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn normals_ignore_translation() {
+        let t = Transform::default().translate(5.0, 6.0, 7.0);
+        let n = t.normal_to_world(normal(0.0, 1.0, 0.0));
+        assert!(n.approx_eq(normal(0.0, 1.0, 0.0)), "got {:?}", n);
+    }
+
     #[test]
     fn stacked_transforms() {
         let p = point(1.0, 1.0, 1.0);
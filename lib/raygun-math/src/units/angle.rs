@@ -2,6 +2,8 @@ use std::cmp;
 use std::marker::PhantomData;
 use std::ops;
 
+use crate::Float;
+
 // ----------------------------------------------------------------------------
 // Unit tags
 // ----------------------------------------------------------------------------
@@ -17,16 +19,16 @@ pub enum Radians {}
 // ----------------------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug)]
-pub struct Angle<Unit>(pub f64, PhantomData<Unit>);
+pub struct Angle<Unit>(pub Float, PhantomData<Unit>);
 
 impl<Unit> Angle<Unit> {
-    pub fn new(x: f64) -> Angle<Unit> {
+    pub fn new(x: Float) -> Angle<Unit> {
         Angle(x, PhantomData)
     }
 }
 
 impl<Unit> Angle<Unit> {
-    pub fn get(&self) -> f64 {
+    pub fn get(&self) -> Float {
         self.0
     }
 }
@@ -39,13 +41,13 @@ impl Angle<Degrees> {
         Angle::new(self.get().to_radians())
     }
 
-    pub fn sin(self) -> f64 {
+    pub fn sin(self) -> Float {
         self.get().to_radians().sin()
     }
-    pub fn cos(self) -> f64 {
+    pub fn cos(self) -> Float {
         self.get().to_radians().cos()
     }
-    pub fn tan(self) -> f64 {
+    pub fn tan(self) -> Float {
         self.get().to_radians().tan()
     }
 }
@@ -58,13 +60,13 @@ impl Angle<Radians> {
         self
     }
 
-    pub fn sin(self) -> f64 {
+    pub fn sin(self) -> Float {
         self.get().sin()
     }
-    pub fn cos(self) -> f64 {
+    pub fn cos(self) -> Float {
         self.get().cos()
     }
-    pub fn tan(self) -> f64 {
+    pub fn tan(self) -> Float {
         self.get().tan()
     }
 }
@@ -96,7 +98,7 @@ macro_rules! angle_mul_impl {
 
             #[inline]
             fn mul(self, other: $t) -> Angle<U> {
-                Angle::new(self.get() * (other as f64))
+                Angle::new(self.get() * (other as Float))
             }
         }
 
@@ -105,7 +107,7 @@ macro_rules! angle_mul_impl {
 
             #[inline]
             fn mul(self, other: Angle<U>) -> Angle<U> {
-                Angle::new((self as f64) * other.get())
+                Angle::new((self as Float) * other.get())
             }
         }
     )*)
@@ -118,7 +120,7 @@ macro_rules! angle_div_impl {
 
             #[inline]
             fn div(self, other: $t) -> Angle<U> {
-                Angle::new(self.get() / (other as f64))
+                Angle::new(self.get() / (other as Float))
             }
         }
 
@@ -127,7 +129,7 @@ macro_rules! angle_div_impl {
 
             #[inline]
             fn div(self, other: Angle<U>) -> Angle<U> {
-                Angle::new((self as f64) / other.get())
+                Angle::new((self as Float) / other.get())
             }
         }
     )*)
@@ -149,11 +151,11 @@ impl<U> ops::Neg for Angle<U> {
 //
 // ----------------------------------------------------------------------------
 
-pub fn degrees(n: f64) -> Angle<Degrees> {
+pub fn degrees(n: Float) -> Angle<Degrees> {
     Angle::new(n)
 }
 
-pub fn radians(n: f64) -> Angle<Radians> {
+pub fn radians(n: Float) -> Angle<Radians> {
     Angle::new(n)
 }
 
@@ -164,115 +166,115 @@ pub fn radians(n: f64) -> Angle<Radians> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::f64::consts;
+    use crate::{consts, APPROX_EPSILON};
 
-    fn floats_are_close(a: f64, b: f64, epsilon: f64) -> bool {
+    fn floats_are_close(a: Float, b: Float, epsilon: Float) -> bool {
         (a - b).abs() < epsilon
     }
 
     #[test]
     fn mul_angle_by_scalar() {
         let a = degrees(2.0) * 8.0f64;
-        assert!(floats_are_close(a.get(), 16.0, 1e-10))
+        assert!(floats_are_close(a.get(), 16.0, APPROX_EPSILON))
     }
 
     #[test]
     fn mul_scalar_by_angle() {
         let a = 8.0f64 * degrees(2.0);
-        assert!(floats_are_close(a.get(), 16.0, 1e-10))
+        assert!(floats_are_close(a.get(), 16.0, APPROX_EPSILON))
     }
 
     #[test]
     fn construct_degrees() {
         let x: Angle<Degrees> = degrees(42.0);
-        assert!(floats_are_close(x.get(), 42f64, 1e-10))
+        assert!(floats_are_close(x.get(), 42.0, APPROX_EPSILON))
     }
 
     #[test]
     fn sin_of_degrees() {
         let x = degrees(0.0);
-        assert!(floats_are_close(x.sin(), 0.0, 1e-10));
+        assert!(floats_are_close(x.sin(), 0.0, APPROX_EPSILON));
 
         let y = degrees(90.0);
-        assert!(floats_are_close(y.sin(), 1.0, 1e-10));
+        assert!(floats_are_close(y.sin(), 1.0, APPROX_EPSILON));
 
         let z = degrees(45.0);
-        assert!(floats_are_close(z.sin(), 0.70710678118, 1e-10));
+        assert!(floats_are_close(z.sin(), 0.70710678118, APPROX_EPSILON));
     }
 
     #[test]
     fn cos_of_degrees() {
         let x = degrees(0.0);
-        assert!(floats_are_close(x.cos(), 1.0, 1e-10));
+        assert!(floats_are_close(x.cos(), 1.0, APPROX_EPSILON));
 
         let y = degrees(90.0);
-        assert!(floats_are_close(y.cos(), 0.0, 1e-10));
+        assert!(floats_are_close(y.cos(), 0.0, APPROX_EPSILON));
 
         let z = degrees(45.0);
-        assert!(floats_are_close(z.cos(), 0.70710678118, 1e-10));
+        assert!(floats_are_close(z.cos(), 0.70710678118, APPROX_EPSILON));
     }
 
     #[test]
     fn tan_of_degrees() {
         let x = degrees(0.0);
-        assert!(floats_are_close(x.tan(), 0f64, 1e-10));
+        assert!(floats_are_close(x.tan(), 0.0, APPROX_EPSILON));
 
         let y = degrees(90.0);
         let tan_y = y.tan();
         assert!(
-            tan_y.is_infinite() || tan_y > 1e16,
+            tan_y.is_infinite() || tan_y.abs() > 1.0 / Float::EPSILON,
             "Expected infinity (or at least a very large number), got {}",
             tan_y
         );
 
         let z = degrees(45.0);
-        assert!(floats_are_close(z.tan(), 1.0, 1e-10));
+        assert!(floats_are_close(z.tan(), 1.0, APPROX_EPSILON));
     }
 
     #[test]
     fn construct_radians() {
         let x: Angle<Radians> = radians(42.0);
-        assert!(floats_are_close(x.get(), 42f64, 1e-10))
+        assert!(floats_are_close(x.get(), 42.0, APPROX_EPSILON))
     }
 
     #[test]
     fn sin_of_radians() {
         let x = radians(0.0);
-        assert!(floats_are_close(x.sin(), 0.0, 1e-10));
+        assert!(floats_are_close(x.sin(), 0.0, APPROX_EPSILON));
 
         let y = radians(consts::PI / 2.0);
-        assert!(floats_are_close(y.sin(), 1.0, 1e-10));
+        assert!(floats_are_close(y.sin(), 1.0, APPROX_EPSILON));
 
         let z = radians(consts::PI / 4.0);
-        assert!(floats_are_close(z.sin(), 0.70710678118, 1e-10));
+        assert!(floats_are_close(z.sin(), 0.70710678118, APPROX_EPSILON));
     }
 
     #[test]
     fn cos_of_radians() {
         let x = radians(0.0);
-        assert!(floats_are_close(x.cos(), 1.0, 1e-10));
+        assert!(floats_are_close(x.cos(), 1.0, APPROX_EPSILON));
 
         let y = radians(consts::PI / 2.0);
-        assert!(floats_are_close(y.cos(), 0.0, 1e-10));
+        assert!(floats_are_close(y.cos(), 0.0, APPROX_EPSILON));
 
         let z = radians(consts::PI / 4.0);
-        assert!(floats_are_close(z.cos(), 0.70710678118, 1e-10));
+        assert!(floats_are_close(z.cos(), 0.70710678118, APPROX_EPSILON));
     }
 
     #[test]
     fn tan_of_radians() {
         let x = radians(0.0);
-        assert!(floats_are_close(x.tan(), 0f64, 1e-10));
+        assert!(floats_are_close(x.tan(), 0.0, APPROX_EPSILON));
 
         let y = radians(consts::PI / 2.0);
         let tan_y = y.tan();
         assert!(
-            tan_y.is_infinite() || tan_y > 1e16,
+            tan_y.is_infinite() || tan_y.abs() > 1.0 / Float::EPSILON,
             "Expected infinity (or at least a very large number), got {}",
             tan_y
         );
 
         let z = radians(consts::PI / 4.0);
-        assert!(floats_are_close(z.tan(), 1.0, 1e-10));
+        assert!(floats_are_close(z.tan(), 1.0, APPROX_EPSILON));
     }
 }
@@ -1,61 +1,51 @@
 use std::{cmp, fmt, ops};
 
-use super::Matrix;
+use super::{Float, Matrix, Point3, APPROX_EPSILON};
 
-/// Defines an immutable 3D vector.
+///
+/// Defines an immutable 3D vector, i.e. a direction and magnitude. Unlike a
+/// point, a vector is unaffected by translation.
+///
 #[derive(Default, Clone, Copy)]
-pub struct Vector {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+pub struct Vector3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
 }
 
 /// Vector construction function
-pub fn vector(x: f64, y: f64, z: f64) -> Vector {
-    Vector::new(x, y, z)
+pub fn vector(x: Float, y: Float, z: Float) -> Vector3 {
+    Vector3::new(x, y, z)
 }
 
-/// Alias of Vector for when you want to emphasize the unit-vector-ness
-/// of the vector. No actual normailsation is performed automatically.
-pub type UnitVector = Vector;
-
 /// Creates a unit vector
-pub fn unit_vector(x: f64, y: f64, z: f64) -> UnitVector {
+pub fn unit_vector(x: Float, y: Float, z: Float) -> Vector3 {
     vector(x, y, z).normalize()
 }
 
-const ZERO_VECTOR: Vector = Vector {
-    x: 0.0,
-    y: 0.0,
-    z: 0.0,
-};
-
-impl Vector {
-    pub fn new(x: f64, y: f64, z: f64) -> Vector {
-        Vector { x: x, y: y, z: z }
+impl Vector3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector3 {
+        Vector3 { x: x, y: y, z: z }
     }
 
     /// Creates a vector representing the direction from `src` to `dst`
-    pub fn between(src: Point, dst: Point) -> Vector {
-        let x = dst.x - src.x;
-        let y = dst.y - src.y;
-        let z = dst.z - src.z;
-        vector(x, y, z)
+    pub fn between(src: Point3, dst: Point3) -> Vector3 {
+        dst - src
     }
 
-    pub fn cross(&self, other: Vector) -> Vector {
+    pub fn cross(&self, other: Vector3) -> Vector3 {
         let x = (self.y * other.z) - (self.z * other.y);
         let y = (self.z * other.x) - (self.x * other.z);
         let z = (self.x * other.y) - (self.y * other.x);
         vector(x, y, z)
     }
 
-    pub fn dot(&self, other: Vector) -> f64 {
+    pub fn dot(&self, other: Vector3) -> Float {
         (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
     }
 
     /// Calculates the length of the vector
-    pub fn length(&self) -> f64 {
+    pub fn length(&self) -> Float {
         let x = self.x * self.x;
         let y = self.y * self.y;
         let z = self.z * self.z;
@@ -63,76 +53,95 @@ impl Vector {
     }
 
     /// Scales the vector such that |self| == 1.0
-    pub fn normalize(&self) -> Vector {
+    pub fn normalize(&self) -> Vector3 {
         let inv_len = 1.0 / self.length();
         vector(self.x * inv_len, self.y * inv_len, self.z * inv_len)
     }
 
+    /// The absolute value of each of the vector's components
+    pub fn abs(&self) -> Vector3 {
+        vector(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    ///
+    /// Finds a pair of unit vectors perpendicular to this one and to each
+    /// other, such that `(s, t, self)` is a right-handed basis. The vector
+    /// must already be normalised.
+    ///
+    pub fn coordinate_system(&self) -> (Vector3, Vector3) {
+        let s = if self.x.abs() > self.y.abs() {
+            vector(-self.z, 0.0, self.x).normalize()
+        } else {
+            vector(0.0, self.z, -self.y).normalize()
+        };
+        (s, self.cross(s))
+    }
+
     ///
     /// Tests that the vector is approximately equal to another vector.
     ///
-    pub fn approx_eq(&self, other: Vector) -> bool {
-        (self.x - other.x).abs() < 1e-10
-            && (self.y - other.y).abs() < 1e-10
-            && (self.z - other.z).abs() < 1e-10
+    pub fn approx_eq(&self, other: Vector3) -> bool {
+        (self.x - other.x).abs() < APPROX_EPSILON
+            && (self.y - other.y).abs() < APPROX_EPSILON
+            && (self.z - other.z).abs() < APPROX_EPSILON
     }
 
-    pub fn transform(&self, t: &Matrix) -> Vector {
-        (t * self) - (t * ZERO_VECTOR)
+    pub fn transform(&self, t: &Matrix) -> Vector3 {
+        t * self
     }
 }
 
-impl fmt::Debug for Vector {
+impl fmt::Debug for Vector3 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({}, {}, {})", self.x, self.y, self.z)
     }
 }
 
-impl cmp::PartialEq for Vector {
-    fn eq(&self, other: &Vector) -> bool {
+impl cmp::PartialEq for Vector3 {
+    fn eq(&self, other: &Vector3) -> bool {
         self.x == other.x && self.y == other.y && self.z == other.z
     }
 
-    fn ne(&self, other: &Vector) -> bool {
+    fn ne(&self, other: &Vector3) -> bool {
         self.x != other.x || self.y != other.y || self.z != other.z
     }
 }
 
-impl cmp::Eq for Vector {}
+impl cmp::Eq for Vector3 {}
 
-impl ops::Add<Vector> for Vector {
-    type Output = Vector;
+impl ops::Add<Vector3> for Vector3 {
+    type Output = Vector3;
 
-    fn add(self, other: Vector) -> Vector {
+    fn add(self, other: Vector3) -> Vector3 {
         vector(self.x + other.x, self.y + other.y, self.z + other.z)
     }
 }
 
-impl ops::Sub<Vector> for Vector {
-    type Output = Vector;
+impl ops::Sub<Vector3> for Vector3 {
+    type Output = Vector3;
 
-    fn sub(self, other: Vector) -> Vector {
+    fn sub(self, other: Vector3) -> Vector3 {
         vector(self.x - other.x, self.y - other.y, self.z - other.z)
     }
 }
 
 macro_rules! vec_mul_impl {
     ($($t:ty)*) => ($(
-        impl ops::Mul<$t> for Vector {
-            type Output = Vector;
+        impl ops::Mul<$t> for Vector3 {
+            type Output = Vector3;
 
             #[inline]
-            fn mul(self, other: $t) -> Vector {
-                let v = other as f64;
+            fn mul(self, other: $t) -> Vector3 {
+                let v = other as Float;
                 vector(self.x * v, self.y * v, self.z * v)
             }
         }
 
-        impl ops::Mul<Vector> for $t {
-            type Output = Vector;
+        impl ops::Mul<Vector3> for $t {
+            type Output = Vector3;
 
-            fn mul(self, other: Vector) -> Vector {
-                let v = self as f64;
+            fn mul(self, other: Vector3) -> Vector3 {
+                let v = self as Float;
                 vector(other.x * v, other.y * v, other.z * v)
             }
         }
@@ -141,21 +150,21 @@ macro_rules! vec_mul_impl {
 
 macro_rules! vec_div_impl {
     ($($t:ty)*) => ($(
-        impl ops::Div<$t> for Vector {
-            type Output = Vector;
+        impl ops::Div<$t> for Vector3 {
+            type Output = Vector3;
 
             #[inline]
-            fn div(self, other: $t) -> Vector {
-                let v = 1.0 / (other as f64);
+            fn div(self, other: $t) -> Vector3 {
+                let v = 1.0 / (other as Float);
                 vector(self.x * v, self.y * v, self.z * v)
             }
         }
 
-        impl ops::Div<Vector> for $t {
-            type Output = Vector;
+        impl ops::Div<Vector3> for $t {
+            type Output = Vector3;
 
-            fn div(self, other: Vector) -> Vector {
-                let v = 1.0 / (self as f64);
+            fn div(self, other: Vector3) -> Vector3 {
+                let v = 1.0 / (self as Float);
                 vector(other.x * v, other.y * v, other.z * v)
             }
         }
@@ -165,98 +174,84 @@ macro_rules! vec_div_impl {
 vec_mul_impl!(usize isize i32 u32 i64 u64 f32 f64);
 vec_div_impl!(usize isize i32 u32 i64 u64 f32 f64);
 
-impl ops::Neg for Vector {
-    type Output = Vector;
+impl ops::Neg for Vector3 {
+    type Output = Vector3;
 
-    fn neg(self) -> Vector {
+    fn neg(self) -> Vector3 {
         vector(-self.x, -self.y, -self.z)
     }
 }
 
+impl From<Point3> for Vector3 {
+    /// The displacement of the point from the origin
+    fn from(p: Point3) -> Vector3 {
+        vector(p.x, p.y, p.z)
+    }
+}
+
 pub mod unit_vectors {
-    use super::Vector;
+    use super::Vector3;
 
-    pub const POS_X: Vector = Vector {
+    pub const POS_X: Vector3 = Vector3 {
         x: 1.0,
         y: 0.0,
         z: 0.0,
     };
-    pub const POS_Y: Vector = Vector {
+    pub const POS_Y: Vector3 = Vector3 {
         x: 0.0,
         y: 1.0,
         z: 0.0,
     };
-    pub const POS_Z: Vector = Vector {
+    pub const POS_Z: Vector3 = Vector3 {
         x: 0.0,
         y: 0.0,
         z: 1.0,
     };
-    pub const NEG_X: Vector = Vector {
+    pub const NEG_X: Vector3 = Vector3 {
         x: -1.0,
         y: 0.0,
         z: 0.0,
     };
-    pub const NEG_Y: Vector = Vector {
+    pub const NEG_Y: Vector3 = Vector3 {
         x: 0.0,
         y: -1.0,
         z: 0.0,
     };
-    pub const NEG_Z: Vector = Vector {
+    pub const NEG_Z: Vector3 = Vector3 {
         x: 0.0,
         y: 0.0,
         z: -1.0,
     };
 }
 
-// -----------------------------------------------------------------------------
-//
-// -----------------------------------------------------------------------------
-
-///
-/// An alias for Vector for when you want to emphasize the point-ness of the
-/// data, rather than the Vector-ness.
-///
-pub type Point = Vector;
-
-///
-/// Point constructor function to avoid the incongrous construction
-/// let p : Point = Vector { ... }
-///
-pub fn point(x: f64, y: f64, z: f64) -> Point {
-    Vector { x: x, y: y, z: z }
-}
-
-// -----------------------------------------------------------------------------
-//
-// -----------------------------------------------------------------------------
-
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::point;
 
     #[test]
     fn vector_between() {
         let src = point(1.0, 2.0, 3.0);
         let dst = point(5.0, 7.0, 9.0);
-        let v = Vector::between(src, dst);
+        let v = Vector3::between(src, dst);
         assert_eq!(v, vector(4.0, 5.0, 6.0))
     }
 
     #[test]
     fn vector_cross_product() {
-        let a = Vector {
+        let a = Vector3 {
             x: 2.0,
             y: 3.0,
             z: 4.0,
         };
-        let b = Vector {
+        let b = Vector3 {
             x: 5.0,
             y: 6.0,
             z: 7.0,
         };
         assert_eq!(
             a.cross(b),
-            Vector {
+            Vector3 {
                 x: -3.0,
                 y: 6.0,
                 z: -3.0
@@ -264,14 +259,33 @@ mod test {
         )
     }
 
+    #[test]
+    fn coordinate_systems_are_orthonormal() {
+        let vs = [
+            vector(1.0, 0.0, 0.0),
+            vector(0.0, -1.0, 0.0),
+            vector(1.0, 2.0, 3.0).normalize(),
+            vector(-0.1, 0.0, 5.0).normalize(),
+        ];
+
+        for &v in vs.iter() {
+            let (s, t) = v.coordinate_system();
+            assert!((s.length() - 1.0).abs() < APPROX_EPSILON);
+            assert!((t.length() - 1.0).abs() < APPROX_EPSILON);
+            assert!(s.dot(v).abs() < APPROX_EPSILON);
+            assert!(t.dot(v).abs() < APPROX_EPSILON);
+            assert!(s.cross(t).approx_eq(v), "{:?} x {:?} != {:?}", s, t, v);
+        }
+    }
+
     #[test]
     fn vector_dot_product() {
-        let a = Vector {
+        let a = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let b = Vector {
+        let b = Vector3 {
             x: 4.0,
             y: 5.0,
             z: 6.0,
@@ -282,7 +296,7 @@ mod test {
     #[test]
     fn vector_length() {
         assert_eq!(
-            (Vector {
+            (Vector3 {
                 x: 1.0,
                 y: 0.0,
                 z: 0.0
@@ -291,7 +305,7 @@ mod test {
             1.0
         );
         assert_eq!(
-            (Vector {
+            (Vector3 {
                 x: 0.0,
                 y: 1.0,
                 z: 0.0
@@ -300,7 +314,7 @@ mod test {
             1.0
         );
         assert_eq!(
-            (Vector {
+            (Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: 1.0
@@ -309,9 +323,9 @@ mod test {
             1.0
         );
 
-        let x: f64 = 3.0;
+        let x: Float = 3.0;
         assert_eq!(
-            (Vector {
+            (Vector3 {
                 x: 1.0,
                 y: 1.0,
                 z: 1.0
@@ -332,10 +346,10 @@ mod test {
 
     #[test]
     fn vector_default() -> () {
-        let v: Vector = Default::default();
+        let v: Vector3 = Default::default();
         assert_eq!(
             v,
-            Vector {
+            Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0
@@ -345,19 +359,19 @@ mod test {
 
     #[test]
     fn vector_equality() -> () {
-        let a = Vector {
+        let a = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
         assert_eq!(a, a);
 
-        let b = Vector {
+        let b = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let c = Vector {
+        let c = Vector3 {
             x: 3.0,
             y: 1.0,
             z: 2.0,
@@ -368,19 +382,19 @@ mod test {
 
     #[test]
     fn vector_addition() -> () {
-        let a = Vector {
+        let a = Vector3 {
             x: 1.0,
             y: 3.0,
             z: 5.0,
         };
-        let b = Vector {
+        let b = Vector3 {
             x: 7.0,
             y: 11.0,
             z: 13.0,
         };
         assert_eq!(
             a + b,
-            Vector {
+            Vector3 {
                 x: 8.0,
                 y: 14.0,
                 z: 18.0
@@ -391,19 +405,19 @@ mod test {
 
     #[test]
     fn vector_subtraction() -> () {
-        let a = Vector {
+        let a = Vector3 {
             x: 1.0,
             y: 3.0,
             z: 5.0,
         };
-        let b = Vector {
+        let b = Vector3 {
             x: 7.0,
             y: 11.0,
             z: 13.0,
         };
         assert_eq!(
             a - b,
-            Vector {
+            Vector3 {
                 x: -6.0,
                 y: -8.0,
                 z: -8.0
@@ -411,7 +425,7 @@ mod test {
         );
         assert_eq!(
             b - a,
-            Vector {
+            Vector3 {
                 x: 6.0,
                 y: 8.0,
                 z: 8.0
@@ -421,14 +435,14 @@ mod test {
 
     #[test]
     fn vector_negation() -> () {
-        let v = Vector {
+        let v = Vector3 {
             x: 2.0,
             y: 4.0,
             z: 6.0,
         };
         assert_eq!(
             -v,
-            Vector {
+            Vector3 {
                 x: -2.0,
                 y: -4.0,
                 z: -6.0
@@ -439,7 +453,7 @@ mod test {
 
     #[test]
     fn vector_scalar_multiplication() -> () {
-        let v = Vector {
+        let v = Vector3 {
             x: 2.0,
             y: 4.0,
             z: 6.0,
@@ -447,7 +461,7 @@ mod test {
         assert_eq!(v * 1.0, v);
         assert_eq!(
             v * 2.0,
-            Vector {
+            Vector3 {
                 x: 4.0,
                 y: 8.0,
                 z: 12.0
@@ -456,10 +470,8 @@ mod test {
     }
 
     #[test]
-    fn point_construction() -> () {
-        let p = point(2.0, 4.0, 6.0);
-        assert_eq!(p.x, 2.0);
-        assert_eq!(p.y, 4.0);
-        assert_eq!(p.z, 6.0);
+    fn vector_transform_ignores_translation() {
+        let m = crate::translation_matrix(1.0, 2.0, 3.0) * crate::scaling_matrix(2.0, 2.0, 2.0);
+        assert_eq!(vector(1.0, 0.0, 0.0).transform(&m), vector(2.0, 0.0, 0.0));
     }
 }
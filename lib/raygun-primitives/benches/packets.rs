@@ -0,0 +1,142 @@
+//! Compares tracing rays one at a time against tracing them in packets.
+//!
+//! Run with `cargo bench -p raygun-primitives`.
+
+use bencher::{benchmark_group, benchmark_main, black_box, Bencher};
+
+use raygun_math::{degrees, point, vector, Float, Matrix, Ray, RayPacket, Transform, LANES};
+use raygun_primitives::{AxisAlignedBox, Plane, Primitive, Sphere};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 64;
+
+///
+/// A grid of coherent rays, as a camera would generate for primary rays
+///
+fn primary_rays() -> Vec<Ray> {
+    let src = point(0.0, 0.0, -10.0);
+    let mut rays = Vec::with_capacity(WIDTH * HEIGHT);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let dx = (x as Float / WIDTH as Float) - 0.5;
+            let dy = (y as Float / HEIGHT as Float) - 0.5;
+            rays.push(Ray::new(src, vector(dx, dy, 1.0)));
+        }
+    }
+    rays
+}
+
+fn packets(rays: &[Ray]) -> Vec<RayPacket> {
+    rays.chunks(LANES)
+        .map(|c| {
+            let mut lanes = [c[0]; LANES];
+            lanes[..c.len()].copy_from_slice(c);
+            RayPacket::new(&lanes)
+        })
+        .collect()
+}
+
+fn bench_single<P: Primitive>(b: &mut Bencher, p: &P) {
+    let rays = primary_rays();
+    b.iter(|| {
+        for r in rays.iter() {
+            black_box(p.intersects(*r));
+        }
+    })
+}
+
+fn bench_packet<P: Primitive>(b: &mut Bencher, p: &P) {
+    let packets = packets(&primary_rays());
+    b.iter(|| {
+        for packet in packets.iter() {
+            black_box(p.intersects_packet(packet));
+        }
+    })
+}
+
+fn sphere_single(b: &mut Bencher) {
+    bench_single(b, &Sphere::default())
+}
+
+fn sphere_packet(b: &mut Bencher) {
+    bench_packet(b, &Sphere::default())
+}
+
+fn plane_single(b: &mut Bencher) {
+    bench_single(b, &Plane::default())
+}
+
+fn plane_packet(b: &mut Bencher) {
+    bench_packet(b, &Plane::default())
+}
+
+fn unit_box() -> AxisAlignedBox {
+    AxisAlignedBox {
+        lower: point(-1.0, -1.0, -1.0),
+        upper: point(1.0, 1.0, 1.0),
+    }
+}
+
+fn aabb_single(b: &mut Bencher) {
+    let bb = unit_box();
+    let rays = primary_rays();
+    b.iter(|| {
+        for r in rays.iter() {
+            black_box(bb.intersects(r));
+        }
+    })
+}
+
+fn aabb_packet(b: &mut Bencher) {
+    let bb = unit_box();
+    let packets = packets(&primary_rays());
+    b.iter(|| {
+        for packet in packets.iter() {
+            black_box(bb.intersects_packet(packet));
+        }
+    })
+}
+
+fn object_matrix() -> Matrix {
+    Transform::for_rotation(
+        degrees(30.0).radians(),
+        degrees(45.0).radians(),
+        degrees(60.0).radians(),
+    )
+    .scale(2.0, 3.0, 4.0)
+    .translate(1.0, 2.0, 3.0)
+    .inverse
+}
+
+fn transform_single(b: &mut Bencher) {
+    let m = object_matrix();
+    let rays = primary_rays();
+    b.iter(|| {
+        for r in rays.iter() {
+            black_box(r.transform(&m));
+        }
+    })
+}
+
+fn transform_packet(b: &mut Bencher) {
+    let m = object_matrix();
+    let packets = packets(&primary_rays());
+    b.iter(|| {
+        for packet in packets.iter() {
+            black_box(packet.transform(&m));
+        }
+    })
+}
+
+benchmark_group!(
+    benches,
+    sphere_single,
+    sphere_packet,
+    plane_single,
+    plane_packet,
+    aabb_single,
+    aabb_packet,
+    transform_single,
+    transform_packet
+);
+benchmark_main!(benches);
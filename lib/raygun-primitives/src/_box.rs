@@ -1,6 +1,8 @@
-use raygun_math::{self as math, point, Point, Ray, Vector};
+use std::cmp::Ordering;
 
-use super::{AxisAlignedBox, Primitive};
+use raygun_math::{self as math, point, vector, Float, Normal3, Point3, Ray};
+
+use super::{primitive::extension_error, AxisAlignedBox, Hit, Primitive};
 
 ///
 /// An axis-aligned box
@@ -9,7 +11,7 @@ use super::{AxisAlignedBox, Primitive};
 pub struct Box(AxisAlignedBox);
 
 impl Box {
-    pub fn new(lower: Point, upper: Point) -> Box {
+    pub fn new(lower: Point3, upper: Point3) -> Box {
         Box(AxisAlignedBox { lower, upper })
     }
 
@@ -17,13 +19,83 @@ impl Box {
         Box(b)
     }
 
-    pub fn lower(&self) -> &Point {
+    pub fn lower(&self) -> &Point3 {
         &self.0.lower
     }
 
-    pub fn upper(&self) -> &Point {
+    pub fn upper(&self) -> &Point3 {
         &self.0.upper
     }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        use math::unit_vectors::*;
+        let Box(b) = self;
+        let mut point = r.extend(t);
+        let mut error = extension_error(r, t);
+
+        // snap the point onto the nearest face, which makes that coordinate
+        // exact. The face id is the index into this table.
+        let faces = [
+            ((point.x - b.lower.x).abs(), 0, b.lower.x, NEG_X),
+            ((point.x - b.upper.x).abs(), 0, b.upper.x, POS_X),
+            ((point.y - b.lower.y).abs(), 1, b.lower.y, NEG_Y),
+            ((point.y - b.upper.y).abs(), 1, b.upper.y, POS_Y),
+            ((point.z - b.lower.z).abs(), 2, b.lower.z, NEG_Z),
+            ((point.z - b.upper.z).abs(), 2, b.upper.z, POS_Z),
+        ];
+        let (face, &(_, axis, snapped, n)) = faces
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1).0.partial_cmp(&(b.1).0).unwrap_or(Ordering::Equal))
+            .unwrap();
+
+        // each face is first parameterised over [0, 1] by the other two axes
+        let size = b.upper - b.lower;
+        let rel = point - b.lower;
+        let ((u, dpdu), (v, dpdv)) = match axis {
+            0 => {
+                point.x = snapped;
+                error.x = 0.0;
+                (
+                    (rel.z / size.z, vector(0.0, 0.0, size.z)),
+                    (rel.y / size.y, vector(0.0, size.y, 0.0)),
+                )
+            }
+            1 => {
+                point.y = snapped;
+                error.y = 0.0;
+                (
+                    (rel.x / size.x, vector(size.x, 0.0, 0.0)),
+                    (rel.z / size.z, vector(0.0, 0.0, size.z)),
+                )
+            }
+            _ => {
+                point.z = snapped;
+                error.z = 0.0;
+                (
+                    (rel.x / size.x, vector(size.x, 0.0, 0.0)),
+                    (rel.y / size.y, vector(0.0, size.y, 0.0)),
+                )
+            }
+        };
+
+        // ...and then packed into a cube map, with a column per axis and the
+        // lower and upper faces in the bottom and top rows
+        let (col, row) = ((face / 2) as Float, (face % 2) as Float);
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: ((col + u) / 3.0, (row + v) / 2.0),
+            dpdu: dpdu * 3.0,
+            dpdv: dpdv * 2.0,
+            face,
+        }
+    }
 }
 
 impl Default for Box {
@@ -36,34 +108,52 @@ impl Default for Box {
 }
 
 impl Primitive for Box {
-    fn intersects(&self, r: Ray) -> Option<f64> {
-        self.0.intersects(&r)
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.0.intersects(&r).map(|t| self.hit(r, t))
     }
 
     fn bounding_box(&self) -> AxisAlignedBox {
         self.0.clone()
     }
+}
 
-    fn normal(&self, pt: Point) -> Vector {
-        use math::unit_vectors::*;
-        let &Box(ref b) = self;
-
-        const EPSILON: f64 = 1e-10;
-
-        if (pt.x - b.lower.x).abs() < EPSILON {
-            NEG_X
-        } else if (pt.x - b.upper.x).abs() < EPSILON {
-            POS_X
-        } else if (pt.y - b.lower.y).abs() < EPSILON {
-            NEG_Y
-        } else if (pt.y - b.upper.y).abs() < EPSILON {
-            POS_Y
-        } else if (pt.z - b.lower.z).abs() < EPSILON {
-            NEG_Z
-        } else if (pt.z - b.upper.z).abs() < EPSILON {
-            POS_Z
-        } else {
-            panic!("Point not on box: {:?}", pt)
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    #[test]
+    fn faces_are_identified() {
+        let b = Box::new(point(0.0, 0.0, 0.0), point(1.0, 2.0, 4.0));
+        let centre = point(0.5, 1.0, 2.0);
+        let cases = [
+            (0, normal(-1.0, 0.0, 0.0)),
+            (1, normal(1.0, 0.0, 0.0)),
+            (2, normal(0.0, -1.0, 0.0)),
+            (3, normal(0.0, 1.0, 0.0)),
+            (4, normal(0.0, 0.0, -1.0)),
+            (5, normal(0.0, 0.0, 1.0)),
+        ];
+
+        for &(face, n) in cases.iter() {
+            let r = Ray::new(centre + (n * 10.0), -n * 1.0);
+            let hit = b.intersects(r).unwrap();
+            assert_eq!(hit.face, face);
+            assert_eq!(hit.normal, n);
+
+            // the centre of every face is the centre of its cell in the
+            // cube map
+            let (u, v) = hit.uv;
+            let (col, row) = ((face / 2) as Float, (face % 2) as Float);
+            assert!(
+                (u - (col + 0.5) / 3.0).abs() < APPROX_EPSILON
+                    && (v - (row + 0.5) / 2.0).abs() < APPROX_EPSILON,
+                "Face {}: got {:?}",
+                face,
+                hit.uv
+            );
+            assert_eq!(n.dot(hit.dpdu), 0.0);
+            assert_eq!(n.dot(hit.dpdv), 0.0);
         }
     }
 }
@@ -1,29 +1,29 @@
-use raygun_math::{Point, Ray};
+use raygun_math::{Float, Floatx4, Point3, Ray, RayPacket, LANES};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AxisAlignedBox {
-    pub lower: Point,
-    pub upper: Point,
+    pub lower: Point3,
+    pub upper: Point3,
 }
 
 impl AxisAlignedBox {
     pub fn union(&self, other: &AxisAlignedBox) -> AxisAlignedBox {
-        let lower = Point {
-            x: f64::min(self.lower.x, other.lower.x),
-            y: f64::min(self.lower.y, other.lower.y),
-            z: f64::min(self.lower.z, other.lower.z),
+        let lower = Point3 {
+            x: Float::min(self.lower.x, other.lower.x),
+            y: Float::min(self.lower.y, other.lower.y),
+            z: Float::min(self.lower.z, other.lower.z),
         };
 
-        let upper = Point {
-            x: f64::max(self.upper.x, other.upper.x),
-            y: f64::max(self.upper.y, other.upper.y),
-            z: f64::max(self.upper.z, other.upper.z),
+        let upper = Point3 {
+            x: Float::max(self.upper.x, other.upper.x),
+            y: Float::max(self.upper.y, other.upper.y),
+            z: Float::max(self.upper.z, other.upper.z),
         };
 
         AxisAlignedBox { lower, upper }
     }
 
-    pub fn intersects(&self, r: &Ray) -> Option<f64> {
+    pub fn intersects(&self, r: &Ray) -> Option<Float> {
         let t_lower_x = (self.lower.x - r.src.x) / r.dir.x;
         let t_upper_x = (self.upper.x - r.src.x) / r.dir.x;
         let t_lower_y = (self.lower.y - r.src.y) / r.dir.y;
@@ -31,23 +31,23 @@ impl AxisAlignedBox {
         let t_lower_z = (self.lower.z - r.src.z) / r.dir.z;
         let t_upper_z = (self.upper.z - r.src.z) / r.dir.z;
 
-        let t_min = f64::max(
-            f64::max(
-                f64::min(t_lower_x, t_upper_x),
-                f64::min(t_lower_y, t_upper_y),
+        let t_min = Float::max(
+            Float::max(
+                Float::min(t_lower_x, t_upper_x),
+                Float::min(t_lower_y, t_upper_y),
             ),
-            f64::min(t_lower_z, t_upper_z),
+            Float::min(t_lower_z, t_upper_z),
         );
 
-        let t_max = f64::min(
-            f64::min(
-                f64::max(t_lower_x, t_upper_x),
-                f64::max(t_lower_y, t_upper_y),
+        let t_max = Float::min(
+            Float::min(
+                Float::max(t_lower_x, t_upper_x),
+                Float::max(t_lower_y, t_upper_y),
             ),
-            f64::max(t_lower_z, t_upper_z),
+            Float::max(t_lower_z, t_upper_z),
         );
 
-        if t_max < 0.0 {
+        if t_max <= 0.0 {
             // ray intersects box if extended infinitely, but the whole box
             // is behind the ray origin, which doesn't count
             //error!("Should never happen");
@@ -55,17 +55,100 @@ impl AxisAlignedBox {
         } else if t_min > t_max {
             // Ray does not intersect box
             None
+        } else if t_min <= 0.0 {
+            // ray starts inside the box, so hits the far side
+            Some(t_max)
         } else {
             Some(t_min)
         }
     }
+
+    ///
+    /// The stretch of `r` inside the box, which may start behind the ray's
+    /// origin. Unlike `intersects`, this copes with unbounded boxes.
+    ///
+    pub fn span(&self, r: &Ray) -> Option<(Float, Float)> {
+        let slab = |lo: Float, hi: Float, src: Float, dir: Float| {
+            let (a, b) = ((lo - src) / dir, (hi - src) / dir);
+            if a.is_nan() || b.is_nan() {
+                // the ray runs along a face of an unbounded box
+                (Float::NEG_INFINITY, Float::INFINITY)
+            } else {
+                (a.min(b), a.max(b))
+            }
+        };
+
+        let (x0, x1) = slab(self.lower.x, self.upper.x, r.src.x, r.dir.x);
+        let (y0, y1) = slab(self.lower.y, self.upper.y, r.src.y, r.dir.y);
+        let (z0, z1) = slab(self.lower.z, self.upper.z, r.src.z, r.dir.z);
+
+        let near = x0.max(y0).max(z0);
+        let far = x1.min(y1).min(z1);
+        if near > far || far <= 0.0 {
+            None
+        } else {
+            Some((near, far))
+        }
+    }
+}
+
+impl AxisAlignedBox {
+    /// The packet equivalent of `intersects`
+    pub fn intersects_packet(&self, p: &RayPacket) -> [Option<Float>; LANES] {
+        let t_lower_x = (Floatx4::splat(self.lower.x) - p.src.x) / p.dir.x;
+        let t_upper_x = (Floatx4::splat(self.upper.x) - p.src.x) / p.dir.x;
+        let t_lower_y = (Floatx4::splat(self.lower.y) - p.src.y) / p.dir.y;
+        let t_upper_y = (Floatx4::splat(self.upper.y) - p.src.y) / p.dir.y;
+        let t_lower_z = (Floatx4::splat(self.lower.z) - p.src.z) / p.dir.z;
+        let t_upper_z = (Floatx4::splat(self.upper.z) - p.src.z) / p.dir.z;
+
+        // a ray running exactly along a face of the box divides zero by zero
+        // for that slab. That's rare enough to hand over to `intersects`,
+        // which copes with the NaN, rather than slow down every packet.
+        let slabs = [
+            t_lower_x, t_upper_x, t_lower_y, t_upper_y, t_lower_z, t_upper_z,
+        ];
+        if Floatx4::any_nan(&slabs) {
+            let mut result = [None; LANES];
+            for (n, r) in result.iter_mut().enumerate() {
+                *r = self.intersects(&p.ray(n));
+            }
+            return result;
+        }
+
+        let t_min = t_lower_x
+            .min(t_upper_x)
+            .max(t_lower_y.min(t_upper_y))
+            .max(t_lower_z.min(t_upper_z));
+
+        let t_max = t_lower_x
+            .max(t_upper_x)
+            .min(t_lower_y.max(t_upper_y))
+            .min(t_lower_z.max(t_upper_z));
+
+        let ahead = t_max.gt(Floatx4::splat(0.0));
+        let outside = t_min.gt(Floatx4::splat(0.0));
+        let missed = t_min.gt(t_max);
+
+        let mut result = [None; LANES];
+        for n in 0..LANES {
+            if ahead[n] && !missed[n] {
+                result[n] = Some(if outside[n] {
+                    t_min.lane(n)
+                } else {
+                    t_max.lane(n)
+                });
+            }
+        }
+        result
+    }
 }
 
 impl Default for AxisAlignedBox {
     fn default() -> AxisAlignedBox {
         AxisAlignedBox {
-            lower: Point::default(),
-            upper: Point::default(),
+            lower: Point3::default(),
+            upper: Point3::default(),
         }
     }
 }
@@ -73,9 +156,9 @@ impl Default for AxisAlignedBox {
 #[cfg(test)]
 mod test {
     use super::*;
-    use raygun_math::{point, vector, Ray};
+    use raygun_math::{point, vector, Ray, RayPacket};
 
-    fn aab(x0: f64, y0: f64, z0: f64, x1: f64, y1: f64, z1: f64) -> AxisAlignedBox {
+    fn aab(x0: Float, y0: Float, z0: Float, x1: Float, y1: Float, z1: Float) -> AxisAlignedBox {
         AxisAlignedBox {
             lower: point(x0, y0, z0),
             upper: point(x1, y1, z1),
@@ -158,4 +241,49 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn rays_from_inside_hit_the_far_side() {
+        let b = aab(-0.5, -0.5, -0.5, 0.5, 0.5, 0.5);
+
+        let inside = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(b.intersects(&inside), Some(0.5));
+
+        // a ray leaving the surface doesn't hit it again
+        let leaving = Ray::new(point(0.0, 0.5, 0.0), vector(0.0, 1.0, 1.0));
+        assert_eq!(b.intersects(&leaving), None);
+    }
+
+    #[test]
+    fn packet_intersection() {
+        let b = aab(-0.5, -0.5, -0.5, 0.5, 0.5, 0.5);
+        let rays = [
+            Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(0.0, 7.0, 0.0), vector(0.0, -1.0, 0.0)),
+            Ray::new(point(2.0, 0.0, -4.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(0.0, 0.0, 4.0), vector(0.0, 0.0, 1.0)),
+        ];
+
+        let actual = b.intersects_packet(&RayPacket::new(&rays));
+        assert_eq!(actual, [Some(3.5), Some(6.5), None, None]);
+        for (n, r) in rays.iter().enumerate() {
+            assert_eq!(actual[n], b.intersects(r));
+        }
+    }
+
+    #[test]
+    fn packets_grazing_a_face() {
+        let b = aab(-0.5, -0.5, -0.5, 0.5, 0.5, 0.5);
+        let rays = [
+            Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(0.5, 0.0, -4.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(-0.5, 0.5, -4.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(0.5, 2.0, -4.0), vector(0.0, 0.0, 1.0)),
+        ];
+
+        let actual = b.intersects_packet(&RayPacket::new(&rays));
+        for (n, r) in rays.iter().enumerate() {
+            assert_eq!(actual[n], b.intersects(r));
+        }
+    }
 }
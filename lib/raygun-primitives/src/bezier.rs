@@ -0,0 +1,556 @@
+use std::{cmp, path::Path};
+
+use crate::{flat::triangle_distance, AxisAlignedBox, Hit, Primitive};
+use raygun_math::{gamma, point, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A bicubic Bézier patch, defined by a 4×4 grid of control points. The
+/// points are given a row at a time, with `u` running along each row and `v`
+/// down the columns. The surface passes through the four corner points and
+/// is pulled towards the rest. The surface faces along `∂P/∂u × ∂P/∂v`.
+///
+/// Rays are intersected by Newton's method, starting from a flat
+/// approximation of the surface. The patch is split up front into pieces
+/// that are close to flat, which both bound the search and give good first
+/// guesses.
+///
+#[derive(Debug)]
+pub struct BezierPatch {
+    points: [Point3; 16],
+    root: Node,
+}
+
+///
+/// A piece of the patch, as found by subdividing it. Leaves are flat enough
+/// to approximate with a pair of triangles.
+///
+#[derive(Debug)]
+struct Node {
+    bounds: AxisAlignedBox,
+    u: (Float, Float),
+    v: (Float, Float),
+    corners: [Point3; 4],
+    children: Vec<Node>,
+}
+
+/// How deep to subdivide the patch at most, leaving at most 4⁶ pieces
+const MAX_DEPTH: usize = 6;
+
+/// How close to flat a piece must be, as a fraction of the patch's size
+const FLATNESS: Float = 1e-3;
+
+/// How far outside the patch a hit may land, to close the seams between
+/// patches
+const SLACK: Float = 1e-6;
+
+/// How closely Newton's method must put a point on both the ray and the
+/// patch, which grows with the size of the numbers involved
+fn tolerance(r: Ray, t: Float, p: Point3) -> Float {
+    let scale = Vector3::from(r.src).abs().length() + t.abs() + Vector3::from(p).length();
+    gamma(16) * 16.0 * (1.0 + scale)
+}
+
+/// The cubic Bernstein polynomials at `t`
+fn bernstein(t: Float) -> [Float; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+/// The derivatives of the cubic Bernstein polynomials at `t`
+fn bernstein_derivative(t: Float) -> [Float; 4] {
+    let s = 1.0 - t;
+    [
+        -3.0 * s * s,
+        3.0 * s * (s - 2.0 * t),
+        3.0 * t * (2.0 * s - t),
+        3.0 * t * t,
+    ]
+}
+
+/// Splits a cubic Bézier curve in half with de Casteljau's algorithm
+fn split(p: [Point3; 4]) -> ([Point3; 4], [Point3; 4]) {
+    let mid = |a: Point3, b: Point3| a + ((b - a) * 0.5);
+    let (p01, p12, p23) = (mid(p[0], p[1]), mid(p[1], p[2]), mid(p[2], p[3]));
+    let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+    let centre = mid(p012, p123);
+    ([p[0], p01, p012, centre], [centre, p123, p23, p[3]])
+}
+
+/// The bounding box of some points
+fn bounds_of(points: &[Point3]) -> AxisAlignedBox {
+    points.iter().skip(1).fold(
+        AxisAlignedBox {
+            lower: points[0],
+            upper: points[0],
+        },
+        |b, p| AxisAlignedBox {
+            lower: point(b.lower.x.min(p.x), b.lower.y.min(p.y), b.lower.z.min(p.z)),
+            upper: point(b.upper.x.max(p.x), b.upper.y.max(p.y), b.upper.z.max(p.z)),
+        },
+    )
+}
+
+impl Node {
+    fn build(
+        points: [Point3; 16],
+        u: (Float, Float),
+        v: (Float, Float),
+        tolerance: Float,
+        depth: usize,
+    ) -> Node {
+        let corners = [points[0], points[3], points[15], points[12]];
+
+        // how far the control points stray from the bilinear surface through
+        // the corners, which bounds how far the patch does
+        let mut deviation: Float = 0.0;
+        for row in 0..4 {
+            for col in 0..4 {
+                let (s, t) = (col as Float / 3.0, row as Float / 3.0);
+                let top = corners[0] + ((corners[1] - corners[0]) * s);
+                let bottom = corners[3] + ((corners[2] - corners[3]) * s);
+                let flat = top + ((bottom - top) * t);
+                deviation = deviation.max((points[(row * 4) + col] - flat).length());
+            }
+        }
+
+        let mut node = Node {
+            bounds: bounds_of(&points),
+            u,
+            v,
+            corners,
+            children: Vec::new(),
+        };
+        if depth >= MAX_DEPTH || deviation <= tolerance {
+            return node;
+        }
+
+        // split every row in u, then every column of each half in v
+        let (mut left, mut right) = ([Point3::default(); 16], [Point3::default(); 16]);
+        for row in 0..4 {
+            let curve = [
+                points[row * 4],
+                points[row * 4 + 1],
+                points[row * 4 + 2],
+                points[row * 4 + 3],
+            ];
+            let (a, b) = split(curve);
+            left[row * 4..row * 4 + 4].copy_from_slice(&a);
+            right[row * 4..row * 4 + 4].copy_from_slice(&b);
+        }
+
+        let u_mid = 0.5 * (u.0 + u.1);
+        let v_mid = 0.5 * (v.0 + v.1);
+        for (half, u_range) in [(left, (u.0, u_mid)), (right, (u_mid, u.1))].iter() {
+            let (mut top, mut bottom) = ([Point3::default(); 16], [Point3::default(); 16]);
+            for col in 0..4 {
+                let curve = [half[col], half[4 + col], half[8 + col], half[12 + col]];
+                let (a, b) = split(curve);
+                for row in 0..4 {
+                    top[row * 4 + col] = a[row];
+                    bottom[row * 4 + col] = b[row];
+                }
+            }
+            for (quarter, v_range) in [(top, (v.0, v_mid)), (bottom, (v_mid, v.1))].iter() {
+                node.children.push(Node::build(
+                    *quarter,
+                    *u_range,
+                    *v_range,
+                    tolerance,
+                    depth + 1,
+                ));
+            }
+        }
+
+        node
+    }
+}
+
+impl BezierPatch {
+    pub fn new(points: [Point3; 16]) -> BezierPatch {
+        let bounds = bounds_of(&points);
+        let tolerance = FLATNESS * (bounds.upper - bounds.lower).length();
+        BezierPatch {
+            points,
+            root: Node::build(points, (0.0, 1.0), (0.0, 1.0), tolerance, 0),
+        }
+    }
+
+    pub fn points(&self) -> &[Point3; 16] {
+        &self.points
+    }
+
+    /// The point on the patch at `(u, v)`, along with its partial
+    /// derivatives
+    fn evaluate(&self, u: Float, v: Float) -> (Point3, Vector3, Vector3) {
+        let (bu, bv) = (bernstein(u), bernstein(v));
+        let (du, dv) = (bernstein_derivative(u), bernstein_derivative(v));
+
+        let (mut p, mut pu, mut pv) = (Vector3::default(), Vector3::default(), Vector3::default());
+        for row in 0..4 {
+            for col in 0..4 {
+                let c = Vector3::from(self.points[(row * 4) + col]);
+                p = p + (c * (bu[col] * bv[row]));
+                pu = pu + (c * (du[col] * bv[row]));
+                pv = pv + (c * (bu[col] * dv[row]));
+            }
+        }
+        (point(0.0, 0.0, 0.0) + p, pu, pv)
+    }
+
+    ///
+    /// Refines a guess at where the ray meets the patch with Newton's
+    /// method, solving `P(u, v) = o + t·d` for `u`, `v` and `t`
+    ///
+    fn newton(&self, r: Ray, guess: (Float, Float, Float)) -> Option<(Float, Float, Float)> {
+        let (mut u, mut v, mut t) = guess;
+
+        for _ in 0..16 {
+            let (p, pu, pv) = self.evaluate(u, v);
+            let f = p - r.extend(t);
+            let tolerance = tolerance(r, t, p);
+            if f.length() <= tolerance {
+                // a hit closer than the tolerance may be the surface the ray
+                // has just left
+                let inside = |x: Float| (-SLACK..=1.0 + SLACK).contains(&x);
+                return if inside(u) && inside(v) && t > tolerance {
+                    Some((t, u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)))
+                } else {
+                    None
+                };
+            }
+
+            // solve [pu pv -d]·(du, dv, dt) = -f by Cramer's rule
+            let d = -r.dir;
+            let det = pu.dot(pv.cross(d));
+            if det == 0.0 || !det.is_finite() {
+                return None;
+            }
+            u -= f.dot(pv.cross(d)) / det;
+            v -= pu.dot(f.cross(d)) / det;
+            t -= pu.dot(pv.cross(f)) / det;
+
+            // give up on guesses that wander well away from the patch
+            if !(-0.5..=1.5).contains(&u) || !(-0.5..=1.5).contains(&v) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The nearest hit on the patch, as its distance and parameters
+    fn nearest(&self, r: Ray) -> Option<(Float, Float, Float)> {
+        let mut nearest: Option<(Float, Float, Float)> = None;
+        let mut stack = vec![&self.root];
+
+        while let Some(node) = stack.pop() {
+            let (near, far) = match node.bounds.span(&r) {
+                Some(span) => span,
+                None => continue,
+            };
+            if let Some((best, _, _)) = nearest {
+                if near > best {
+                    continue;
+                }
+            }
+            if !node.children.is_empty() {
+                stack.extend(node.children.iter());
+                continue;
+            }
+
+            // start from where the ray crosses the flattened piece, or failing
+            // that from the middle of the piece
+            let [a, b, c, d] = node.corners;
+            let (du, dv) = (node.u.1 - node.u.0, node.v.1 - node.v.0);
+            let guess = triangle_distance(r, a, b, c)
+                .map(|(t, b1, b2)| (node.u.0 + (b1 + b2) * du, node.v.0 + b2 * dv, t))
+                .or_else(|| {
+                    triangle_distance(r, a, c, d)
+                        .map(|(t, b1, b2)| (node.u.0 + b1 * du, node.v.0 + (b1 + b2) * dv, t))
+                })
+                .unwrap_or((
+                    node.u.0 + 0.5 * du,
+                    node.v.0 + 0.5 * dv,
+                    0.5 * (near.max(0.0) + far),
+                ));
+
+            if let Some(hit) = self.newton(r, guess) {
+                match nearest {
+                    Some((best, _, _)) if best <= hit.0 => {}
+                    _ => nearest = Some(hit),
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The hit record for an intersection `t` units along `r`, at `(u, v)` on
+    /// the patch as found by `nearest`
+    fn hit(&self, r: Ray, t: Float, u: Float, v: Float) -> Hit {
+        let (point, pu, pv) = self.evaluate(u, v);
+
+        // where the patch pinches to a point, as at the top of the teapot's
+        // lid, one of the derivatives vanishes; take the normal from just
+        // inside instead
+        let mut n = pu.cross(pv);
+        if n.length() <= Float::EPSILON * (pu.length() + pv.length()) {
+            let nudge = |x: Float| x + (0.5 - x) * 1e-3;
+            let (_, pu, pv) = self.evaluate(nudge(u), nudge(v));
+            n = pu.cross(pv);
+        }
+        let n = n.normalize();
+
+        // the error in evaluating the patch is bounded by the sum of the
+        // weighted control points, on top of how far Newton's method left
+        // the point from the ray
+        let (bu, bv) = (bernstein(u), bernstein(v));
+        let mut magnitude = Vector3::default();
+        for (n, p) in self.points.iter().enumerate() {
+            magnitude = magnitude + (p.abs() * (bu[n % 4] * bv[n / 4]));
+        }
+
+        Hit {
+            t,
+            point,
+            error: (magnitude * gamma(12)) + (Vector3::new(1.0, 1.0, 1.0) * tolerance(r, t, point)),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: (u, v),
+            dpdu: pu,
+            dpdv: pv,
+            face: 0,
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a patch object
+impl cmp::PartialEq for BezierPatch {
+    fn eq(&self, other: &BezierPatch) -> bool {
+        self.points == other.points
+    }
+}
+
+impl Primitive for BezierPatch {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, u, v)| self.hit(r, t, u, v))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        self.root.bounds.clone()
+    }
+}
+
+///
+/// A surface made up of many Bézier patches, such as the Utah teapot. Hits
+/// report the index of the patch hit as their face.
+///
+#[derive(Debug)]
+pub struct BezierPatches {
+    patches: Vec<BezierPatch>,
+}
+
+impl BezierPatches {
+    /// Gathers up some patches. Returns `None` if there aren't any.
+    pub fn new(patches: Vec<BezierPatch>) -> Option<BezierPatches> {
+        if patches.is_empty() {
+            None
+        } else {
+            Some(BezierPatches { patches })
+        }
+    }
+
+    pub fn patches(&self) -> &[BezierPatch] {
+        &self.patches
+    }
+
+    ///
+    /// Reads a set of patches in the format used for the Utah teapot: the
+    /// number of patches, then a line of 16 comma-separated vertex numbers
+    /// for each, counting from 1; then the number of vertices, then a line
+    /// of comma-separated x, y and z coordinates for each.
+    ///
+    pub fn read(text: &str) -> Result<BezierPatches, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        fn count<'a>(
+            lines: &mut impl Iterator<Item = &'a str>,
+            what: &str,
+        ) -> Result<usize, String> {
+            let line = lines
+                .next()
+                .ok_or(format!("expected the number of {}", what))?;
+            line.parse()
+                .map_err(|_| format!("expected the number of {}, not {:?}", what, line))
+        }
+
+        let patch_count = count(&mut lines, "patches")?;
+        let mut patches = Vec::with_capacity(patch_count);
+        for _ in 0..patch_count {
+            let line = lines
+                .next()
+                .ok_or("the file ends part way through the patches")?;
+            let indices = line
+                .split(',')
+                .map(|n| n.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("expected 16 vertex numbers, not {:?}", line))?;
+            if indices.len() != 16 {
+                return Err(format!("expected 16 vertex numbers, not {:?}", line));
+            }
+            patches.push(indices);
+        }
+
+        let vertex_count = count(&mut lines, "vertices")?;
+        let mut vertices = Vec::with_capacity(vertex_count);
+        for _ in 0..vertex_count {
+            let line = lines
+                .next()
+                .ok_or("the file ends part way through the vertices")?;
+            let xyz = line
+                .split(',')
+                .map(|n| n.trim().parse::<Float>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("expected a vertex, not {:?}", line))?;
+            if xyz.len() != 3 {
+                return Err(format!("expected a vertex, not {:?}", line));
+            }
+            vertices.push(point(xyz[0], xyz[1], xyz[2]));
+        }
+
+        let patches = patches
+            .into_iter()
+            .map(|indices| {
+                let mut points = [Point3::default(); 16];
+                for (p, &i) in points.iter_mut().zip(indices.iter()) {
+                    *p = *i
+                        .checked_sub(1)
+                        .and_then(|i| vertices.get(i))
+                        .ok_or(format!("there is no vertex {}", i))?;
+                }
+                Ok(BezierPatch::new(points))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        BezierPatches::new(patches).ok_or_else(|| "there are no patches".to_owned())
+    }
+
+    /// Loads a set of patches from a file in the teapot format
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<BezierPatches, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to load {:?}: {}", path, e))?;
+        BezierPatches::read(&text).map_err(|e| format!("in {:?}: {}", path, e))
+    }
+
+    /// The nearest hit, as its distance, the index of the patch hit and the
+    /// parameters on that patch
+    fn nearest(&self, r: Ray) -> Option<(Float, usize, Float, Float)> {
+        self.patches
+            .iter()
+            .enumerate()
+            .filter_map(|(n, p)| p.nearest(r).map(|(t, u, v)| (t, n, u, v)))
+            .fold(None, |best, hit| match best {
+                Some((b, ..)) if b <= hit.0 => best,
+                _ => Some(hit),
+            })
+    }
+}
+
+impl Primitive for BezierPatches {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, n, u, v)| Hit {
+            face: n,
+            ..self.patches[n].hit(r, t, u, v)
+        })
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let first = self.patches[0].bounding_box();
+        self.patches
+            .iter()
+            .skip(1)
+            .fold(first, |acc, p| acc.union(&p.bounding_box()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    /// A patch over the unit square in x and z, with the middle raised
+    fn bump() -> BezierPatch {
+        let mut points = [Point3::default(); 16];
+        for row in 0..4 {
+            for col in 0..4 {
+                let inner = (1..3).contains(&row) && (1..3).contains(&col);
+                let y = if inner { 1.0 } else { 0.0 };
+                points[(row * 4) + col] = point(col as Float / 3.0, y, row as Float / 3.0);
+            }
+        }
+        BezierPatch::new(points)
+    }
+
+    #[test]
+    fn rays_hit_the_surface() {
+        let p = bump();
+
+        // the middle of the patch is raised to 9/16 of the inner points
+        let r = Ray::new(point(0.5, 10.0, 0.5), vector(0.0, -1.0, 0.0));
+        let h = p.intersects(r).unwrap();
+        assert!((h.t - (10.0 - 0.5625)).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!((h.uv.0 - 0.5).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, -1.0, 0.0)), "{:?}", h);
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+
+        // oblique rays land on the surface
+        let r = Ray::new(point(-2.0, 3.0, 0.1), vector(1.0, -1.2, 0.2).normalize());
+        let h = p.intersects(r).unwrap();
+        assert!(
+            h.point.approx_eq(r.extend(h.t)),
+            "{:?} vs {:?}",
+            h,
+            r.extend(h.t)
+        );
+
+        let r = Ray::new(point(1.5, 10.0, 0.5), vector(0.0, -1.0, 0.0));
+        assert!(p.intersects(r).is_none());
+    }
+
+    #[test]
+    fn curved_patches_are_subdivided() {
+        let p = bump();
+        assert!(!p.root.children.is_empty());
+
+        let b = p.bounding_box();
+        assert!(b.lower.approx_eq(point(0.0, 0.0, 0.0)), "{:?}", b);
+        assert!(b.upper.approx_eq(point(1.0, 1.0, 1.0)), "{:?}", b);
+    }
+
+    #[test]
+    fn read_teapot_format() {
+        let text = "2
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
+            16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1
+            16
+            0, 0, 0\n1, 0, 0\n2, 0, 0\n3, 0, 0
+            0, 1, 0\n1, 1, 0\n2, 1, 0\n3, 1, 0
+            0, 2, 0\n1, 2, 0\n2, 2, 0\n3, 2, 0
+            0, 3, 0\n1, 3, 0\n2, 3, 0\n3, 3, 0";
+        let group = BezierPatches::read(text).unwrap();
+        let patches = group.patches();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].points()[5], point(1.0, 1.0, 0.0));
+        assert_eq!(patches[1].points()[0], point(3.0, 3.0, 0.0));
+
+        // both patches cover the same square, facing opposite ways
+        let r = Ray::new(point(1.5, 1.5, -10.0), vector(0.0, 0.0, 1.0));
+        let h = group.intersects(r).unwrap();
+        assert!((h.t - 10.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert_eq!(h.face, 0);
+
+        assert!(BezierPatches::read("1\n1, 2, 3\n").is_err());
+        assert!(BezierPatches::read("1\n1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,2\n1\n0,0,0").is_err());
+        assert!(BezierPatches::read("0\n0\n").is_err());
+    }
+}
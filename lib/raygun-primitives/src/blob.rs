@@ -0,0 +1,400 @@
+use std::cmp;
+
+use crate::{
+    primitive::{extension_error, normal_uv},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{solve_quadratic, solve_quartic, vector, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// One of the components making up a blob. Each one has a density that
+/// falls from `strength` at its centre (or axis) to zero at `radius`,
+/// following `strength * (1 - d²/radius²)²` for a point `d` away. A negative
+/// strength carves into the other components rather than adding to them.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Component {
+    Sphere {
+        centre: Point3,
+        radius: Float,
+        strength: Float,
+    },
+
+    /// A cylinder from `base` to `cap`, whose density falls away from the
+    /// axis, so that on its own it's rounded off at both ends
+    Cylinder {
+        base: Point3,
+        cap: Point3,
+        radius: Float,
+        strength: Float,
+    },
+}
+
+impl Component {
+    fn radius(&self) -> Float {
+        match self {
+            Component::Sphere { radius, .. } | Component::Cylinder { radius, .. } => *radius,
+        }
+    }
+
+    fn strength(&self) -> Float {
+        match self {
+            Component::Sphere { strength, .. } | Component::Cylinder { strength, .. } => *strength,
+        }
+    }
+
+    /// The nearest point to `p` on the component's centre or axis
+    fn closest(&self, p: Point3) -> Point3 {
+        match self {
+            Component::Sphere { centre, .. } => *centre,
+            Component::Cylinder { base, cap, .. } => {
+                let axis = *cap - *base;
+                let h = axis.length();
+                if h == 0.0 {
+                    return *base;
+                }
+                let w = axis / h;
+                *base + (w * (p - *base).dot(w).clamp(0.0, h))
+            }
+        }
+    }
+
+    /// The gradient of the component's density at `p`
+    fn gradient(&self, p: Point3) -> Vector3 {
+        let r2 = self.radius() * self.radius();
+        let v = p - self.closest(p);
+        let falloff = 1.0 - (v.dot(v) / r2);
+        if falloff <= 0.0 {
+            vector(0.0, 0.0, 0.0)
+        } else {
+            v * (-4.0 * self.strength() * falloff / r2)
+        }
+    }
+
+    /// A box around everywhere the component has any density
+    pub fn bounds(&self) -> AxisAlignedBox {
+        let r = self.radius();
+        let ball = |c: Point3| AxisAlignedBox {
+            lower: c - vector(r, r, r),
+            upper: c + vector(r, r, r),
+        };
+        match self {
+            Component::Sphere { centre, .. } => ball(*centre),
+            Component::Cylinder { base, cap, .. } => ball(*base).union(&ball(*cap)),
+        }
+    }
+
+    ///
+    /// Splits the component into pieces along `r`, each covering the span of
+    /// the ray where the squared distance to the component is a single
+    /// quadratic in `t`. A sphere is one piece, but a cylinder is its body
+    /// plus a ball around either end.
+    ///
+    fn pieces(&self, r: Ray, out: &mut Vec<Piece>) {
+        let (radius, strength) = (self.radius(), self.strength());
+        let mut add = |region: Region, axial: Option<(Point3, Vector3, Float, Float)>| {
+            let piece = Piece {
+                t0: 0.0,
+                t1: 0.0,
+                region,
+                radius,
+                strength,
+            };
+            let (a, b, c) = piece.quadratic(r);
+            let (mut t0, mut t1) = match solve_quadratic(a, b, c - 1.0) {
+                Some(ts) => ts,
+                None if a == 0.0 && c < 1.0 => (Float::NEG_INFINITY, Float::INFINITY),
+                None => return,
+            };
+
+            // restrict the piece to one stretch along the cylinder's axis
+            if let Some((base, w, lo, hi)) = axial {
+                let (s, ds) = ((r.src - base).dot(w), r.dir.dot(w));
+                if ds == 0.0 {
+                    if s < lo || s > hi {
+                        return;
+                    }
+                } else {
+                    let (a, b) = ((lo - s) / ds, (hi - s) / ds);
+                    t0 = t0.max(a.min(b));
+                    t1 = t1.min(a.max(b));
+                }
+            }
+
+            if t0 < t1 {
+                out.push(Piece { t0, t1, ..piece });
+            }
+        };
+
+        match self {
+            Component::Sphere { centre, .. } => add(Region::Ball(*centre), None),
+            Component::Cylinder { base, cap, .. } => {
+                let axis = *cap - *base;
+                let h = axis.length();
+                if h == 0.0 {
+                    add(Region::Ball(*base), None);
+                    return;
+                }
+                let w = axis / h;
+                let (inf, zero) = (Float::INFINITY, 0.0);
+                add(Region::Tube(*base, w), Some((*base, w, zero, h)));
+                add(Region::Ball(*base), Some((*base, w, -inf, zero)));
+                add(Region::Ball(*cap), Some((*base, w, h, inf)));
+            }
+        }
+    }
+}
+
+/// What a piece of a component measures distance from
+#[derive(Clone, Copy, Debug)]
+enum Region {
+    Ball(Point3),
+    Tube(Point3, Vector3),
+}
+
+/// A stretch of ray, `t0` to `t1`, inside a piece of a component
+#[derive(Clone, Copy, Debug)]
+struct Piece {
+    t0: Float,
+    t1: Float,
+    region: Region,
+    radius: Float,
+    strength: Float,
+}
+
+impl Piece {
+    ///
+    /// The squared distance from the piece, as a fraction of its radius
+    /// squared, in terms of `t` along `r`. The result is the coefficients
+    /// `(a, b, c)` of `at² + bt + c`.
+    ///
+    fn quadratic(&self, r: Ray) -> (Float, Float, Float) {
+        let (v, d) = match self.region {
+            Region::Ball(centre) => (r.src - centre, r.dir),
+            Region::Tube(base, w) => {
+                let v = r.src - base;
+                (v - (w * v.dot(w)), r.dir - (w * r.dir.dot(w)))
+            }
+        };
+        let r2 = self.radius * self.radius;
+        (d.dot(d) / r2, 2.0 * v.dot(d) / r2, v.dot(v) / r2)
+    }
+
+    ///
+    /// The piece's density along `r`, as the coefficients of a quartic in
+    /// `t`, highest power first
+    ///
+    fn density(&self, r: Ray) -> [Float; 5] {
+        // s(1 - q)² where q = at² + bt + c
+        let (a, b, c) = self.quadratic(r);
+        let s = self.strength;
+        [
+            s * a * a,
+            s * 2.0 * a * b,
+            s * ((b * b) + (2.0 * a * c) - (2.0 * a)),
+            s * ((2.0 * b * c) - (2.0 * b)),
+            s * ((c * c) - (2.0 * c) + 1.0),
+        ]
+    }
+}
+
+///
+/// A blob, or metaball, object: a surface wrapped around a set of
+/// components wherever their combined density reaches `threshold`. Nearby
+/// components flow smoothly into each other.
+///
+#[derive(Debug)]
+pub struct Blob {
+    pub threshold: Float,
+    pub components: Vec<Component>,
+}
+
+impl Blob {
+    pub fn new(threshold: Float, components: Vec<Component>) -> Blob {
+        Blob {
+            threshold,
+            components,
+        }
+    }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        let p = r.extend(t);
+
+        // the density rises towards the inside
+        let n = -self
+            .components
+            .iter()
+            .fold(vector(0.0, 0.0, 0.0), |acc, c| acc + c.gradient(p))
+            .normalize();
+        let (dpdu, dpdv) = n.coordinate_system();
+
+        Hit {
+            t,
+            point: p,
+            error: extension_error(r, t),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: normal_uv(n),
+            dpdu,
+            dpdv,
+            face: 0,
+        }
+    }
+}
+
+impl Default for Blob {
+    fn default() -> Blob {
+        Blob::new(1.0, Vec::new())
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a blob object
+impl cmp::PartialEq for Blob {
+    fn eq(&self, other: &Blob) -> bool {
+        self.threshold == other.threshold && self.components == other.components
+    }
+}
+
+impl Primitive for Blob {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let mut pieces = Vec::new();
+        for c in &self.components {
+            if c.bounds().intersects(&r).is_some() {
+                c.pieces(r, &mut pieces);
+            }
+        }
+
+        // every boundary between pieces starts a new interval, inside which
+        // the density is a single quartic
+        let mut bounds: Vec<Float> = pieces
+            .iter()
+            .flat_map(|p| vec![p.t0, p.t1])
+            .filter(|&t| t > 0.0)
+            .collect();
+        bounds.push(0.0);
+        bounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        bounds.dedup();
+
+        for span in bounds.windows(2) {
+            let (start, end) = (span[0], span[1]);
+            let middle = (start + end) / 2.0;
+
+            // solve relative to the start of the span, which keeps the
+            // coefficients from growing with the distance to the ray origin
+            let local = Ray::new(r.extend(start), r.dir);
+            let mut k = [0.0; 5];
+            for p in pieces.iter().filter(|p| p.t0 <= middle && middle <= p.t1) {
+                for (k, d) in k.iter_mut().zip(p.density(local).iter()) {
+                    *k += d;
+                }
+            }
+            if k == [0.0; 5] {
+                continue;
+            }
+
+            let roots = solve_quartic(k[0], k[1], k[2], k[3], k[4] - self.threshold);
+            if let Some(t) = roots.iter().find(|&&t| t > 0.0 && t <= end - start) {
+                return Some(self.hit(r, start + t));
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        // only the components that add density can reach the threshold
+        self.components
+            .iter()
+            .filter(|c| c.strength() > 0.0)
+            .map(Component::bounds)
+            .fold(None, |acc: Option<AxisAlignedBox>, b| match acc {
+                Some(acc) => Some(acc.union(&b)),
+                None => Some(b),
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, point, APPROX_EPSILON};
+
+    fn sphere(x: Float, radius: Float, strength: Float) -> Component {
+        Component::Sphere {
+            centre: point(x, 0.0, 0.0),
+            radius,
+            strength,
+        }
+    }
+
+    fn hit(b: &Blob, src: Point3, dir: Vector3) -> Option<Hit> {
+        let r = Ray::new(src, dir);
+        b.intersects(r)
+    }
+
+    #[test]
+    fn lone_sphere_shrinks_with_threshold() {
+        // the density is 1 - d²/4 squared, which is 0.25 at d = √2
+        let b = Blob::new(0.25, vec![sphere(0.0, 2.0, 1.0)]);
+        let h = hit(&b, point(-10.0, 0.0, 0.0), vector(1.0, 0.0, 0.0)).unwrap();
+
+        let edge = (2.0 as Float).sqrt();
+        assert!((h.t - (10.0 - edge)).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(-1.0, 0.0, 0.0)), "{:?}", h);
+
+        // and the surface vanishes once the threshold is out of reach
+        let b = Blob::new(1.5, vec![sphere(0.0, 2.0, 1.0)]);
+        assert!(hit(&b, point(-10.0, 0.0, 0.0), vector(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn nearby_spheres_merge() {
+        let b = Blob::new(0.5, vec![sphere(-1.0, 1.5, 1.0), sphere(1.0, 1.5, 1.0)]);
+
+        // neither sphere is dense enough at the origin alone, but together
+        // they bridge the gap
+        let r = point(0.0, 10.0, 0.0);
+        let h = hit(&b, r, vector(0.0, -1.0, 0.0)).unwrap();
+        assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)), "{:?}", h);
+
+        let single = Blob::new(0.5, vec![sphere(-1.0, 1.5, 1.0)]);
+        assert!(hit(&single, r, vector(0.0, -1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn negative_strength_carves() {
+        let b = Blob::new(0.5, vec![sphere(0.0, 2.0, 1.0), sphere(-1.5, 1.0, -2.0)]);
+        let h = hit(&b, point(-10.0, 0.0, 0.0), vector(1.0, 0.0, 0.0)).unwrap();
+        assert!(h.point.x > -0.75, "{:?}", h);
+
+        let bb = b.bounding_box();
+        assert_eq!(bb.lower, point(-2.0, -2.0, -2.0));
+    }
+
+    #[test]
+    fn cylinders_have_rounded_ends() {
+        let b = Blob::new(
+            0.25,
+            vec![Component::Cylinder {
+                base: point(0.0, 0.0, 0.0),
+                cap: point(0.0, 4.0, 0.0),
+                radius: 2.0,
+                strength: 1.0,
+            }],
+        );
+        let edge = (2.0 as Float).sqrt();
+
+        let h = hit(&b, point(-10.0, 2.0, 0.0), vector(1.0, 0.0, 0.0)).unwrap();
+        assert!(h.point.approx_eq(point(-edge, 2.0, 0.0)), "{:?}", h);
+
+        let h = hit(&b, point(0.0, 10.0, 0.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert!(h.point.approx_eq(point(0.0, 4.0 + edge, 0.0)), "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)), "{:?}", h);
+
+        // a ray grazing the end misses the corner a plain cylinder would have
+        let corner = point(1.3, 4.0 + 1.3, 0.0);
+        assert!(hit(&b, corner + vector(0.0, 0.0, -10.0), vector(0.0, 0.0, 1.0)).is_none());
+    }
+}
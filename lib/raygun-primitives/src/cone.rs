@@ -0,0 +1,285 @@
+use std::cmp;
+
+use crate::{flat::disc_bounds, AxisAlignedBox, Hit, Primitive};
+use raygun_math::{
+    consts::PI, gamma, point, solve_quadratic, Float, Normal3, Point3, Ray, Vector3,
+};
+
+// face ids reported in hits
+const SIDE: usize = 0;
+const BASE: usize = 1;
+const CAP: usize = 2;
+
+///
+/// A truncated cone running from `base` to `cap`, with a (possibly different)
+/// radius at each end. The ends are closed with flat discs unless the cone
+/// is `open`. Hits report face 0 for the side, 1 for the base disc and 2 for
+/// the cap disc.
+///
+#[derive(Debug)]
+pub struct Cone {
+    pub base: Point3,
+    pub base_radius: Float,
+    pub cap: Point3,
+    pub cap_radius: Float,
+    pub open: bool,
+}
+
+impl Cone {
+    pub fn new(base: Point3, base_radius: Float, cap: Point3, cap_radius: Float) -> Cone {
+        Cone {
+            base,
+            base_radius,
+            cap,
+            cap_radius,
+            open: false,
+        }
+    }
+
+    /// The unit vector along the axis from base to cap, and the axis' length
+    fn axis(&self) -> (Vector3, Float) {
+        let v = self.cap - self.base;
+        let h = v.length();
+        (v / h, h)
+    }
+
+    /// The rate the radius changes per unit travelled along the axis
+    fn slope(&self, height: Float) -> Float {
+        (self.cap_radius - self.base_radius) / height
+    }
+
+    ///
+    /// Finds the closest intersection in front of the ray, and which face of
+    /// the cone it hits
+    ///
+    fn nearest(&self, r: Ray) -> Option<(Float, usize)> {
+        let (w, h) = self.axis();
+        let k = self.slope(h);
+
+        // work relative to the base, splitting everything into the parts
+        // along and across the axis
+        let oa = r.src - self.base;
+        let (ow, dw) = (oa.dot(w), r.dir.dot(w));
+        let radius = self.base_radius + (k * ow);
+
+        // the infinite (double) cone through the sides
+        let a = 1.0 - (dw * dw * (1.0 + k * k));
+        let b = 2.0 * (oa.dot(r.dir) - (ow * dw) - (k * dw * radius));
+        let c = oa.dot(oa) - (ow * ow) - (radius * radius);
+
+        let roots = match solve_quadratic(a, b, c) {
+            Some((t0, t1)) => [Some(t0), Some(t1)],
+            // the ray runs parallel to the side, so can only cross it once
+            None if a == 0.0 && b != 0.0 => [Some(-c / b), None],
+            None => [None, None],
+        };
+
+        let mut best: Option<(Float, usize)> = None;
+        let mut consider = |t: Float, face: usize| match best {
+            Some((nearest, _)) if nearest <= t => {}
+            _ if t > 0.0 => best = Some((t, face)),
+            _ => {}
+        };
+
+        for &t in roots.iter().flatten() {
+            let y = ow + (t * dw);
+            if y >= 0.0 && y <= h {
+                consider(t, SIDE);
+            }
+        }
+
+        if !self.open && dw != 0.0 {
+            let ends = [(0.0, self.base_radius, BASE), (h, self.cap_radius, CAP)];
+            for &(y, radius, face) in ends.iter().filter(|e| e.1 > 0.0) {
+                let t = (y - ow) / dw;
+                let q = oa + (r.dir * t);
+                if q.dot(q) - (y * y) <= radius * radius {
+                    consider(t, face);
+                }
+            }
+        }
+
+        best
+    }
+
+    /// The hit record for an intersection `t` units along `r`, on the face
+    /// found by `nearest`
+    fn hit(&self, r: Ray, t: Float, face: usize) -> Hit {
+        let (w, h) = self.axis();
+        let (e1, e2) = w.coordinate_system();
+
+        let q = r.extend(t) - self.base;
+        let (x1, x2, y) = (q.dot(e1), q.dot(e2), q.dot(w));
+
+        // the point is rebuilt from its cylindrical coordinates, putting it
+        // back onto the surface whatever the error in `t`
+        let (along, across, n, uv, dpdu, dpdv) = if face == SIDE {
+            // u runs around the axis, and v along it from base to cap
+            let k = self.slope(h);
+            let phi = x2.atan2(x1);
+            let (cos_phi, sin_phi) = (phi.cos(), phi.sin());
+            let radial = (e1 * cos_phi) + (e2 * sin_phi);
+            let radius = self.base_radius + (k * y);
+            (
+                w * y,
+                radial * radius,
+                (radial - (w * k)).normalize(),
+                ((phi + PI) / (2.0 * PI), y / h),
+                ((e2 * cos_phi) - (e1 * sin_phi)) * (2.0 * PI * radius),
+                (w + (radial * k)) * h,
+            )
+        } else {
+            // the end discs are mapped onto the unit square they fit in
+            let (y, n, radius) = if face == BASE {
+                (0.0, -w, self.base_radius)
+            } else {
+                (h, w, self.cap_radius)
+            };
+            (
+                w * y,
+                (e1 * x1) + (e2 * x2),
+                n,
+                ((x1 / radius + 1.0) / 2.0, (x2 / radius + 1.0) / 2.0),
+                e1 * (2.0 * radius),
+                e2 * (2.0 * radius),
+            )
+        };
+
+        let point = self.base + along + across;
+        let error = (Vector3::from(self.base).abs() + along.abs() + across.abs()) * gamma(9);
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv,
+            dpdu,
+            dpdv,
+            face,
+        }
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Cone {
+        Cone::new(point(0.0, 0.0, 0.0), 1.0, point(0.0, 1.0, 0.0), 0.0)
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a cone object
+impl cmp::PartialEq for Cone {
+    fn eq(&self, other: &Cone) -> bool {
+        self.base == other.base
+            && self.base_radius == other.base_radius
+            && self.cap == other.cap
+            && self.cap_radius == other.cap_radius
+            && self.open == other.open
+    }
+}
+
+impl Primitive for Cone {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, face)| self.hit(r, t, face))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let (w, _) = self.axis();
+        disc_bounds(self.base, w, self.base_radius).union(&disc_bounds(
+            self.cap,
+            w,
+            self.cap_radius,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    fn hit(c: &Cone, src: Point3, dir: Vector3) -> Option<Hit> {
+        let r = Ray::new(src, dir);
+        c.intersects(r)
+    }
+
+    #[test]
+    fn side_normals_lean_towards_the_narrow_end() {
+        let c = Cone::default();
+        let h = hit(&c, point(10.0, 0.5, 0.0), vector(-1.0, 0.0, 0.0)).unwrap();
+
+        assert_eq!(h.face, SIDE);
+        assert!(h.point.approx_eq(point(0.5, 0.5, 0.0)), "{:?}", h);
+
+        let expected = normal(1.0, 1.0, 0.0).normalize();
+        assert!(
+            h.normal.approx_eq(expected),
+            "Expected {:?}, got {:?}",
+            expected,
+            h.normal
+        );
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn ends_are_closed_unless_open() {
+        let mut c = Cone::new(point(0.0, 0.0, 0.0), 2.0, point(0.0, 2.0, 0.0), 1.0);
+
+        let h = hit(&c, point(0.5, 10.0, 0.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert_eq!(h.face, CAP);
+        assert!((h.t - 8.0).abs() < APPROX_EPSILON);
+        assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)));
+
+        let h = hit(&c, point(0.5, -10.0, 0.0), vector(0.0, 1.0, 0.0)).unwrap();
+        assert_eq!(h.face, BASE);
+        assert!(h.normal.approx_eq(normal(0.0, -1.0, 0.0)));
+
+        // with the ends open, the ray passes straight through
+        c.open = true;
+        assert!(hit(&c, point(0.5, 10.0, 0.0), vector(0.0, -1.0, 0.0)).is_none());
+
+        // ... and can see inside
+        let flared = Cone {
+            open: true,
+            ..Cone::new(point(0.0, 0.0, 0.0), 1.0, point(0.0, 2.0, 0.0), 2.0)
+        };
+        let h = hit(&flared, point(0.0, 10.0, 0.0), vector(0.2, -1.0, 0.0)).unwrap();
+        assert_eq!(h.face, SIDE);
+        assert!(h.normal.dot(h.point - point(0.0, h.point.y, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn arbitrary_axes() {
+        let c = Cone::new(point(1.0, 1.0, 1.0), 1.0, point(3.0, 3.0, 3.0), 1.0);
+        let mid = point(2.0, 2.0, 2.0);
+        let across = vector(1.0, -1.0, 0.0).normalize();
+
+        let h = hit(&c, mid + (across * 10.0), -across).unwrap();
+        assert_eq!(h.face, SIDE);
+        assert!(h.point.approx_eq(mid + across), "{:?}", h);
+        assert!((h.uv.1 - 0.5).abs() < APPROX_EPSILON);
+
+        let bb = c.bounding_box();
+        let e = (2.0 as Float / 3.0).sqrt();
+        assert!(
+            bb.lower.approx_eq(point(1.0 - e, 1.0 - e, 1.0 - e)),
+            "{:?}",
+            bb
+        );
+        assert!(
+            bb.upper.approx_eq(point(3.0 + e, 3.0 + e, 3.0 + e)),
+            "{:?}",
+            bb
+        );
+    }
+
+    #[test]
+    fn misses() {
+        let c = Cone::default();
+        assert!(hit(&c, point(10.0, 2.0, 0.0), vector(-1.0, 0.0, 0.0)).is_none());
+        assert!(hit(&c, point(10.0, 0.5, 0.0), vector(1.0, 0.0, 0.0)).is_none());
+        assert!(hit(&c, point(1.5, 10.0, 0.0), vector(0.0, -1.0, 0.0)).is_none());
+    }
+}
@@ -0,0 +1,96 @@
+use std::cmp;
+
+use crate::{AxisAlignedBox, Cone, Hit, Primitive};
+use raygun_math::{point, Float, Point3, Ray};
+
+///
+/// A cylinder running from `base` to `cap`. The ends are closed with flat
+/// discs unless the cylinder is `open`. Hits report faces as for a `Cone`.
+///
+#[derive(Debug)]
+pub struct Cylinder {
+    pub base: Point3,
+    pub cap: Point3,
+    pub radius: Float,
+    pub open: bool,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, cap: Point3, radius: Float) -> Cylinder {
+        Cylinder {
+            base,
+            cap,
+            radius,
+            open: false,
+        }
+    }
+
+    /// A cylinder is just a cone with the same radius at both ends
+    fn as_cone(&self) -> Cone {
+        Cone {
+            open: self.open,
+            ..Cone::new(self.base, self.radius, self.cap, self.radius)
+        }
+    }
+}
+
+impl Default for Cylinder {
+    fn default() -> Cylinder {
+        Cylinder::new(point(0.0, 0.0, 0.0), point(0.0, 1.0, 0.0), 1.0)
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a cylinder object
+impl cmp::PartialEq for Cylinder {
+    fn eq(&self, other: &Cylinder) -> bool {
+        self.base == other.base
+            && self.cap == other.cap
+            && self.radius == other.radius
+            && self.open == other.open
+    }
+}
+
+impl Primitive for Cylinder {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.as_cone().intersects(r)
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        self.as_cone().bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    #[test]
+    fn side_normals_point_straight_out() {
+        let c = Cylinder::new(point(0.0, -1.0, 0.0), point(0.0, 1.0, 0.0), 2.0);
+        let r = Ray::new(point(1.0, 0.5, -10.0), vector(0.0, 0.0, 1.0));
+        let h = c.intersects(r).unwrap();
+
+        let z = -(3.0 as Float).sqrt();
+        assert!(h.point.approx_eq(point(1.0, 0.5, z)), "{:?}", h);
+
+        let expected = normal(0.5, 0.0, z / 2.0);
+        assert!(
+            h.normal.approx_eq(expected),
+            "Expected {:?}, got {:?}",
+            expected,
+            h.normal
+        );
+        assert!((h.uv.1 - 0.75).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn rays_along_the_axis_hit_the_ends() {
+        let mut c = Cylinder::default();
+        let r = Ray::new(point(0.5, -10.0, 0.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(c.intersects(r).map(|h| h.t), Some(10.0));
+
+        c.open = true;
+        assert_eq!(c.intersects(r), None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::cmp;
+
+use crate::{
+    flat::{disc_bounds, plane_distance, project_onto_plane},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{consts::PI, point, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A flat disc centred on `centre` and facing along `normal`, which must be
+/// normalised. A non-zero `inner_radius` punches a hole in the middle,
+/// making an annulus.
+///
+#[derive(Debug)]
+pub struct Disc {
+    pub centre: Point3,
+    pub normal: Normal3,
+    pub radius: Float,
+    pub inner_radius: Float,
+}
+
+impl Disc {
+    pub fn new(centre: Point3, normal: Normal3, radius: Float) -> Disc {
+        Disc {
+            centre,
+            normal,
+            radius,
+            inner_radius: 0.0,
+        }
+    }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        let n = Vector3::from(self.normal);
+        let (point, error) = project_onto_plane(r, t, self.centre, n);
+
+        // u runs around the centre, and v outwards from the inner edge
+        let (s, t_axis) = n.coordinate_system();
+        let d = point - self.centre;
+        let phi = d.dot(t_axis).atan2(d.dot(s));
+        let (cos_phi, sin_phi) = (phi.cos(), phi.sin());
+        let rho = d.length();
+        let width = self.radius - self.inner_radius;
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: self.normal,
+            shading_normal: self.normal,
+            uv: ((phi + PI) / (2.0 * PI), (rho - self.inner_radius) / width),
+            dpdu: ((t_axis * cos_phi) - (s * sin_phi)) * (2.0 * PI * rho),
+            dpdv: ((s * cos_phi) + (t_axis * sin_phi)) * width,
+            face: 0,
+        }
+    }
+}
+
+impl Default for Disc {
+    fn default() -> Disc {
+        Disc::new(point(0.0, 0.0, 0.0), Normal3::new(0.0, 1.0, 0.0), 1.0)
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a disc object
+impl cmp::PartialEq for Disc {
+    fn eq(&self, other: &Disc) -> bool {
+        self.centre == other.centre
+            && self.normal == other.normal
+            && self.radius == other.radius
+            && self.inner_radius == other.inner_radius
+    }
+}
+
+impl Primitive for Disc {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let n = Vector3::from(self.normal);
+        let t = plane_distance(r, self.centre, n)?;
+
+        let d = r.extend(t) - self.centre;
+        let dist2 = d.dot(d) - (d.dot(n) * d.dot(n));
+        let inside = dist2 <= self.radius * self.radius;
+        let outside_hole = dist2 >= self.inner_radius * self.inner_radius;
+        if inside && outside_hole {
+            Some(self.hit(r, t))
+        } else {
+            None
+        }
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        disc_bounds(self.centre, Vector3::from(self.normal), self.radius)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{vector, APPROX_EPSILON};
+
+    #[test]
+    fn rays_hit_between_the_radii() {
+        let d = Disc {
+            inner_radius: 1.0,
+            ..Disc::new(point(0.0, 1.0, 0.0), Normal3::new(0.0, 1.0, 0.0), 2.0)
+        };
+        let down = vector(0.0, -1.0, 0.0);
+
+        assert!(d.intersects(Ray::new(point(0.0, 5.0, 0.0), down)).is_none());
+        assert!(d.intersects(Ray::new(point(2.5, 5.0, 0.0), down)).is_none());
+
+        let r = Ray::new(point(1.5, 5.0, 0.0), down);
+        let h = d.intersects(r).unwrap();
+        assert_eq!(h.t, 4.0);
+        assert!(h.point.approx_eq(point(1.5, 1.0, 0.0)));
+        assert!((h.uv.1 - 0.5).abs() < APPROX_EPSILON, "{:?}", h);
+    }
+
+    #[test]
+    fn bounding_box_is_tight() {
+        let d = Disc::new(point(1.0, 1.0, 1.0), Normal3::new(0.0, 0.0, 1.0), 2.0);
+        let bb = d.bounding_box();
+        assert!(bb.lower.approx_eq(point(-1.0, -1.0, 1.0)), "{:?}", bb);
+        assert!(bb.upper.approx_eq(point(3.0, 3.0, 1.0)), "{:?}", bb);
+    }
+}
@@ -0,0 +1,108 @@
+//! Helpers shared by the bounded flat primitives.
+
+use raygun_math::{gamma, vector, Float, Point3, Ray, Vector3};
+
+use crate::AxisAlignedBox;
+
+///
+/// The distance along `r` to the plane through `origin` with normal `n`, if
+/// the plane is in front of the ray
+///
+pub(crate) fn plane_distance(r: Ray, origin: Point3, n: Vector3) -> Option<Float> {
+    match (origin - r.src).dot(n) / r.dir.dot(n) {
+        t if t > 0.0 => Some(t),
+        _ => None,
+    }
+}
+
+///
+/// Re-projects the point `t` along `r` onto the plane through `origin` with
+/// normal `n`, returning the projected point and a bound on its error
+///
+pub(crate) fn project_onto_plane(
+    r: Ray,
+    t: Float,
+    origin: Point3,
+    n: Vector3,
+) -> (Point3, Vector3) {
+    let p = r.extend(t);
+    let point = p - n * ((p - origin).dot(n) / n.dot(n));
+
+    let m = point.abs() + origin.abs();
+    let e = gamma(7) * m.x.max(m.y).max(m.z);
+    (point, vector(e, e, e))
+}
+
+///
+/// The bounding box of a disc of `radius` centred on `centre`, perpendicular
+/// to the unit vector `n`
+///
+pub(crate) fn disc_bounds(centre: Point3, n: Vector3, radius: Float) -> AxisAlignedBox {
+    // along each axis the disc reaches r·√(1 - n²)
+    let e = vector(
+        radius * (1.0 - n.x * n.x).max(0.0).sqrt(),
+        radius * (1.0 - n.y * n.y).max(0.0).sqrt(),
+        radius * (1.0 - n.z * n.z).max(0.0).sqrt(),
+    );
+    AxisAlignedBox {
+        lower: centre - e,
+        upper: centre + e,
+    }
+}
+
+///
+/// The barycentric coordinates `(a, b)` of `p` in the triangle
+/// `o, o + e1, o + e2`, such that `p = o + a·e1 + b·e2` once projected into
+/// the triangle's plane. Returns `None` for degenerate triangles.
+///
+pub(crate) fn barycentric(
+    p: Point3,
+    o: Point3,
+    e1: Vector3,
+    e2: Vector3,
+) -> Option<(Float, Float)> {
+    let w = e1.cross(e2);
+    let w2 = w.dot(w);
+    if w2 == 0.0 {
+        return None;
+    }
+    let d = p - o;
+    Some((d.cross(e2).dot(w) / w2, e1.cross(d).dot(w) / w2))
+}
+
+///
+/// Intersects `r` with the triangle `p0, p1, p2`, giving the distance along
+/// the ray and the barycentric weights `(b1, b2)` of `p1` and `p2` at the hit.
+/// Edges are widened by a whisker of rounding error, so that a ray through
+/// an edge shared by two triangles can't slip between them.
+///
+pub(crate) fn triangle_distance(
+    r: Ray,
+    p0: Point3,
+    p1: Point3,
+    p2: Point3,
+) -> Option<(Float, Float, Float)> {
+    let (e1, e2) = (p1 - p0, p2 - p0);
+    let pv = r.dir.cross(e2);
+    let det = e1.dot(pv);
+    if det == 0.0 {
+        return None;
+    }
+
+    let slack = -gamma(4);
+    let tv = r.src - p0;
+    let b1 = tv.dot(pv) / det;
+    if b1 < slack || b1 > 1.0 - slack {
+        return None;
+    }
+    let qv = tv.cross(e1);
+    let b2 = r.dir.dot(qv) / det;
+    if b2 < slack || b1 + b2 > 1.0 - slack {
+        return None;
+    }
+
+    match e2.dot(qv) / det {
+        t if t > 0.0 => Some((t, b1, b2)),
+        _ => None,
+    }
+}
@@ -0,0 +1,414 @@
+use std::{cmp, path::Path};
+
+use image::DynamicImage;
+
+use crate::{flat::triangle_distance, AxisAlignedBox, Hit, Primitive};
+use raygun_math::{gamma, point, vector, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A terrain made from a regular grid of heights, each between 0 and 1. The
+/// grid is stretched over the unit square in x and z, and each grid cell is
+/// split into two triangles along its diagonal.
+///
+/// With `smooth` set, normals are interpolated across each triangle from
+/// the slope of the grid at its corners, hiding the facets.
+///
+#[derive(Debug)]
+pub struct HeightField {
+    columns: usize,
+    rows: usize,
+    heights: Vec<Float>,
+
+    /// The lowest and highest corner of each cell, for skipping cells a ray
+    /// passes over or under
+    ranges: Vec<(Float, Float)>,
+
+    /// The grid's normal at each height, for smoothing
+    normals: Vec<Vector3>,
+
+    pub smooth: bool,
+}
+
+///
+/// Where a ray crosses the grid: the cell, which of its two triangles, and
+/// the barycentric weights of that triangle's second and third corners
+///
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+    i: usize,
+    j: usize,
+    tri: usize,
+    b1: Float,
+    b2: Float,
+}
+
+impl HeightField {
+    ///
+    /// Builds a height field from `rows` rows of `columns` heights each. The
+    /// first row runs along z = 0, and the first column along x = 0. Returns
+    /// `None` unless there are at least two rows and columns to make a cell.
+    ///
+    pub fn new(columns: usize, rows: usize, heights: Vec<Float>) -> Option<HeightField> {
+        if columns < 2 || rows < 2 || heights.len() != columns * rows {
+            return None;
+        }
+
+        let mut hf = HeightField {
+            columns,
+            rows,
+            heights,
+            ranges: Vec::with_capacity((columns - 1) * (rows - 1)),
+            normals: Vec::with_capacity(columns * rows),
+            smooth: false,
+        };
+
+        for j in 0..rows - 1 {
+            for i in 0..columns - 1 {
+                let corners = [
+                    hf.height(i, j),
+                    hf.height(i + 1, j),
+                    hf.height(i, j + 1),
+                    hf.height(i + 1, j + 1),
+                ];
+                let lo = corners.iter().cloned().fold(Float::INFINITY, Float::min);
+                let hi = corners
+                    .iter()
+                    .cloned()
+                    .fold(Float::NEG_INFINITY, Float::max);
+                hf.ranges.push((lo, hi));
+            }
+        }
+
+        // the slope at each height, by differences with its neighbours
+        let (dx, dz) = hf.spacing();
+        for j in 0..rows {
+            for i in 0..columns {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(columns - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(rows - 1));
+                let slope_x = (hf.height(i1, j) - hf.height(i0, j)) / ((i1 - i0) as Float * dx);
+                let slope_z = (hf.height(i, j1) - hf.height(i, j0)) / ((j1 - j0) as Float * dz);
+                hf.normals.push(vector(-slope_x, 1.0, -slope_z).normalize());
+            }
+        }
+
+        Some(hf)
+    }
+
+    ///
+    /// Builds a height field from the brightness of an image, at its full
+    /// bit depth. The image is laid out as seen from above, with its top
+    /// edge along z = 1.
+    ///
+    pub fn from_image(image: &DynamicImage) -> Option<HeightField> {
+        let (columns, rows, heights) = match image.grayscale() {
+            DynamicImage::ImageLuma16(img) => {
+                let (w, h) = img.dimensions();
+                let scale = 1.0 / Float::from(u16::MAX);
+                let mut heights = Vec::with_capacity((w * h) as usize);
+                for y in (0..h).rev() {
+                    heights.extend((0..w).map(|x| Float::from(img.get_pixel(x, y)[0]) * scale));
+                }
+                (w, h, heights)
+            }
+            gray => {
+                let img = gray.to_luma();
+                let (w, h) = img.dimensions();
+                let scale = 1.0 / Float::from(u8::MAX);
+                let mut heights = Vec::with_capacity((w * h) as usize);
+                for y in (0..h).rev() {
+                    heights.extend((0..w).map(|x| Float::from(img.get_pixel(x, y)[0]) * scale));
+                }
+                (w, h, heights)
+            }
+        };
+        HeightField::new(columns as usize, rows as usize, heights)
+    }
+
+    /// Loads a height field from a (grayscale) image file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<HeightField, String> {
+        let path = path.as_ref();
+        let image = image::open(path).map_err(|e| format!("failed to load {:?}: {}", path, e))?;
+        HeightField::from_image(&image)
+            .ok_or_else(|| format!("{:?} needs to be at least 2x2 pixels", path))
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The height at column `i` of row `j`
+    pub fn height(&self, i: usize, j: usize) -> Float {
+        self.heights[(j * self.columns) + i]
+    }
+
+    /// The size of a cell in x and z
+    fn spacing(&self) -> (Float, Float) {
+        (
+            1.0 / (self.columns - 1) as Float,
+            1.0 / (self.rows - 1) as Float,
+        )
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let (dx, dz) = self.spacing();
+        point(i as Float * dx, self.height(i, j), j as Float * dz)
+    }
+
+    /// The corners of the two triangles making up a cell
+    fn triangles(&self, i: usize, j: usize) -> [[(usize, usize); 3]; 2] {
+        [
+            [(i, j), (i + 1, j), (i + 1, j + 1)],
+            [(i, j), (i + 1, j + 1), (i, j + 1)],
+        ]
+    }
+
+    ///
+    /// Finds the first triangle hit in a single cell, if any
+    ///
+    fn cell_distance(&self, r: Ray, i: usize, j: usize) -> Option<(Float, Cell)> {
+        self.triangles(i, j)
+            .iter()
+            .enumerate()
+            .filter_map(|(tri, &[a, b, c])| {
+                triangle_distance(
+                    r,
+                    self.vertex(a.0, a.1),
+                    self.vertex(b.0, b.1),
+                    self.vertex(c.0, c.1),
+                )
+                .map(|(t, b1, b2)| (t, Cell { i, j, tri, b1, b2 }))
+            })
+            .fold(None, |best: Option<(Float, Cell)>, hit| match best {
+                Some((b, _)) if b <= hit.0 => best,
+                _ => Some(hit),
+            })
+    }
+
+    /// The hit record for an intersection `t` units along `r`, in the cell
+    /// found by `cell_distance`
+    fn hit(&self, r: Ray, t: Float, cell: Cell) -> Hit {
+        let (dx, dz) = self.spacing();
+        let p = r.extend(t);
+
+        let Cell { i, j, tri, b1, b2 } = cell;
+        let [a, b, c] = self.triangles(i, j)[tri];
+        let b0 = 1.0 - b1 - b2;
+
+        // rebuild the height from the triangle, which puts the point back
+        // on the surface
+        let (ha, hb, hc) = (
+            self.height(a.0, a.1),
+            self.height(b.0, b.1),
+            self.height(c.0, c.1),
+        );
+        let point = point(p.x, (b0 * ha) + (b1 * hb) + (b2 * hc), p.z);
+
+        // the triangle's slope along x and z
+        let (slope_x, slope_z) = if tri == 0 {
+            ((hb - ha) / dx, (hc - hb) / dz)
+        } else {
+            ((hb - hc) / dx, (hc - ha) / dz)
+        };
+        let n = vector(-slope_x, 1.0, -slope_z).normalize();
+
+        let shading = if self.smooth {
+            let normal_at = |v: (usize, usize)| self.normals[(v.1 * self.columns) + v.0];
+            ((normal_at(a) * b0) + (normal_at(b) * b1) + (normal_at(c) * b2)).normalize()
+        } else {
+            n
+        };
+
+        Hit {
+            t,
+            point,
+            error: point.abs() * gamma(7),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(shading),
+            uv: (point.x.clamp(0.0, 1.0), point.z.clamp(0.0, 1.0)),
+            dpdu: vector(1.0, slope_x, 0.0),
+            dpdv: vector(0.0, slope_z, 1.0),
+            face: (((j * (self.columns - 1)) + i) * 2) + tri,
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a height field object
+impl cmp::PartialEq for HeightField {
+    fn eq(&self, other: &HeightField) -> bool {
+        self.columns == other.columns
+            && self.rows == other.rows
+            && self.heights == other.heights
+            && self.smooth == other.smooth
+    }
+}
+
+impl Primitive for HeightField {
+    ///
+    /// Walks the cells under the ray in order, with a 2D DDA, testing the
+    /// triangles in any cell whose range of heights the ray passes through
+    ///
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let (near, far) = self.bounding_box().span(&r)?;
+        let start = near.max(0.0);
+
+        let (cells_x, cells_z) = ((self.columns - 1) as isize, (self.rows - 1) as isize);
+        let (dx, dz) = self.spacing();
+        let p = r.extend(start);
+        let mut i = ((p.x / dx).floor() as isize).clamp(0, cells_x - 1);
+        let mut j = ((p.z / dz).floor() as isize).clamp(0, cells_z - 1);
+
+        // how far along the ray the next cell boundary is on each axis, and
+        // how far apart the boundaries are
+        let axis = |cell: isize, size: Float, src: Float, dir: Float| {
+            if dir == 0.0 {
+                (0, Float::INFINITY, Float::INFINITY)
+            } else if dir > 0.0 {
+                (1, (((cell + 1) as Float * size) - src) / dir, size / dir)
+            } else {
+                (-1, ((cell as Float * size) - src) / dir, -size / dir)
+            }
+        };
+        let (step_i, mut next_x, delta_x) = axis(i, dx, r.src.x, r.dir.x);
+        let (step_j, mut next_z, delta_z) = axis(j, dz, r.src.z, r.dir.z);
+
+        let mut t_in = start;
+        loop {
+            let t_out = next_x.min(next_z).min(far);
+
+            let (y0, y1) = (r.extend(t_in).y, r.extend(t_out).y);
+            let (lo, hi) = self.ranges[((j * cells_x) + i) as usize];
+            let slack = gamma(4) * (1.0 + hi.abs());
+            if y0.min(y1) <= hi + slack && y0.max(y1) >= lo - slack {
+                if let Some((t, cell)) = self.cell_distance(r, i as usize, j as usize) {
+                    return Some(self.hit(r, t, cell));
+                }
+            }
+
+            if t_out >= far {
+                return None;
+            }
+            if next_x < next_z {
+                i += step_i;
+                next_x += delta_x;
+            } else {
+                j += step_j;
+                next_z += delta_z;
+            }
+            if i < 0 || i >= cells_x || j < 0 || j >= cells_z {
+                return None;
+            }
+            t_in = t_out;
+        }
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let lo = self
+            .ranges
+            .iter()
+            .map(|r| r.0)
+            .fold(Float::INFINITY, Float::min);
+        let hi = self
+            .ranges
+            .iter()
+            .map(|r| r.1)
+            .fold(Float::NEG_INFINITY, Float::max);
+        AxisAlignedBox {
+            lower: point(0.0, lo, 0.0),
+            upper: point(1.0, hi, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma};
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    /// A 3x3 grid with a peak of 1 in the middle
+    fn peak() -> HeightField {
+        let mut heights = vec![0.0; 9];
+        heights[4] = 1.0;
+        HeightField::new(3, 3, heights).unwrap()
+    }
+
+    fn hit(hf: &HeightField, src: Point3, dir: Vector3) -> Option<Hit> {
+        let r = Ray::new(src, dir);
+        hf.intersects(r)
+    }
+
+    #[test]
+    fn grids_need_a_whole_cell() {
+        assert!(HeightField::new(1, 4, vec![0.0; 4]).is_none());
+        assert!(HeightField::new(2, 2, vec![0.0; 3]).is_none());
+        assert!(HeightField::new(2, 2, vec![0.0; 4]).is_some());
+    }
+
+    #[test]
+    fn rays_from_above_hit_the_interpolated_height() {
+        let hf = peak();
+        let h = hit(&hf, point(0.5, 10.0, 0.5), vector(0.0, -1.0, 0.0)).unwrap();
+        assert!((h.t - 9.0).abs() < APPROX_EPSILON, "{:?}", h);
+
+        // halfway down the slope towards the -x edge
+        let h = hit(&hf, point(0.25, 10.0, 0.5), vector(0.0, -1.0, 0.0)).unwrap();
+        assert!(h.point.approx_eq(point(0.25, 0.5, 0.5)), "{:?}", h);
+        assert!((h.uv.0 - 0.25).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+        assert!(h.normal.x < 0.0 && h.normal.y > 0.0, "{:?}", h);
+    }
+
+    #[test]
+    fn low_rays_walk_across_cells() {
+        let hf = peak();
+
+        // skims in from the side, passing over the first cells, and hits the
+        // far slope of the peak
+        let h = hit(&hf, point(-1.0, 0.75, 0.5), vector(1.0, 0.0, 0.0)).unwrap();
+        assert!(h.point.approx_eq(point(0.375, 0.75, 0.5)), "{:?}", h);
+
+        // passes over the peak entirely
+        assert!(hit(&hf, point(-1.0, 1.5, 0.5), vector(1.0, 0.0, 0.0)).is_none());
+
+        // and a diagonal ray down through every row
+        let dir = vector(1.0, -0.2, 1.0).normalize();
+        let h = hit(&hf, point(-1.0, 0.9, -1.0), dir).unwrap();
+        assert!(h.point.y > 0.0 && h.point.y < 1.0, "{:?}", h);
+    }
+
+    #[test]
+    fn smooth_normals_follow_the_grid() {
+        let mut hf = HeightField::new(3, 2, vec![0.0, 0.5, 0.0, 0.0, 0.5, 0.0]).unwrap();
+        let at_ridge = |hf: &HeightField| {
+            hit(hf, point(0.5, 10.0, 0.5), vector(0.0, -1.0, 0.0))
+                .unwrap()
+                .shading_normal
+        };
+
+        // the ridge is a crease between facets, but smooth on top
+        assert!(!at_ridge(&hf).approx_eq(normal(0.0, 1.0, 0.0)));
+        hf.smooth = true;
+        assert!(at_ridge(&hf).approx_eq(normal(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn images_are_read_at_full_depth() {
+        let img: GrayImage = ImageBuffer::from_fn(2, 2, |x, y| Luma([(x * 204 + y * 51) as u8]));
+        let hf = HeightField::from_image(&DynamicImage::ImageLuma8(img)).unwrap();
+
+        // the top row of the image lies along the far edge
+        assert_eq!(hf.height(0, 1), 0.0);
+        assert_eq!(hf.height(1, 0), 1.0);
+        assert!((hf.height(1, 1) - 0.8).abs() < APPROX_EPSILON);
+        assert!((hf.height(0, 0) - 0.2).abs() < APPROX_EPSILON);
+
+        let img = ImageBuffer::from_fn(2, 2, |x, _| Luma([(x * 1000) as u16]));
+        let hf = HeightField::from_image(&DynamicImage::ImageLuma16(img)).unwrap();
+        assert!((hf.height(1, 0) - (1000.0 / 65535.0)).abs() < APPROX_EPSILON);
+    }
+}
@@ -0,0 +1,429 @@
+use std::cmp;
+
+use crate::{spline::Segment, AxisAlignedBox, Hit, Primitive, Spline};
+use raygun_math::{
+    consts::PI, gamma, point, solve_polynomial, solve_quadratic, vector, Float, Normal3, Ray,
+    Vector3,
+};
+
+///
+/// A surface of revolution, made by spinning a profile curve around the y
+/// axis. The profile is given as `(radius, height)` points joined up by a
+/// spline. To make a closed solid, start and end the profile on the axis.
+///
+/// Normals face to the right of the profile as it's drawn, so a profile
+/// drawn upwards faces outwards.
+///
+#[derive(Debug)]
+pub struct Lathe {
+    spline: Spline,
+    points: Vec<(Float, Float)>,
+    segments: Vec<Piece>,
+}
+
+///
+/// One segment of the profile, as polynomials in its parameter, along with
+/// its bounding box
+///
+#[derive(Debug)]
+struct Piece {
+    radius: [Float; 4],
+    height: [Float; 4],
+    bounds: AxisAlignedBox,
+}
+
+/// Evaluates a polynomial, lowest power first
+fn eval(c: &[Float]) -> impl Fn(Float) -> Float + '_ {
+    move |s| c.iter().rev().fold(0.0, |acc, &c| (acc * s) + c)
+}
+
+/// The derivative of a cubic, lowest power first
+fn derivative(c: &[Float; 4]) -> [Float; 4] {
+    [c[1], 2.0 * c[2], 3.0 * c[3], 0.0]
+}
+
+/// Multiplies two cubics, lowest power first
+fn product(a: &[Float; 4], b: &[Float; 4]) -> [Float; 7] {
+    let mut c = [0.0; 7];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            c[i + j] += x * y;
+        }
+    }
+    c
+}
+
+/// The range of a cubic over the segment
+fn extent(c: &[Float; 4]) -> (Float, Float) {
+    let f = eval(c);
+    let mut turning_points = solve_polynomial(&derivative(c), 0.0, 1.0);
+    turning_points.extend([0.0, 1.0].iter());
+    turning_points
+        .into_iter()
+        .map(f)
+        .fold((Float::INFINITY, Float::NEG_INFINITY), |(lo, hi), x| {
+            (lo.min(x), hi.max(x))
+        })
+}
+
+/// How far the parameter of a segment may stray outside `[0, 1]`, so that a
+/// ray through the join between two segments can't miss both
+fn slack() -> Float {
+    Float::EPSILON.sqrt()
+}
+
+impl Lathe {
+    ///
+    /// Creates a lathe from its profile. Returns `None` if there are the
+    /// wrong number of points for the spline.
+    ///
+    pub fn new(spline: Spline, points: Vec<(Float, Float)>) -> Option<Lathe> {
+        let segments = spline
+            .segments(points.len())?
+            .iter()
+            .map(|seg: &Segment| {
+                let radius = seg.coefficients(|i| points[i].0);
+                let height = seg.coefficients(|i| points[i].1);
+                let (r0, r1) = extent(&radius);
+                let (y0, y1) = extent(&height);
+                let r = r0.abs().max(r1.abs());
+
+                // pad the box a little, as a flat segment has no thickness
+                let pad = gamma(8) * (1.0 + r + y0.abs().max(y1.abs()));
+                Piece {
+                    radius,
+                    height,
+                    bounds: AxisAlignedBox {
+                        lower: point(-r - pad, y0 - pad, -r - pad),
+                        upper: point(r + pad, y1 + pad, r + pad),
+                    },
+                }
+            })
+            .collect();
+
+        Some(Lathe {
+            spline,
+            points,
+            segments,
+        })
+    }
+
+    pub fn spline(&self) -> Spline {
+        self.spline
+    }
+
+    pub fn points(&self) -> &[(Float, Float)] {
+        &self.points
+    }
+
+    ///
+    /// The nearest hit along the ray, with the segment hit and the
+    /// parameter along it
+    ///
+    fn nearest(&self, r: Ray) -> Option<(Float, usize, Float)> {
+        let mut nearest: Option<(Float, usize, Float)> = None;
+
+        for (n, piece) in self.segments.iter().enumerate() {
+            let (near, _) = match piece.bounds.span(&r) {
+                Some(span) => span,
+                None => continue,
+            };
+            let near = near.max(0.0);
+            for (t, s) in piece.intersect(r, near) {
+                let t = t + near;
+                match nearest {
+                    Some((best, _, _)) if best <= t => {}
+                    _ if t > 0.0 => nearest = Some((t, n, s)),
+                    _ => {}
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The hit record for an intersection `t` units along `r`, at parameter
+    /// `s` of segment `n` as found by `nearest`. The point is rebuilt from the
+    /// profile, and however far it is from where the ray was at `t` counts
+    /// towards its error, as that's how far out the root may be.
+    fn hit(&self, r: Ray, t: Float, n: usize, s: Float) -> Hit {
+        let p = r.extend(t);
+        let s = s.clamp(0.0, 1.0);
+        let piece = &self.segments[n];
+
+        // rebuild the point on the surface from the profile, keeping the
+        // angle around the axis from the hit
+        let phi = p.z.atan2(p.x);
+        let outward = vector(phi.cos(), 0.0, phi.sin());
+        let radius = eval(&piece.radius)(s);
+        let (dr, dh) = (
+            eval(&derivative(&piece.radius))(s),
+            eval(&derivative(&piece.height))(s),
+        );
+
+        // a profile that strays across the axis is mirrored back
+        let (radius, dr) = if radius < 0.0 {
+            (-radius, -dr)
+        } else {
+            (radius, dr)
+        };
+        let across = outward * radius;
+        let up = vector(0.0, eval(&piece.height)(s), 0.0);
+        let point = point(0.0, 0.0, 0.0) + across + up;
+        let drift = (p - point).abs();
+
+        // the tangent along the profile, turned to its right
+        let along = (outward * dr) + vector(0.0, dh, 0.0);
+        let normal = (outward * dh) - vector(0.0, dr, 0.0);
+        let normal = if normal.length() > 0.0 {
+            normal.normalize()
+        } else {
+            // the profile has stalled; fall back to facing off the axis
+            outward
+        };
+
+        let segments = self.segments.len() as Float;
+        Hit {
+            t,
+            point,
+            error: ((across.abs() + up.abs()) * gamma(7)) + drift,
+            normal: Normal3::from(normal),
+            shading_normal: Normal3::from(normal),
+            uv: ((phi + PI) / (2.0 * PI), (n as Float + s) / segments),
+            dpdu: vector(-point.z, 0.0, point.x) * (2.0 * PI),
+            dpdv: along * segments,
+            face: n,
+        }
+    }
+}
+
+impl Piece {
+    ///
+    /// Intersects the ray with this segment's surface, starting `near` units
+    /// along the ray to keep the numbers small. Gives the distance from
+    /// there, and the segment parameter, of each hit.
+    ///
+    fn intersect(&self, r: Ray, near: Float) -> Vec<(Float, Float)> {
+        let o = Vector3::from(r.extend(near));
+        let d = r.dir;
+
+        // the squared distance from the axis along the ray, as at² + bt + c
+        let a = (d.x * d.x) + (d.z * d.z);
+        let b = 2.0 * ((o.x * d.x) + (o.z * d.z));
+        let c = (o.x * o.x) + (o.z * o.z);
+
+        // the height of the segment relative to the ray's origin
+        let mut rise = self.height;
+        rise[0] -= o.y;
+
+        let radius = eval(&self.radius);
+        let mut hits = Vec::new();
+
+        // a ray running (nearly) level can't be solved for the segment
+        // parameter through its height, so find where the segment reaches
+        // the ray's height and then how far along the ray that radius is
+        if d.y.abs() < Float::EPSILON.sqrt().sqrt() {
+            for s in solve_polynomial(&rise, -slack(), 1.0 + slack()) {
+                let rs = radius(s);
+                if let Some((t0, t1)) = solve_quadratic(a, b, c - (rs * rs)) {
+                    hits.extend(self.polish(o, d, t0, s));
+                    hits.extend(self.polish(o, d, t1, s));
+                }
+            }
+            return hits;
+        }
+
+        // otherwise, substituting t = rise(s) / dy gives a polynomial in s:
+        // dy²·radius(s)² = a·rise(s)² + b·dy·rise(s) + c·dy². Any error in s
+        // is magnified by 1/dy in t, so each root is polished as well.
+        let dy = d.y;
+        let rr = product(&self.radius, &self.radius);
+        let yy = product(&rise, &rise);
+        let mut f = [0.0; 7];
+        for (n, f) in f.iter_mut().enumerate() {
+            *f = (dy * dy * rr[n]) - (a * yy[n]);
+            if n < 4 {
+                *f -= b * dy * rise[n];
+            }
+        }
+        f[0] -= c * dy * dy;
+
+        let rise = eval(&rise);
+        for s in solve_polynomial(&f, -slack(), 1.0 + slack()) {
+            hits.extend(self.polish(o, d, rise(s) / dy, s));
+        }
+        hits
+    }
+
+    ///
+    /// Refines a rough hit at distance `t` and segment parameter `s` with a
+    /// few rounds of Newton's method on the pair of equations that put the
+    /// point both on the ray and on the surface
+    ///
+    fn polish(&self, o: Vector3, d: Vector3, t: Float, s: Float) -> Option<(Float, Float)> {
+        let (radius, dr) = (eval(&self.radius), derivative(&self.radius));
+        let (height, dh) = (eval(&self.height), derivative(&self.height));
+        let (dr, dh) = (eval(&dr), eval(&dh));
+
+        let (mut t, mut s) = (t, s);
+        for _ in 0..4 {
+            let p = o + (d * t);
+            let rs = radius(s);
+            let f1 = height(s) - p.y;
+            let f2 = (rs * rs) - (p.x * p.x) - (p.z * p.z);
+
+            // the Jacobian of (f1, f2) with respect to (s, t)
+            let (j11, j12) = (dh(s), -d.y);
+            let (j21, j22) = (2.0 * rs * dr(s), -2.0 * ((p.x * d.x) + (p.z * d.z)));
+            let det = (j11 * j22) - (j12 * j21);
+            if det == 0.0 {
+                break;
+            }
+            s -= ((j22 * f1) - (j12 * f2)) / det;
+            t -= ((j11 * f2) - (j21 * f1)) / det;
+        }
+
+        if s >= -slack() && s <= 1.0 + slack() && t.is_finite() {
+            Some((t, s))
+        } else {
+            None
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a lathe object
+impl cmp::PartialEq for Lathe {
+    fn eq(&self, other: &Lathe) -> bool {
+        self.spline == other.spline && self.points == other.points
+    }
+}
+
+impl Primitive for Lathe {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, n, s)| self.hit(r, t, n, s))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let first = self.segments[0].bounds.clone();
+        self.segments
+            .iter()
+            .skip(1)
+            .fold(first, |acc, piece| acc.union(&piece.bounds))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    /// A cylinder of radius 1 from y = 0 to 2, with its ends capped
+    fn capped_cylinder() -> Lathe {
+        Lathe::new(
+            Spline::Linear,
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 2.0), (0.0, 2.0)],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn linear_profiles_make_flat_and_straight_sides() {
+        let l = capped_cylinder();
+
+        let r = Ray::new(point(-10.0, 1.0, 0.0), vector(1.0, 0.0, 0.0));
+        let h = l.intersects(r).unwrap();
+        assert!((h.t - 9.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(-1.0, 0.0, 0.0)), "{:?}", h);
+        assert_eq!(h.face, 1);
+
+        let r = Ray::new(point(0.5, 10.0, 0.2), vector(0.0, -1.0, 0.0));
+        let h = l.intersects(r).unwrap();
+        assert!((h.t - 8.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)), "{:?}", h);
+        assert_eq!(h.face, 2);
+
+        assert!(l
+            .intersects(Ray::new(point(1.5, 10.0, 0.0), vector(0.0, -1.0, 0.0)))
+            .is_none());
+    }
+
+    #[test]
+    fn oblique_rays_hit_the_surface() {
+        let l = capped_cylinder();
+        let dir = vector(1.0, -0.3, 0.2).normalize();
+        let r = Ray::new(point(-5.0, 2.5, -0.5), dir);
+        let h = l.intersects(r).unwrap();
+
+        let p = r.extend(h.t);
+        assert!(((p.x * p.x) + (p.z * p.z) - 1.0).abs() < 1e-4, "{:?}", h);
+        assert!(h.point.approx_eq(p), "{:?} vs {:?}", h.point, p);
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn curved_profiles() {
+        // a quadratic bulging out to a radius of 1 at y = 1
+        let l = Lathe::new(Spline::Quadratic, vec![(0.0, 0.0), (2.0, 1.0), (0.0, 2.0)]).unwrap();
+        let bounds = l.bounding_box();
+        assert!((bounds.upper.x - 1.0).abs() < 1e-4, "{:?}", bounds);
+        assert!((bounds.upper.y - 2.0).abs() < 1e-4, "{:?}", bounds);
+
+        let r = Ray::new(point(-10.0, 1.0, 0.0), vector(1.0, 0.0, 0.0));
+        let h = l.intersects(r).unwrap();
+        assert!(h.point.approx_eq(point(-1.0, 1.0, 0.0)), "{:?}", h);
+        assert!(h.normal.approx_eq(normal(-1.0, 0.0, 0.0)), "{:?}", h);
+
+        // the same profile as a Catmull-Rom spline
+        let points = vec![(0.0, -1.0), (0.0, 0.0), (1.0, 1.0), (0.0, 2.0), (0.0, 3.0)];
+        let l = Lathe::new(Spline::Cubic, points).unwrap();
+        let r = Ray::new(point(0.3, 10.0, 0.4), vector(0.0, -1.0, 0.0));
+        let h = l.intersects(r).unwrap();
+        assert!(h.point.y > 1.0 && h.point.y < 2.0, "{:?}", h);
+        assert!(h.normal.y > 0.0, "{:?}", h);
+    }
+
+    #[test]
+    fn shadow_rays_leave_the_surface() {
+        // a vase, lit from where it's seen from, so nothing on it can be in
+        // shadow
+        let points = vec![
+            (0.0, -1.0),
+            (0.0, 0.0),
+            (1.0, 0.5),
+            (0.4, 2.0),
+            (0.6, 3.0),
+            (0.6, 4.0),
+        ];
+        let l = Lathe::new(Spline::Cubic, points).unwrap();
+        let eye = point(0.0, 2.0, -10.0);
+
+        for j in 0..50 {
+            for i in 0..50 {
+                let target = point(i as Float / 25.0 - 1.0, j as Float / 10.0 - 1.0, 0.0);
+                let h = match l.intersects(Ray::new(eye, target - eye)) {
+                    Some(h) => h,
+                    None => continue,
+                };
+
+                let to_eye = eye - h.point;
+                let shadow = raygun_math::spawn_ray(h.point, h.error, h.normal, to_eye);
+                if let Some(blocker) = l.intersects(shadow) {
+                    assert!(
+                        blocker.t >= to_eye.length(),
+                        "{:?} shadows {:?}",
+                        blocker,
+                        h
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_point_counts() {
+        assert!(Lathe::new(Spline::Linear, vec![(0.0, 0.0)]).is_none());
+        assert!(Lathe::new(Spline::Quadratic, vec![(0.0, 0.0); 4]).is_none());
+        assert!(Lathe::new(Spline::Cubic, vec![(0.0, 0.0); 3]).is_none());
+    }
+}
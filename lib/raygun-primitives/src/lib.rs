@@ -1,41 +1,102 @@
 pub mod _box;
 pub mod aabb;
+pub mod bezier;
+pub mod blob;
+pub mod cone;
+pub mod cylinder;
+pub mod disc;
+mod flat;
+pub mod height_field;
+pub mod lathe;
 pub mod light;
+pub mod mesh;
 pub mod object;
 pub mod plane;
 pub mod point_light;
+pub mod polygon;
 pub mod primitive;
+pub mod rectangle;
+pub mod sdf;
 pub mod sphere;
+pub mod spline;
+pub mod spot_light;
+pub mod sweep;
+pub mod text;
+pub mod torus;
 pub mod union;
 
 pub use self::{
     _box::Box,
     aabb::AxisAlignedBox,
+    bezier::{BezierPatch, BezierPatches},
+    blob::Blob,
+    cone::Cone,
+    cylinder::Cylinder,
+    disc::Disc,
+    height_field::HeightField,
+    lathe::Lathe,
     light::Light,
+    mesh::Mesh,
     object::{Object, ObjectList},
     plane::Plane,
     point_light::PointLight,
+    polygon::Polygon,
     primitive::Primitive,
+    rectangle::Rectangle,
+    sdf::{Field, Sdf},
     sphere::Sphere,
+    spline::Spline,
+    spot_light::SpotLight,
+    sweep::Sweep,
+    text::{Alignment, Font, Text},
+    torus::Torus,
     union::Union,
 };
 
 use raygun_material::{Colour, Finish};
-use raygun_math::{Transform, Vector};
-use std::sync::Arc;
+use raygun_math::{Float, Normal3, Point3, Vector3};
 
 ///
 /// The details of an object's surface at a given point.
 ///
 #[derive(Debug)]
 pub struct SurfaceInfo<'a> {
-    pub normal: Vector,
+    pub normal: Normal3,
     pub colour: Colour,
     pub finish: &'a Finish,
 }
 
-pub trait Visitor {
-    fn push_transform(&mut self, _t: &Transform) {}
-    fn pop_transform(&mut self) {}
-    fn visit(&mut self, obj: Arc<Object>);
+///
+/// Everything known about where a ray hits an object
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// The distance along the ray
+    pub t: Float,
+
+    /// The point hit
+    pub point: Point3,
+
+    /// A conservative bound on the floating point error in each of `point`'s
+    /// coordinates
+    pub error: Vector3,
+
+    /// The true (unit) normal of the surface geometry at `point`
+    pub normal: Normal3,
+
+    /// The (unit) normal to use for shading, which may be perturbed away
+    /// from the geometric normal
+    pub shading_normal: Normal3,
+
+    /// The surface parameterisation at `point`, for texture mapping. Each
+    /// primitive maps its surface into the unit square.
+    pub uv: (Float, Float),
+
+    /// The rate of change of the point with respect to `u` and `v`
+    pub dpdu: Vector3,
+    pub dpdv: Vector3,
+
+    /// Identifies which face or sub-object of the primitive was hit. What
+    /// this means is up to each primitive.
+    pub face: usize,
 }
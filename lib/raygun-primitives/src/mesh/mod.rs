@@ -0,0 +1,513 @@
+//! Triangle meshes, and loaders for the file formats they come in.
+
+use std::{cmp, fs, ops::Range, path::Path};
+
+use crate::{
+    flat::{barycentric, triangle_distance},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_material::Colour;
+use raygun_math::{gamma, point, Float, Normal3, Point3, Ray, Vector3};
+
+mod obj;
+mod ply;
+mod stl;
+
+///
+/// A mesh of triangles, each given as the indices of its three corners in
+/// the list of vertices. The triangles face the way their corners wind
+/// anticlockwise. Vertices may also carry normals, which are interpolated
+/// across each triangle for smooth shading; colours, for use with a
+/// `Vertex` pigment; and texture coordinates.
+///
+/// The triangles are sorted into a bounding volume hierarchy, so even very
+/// large meshes are quick to intersect.
+///
+#[derive(Debug)]
+pub struct Mesh {
+    vertices: Vec<Point3>,
+    triangles: Vec<[usize; 3]>,
+    normals: Option<Vec<Vector3>>,
+    colours: Option<Vec<Colour>>,
+    uvs: Option<Vec<(Float, Float)>>,
+
+    // the triangles worth intersecting, in the order the hierarchy's leaves
+    // refer to them
+    order: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+/// A node of the bounding volume hierarchy
+#[derive(Debug)]
+struct Node {
+    bounds: AxisAlignedBox,
+    content: Content,
+}
+
+#[derive(Debug)]
+enum Content {
+    /// A run of `order`
+    Leaf(Range<usize>),
+
+    /// A branch, whose first child is the next node and whose second is at
+    /// the given index
+    Branch(usize),
+}
+
+/// The most triangles a leaf of the hierarchy holds
+const LEAF_SIZE: usize = 4;
+
+fn bounds_of<'a, I: Iterator<Item = &'a Point3>>(points: I) -> AxisAlignedBox {
+    let inf = Float::INFINITY;
+    points.fold(
+        AxisAlignedBox {
+            lower: point(inf, inf, inf),
+            upper: point(-inf, -inf, -inf),
+        },
+        |b, p| AxisAlignedBox {
+            lower: point(b.lower.x.min(p.x), b.lower.y.min(p.y), b.lower.z.min(p.z)),
+            upper: point(b.upper.x.max(p.x), b.upper.y.max(p.y), b.upper.z.max(p.z)),
+        },
+    )
+}
+
+impl Mesh {
+    ///
+    /// Creates a mesh of `triangles` over `vertices`. Returns `None` if a
+    /// triangle refers to a vertex that doesn't exist, or if every triangle
+    /// is degenerate.
+    ///
+    pub fn new(vertices: Vec<Point3>, triangles: Vec<[usize; 3]>) -> Option<Mesh> {
+        if triangles.iter().flatten().any(|&i| i >= vertices.len()) {
+            return None;
+        }
+
+        let mut mesh = Mesh {
+            vertices,
+            triangles,
+            normals: None,
+            colours: None,
+            uvs: None,
+            order: Vec::new(),
+            nodes: Vec::new(),
+        };
+
+        // there's no hitting triangles with no area
+        mesh.order = (0..mesh.triangles.len())
+            .filter(|&i| mesh.face_normal(i) != Vector3::default())
+            .collect();
+        if mesh.order.is_empty() {
+            return None;
+        }
+        mesh.build(0, mesh.order.len());
+
+        Some(mesh)
+    }
+
+    /// Gives each vertex a normal. Returns `None` if there are the wrong
+    /// number of them.
+    pub fn with_normals(mut self, normals: Vec<Vector3>) -> Option<Mesh> {
+        if normals.len() != self.vertices.len() {
+            return None;
+        }
+        self.normals = Some(normals.into_iter().map(|n| n.normalize()).collect());
+        Some(self)
+    }
+
+    /// Gives each vertex a colour. Returns `None` if there are the wrong
+    /// number of them.
+    pub fn with_colours(mut self, colours: Vec<Colour>) -> Option<Mesh> {
+        if colours.len() != self.vertices.len() {
+            return None;
+        }
+        self.colours = Some(colours);
+        Some(self)
+    }
+
+    /// Gives each vertex a texture coordinate. Returns `None` if there are
+    /// the wrong number of them.
+    pub fn with_uvs(mut self, uvs: Vec<(Float, Float)>) -> Option<Mesh> {
+        if uvs.len() != self.vertices.len() {
+            return None;
+        }
+        self.uvs = Some(uvs);
+        Some(self)
+    }
+
+    ///
+    /// Loads a mesh from a file, choosing the format from its extension:
+    /// `.ply` for the Stanford polygon format (ASCII or binary), `.stl` for
+    /// stereolithography files (likewise), or `.obj` for Wavefront files.
+    ///
+    pub fn load(path: &Path) -> Result<Mesh, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let read = match extension.as_deref() {
+            Some("obj") => obj::read,
+            Some("ply") => ply::read,
+            Some("stl") => stl::read,
+            _ => {
+                return Err(format!(
+                    "{}: unknown mesh format, expected a .ply, .stl or .obj file",
+                    path.display()
+                ))
+            }
+        };
+
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        read(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn vertices(&self) -> &[Point3] {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    pub fn normals(&self) -> Option<&[Vector3]> {
+        self.normals.as_deref()
+    }
+
+    pub fn colours(&self) -> Option<&[Colour]> {
+        self.colours.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[(Float, Float)]> {
+        self.uvs.as_deref()
+    }
+
+    fn corners(&self, triangle: usize) -> (Point3, Point3, Point3) {
+        let [a, b, c] = self.triangles[triangle];
+        (self.vertices[a], self.vertices[b], self.vertices[c])
+    }
+
+    /// The unit normal of a triangle, or zero if it's degenerate
+    fn face_normal(&self, triangle: usize) -> Vector3 {
+        let (p0, p1, p2) = self.corners(triangle);
+        let n = (p1 - p0).cross(p2 - p0);
+        match n.length() {
+            l if l > 0.0 => n / l,
+            _ => Vector3::default(),
+        }
+    }
+
+    /// Builds the hierarchy over `order[start..end]`, returning the index
+    /// of its root
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let triangles = &self.triangles;
+        let vertices = &self.vertices;
+        let corners = |t: usize| triangles[t].iter().map(move |&i| &vertices[i]);
+
+        let bounds = bounds_of(self.order[start..end].iter().flat_map(|&t| corners(t)));
+        let index = self.nodes.len();
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node {
+                bounds,
+                content: Content::Leaf(start..end),
+            });
+            return index;
+        }
+
+        // split at the median centroid along the axis they spread furthest
+        let centroid =
+            |t: usize| corners(t).fold(Vector3::default(), |acc, &p| acc + Vector3::from(p)) / 3.0;
+        let spread = bounds_of(
+            self.order[start..end]
+                .iter()
+                .map(|&t| Point3::from(centroid(t)))
+                .collect::<Vec<_>>()
+                .iter(),
+        );
+        let size = spread.upper - spread.lower;
+        let axis = |v: Vector3| {
+            if size.x >= size.y && size.x >= size.z {
+                v.x
+            } else if size.y >= size.z {
+                v.y
+            } else {
+                v.z
+            }
+        };
+        let middle = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(middle - start, |&a, &b| {
+            axis(centroid(a))
+                .partial_cmp(&axis(centroid(b)))
+                .unwrap_or(cmp::Ordering::Equal)
+        });
+
+        self.nodes.push(Node {
+            bounds,
+            content: Content::Branch(0),
+        });
+        self.build(start, middle);
+        let second = self.build(middle, end);
+        self.nodes[index].content = Content::Branch(second);
+        index
+    }
+
+    /// The nearest triangle along `r`, with the barycentric weights of its
+    /// second and third corners at the hit
+    fn nearest(&self, r: Ray) -> Option<(Float, usize, Float, Float)> {
+        let mut nearest: Option<(Float, usize, Float, Float)> = None;
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            match (node.bounds.span(&r), nearest) {
+                (None, _) => continue,
+                (Some((near, _)), Some((best, ..))) if near > best => continue,
+                _ => {}
+            }
+
+            match node.content {
+                Content::Leaf(ref range) => {
+                    for &triangle in self.order[range.clone()].iter() {
+                        let (p0, p1, p2) = self.corners(triangle);
+                        if let Some((t, b1, b2)) = triangle_distance(r, p0, p1, p2) {
+                            match nearest {
+                                Some((best, ..)) if best <= t => {}
+                                _ => nearest = Some((t, triangle, b1, b2)),
+                            }
+                        }
+                    }
+                }
+                Content::Branch(second) => {
+                    stack.push(second);
+                    stack.push(index + 1);
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The hit record for an intersection `t` units along a ray, on
+    /// `triangle` with the barycentric weights `b1` and `b2` found by
+    /// `nearest`
+    fn hit(&self, t: Float, triangle: usize, b1: Float, b2: Float) -> Hit {
+        let [i0, i1, i2] = self.triangles[triangle];
+        let (p0, p1, p2) = self.corners(triangle);
+        let b0 = 1.0 - b1 - b2;
+
+        // the point from the barycentric weights is more accurate than
+        // extending the ray
+        let (v0, v1, v2) = (
+            Vector3::from(p0) * b0,
+            Vector3::from(p1) * b1,
+            Vector3::from(p2) * b2,
+        );
+        let point = Point3::from(v0 + v1 + v2);
+        let error = (v0.abs() + v1.abs() + v2.abs()) * gamma(7);
+
+        let mut normal = self.face_normal(triangle);
+        let shading = match self.normals {
+            Some(ref normals) => {
+                let n = (normals[i0] * b0) + (normals[i1] * b1) + (normals[i2] * b2);
+                let n = n.normalize();
+                // keep the true normal on the same side as the file's
+                if n.dot(normal) < 0.0 {
+                    normal = -normal;
+                }
+                n
+            }
+            None => normal,
+        };
+
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let (uv, dpdu, dpdv) = match self.uvs {
+            Some(ref uvs) => {
+                let (uv0, uv1, uv2) = (uvs[i0], uvs[i1], uvs[i2]);
+                let uv = (
+                    uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2,
+                    uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2,
+                );
+                let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+                let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+                let det = du1 * dv2 - dv1 * du2;
+                if det == 0.0 {
+                    (uv, e1, e2)
+                } else {
+                    (
+                        uv,
+                        ((e1 * dv2) - (e2 * dv1)) / det,
+                        ((e2 * du1) - (e1 * du2)) / det,
+                    )
+                }
+            }
+            None => ((b1, b2), e1, e2),
+        };
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: Normal3::from(normal),
+            shading_normal: Normal3::from(shading),
+            uv,
+            dpdu,
+            dpdv,
+            face: triangle,
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a mesh object
+impl cmp::PartialEq for Mesh {
+    fn eq(&self, other: &Mesh) -> bool {
+        self.vertices == other.vertices
+            && self.triangles == other.triangles
+            && self.normals == other.normals
+            && self.colours == other.colours
+            && self.uvs == other.uvs
+    }
+}
+
+impl Primitive for Mesh {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r)
+            .map(|(t, triangle, b1, b2)| self.hit(t, triangle, b1, b2))
+    }
+
+    fn vertex_colour(&self, p: Point3, face: usize) -> Option<Colour> {
+        let colours = self.colours.as_ref()?;
+        let [i0, i1, i2] = *self.triangles.get(face)?;
+        let (p0, p1, p2) = self.corners(face);
+        let (b1, b2) = barycentric(p, p0, p1 - p0, p2 - p0)?;
+        let (b1, b2) = (b1.clamp(0.0, 1.0), b2.clamp(0.0, 1.0));
+        let b0 = (1.0 - b1 - b2).max(0.0);
+
+        Some((colours[i0] * b0) + (colours[i1] * b1) + (colours[i2] * b2))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        self.nodes[0].bounds.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    /// A unit cube made of many small triangles, each face a grid
+    fn cube(cells: usize) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        let axes = [
+            (
+                vector(1.0, 0.0, 0.0),
+                vector(0.0, 1.0, 0.0),
+                vector(0.0, 0.0, 1.0),
+            ),
+            (
+                vector(0.0, 1.0, 0.0),
+                vector(0.0, 0.0, 1.0),
+                vector(1.0, 0.0, 0.0),
+            ),
+            (
+                vector(0.0, 0.0, 1.0),
+                vector(1.0, 0.0, 0.0),
+                vector(0.0, 1.0, 0.0),
+            ),
+        ];
+        for &(n, u, v) in axes.iter() {
+            for &side in [-1.0, 1.0].iter() {
+                let (u, v) = if side > 0.0 { (u, v) } else { (v, u) };
+                let first = vertices.len();
+                for j in 0..=cells {
+                    for i in 0..=cells {
+                        let (s, t) = (
+                            i as Float / cells as Float - 0.5,
+                            j as Float / cells as Float - 0.5,
+                        );
+                        vertices.push(Point3::from((n * (side * 0.5)) + (u * s) + (v * t)));
+                    }
+                }
+                let at = |i: usize, j: usize| first + j * (cells + 1) + i;
+                for j in 0..cells {
+                    for i in 0..cells {
+                        triangles.push([at(i, j), at(i + 1, j), at(i + 1, j + 1)]);
+                        triangles.push([at(i, j), at(i + 1, j + 1), at(i, j + 1)]);
+                    }
+                }
+            }
+        }
+        Mesh::new(vertices, triangles).unwrap()
+    }
+
+    #[test]
+    fn meshes_are_intersected_through_the_hierarchy() {
+        let m = cube(8);
+        assert!(m.nodes.len() > 1);
+
+        let b = m.bounding_box();
+        assert!(b.lower.approx_eq(point(-0.5, -0.5, -0.5)), "{:?}", b);
+        assert!(b.upper.approx_eq(point(0.5, 0.5, 0.5)), "{:?}", b);
+
+        for &(src, dir, n) in [
+            (
+                point(0.1, 0.2, -5.0),
+                vector(0.0, 0.0, 1.0),
+                normal(0.0, 0.0, -1.0),
+            ),
+            (
+                point(0.3, 5.0, -0.2),
+                vector(0.0, -1.0, 0.0),
+                normal(0.0, 1.0, 0.0),
+            ),
+            (
+                point(5.0, -0.4, 0.4),
+                vector(-1.0, 0.0, 0.0),
+                normal(1.0, 0.0, 0.0),
+            ),
+        ]
+        .iter()
+        {
+            let r = Ray::new(src, dir);
+            let h = m.intersects(r).unwrap();
+            assert!((h.t - 4.5).abs() < APPROX_EPSILON, "{:?}", h);
+            assert!(h.normal.approx_eq(n), "{:?}", h);
+        }
+
+        let r = Ray::new(point(0.6, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(m.intersects(r).is_none());
+    }
+
+    #[test]
+    fn vertex_attributes_are_interpolated() {
+        let vertices = vec![
+            point(0.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            point(0.0, 1.0, 0.0),
+        ];
+        let m = Mesh::new(vertices, vec![[0, 2, 1]])
+            .unwrap()
+            .with_normals(vec![
+                vector(0.0, 0.0, -1.0),
+                vector(1.0, 0.0, -1.0),
+                vector(0.0, 0.0, -1.0),
+            ])
+            .unwrap()
+            .with_colours(vec![
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(0.0, 1.0, 0.0),
+                Colour::new(0.0, 0.0, 1.0),
+            ])
+            .unwrap();
+
+        let r = Ray::new(point(0.5, 0.25, -1.0), vector(0.0, 0.0, 1.0));
+        let h = m.intersects(r).unwrap();
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, -1.0)), "{:?}", h);
+        assert!(h.shading_normal.x > 0.0, "{:?}", h);
+
+        let c = m.vertex_colour(h.point, h.face).unwrap();
+        assert!(c.approx_eq(Colour::new(0.25, 0.5, 0.25)), "{:?}", c);
+
+        assert!(Mesh::new(vec![point(0.0, 0.0, 0.0)], vec![[0, 0, 1]]).is_none());
+        assert!(cube(1).with_colours(Vec::new()).is_none());
+    }
+}
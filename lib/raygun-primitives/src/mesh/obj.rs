@@ -0,0 +1,222 @@
+//! Reads meshes in the Wavefront OBJ format. Vertex positions, texture
+//! coordinates and normals are read, along with vertex colours written as
+//! three extra values after a position. Faces with more than three corners
+//! are split into fans of triangles. Lines, groups, smoothing and materials
+//! are skipped over.
+
+use std::{collections::HashMap, str};
+
+use super::Mesh;
+use raygun_material::Colour;
+use raygun_math::{Float, Point3, Vector3};
+
+/// A face corner's indices into the positions, texture coordinates and
+/// normals, counting from zero
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Reads `N` numbers from the start of a statement's arguments
+fn numbers<const N: usize>(args: &[&str]) -> Result<[f64; N], String> {
+    if args.len() < N {
+        return Err(format!("expected {} numbers, found {}", N, args.len()));
+    }
+    let mut values = [0.0; N];
+    for (value, word) in values.iter_mut().zip(args) {
+        *value = word
+            .parse()
+            .map_err(|_| format!("\"{}\" isn't a number", word))?;
+    }
+    Ok(values)
+}
+
+/// Turns a one-based index, or a negative one counting back from the most
+/// recent item, into a zero-based one
+fn index(word: &str, count: usize) -> Result<usize, String> {
+    let n: isize = word
+        .parse()
+        .map_err(|_| format!("\"{}\" isn't an index", word))?;
+    let i = if n < 0 { count as isize + n } else { n - 1 };
+    if i < 0 || i as usize >= count {
+        return Err(format!("index {} is out of range", n));
+    }
+    Ok(i as usize)
+}
+
+/// Parses a face corner, written `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn corner(word: &str, counts: [usize; 3]) -> Result<Corner, String> {
+    let mut parts = word.split('/');
+    let v = index(parts.next().unwrap_or(""), counts[0])?;
+    let mut optional = |count| match parts.next() {
+        None | Some("") => Ok(None),
+        Some(w) => index(w, count).map(Some),
+    };
+    let vt = optional(counts[1])?;
+    let vn = optional(counts[2])?;
+    Ok((v, vt, vn))
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Mesh, String> {
+    let text = str::from_utf8(data).map_err(|_| "the file isn't text")?;
+
+    let mut positions = Vec::new();
+    let mut colours = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut faces: Vec<Vec<Corner>> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (statement, args) = match words.split_first() {
+            Some((s, args)) => (*s, args),
+            None => continue,
+        };
+
+        match statement {
+            "v" => {
+                let [x, y, z] = numbers(args)?;
+                positions.push(Point3::new(x as Float, y as Float, z as Float));
+                if args.len() >= 6 {
+                    let [r, g, b] = numbers(&args[3..])?;
+                    colours.push(Colour::new(r, g, b));
+                }
+            }
+            "vt" => {
+                // the second coordinate is optional, as is an unused third
+                let [u] = numbers(args)?;
+                let [v] = if args.len() > 1 {
+                    numbers(&args[1..])?
+                } else {
+                    [0.0]
+                };
+                uvs.push((u as Float, v as Float));
+            }
+            "vn" => {
+                let [x, y, z] = numbers(args)?;
+                normals.push(Vector3::new(x as Float, y as Float, z as Float));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err("a face has fewer than 3 corners".to_owned());
+                }
+                let counts = [positions.len(), uvs.len(), normals.len()];
+                let corners = args
+                    .iter()
+                    .map(|w| corner(w, counts))
+                    .collect::<Result<_, _>>()?;
+                faces.push(corners);
+            }
+            _ => {}
+        }
+    }
+
+    // attributes only apply if every corner has them
+    let corners = || faces.iter().flatten();
+    let with_colours = !colours.is_empty() && colours.len() == positions.len();
+    let with_uvs = corners().all(|c| c.1.is_some());
+    let with_normals = corners().all(|c| c.2.is_some());
+
+    // each distinct combination of position and attributes becomes a vertex
+    let mut seen = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for face in faces.iter() {
+        let mut at = |&(v, vt, vn): &Corner| {
+            let key = (v, vt.filter(|_| with_uvs), vn.filter(|_| with_normals));
+            *seen.entry(key).or_insert_with(|| {
+                vertices.push(key);
+                vertices.len() - 1
+            })
+        };
+        let first = at(&face[0]);
+        for pair in face[1..].windows(2) {
+            triangles.push([first, at(&pair[0]), at(&pair[1])]);
+        }
+    }
+
+    let points = vertices.iter().map(|&(v, _, _)| positions[v]).collect();
+    let mut mesh =
+        Mesh::new(points, triangles).ok_or_else(|| "the faces have no area".to_owned())?;
+    if with_colours {
+        let colours = vertices.iter().map(|&(v, _, _)| colours[v]).collect();
+        mesh = mesh.with_colours(colours).unwrap();
+    }
+    if with_uvs {
+        let uvs = vertices
+            .iter()
+            .map(|&(_, vt, _)| uvs[vt.unwrap()])
+            .collect();
+        mesh = mesh.with_uvs(uvs).unwrap();
+    }
+    if with_normals {
+        let normals = vertices
+            .iter()
+            .map(|&(_, _, vn)| normals[vn.unwrap()])
+            .collect();
+        mesh = mesh.with_normals(normals).unwrap();
+    }
+
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::vector;
+
+    #[test]
+    fn faces_share_vertices() {
+        let text = "# a unit square
+            o square
+            v 0 0 0
+            v 0 1 0
+            v 1 1 0
+            v 1 0 0
+            s off
+            f 1 2 3 4
+        ";
+        let mesh = read(text.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.vertices()[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.triangles(), &[[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.normals().is_none() && mesh.uvs().is_none());
+    }
+
+    #[test]
+    fn attributes_split_vertices() {
+        let text = "
+            v 0 0 0 1 0 0
+            v 0 1 0 0 1 0
+            v 1 0 0 0 0 1
+            vt 0 0
+            vt 0 1
+            vt 1 0
+            vn 0 0 -1
+            vn 0 0 1
+            f 1/1/1 2/2/1 3/3/1
+            f -3/1/-1 -1/3/-1 -2/2/-1
+        ";
+        let mesh = read(text.as_bytes()).unwrap();
+
+        // the same corners with different normals can't be shared
+        assert_eq!(mesh.vertices().len(), 6);
+        assert_eq!(mesh.triangles(), &[[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(mesh.normals().unwrap()[3], vector(0.0, 0.0, 1.0));
+        assert_eq!(mesh.uvs().unwrap()[4], (1.0, 0.0));
+        assert_eq!(mesh.colours().unwrap()[1], Colour::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn bad_files() {
+        for text in [
+            "v 0 0 0\nv 1 0 0\nf 1 2\n",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+            "v 0 0\n",
+            "v 0 0 0\nv 1 0 0\nv 2 0 0\nf 1 2 3\n",
+        ]
+        .iter()
+        {
+            assert!(read(text.as_bytes()).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Reads meshes in the Stanford polygon (PLY) format, whether ASCII or
+//! binary. Vertex positions, normals, colours and texture coordinates are
+//! read, and polygonal faces are split into fans of triangles. Any other
+//! elements are skipped over.
+
+use std::str;
+
+use super::Mesh;
+use raygun_material::Colour;
+use raygun_math::{Float, Point3, Vector3};
+
+/// The type of a single value in a PLY file
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Scalar, String> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(format!("unknown property type \"{}\"", name)),
+        })
+    }
+
+    /// What a colour channel of this type is divided by to bring it
+    /// between 0 and 1
+    fn full_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+
+    /// A list, with the type of its length and of its items
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Where the values in the body of the file come from
+trait Values {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, String>;
+}
+
+struct Ascii<'a>(str::SplitAsciiWhitespace<'a>);
+
+impl<'a> Values for Ascii<'a> {
+    fn next(&mut self, _: Scalar) -> Result<f64, String> {
+        let word = self.0.next().ok_or("the file ends too soon")?;
+        word.parse()
+            .map_err(|_| format!("\"{}\" isn't a number", word))
+    }
+}
+
+struct Binary<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Binary<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.data.len() < N {
+            return Err("the file ends too soon".to_owned());
+        }
+        let (bytes, rest) = self.data.split_at(N);
+        self.data = rest;
+        let mut b = [0; N];
+        b.copy_from_slice(bytes);
+        if self.big_endian {
+            b.reverse();
+        }
+        Ok(b)
+    }
+}
+
+impl<'a> Values for Binary<'a> {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, String> {
+        Ok(match scalar {
+            Scalar::I8 => f64::from(i8::from_le_bytes(self.take()?)),
+            Scalar::U8 => f64::from(u8::from_le_bytes(self.take()?)),
+            Scalar::I16 => f64::from(i16::from_le_bytes(self.take()?)),
+            Scalar::U16 => f64::from(u16::from_le_bytes(self.take()?)),
+            Scalar::I32 => f64::from(i32::from_le_bytes(self.take()?)),
+            Scalar::U32 => f64::from(u32::from_le_bytes(self.take()?)),
+            Scalar::F32 => f64::from(f32::from_le_bytes(self.take()?)),
+            Scalar::F64 => f64::from_le_bytes(self.take()?),
+        })
+    }
+}
+
+/// Splits the file into its header lines and its body
+fn split_header(data: &[u8]) -> Result<(Vec<&str>, &[u8]), String> {
+    let mut lines = Vec::new();
+    let mut rest = data;
+    loop {
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or("the header has no end_header line")?;
+        let line = str::from_utf8(&rest[..end])
+            .map_err(|_| "the header isn't text")?
+            .trim_end_matches('\r');
+        rest = &rest[end + 1..];
+        if line.trim() == "end_header" {
+            return Ok((lines, rest));
+        }
+        lines.push(line);
+    }
+}
+
+fn parse_header(lines: &[&str]) -> Result<(Option<bool>, Vec<Element>), String> {
+    if lines.first().map(|l| l.trim()) != Some("ply") {
+        return Err("not a PLY file".to_owned());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines[1..].iter() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", kind, _version] => {
+                format = Some(match *kind {
+                    "ascii" => None,
+                    "binary_little_endian" => Some(false),
+                    "binary_big_endian" => Some(true),
+                    _ => return Err(format!("unknown format \"{}\"", kind)),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("bad element count \"{}\"", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or("a property comes before any element")?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    Scalar::parse(count)?,
+                    Scalar::parse(item)?,
+                )),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or("a property comes before any element")?
+                .properties
+                .push(Property::Scalar(name.to_string(), Scalar::parse(scalar)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("can't understand header line \"{}\"", line)),
+        }
+    }
+
+    let format = format.ok_or("the header gives no format")?;
+    Ok((format, elements))
+}
+
+/// Finds the first of `names` among an element's scalar properties
+fn find(properties: &[Property], names: &[&str]) -> Option<(usize, Scalar)> {
+    names.iter().find_map(|wanted| {
+        properties.iter().enumerate().find_map(|(i, p)| match p {
+            Property::Scalar(name, scalar) if name == wanted => Some((i, *scalar)),
+            _ => None,
+        })
+    })
+}
+
+/// Finds all of a set of scalar properties, if the element has them all
+fn find_all<const N: usize>(
+    properties: &[Property],
+    names: [&[&str]; N],
+) -> Option<[(usize, Scalar); N]> {
+    let mut found = [(0, Scalar::F32); N];
+    for (f, names) in found.iter_mut().zip(names.iter()) {
+        *f = find(properties, names)?;
+    }
+    Some(found)
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Mesh, String> {
+    let (header, body) = split_header(data)?;
+    let (format, elements) = parse_header(&header)?;
+
+    let mut values: Box<dyn Values> = match format {
+        None => Box::new(Ascii(
+            str::from_utf8(body)
+                .map_err(|_| "the body isn't text")?
+                .split_ascii_whitespace(),
+        )),
+        Some(big_endian) => Box::new(Binary {
+            data: body,
+            big_endian,
+        }),
+    };
+
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut colours = Vec::new();
+    let mut uvs = Vec::new();
+    let mut triangles = Vec::new();
+
+    for element in elements.iter() {
+        let props = &element.properties;
+        let position = find_all(props, [&["x"], &["y"], &["z"]]);
+        let normal = find_all(props, [&["nx"], &["ny"], &["nz"]]);
+        let colour = find_all(
+            props,
+            [
+                &["red", "diffuse_red", "r"],
+                &["green", "diffuse_green", "g"],
+                &["blue", "diffuse_blue", "b"],
+            ],
+        );
+        let uv = find_all(
+            props,
+            [
+                &["u", "s", "texture_u", "texture_s"],
+                &["v", "t", "texture_v", "texture_t"],
+            ],
+        );
+        let indices = props.iter().position(|p| match p {
+            Property::List(name, ..) => name == "vertex_indices" || name == "vertex_index",
+            _ => false,
+        });
+
+        let mut row = vec![0.0; props.len()];
+        let mut polygon = Vec::new();
+        for _ in 0..element.count {
+            for (i, p) in props.iter().enumerate() {
+                match p {
+                    Property::Scalar(_, scalar) => row[i] = values.next(*scalar)?,
+                    Property::List(_, count, item) => {
+                        let count = values.next(*count)?;
+                        if !(0.0..=1e9).contains(&count) {
+                            return Err(format!("bad list length {}", count));
+                        }
+                        polygon.clear();
+                        for _ in 0..count as usize {
+                            polygon.push(values.next(*item)?);
+                        }
+                        if Some(i) == indices && element.name == "face" {
+                            if polygon.iter().any(|&v| v < 0.0) {
+                                return Err("a face has a negative vertex index".to_owned());
+                            }
+                            for k in 1..polygon.len().saturating_sub(1) {
+                                triangles.push([
+                                    polygon[0] as usize,
+                                    polygon[k] as usize,
+                                    polygon[k + 1] as usize,
+                                ]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if element.name != "vertex" {
+                continue;
+            }
+            let get = |(i, _): (usize, Scalar)| row[i] as Float;
+            if let Some([x, y, z]) = position {
+                vertices.push(Point3::new(get(x), get(y), get(z)));
+            }
+            if let Some([x, y, z]) = normal {
+                normals.push(Vector3::new(get(x), get(y), get(z)));
+            }
+            if let Some(channels) = colour {
+                let [r, g, b] = channels.map(|(i, scalar)| row[i] / scalar.full_scale());
+                colours.push(Colour::new(r, g, b));
+            }
+            if let Some([u, v]) = uv {
+                uvs.push((get(u), get(v)));
+            }
+        }
+
+        if element.name == "vertex" && position.is_none() {
+            return Err("the vertices have no positions".to_owned());
+        }
+    }
+
+    let mut mesh = Mesh::new(vertices, triangles)
+        .ok_or("the faces refer to missing vertices, or have no area")?;
+    if !normals.is_empty() {
+        mesh = mesh.with_normals(normals).ok_or("bad vertex normals")?;
+    }
+    if !colours.is_empty() {
+        mesh = mesh.with_colours(colours).ok_or("bad vertex colours")?;
+    }
+    if !uvs.is_empty() {
+        mesh = mesh.with_uvs(uvs).ok_or("bad texture coordinates")?;
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HEADER: &str = "ply
+format {}
+comment a square, with one corner coloured
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    #[test]
+    fn ascii_files() {
+        let text = HEADER.replace("{}", "ascii 1.0")
+            + "0 0 0 255 0 0\n1 0 0 0 0 0\n1 1 0 0 0 0\n0 1 0 0 0 0\n4 0 3 2 1\n";
+        let mesh = read(text.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.triangles(), &[[0, 3, 2], [0, 2, 1]]);
+        assert_eq!(mesh.colours().unwrap()[0], Colour::new(1.0, 0.0, 0.0));
+        assert!(mesh.normals().is_none());
+    }
+
+    #[test]
+    fn binary_files() {
+        for &big_endian in [false, true].iter() {
+            let kind = if big_endian {
+                "binary_big_endian 1.0"
+            } else {
+                "binary_little_endian 1.0"
+            };
+            let mut data = HEADER.replace("{}", kind).into_bytes();
+            let float = |data: &mut Vec<u8>, x: f32| {
+                data.extend_from_slice(&if big_endian {
+                    x.to_be_bytes()
+                } else {
+                    x.to_le_bytes()
+                })
+            };
+            for &(x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+                float(&mut data, x);
+                float(&mut data, y);
+                float(&mut data, 0.0);
+                data.extend_from_slice(&[0, 128, 255]);
+            }
+            data.push(3);
+            for &i in [0i32, 2, 1].iter() {
+                data.extend_from_slice(&if big_endian {
+                    i.to_be_bytes()
+                } else {
+                    i.to_le_bytes()
+                });
+            }
+
+            let mesh = read(&data).unwrap();
+            assert_eq!(mesh.vertices()[2], Point3::new(1.0, 1.0, 0.0));
+            assert_eq!(mesh.triangles(), &[[0, 2, 1]]);
+            assert_eq!(mesh.colours().unwrap()[3].b, 1.0);
+        }
+    }
+
+    #[test]
+    fn bad_files() {
+        assert!(read(b"ply\nformat ascii 1.0\n").is_err());
+        assert!(read(b"obj\nend_header\n").is_err());
+        let text = HEADER.replace("{}", "ascii 1.0") + "0 0 0 255 0 0\n";
+        assert!(read(text.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Reads meshes in the stereolithography (STL) format, whether ASCII or
+//! binary. STL gives every triangle its own three corners, so corners at
+//! exactly the same place are merged back into shared vertices. The normals
+//! in the file are ignored in favour of the triangles' winding, which is
+//! what most software goes by.
+
+use std::{collections::HashMap, str};
+
+use super::Mesh;
+use raygun_math::{Float, Point3};
+
+/// Builds up the mesh's vertices, merging repeats
+#[derive(Default)]
+struct Vertices {
+    points: Vec<Point3>,
+    seen: HashMap<[u64; 3], usize>,
+}
+
+impl Vertices {
+    fn index(&mut self, [x, y, z]: [f64; 3]) -> usize {
+        let points = &mut self.points;
+        *self
+            .seen
+            .entry([x.to_bits(), y.to_bits(), z.to_bits()])
+            .or_insert_with(|| {
+                points.push(Point3::new(x as Float, y as Float, z as Float));
+                points.len() - 1
+            })
+    }
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Mesh, String> {
+    // binary files may also start with "solid", so check whether the size
+    // matches the triangle count first
+    let binary_size = data
+        .get(80..84)
+        .map(|n| 84 + 50 * u32::from_le_bytes([n[0], n[1], n[2], n[3]]) as usize);
+    let (vertices, triangles) = if binary_size == Some(data.len()) || !data.starts_with(b"solid") {
+        read_binary(data)?
+    } else {
+        read_ascii(data)?
+    };
+
+    Mesh::new(vertices.points, triangles).ok_or_else(|| "the triangles have no area".to_owned())
+}
+
+fn read_binary(data: &[u8]) -> Result<(Vertices, Vec<[usize; 3]>), String> {
+    let records = data
+        .get(84..)
+        .ok_or("the file is too short to be a binary STL file")?;
+    let mut vertices = Vertices::default();
+    let mut triangles = Vec::new();
+
+    let float = |b: &[u8]| f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    for record in records.chunks_exact(50) {
+        // skip the normal, and the attribute bytes at the end
+        let mut corners = [0; 3];
+        for (k, corner) in corners.iter_mut().enumerate() {
+            let at = 12 + 12 * k;
+            *corner = vertices.index([
+                float(&record[at..]),
+                float(&record[at + 4..]),
+                float(&record[at + 8..]),
+            ]);
+        }
+        triangles.push(corners);
+    }
+
+    Ok((vertices, triangles))
+}
+
+fn read_ascii(data: &[u8]) -> Result<(Vertices, Vec<[usize; 3]>), String> {
+    let text = str::from_utf8(data).map_err(|_| "the file isn't text")?;
+    let mut vertices = Vertices::default();
+    let mut triangles = Vec::new();
+    let mut corners = Vec::with_capacity(3);
+
+    let mut words = text.split_ascii_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "vertex" => {
+                let mut p = [0.0; 3];
+                for x in p.iter_mut() {
+                    let word = words.next().ok_or("the file ends too soon")?;
+                    *x = word
+                        .parse()
+                        .map_err(|_| format!("\"{}\" isn't a number", word))?;
+                }
+                corners.push(vertices.index(p));
+            }
+            "endfacet" => {
+                // polygons with more corners are split into fans
+                if corners.len() < 3 {
+                    return Err("a facet has fewer than 3 vertices".to_owned());
+                }
+                for k in 1..corners.len() - 1 {
+                    triangles.push([corners[0], corners[k], corners[k + 1]]);
+                }
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok((vertices, triangles))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ascii_files() {
+        let text = "solid square
+            facet normal 0 0 -1
+                outer loop
+                    vertex 0 0 0
+                    vertex 0 1 0
+                    vertex 1 1 0
+                endloop
+            endfacet
+            facet normal 0 0 -1
+                outer loop
+                    vertex 0 0 0
+                    vertex 1 1 0
+                    vertex 1 0 0
+                endloop
+            endfacet
+        endsolid square";
+        let mesh = read(text.as_bytes()).unwrap();
+
+        // the shared corners are merged
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.triangles(), &[[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn binary_files() {
+        // a header that looks like an ASCII file's, to be fooled by
+        let mut data = b"solid but really binary".to_vec();
+        data.resize(80, b' ');
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for &x in [
+            0.0f32, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0,
+        ]
+        .iter()
+        {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 0]);
+
+        let mesh = read(&data).unwrap();
+        assert_eq!(mesh.vertices()[1], Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.triangles(), &[[0, 1, 2]]);
+
+        assert!(read(&data[..60]).is_err());
+    }
+}
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
-use crate::{AxisAlignedBox, Light, Primitive};
+use crate::{AxisAlignedBox, Hit, Light, Primitive};
 
 use super::SurfaceInfo;
-use raygun_material::Material;
-use raygun_math::{Point, Ray, Transform};
+use raygun_material::{Material, Pigment};
+use raygun_math::{Float, Point3, Ray, RayPacket, Transform, LANES};
 
 #[derive(Debug)]
 pub struct Object {
@@ -26,43 +26,81 @@ impl Object {
         self.primitive.as_light()
     }
 
-    pub fn intersects(&self, r: Ray) -> Option<Point> {
+    pub fn intersects(&self, r: Ray) -> Option<Hit> {
         let r_ = match self.transform {
             Some(ref t) => r.transform(&t.inverse),
             None => r,
         };
 
-        self.primitive.intersects(r_).map(|n| {
-            let object_space_point = r_.extend(n);
-            match self.transform {
-                Some(ref t) => t.matrix * object_space_point,
-                None => object_space_point,
+        self.primitive
+            .intersects(r_)
+            .map(|hit| self.hit_to_world(r, hit))
+    }
+
+    /// The packet equivalent of `intersects`
+    pub fn intersects_packet(&self, p: &RayPacket) -> [Option<Hit>; LANES] {
+        let p_ = match self.transform {
+            Some(ref t) => p.transform(&t.inverse),
+            None => *p,
+        };
+
+        let hits = self.primitive.intersects_packet(&p_);
+        let mut result = [None; LANES];
+        for (n, hit) in hits.iter().enumerate() {
+            result[n] = hit.map(|hit| self.hit_to_world(p.ray(n), hit));
+        }
+        result
+    }
+
+    ///
+    /// Moves a hit found in object space into world space, carrying the error
+    /// bound and surface frame along with it. The distance is re-measured
+    /// along the world-space ray `r`, as the object transform may have
+    /// scaled it.
+    ///
+    fn hit_to_world(&self, r: Ray, hit: Hit) -> Hit {
+        let hit = match self.transform {
+            Some(ref t) => {
+                let (point, error) = t.matrix.transform_with_error(hit.point, hit.error);
+                Hit {
+                    point,
+                    error,
+                    normal: t.normal_to_world(hit.normal),
+                    shading_normal: t.normal_to_world(hit.shading_normal),
+                    dpdu: hit.dpdu.transform(&t.matrix),
+                    dpdv: hit.dpdv.transform(&t.matrix),
+                    ..hit
+                }
             }
-        })
+            None => hit,
+        };
+        Hit {
+            t: (hit.point - r.src).length(),
+            ..hit
+        }
     }
 
-    /// Gets information about the surface at this point. Behaviour is
-    /// undefined the supplied point does not lie on the surface of the
-    /// object.
-    pub fn surface_at(&self, pt: Point) -> SurfaceInfo {
+    /// Gets information about the surface at a hit found by `intersects`.
+    pub fn surface_at(&self, hit: &Hit) -> SurfaceInfo<'_> {
         // convert the global point into the the local object space
         let local_pt = match self.transform {
-            Some(ref t) => t.inverse * pt,
-            None => pt,
+            Some(ref t) => t.inverse * hit.point,
+            None => hit.point,
         };
 
-        // sample the surface
-        let (colour, finish) = self.material.sample(local_pt);
-
-        // translate the surface info back into global space
-        let object_space_normal = self.primitive.normal(local_pt);
-        let world_space_normal = match self.transform {
-            Some(ref t) => object_space_normal.transform(&t.matrix),
-            None => object_space_normal,
+        // sample the surface, letting primitives with vertex colours supply
+        // their own
+        let vertex = match self.material.pigment {
+            Pigment::Vertex(_) => self.primitive.vertex_colour(local_pt, hit.face),
+            _ => None,
+        };
+        let (colour, finish) = match vertex {
+            Some(c) => (c, &self.material.finish),
+            None => self.material.sample(local_pt, hit.uv),
         };
 
         SurfaceInfo {
-            normal: world_space_normal,
+            normal: hit.shading_normal,
             colour,
             finish,
         }
@@ -75,10 +113,6 @@ impl Object {
         self.primitive.downcast_ref::<P>().ok()
     }
 
-    pub fn accept(&self, visitor: &mut dyn crate::Visitor) {
-        self.primitive.accept_children(self, visitor)
-    }
-
     /// Creates a bounding box for the object
     pub fn bounding_box(&self) -> AxisAlignedBox {
         let inner_bb = self.primitive.bounding_box();
@@ -88,14 +122,14 @@ impl Object {
                 let AxisAlignedBox { lower: l, upper: u } = inner_bb;
 
                 let points = [
-                    t.matrix * Point::new(l.x, l.y, l.z),
-                    t.matrix * Point::new(u.x, l.y, l.z),
-                    t.matrix * Point::new(u.x, u.y, l.z),
-                    t.matrix * Point::new(l.x, u.y, l.z),
-                    t.matrix * Point::new(l.x, l.y, u.z),
-                    t.matrix * Point::new(u.x, l.y, u.z),
-                    t.matrix * Point::new(u.x, u.y, u.z),
-                    t.matrix * Point::new(l.x, u.y, u.z),
+                    t.matrix * Point3::new(l.x, l.y, l.z),
+                    t.matrix * Point3::new(u.x, l.y, l.z),
+                    t.matrix * Point3::new(u.x, u.y, l.z),
+                    t.matrix * Point3::new(l.x, u.y, l.z),
+                    t.matrix * Point3::new(l.x, l.y, u.z),
+                    t.matrix * Point3::new(u.x, l.y, u.z),
+                    t.matrix * Point3::new(u.x, u.y, u.z),
+                    t.matrix * Point3::new(l.x, u.y, u.z),
                 ];
 
                 let (min, max) =
@@ -103,12 +137,12 @@ impl Object {
                         .iter()
                         .skip(1)
                         .fold((points[0], points[0]), |(mut min, mut max), p| {
-                            min.x = f64::min(min.x, p.x);
-                            min.y = f64::min(min.y, p.y);
-                            min.z = f64::min(min.z, p.z);
-                            max.x = f64::max(max.x, p.x);
-                            max.y = f64::max(max.y, p.y);
-                            max.z = f64::max(max.z, p.z);
+                            min.x = Float::min(min.x, p.x);
+                            min.y = Float::min(min.y, p.y);
+                            min.z = Float::min(min.z, p.z);
+                            max.x = Float::max(max.x, p.x);
+                            max.y = Float::max(max.y, p.y);
+                            max.z = Float::max(max.z, p.z);
                             (min, max)
                         });
 
@@ -127,12 +161,12 @@ pub type ObjectList = Vec<Arc<Object>>;
 mod test {
     use crate::Object;
     use raygun_material::Material;
-    use raygun_math::{degrees, point, Transform};
-    use std::f64::consts::SQRT_2;
+    use raygun_math::consts::SQRT_2;
+    use raygun_math::{degrees, normal, point, Ray, Transform};
 
     #[test]
     fn bounding_box() {
-        use crate::{AxisAlignedBox, _box::Box as _Box};
+        use crate::{_box::Box as _Box, AxisAlignedBox};
         use std::sync::Arc;
 
         let obj = Object {
@@ -154,4 +188,48 @@ mod test {
         assert!(bb.lower.approx_eq(expected.lower));
         assert!(bb.upper.approx_eq(expected.upper));
     }
+
+    #[test]
+    fn scaled_sphere_normals() {
+        use crate::Sphere;
+        use std::sync::Arc;
+
+        // a unit sphere scaled into an ellipsoid with semi-axes (a, b, c)
+        let (a, b, c) = (3.0, 1.0, 0.5);
+        let obj = Object {
+            primitive: Arc::new(Sphere::new(point(0.0, 0.0, 0.0), 1.0)),
+            material: Material::default(),
+            transform: Some(Box::new(Transform::for_scale(a, b, c))),
+        };
+
+        let angles = [0.0, 30.0, 45.0, 60.0, 90.0, 135.0, 200.0];
+        for &theta in angles.iter() {
+            for &phi in angles.iter() {
+                let (st, ct) = (degrees(theta).sin(), degrees(theta).cos());
+                let (sp, cp) = (degrees(phi).sin(), degrees(phi).cos());
+                let (x, y, z) = (a * ct * cp, b * st * cp, c * sp);
+
+                // fire a ray at the point along the outward normal
+                let outward = normal(x / (a * a), y / (b * b), z / (c * c)).normalize();
+                let r = Ray::new(point(x, y, z) + (outward * 10.0), -outward * 1.0);
+                let hit = obj.intersects(r).unwrap();
+                let n = obj.surface_at(&hit).normal;
+
+                // the gradient of x²/a² + y²/b² + z²/c² where the ray
+                // actually landed
+                let p = hit.point;
+                let expected = normal(p.x / (a * a), p.y / (b * b), p.z / (c * c)).normalize();
+
+                assert!(
+                    n.approx_eq(expected),
+                    "At ({}, {}, {}): expected {:?}, got {:?}",
+                    x,
+                    y,
+                    z,
+                    expected,
+                    n
+                );
+            }
+        }
+    }
 }
@@ -1,33 +1,76 @@
-use crate::{AxisAlignedBox, Primitive};
+use crate::{AxisAlignedBox, Hit, Primitive};
 
-use raygun_math::{point, vector, Point, Ray, Vector};
+use raygun_math::{
+    gamma, point, Float, Floatx4, Normal3, Ray, RayPacket, Vector3, Vector3x4, LANES,
+};
 
 #[derive(Debug)]
 pub struct Plane {
-    pub normal: Vector,
-    pub offset: f64,
+    pub normal: Normal3,
+    pub offset: Float,
+}
+
+impl Plane {
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        // re-project the point onto the plane
+        let p = r.extend(t);
+        let n = Vector3::from(self.normal);
+        let point = p - n * ((n.dot(Vector3::from(p)) - self.offset) / n.dot(n));
+
+        let m = point.abs();
+        let e = gamma(7) * m.x.max(m.y).max(m.z).max(self.offset.abs());
+
+        // the plane is tiled with unit squares, aligned to an arbitrary pair of
+        // axes perpendicular to the normal
+        let n = n.normalize();
+        let (s, t_axis) = n.coordinate_system();
+        let v = Vector3::from(point);
+
+        Hit {
+            t,
+            point,
+            error: Vector3::new(e, e, e),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: (v.dot(s).rem_euclid(1.0), v.dot(t_axis).rem_euclid(1.0)),
+            dpdu: s,
+            dpdv: t_axis,
+            face: 0,
+        }
+    }
 }
 
 impl Primitive for Plane {
-    fn intersects(&self, r: Ray) -> Option<f64> {
-        let n = self.offset - r.src.dot(self.normal);
-        let d = r.dir.dot(self.normal);
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let n = self.offset - self.normal.dot(Vector3::from(r.src));
+        let d = self.normal.dot(r.dir);
         match n / d {
-            a if a > 0.0 => Some(a),
+            a if a > 0.0 => Some(self.hit(r, a)),
             _ => None,
         }
     }
 
-    fn normal(&self, _pt: Point) -> Vector {
-        self.normal
+    fn intersects_packet(&self, p: &RayPacket) -> [Option<Hit>; LANES] {
+        let normal = Vector3x4::splat(self.normal.x, self.normal.y, self.normal.z);
+        let n = Floatx4::splat(self.offset) - normal.dot(&p.src);
+        let d = normal.dot(&p.dir);
+        let a = n / d;
+
+        let mut result = [None; LANES];
+        for (n, r) in result.iter_mut().enumerate() {
+            if a.lane(n) > 0.0 {
+                *r = Some(self.hit(p.ray(n), a.lane(n)));
+            }
+        }
+        result
     }
 
     fn bounding_box(&self) -> AxisAlignedBox {
-        use std::f64::{INFINITY, NEG_INFINITY};
-
+        let (lo, hi) = (Float::NEG_INFINITY, Float::INFINITY);
         AxisAlignedBox {
-            lower: point(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
-            upper: point(INFINITY, INFINITY, INFINITY),
+            lower: point(lo, lo, lo),
+            upper: point(hi, hi, hi),
         }
     }
 }
@@ -35,7 +78,7 @@ impl Primitive for Plane {
 impl Default for Plane {
     fn default() -> Plane {
         Plane {
-            normal: vector(0.0, 1.0, 0.0),
+            normal: Normal3::new(0.0, 1.0, 0.0),
             offset: 0.0,
         }
     }
@@ -45,20 +88,20 @@ impl Default for Plane {
 mod test {
     use super::*;
     use float_cmp::approx_eq;
-    use raygun_math::{point, vector};
-    use std::f64::consts::SQRT_2;
+    use raygun_math::consts::SQRT_2;
+    use raygun_math::{normal, point, vector, APPROX_EPSILON};
 
     #[test]
     fn intersecting_ray_intersects() {
         let r = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, -1.0, 1.0).normalize());
         let p = Plane {
-            normal: vector(0.0, 1.0, 0.0),
+            normal: Normal3::new(0.0, 1.0, 0.0),
             offset: 0.0,
         };
 
-        let value = p.intersects(r).unwrap();
+        let value = p.intersects(r).unwrap().t;
         assert!(
-            approx_eq!(f64, value, SQRT_2, ulps = 5),
+            approx_eq!(Float, value, SQRT_2, ulps = 5),
             "Expected {}, got {}",
             SQRT_2,
             value
@@ -69,7 +112,7 @@ mod test {
     fn non_intersecting_ray_does_not() {
         let r = Ray::new(point(0.0, 1.0, 0.0), vector(0.0, 0.0, 1.0).normalize());
         let p = Plane {
-            normal: vector(0.0, 1.0, 0.0),
+            normal: Normal3::new(0.0, 1.0, 0.0),
             offset: 0.0,
         };
 
@@ -77,12 +120,55 @@ mod test {
     }
 
     #[test]
-    fn normal() {
+    fn hits_carry_the_normal_and_a_tangent_frame() {
         let p = Plane {
-            normal: vector(0.0, 1.0, 0.0),
-            offset: 0.0,
+            normal: Normal3::new(0.0, 2.0, 0.0),
+            offset: 1.0,
         };
+        let r = Ray::new(point(100.0, 5.0, 100.0), vector(0.0, -1.0, 0.0));
+        let hit = p.intersects(r).unwrap();
 
-        assert_eq!(p.normal(point(100.0, 0.0, 100.0)), p.normal);
+        let n = normal(0.0, 1.0, 0.0);
+        assert_eq!(hit.normal, n);
+        assert!(n.dot(hit.dpdu).abs() < APPROX_EPSILON);
+        assert!(n.dot(hit.dpdv).abs() < APPROX_EPSILON);
+        assert!(hit.dpdu.dot(hit.dpdv).abs() < APPROX_EPSILON);
+
+        // the texture repeats every unit along the tangents
+        let (u, v) = hit.uv;
+        let unit = 0.0..1.0;
+        assert!(unit.contains(&u) && unit.contains(&v), "{:?}", hit.uv);
+
+        let at = |offset: Vector3| {
+            let r = Ray::new(point(100.5, 5.0, 100.5) + offset, vector(0.0, -1.0, 0.0));
+            p.intersects(r).unwrap().uv
+        };
+        let (u0, v0) = at(vector(0.0, 0.0, 0.0));
+        let (u1, v1) = at(hit.dpdu);
+        assert!((u1 - u0).abs() < APPROX_EPSILON && (v1 - v0).abs() < APPROX_EPSILON);
+
+        let (u2, v2) = at(hit.dpdu * 0.5);
+        assert!(((u2 - u0).abs() - 0.5).abs() < APPROX_EPSILON);
+        assert!((v2 - v0).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn packet_matches_single_rays() {
+        let p = Plane {
+            normal: Normal3::new(0.0, 1.0, 0.0),
+            offset: -1.0,
+        };
+        let rays = [
+            Ray::new(point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            Ray::new(point(0.0, 1.0, 0.0), vector(1.0, -1.0, 0.0)),
+            Ray::new(point(0.0, 1.0, 0.0), vector(0.0, 1.0, 0.0)),
+            Ray::new(point(0.0, 1.0, 0.0), vector(0.0, 0.0, 1.0)),
+        ];
+
+        let actual = p.intersects_packet(&RayPacket::new(&rays));
+        assert_eq!(actual[0].map(|h| h.t), Some(2.0));
+        for (n, r) in rays.iter().enumerate() {
+            assert_eq!(actual[n], p.intersects(*r), "lane {}", n);
+        }
     }
 }
@@ -1,22 +1,22 @@
-use crate::{AxisAlignedBox, Light, Primitive};
+use crate::{AxisAlignedBox, Hit, Light, Primitive};
 use raygun_material::Colour;
-use raygun_math::{Point, Ray, Vector};
+use raygun_math::{Point3, Ray};
 
 #[derive(Debug)]
 pub struct PointLight {
-    pub loc: Point,
+    pub loc: Point3,
     pub colour: Colour,
 }
 
 impl PointLight {
-    pub fn new(pos: Point, colour: Colour) -> PointLight {
+    pub fn new(pos: Point3, colour: Colour) -> PointLight {
         PointLight {
             loc: pos,
             colour: colour,
         }
     }
 
-    pub fn position(&self) -> Point {
+    pub fn position(&self) -> Point3 {
         self.loc
     }
 
@@ -26,21 +26,17 @@ impl PointLight {
 }
 
 impl Primitive for PointLight {
-    fn intersects(&self, _r: Ray) -> Option<f64> {
+    fn intersects(&self, _r: Ray) -> Option<Hit> {
         None
     }
 
     fn bounding_box(&self) -> AxisAlignedBox {
         AxisAlignedBox {
-            lower: Point::default(),
-            upper: Point::default(),
+            lower: Point3::default(),
+            upper: Point3::default(),
         }
     }
 
-    fn normal(&self, _pt: Point) -> Vector {
-        panic!("This should never be called")
-    }
-
     fn as_light(&self) -> Option<&dyn Light> {
         Some(self as &dyn Light)
     }
@@ -49,18 +45,18 @@ impl Primitive for PointLight {
 impl Default for PointLight {
     fn default() -> PointLight {
         PointLight {
-            loc: Point::new(0.0, 0.0, 0.0),
+            loc: Point3::new(0.0, 0.0, 0.0),
             colour: Colour::default(),
         }
     }
 }
 
 impl Light for PointLight {
-    fn src(&self) -> Point {
+    fn src(&self) -> Point3 {
         self.loc
     }
 
-    fn illuminates(&self, _p: Point) -> Option<Colour> {
+    fn illuminates(&self, _p: Point3) -> Option<Colour> {
         Some(self.colour)
     }
 }
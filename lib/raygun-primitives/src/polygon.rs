@@ -0,0 +1,182 @@
+use crate::{
+    flat::{barycentric, plane_distance, project_onto_plane},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A flat polygon, rendered as a fan of triangles around its first point.
+/// This is exact for convex polygons, and for any other polygon where every
+/// point can be seen from the first. Hits report the index of the triangle
+/// in the fan as their face.
+///
+#[derive(Debug)]
+pub struct Polygon {
+    points: Vec<Point3>,
+    normal: Vector3,
+
+    // the in-plane axes used for texture mapping, and the extent of the
+    // polygon along each
+    axes: (Vector3, Vector3),
+    lower: (Float, Float),
+    size: (Float, Float),
+}
+
+impl Polygon {
+    ///
+    /// Creates a polygon from its points, in order around the edge. The
+    /// surface faces the side from which the points run anticlockwise.
+    /// Returns `None` if there are fewer than three points, or they all lie
+    /// on a line.
+    ///
+    pub fn new(points: Vec<Point3>) -> Option<Polygon> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        // Newell's method, which averages out any slight non-planarity
+        let mut n = Vector3::default();
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            n.x += (a.y - b.y) * (a.z + b.z);
+            n.y += (a.z - b.z) * (a.x + b.x);
+            n.z += (a.x - b.x) * (a.y + b.y);
+        }
+        if n.length() == 0.0 {
+            return None;
+        }
+        let normal = n.normalize();
+
+        let (s, t) = normal.coordinate_system();
+        let along = |p: &Point3| {
+            let v = Vector3::from(*p);
+            (v.dot(s), v.dot(t))
+        };
+        let (s0, t0) = along(&points[0]);
+        let (lower, upper) = points
+            .iter()
+            .map(along)
+            .fold(((s0, t0), (s0, t0)), |((ls, lt), (us, ut)), (ps, pt)| {
+                ((ls.min(ps), lt.min(pt)), (us.max(ps), ut.max(pt)))
+            });
+
+        Some(Polygon {
+            points,
+            normal,
+            axes: (s, t),
+            lower,
+            size: (upper.0 - lower.0, upper.1 - lower.1),
+        })
+    }
+
+    pub fn points(&self) -> &[Point3] {
+        &self.points
+    }
+
+    /// Where the ray crosses the polygon, and which triangle of the fan it
+    /// crosses
+    fn locate(&self, r: Ray) -> Option<(Float, usize)> {
+        let origin = self.points[0];
+        let t = plane_distance(r, origin, self.normal)?;
+        let p = r.extend(t);
+
+        self.points[1..]
+            .windows(2)
+            .position(
+                |edge| match barycentric(p, origin, edge[0] - origin, edge[1] - origin) {
+                    Some((a, b)) => a >= 0.0 && b >= 0.0 && a + b <= 1.0,
+                    None => false,
+                },
+            )
+            .map(|face| (t, face))
+    }
+
+    /// The hit record for an intersection `t` units along `r`, on the
+    /// triangle of the fan found by `locate`
+    fn hit(&self, r: Ray, t: Float, face: usize) -> Hit {
+        let (point, error) = project_onto_plane(r, t, self.points[0], self.normal);
+
+        // texture coordinates are mapped over the polygon's extent in its
+        // own plane
+        let (s, t_axis) = self.axes;
+        let v = Vector3::from(point);
+        let uv = (
+            (v.dot(s) - self.lower.0) / self.size.0,
+            (v.dot(t_axis) - self.lower.1) / self.size.1,
+        );
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: Normal3::from(self.normal),
+            shading_normal: Normal3::from(self.normal),
+            uv,
+            dpdu: s * self.size.0,
+            dpdv: t_axis * self.size.1,
+            face,
+        }
+    }
+}
+
+impl Primitive for Polygon {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.locate(r).map(|(t, face)| self.hit(r, t, face))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let first = AxisAlignedBox {
+            lower: self.points[0],
+            upper: self.points[0],
+        };
+        self.points.iter().fold(first, |bb, &p| {
+            bb.union(&AxisAlignedBox { lower: p, upper: p })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, point, vector};
+
+    fn hexagon() -> Polygon {
+        let points = (0..6)
+            .map(|i| {
+                let a = (i as Float) * raygun_math::consts::PI / 3.0;
+                point(a.cos(), 0.0, -a.sin())
+            })
+            .collect();
+        Polygon::new(points).unwrap()
+    }
+
+    #[test]
+    fn degenerate_polygons_are_rejected() {
+        assert!(Polygon::new(vec![point(0.0, 0.0, 0.0), point(1.0, 0.0, 0.0)]).is_none());
+        assert!(Polygon::new(vec![
+            point(0.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            point(2.0, 0.0, 0.0)
+        ])
+        .is_none());
+    }
+
+    #[test]
+    fn rays_hit_every_triangle_of_the_fan() {
+        let p = hexagon();
+        assert!(p.normal.approx_eq(vector(0.0, 1.0, 0.0)), "{:?}", p.normal);
+
+        let down = vector(0.0, -1.0, 0.0);
+        for face in 0..4 {
+            let a = ((face as Float) + 1.5) * raygun_math::consts::PI / 3.0;
+            let r = Ray::new(point(0.8 * a.cos(), 1.0, -0.8 * a.sin()), down);
+            let h = p.intersects(r).unwrap();
+            assert_eq!(h.face, face);
+            assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)));
+        }
+
+        assert!(p
+            .intersects(Ray::new(point(0.95, 1.0, 0.5), down))
+            .is_none());
+    }
+}
@@ -1,26 +1,60 @@
 use downcast::*;
 use std::fmt::Debug;
 
-use crate::{AxisAlignedBox, Light, Object};
-use raygun_math::{Point, Ray, Vector};
+use crate::{AxisAlignedBox, Hit, Light};
+use raygun_material::Colour;
+use raygun_math::{consts::PI, gamma, Float, Point3, Ray, RayPacket, Vector3, LANES};
 
 ///
 /// The trait that defines a primitive object
 ///
 pub trait Primitive: downcast::Any + Debug + Send + Sync {
-    fn intersects(&self, r: Ray) -> Option<f64>;
-    fn normal(&self, pt: Point) -> Vector;
+    /// Finds the nearest hit in front of the ray, if there is one. Objects
+    /// that can't be hit, like lights and unions, never return anything.
+    fn intersects(&self, r: Ray) -> Option<Hit>;
+
+    /// Intersects a whole packet of rays at once. Primitives without a packet
+    /// implementation fall back to testing each ray individually.
+    fn intersects_packet(&self, p: &RayPacket) -> [Option<Hit>; LANES] {
+        let mut result = [None; LANES];
+        for (n, r) in result.iter_mut().enumerate() {
+            *r = self.intersects(p.ray(n));
+        }
+        result
+    }
 
     /// Is this primitive a light?
     fn as_light(&self) -> Option<&dyn Light> {
         None
     }
 
+    /// The colour given to the point `p` on face `face` (both as found in a
+    /// hit) by the primitive's per-vertex colours, if it has any
+    fn vertex_colour(&self, _p: Point3, _face: usize) -> Option<Colour> {
+        None
+    }
+
     /// Bounding box
     fn bounding_box(&self) -> AxisAlignedBox;
-
-    /// Visitor entry point if the object has any children
-    fn accept_children(&self, _object: &Object, _visitor: &mut dyn crate::Visitor) {}
 }
 
 downcast!(dyn Primitive);
+
+///
+/// A bound on the error in `r.extend(t)`, for primitives that can't project
+/// the point back onto their surface
+///
+pub(crate) fn extension_error(r: Ray, t: Float) -> Vector3 {
+    (Vector3::from(r.src).abs() + (r.dir * t).abs()) * gamma(7)
+}
+
+///
+/// Maps the direction of a (unit) normal into the unit square, for surfaces
+/// with no natural parameterisation of their own
+///
+pub(crate) fn normal_uv(n: Vector3) -> (Float, Float) {
+    (
+        (n.z.atan2(n.x) + PI) / (2.0 * PI),
+        n.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
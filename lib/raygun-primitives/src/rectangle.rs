@@ -0,0 +1,129 @@
+use std::cmp;
+
+use crate::{
+    flat::{barycentric, plane_distance, project_onto_plane},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{point, vector, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A flat parallelogram with one corner at `corner` and sides running along
+/// `u` and `v`. The surface faces along `u × v`, and is parameterised by how
+/// far along each side a point is.
+///
+#[derive(Debug)]
+pub struct Rectangle {
+    pub corner: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+}
+
+impl Rectangle {
+    /// Where the ray crosses the rectangle's plane, and the coordinates of
+    /// that point along each side
+    fn locate(&self, r: Ray) -> Option<(Float, (Float, Float))> {
+        let n = self.u.cross(self.v);
+        let t = plane_distance(r, self.corner, n)?;
+        let (a, b) = barycentric(r.extend(t), self.corner, self.u, self.v)?;
+        Some((t, (a, b)))
+    }
+
+    /// The hit record for an intersection `t` units along `r`, at `uv` on the
+    /// rectangle as found by `locate`
+    fn hit(&self, r: Ray, t: Float, uv: (Float, Float)) -> Hit {
+        let n = self.u.cross(self.v).normalize();
+        let (point, error) = project_onto_plane(r, t, self.corner, n);
+
+        Hit {
+            t,
+            point,
+            error,
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv,
+            dpdu: self.u,
+            dpdv: self.v,
+            face: 0,
+        }
+    }
+}
+
+impl Default for Rectangle {
+    /// A unit square centred on the origin and facing up the y axis
+    fn default() -> Rectangle {
+        Rectangle {
+            corner: point(-0.5, 0.0, -0.5),
+            u: vector(0.0, 0.0, 1.0),
+            v: vector(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a rectangle object
+impl cmp::PartialEq for Rectangle {
+    fn eq(&self, other: &Rectangle) -> bool {
+        self.corner == other.corner && self.u == other.u && self.v == other.v
+    }
+}
+
+impl Primitive for Rectangle {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let unit = 0.0..=1.0;
+        match self.locate(r) {
+            Some((t, (a, b))) if unit.contains(&a) && unit.contains(&b) => {
+                Some(self.hit(r, t, (a, b)))
+            }
+            _ => None,
+        }
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let corners = [
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ];
+        corners.iter().fold(
+            AxisAlignedBox {
+                lower: self.corner,
+                upper: self.corner,
+            },
+            |bb, &c| bb.union(&AxisAlignedBox { lower: c, upper: c }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    #[test]
+    fn rays_hit_inside_the_sides() {
+        let rect = Rectangle {
+            corner: point(0.0, 0.0, 0.0),
+            u: vector(2.0, 0.0, 0.0),
+            v: vector(1.0, 1.0, 0.0),
+        };
+        let back = vector(0.0, 0.0, -1.0);
+
+        // a parallelogram, so (0.5, 1) is outside even though it's within
+        // the bounding box
+        assert!(rect
+            .intersects(Ray::new(point(0.5, 0.9, 5.0), back))
+            .is_none());
+        assert!(rect
+            .intersects(Ray::new(point(3.5, 0.5, 5.0), back))
+            .is_none());
+
+        let r = Ray::new(point(2.0, 0.5, 5.0), back);
+        let h = rect.intersects(r).unwrap();
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, 1.0)));
+        assert!((h.uv.0 - 0.75).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!((h.uv.1 - 0.5).abs() < APPROX_EPSILON, "{:?}", h);
+
+        let bb = rect.bounding_box();
+        assert_eq!(bb.lower, point(0.0, 0.0, 0.0));
+        assert_eq!(bb.upper, point(3.0, 1.0, 0.0));
+    }
+}
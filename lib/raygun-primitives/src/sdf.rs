@@ -0,0 +1,539 @@
+use std::cmp;
+
+use crate::{
+    primitive::{extension_error, normal_uv},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{point, vector, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A signed distance field: the distance from any point to the nearest
+/// surface, negative inside. Fields are built from a handful of shapes about
+/// the origin, then combined and deformed by the remaining variants.
+///
+/// Where a combinator can't give the exact distance it gives a lower bound
+/// instead, which keeps sphere tracing safe at the cost of extra steps.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    /// A sphere of the given radius
+    Sphere(Float),
+
+    /// A box with half-extents `size`, its edges rounded off by `rounding`
+    Box {
+        size: Vector3,
+        rounding: Float,
+    },
+
+    /// A torus lying in the xz plane
+    Torus {
+        major_radius: Float,
+        minor_radius: Float,
+    },
+
+    /// A capped cylinder running along the y axis, `height` units tall
+    Cylinder {
+        radius: Float,
+        height: Float,
+    },
+
+    /// The Mandelbulb fractal, which fits inside a sphere of radius 2
+    Mandelbulb {
+        power: Float,
+        iterations: usize,
+    },
+
+    Union(Vec<Field>),
+    Intersection(Vec<Field>),
+
+    /// The first field, with the second carved out of it
+    Difference(Box<Field>, Box<Field>),
+
+    /// A union that blends its children together within `radius` of where
+    /// they meet
+    SmoothUnion {
+        radius: Float,
+        children: Vec<Field>,
+    },
+
+    Translate(Vector3, Box<Field>),
+
+    /// Endless copies of the field, every `period` units along each axis. A
+    /// period of zero leaves that axis alone. The field should fit inside a
+    /// single period, or the copies will cut into each other.
+    Repeat(Vector3, Box<Field>),
+
+    /// Twists the field about the y axis by the given number of radians per
+    /// unit of height
+    Twist(Float, Box<Field>),
+
+    /// Grows the surface outwards by the given distance, rounding its edges
+    Round(Float, Box<Field>),
+}
+
+impl Field {
+    /// The signed distance from `p` to the surface
+    pub fn distance(&self, p: Vector3) -> Float {
+        match self {
+            Field::Sphere(radius) => p.length() - radius,
+
+            Field::Box { size, rounding } => {
+                let q = p.abs() - (*size - vector(*rounding, *rounding, *rounding));
+                let outside = vector(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+                let inside = q.x.max(q.y).max(q.z).min(0.0);
+                outside + inside - rounding
+            }
+
+            Field::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let rho = ((p.x * p.x) + (p.z * p.z)).sqrt() - major_radius;
+                ((rho * rho) + (p.y * p.y)).sqrt() - minor_radius
+            }
+
+            Field::Cylinder { radius, height } => {
+                let across = ((p.x * p.x) + (p.z * p.z)).sqrt() - radius;
+                let along = p.y.abs() - (height / 2.0);
+                let outside = vector(across.max(0.0), along.max(0.0), 0.0).length();
+                outside + across.max(along).min(0.0)
+            }
+
+            Field::Mandelbulb { power, iterations } => mandelbulb(p, *power, *iterations),
+
+            Field::Union(children) => children
+                .iter()
+                .map(|c| c.distance(p))
+                .fold(Float::INFINITY, Float::min),
+
+            Field::Intersection(children) => children
+                .iter()
+                .map(|c| c.distance(p))
+                .fold(Float::NEG_INFINITY, Float::max),
+
+            Field::Difference(a, b) => a.distance(p).max(-b.distance(p)),
+
+            Field::SmoothUnion { radius, children } => children
+                .iter()
+                .map(|c| c.distance(p))
+                .fold(Float::INFINITY, |a, b| smooth_min(a, b, *radius)),
+
+            Field::Translate(offset, f) => f.distance(p - *offset),
+
+            Field::Repeat(period, f) => {
+                let wrap = |x: Float, c: Float| {
+                    if c > 0.0 {
+                        x - (c * (x / c).round())
+                    } else {
+                        x
+                    }
+                };
+                f.distance(vector(
+                    wrap(p.x, period.x),
+                    wrap(p.y, period.y),
+                    wrap(p.z, period.z),
+                ))
+            }
+
+            Field::Twist(rate, f) => {
+                let (sin, cos) = (-rate * p.y).sin_cos();
+                let q = vector((cos * p.x) - (sin * p.z), p.y, (sin * p.x) + (cos * p.z));
+
+                // twisting stretches space by up to this much, so the
+                // child's distances have to be scaled back down to match.
+                // An unbounded child falls back on the stretch at `p`.
+                let reach = radial_extent(&f.bounds());
+                let reach = if reach.is_finite() {
+                    reach
+                } else {
+                    ((p.x * p.x) + (p.z * p.z)).sqrt()
+                };
+                let stretch = (1.0 + (rate * reach).powi(2)).sqrt();
+                f.distance(q) / stretch
+            }
+
+            Field::Round(radius, f) => f.distance(p) - radius,
+        }
+    }
+
+    /// A box containing every point where the field is negative
+    pub fn bounds(&self) -> AxisAlignedBox {
+        match self {
+            Field::Sphere(radius) => centred_box(vector(*radius, *radius, *radius)),
+
+            Field::Box { size, .. } => centred_box(*size),
+
+            Field::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let xz = major_radius + minor_radius;
+                centred_box(vector(xz, *minor_radius, xz))
+            }
+
+            Field::Cylinder { radius, height } => {
+                centred_box(vector(*radius, height / 2.0, *radius))
+            }
+
+            Field::Mandelbulb { .. } => centred_box(vector(2.0, 2.0, 2.0)),
+
+            Field::Union(children) => union_bounds(children),
+
+            Field::Intersection(children) => {
+                let all = centred_box(vector(Float::INFINITY, Float::INFINITY, Float::INFINITY));
+                children.iter().fold(all, |acc, c| {
+                    let b = c.bounds();
+                    AxisAlignedBox {
+                        lower: point(
+                            acc.lower.x.max(b.lower.x),
+                            acc.lower.y.max(b.lower.y),
+                            acc.lower.z.max(b.lower.z),
+                        ),
+                        upper: point(
+                            acc.upper.x.min(b.upper.x),
+                            acc.upper.y.min(b.upper.y),
+                            acc.upper.z.min(b.upper.z),
+                        ),
+                    }
+                })
+            }
+
+            Field::Difference(a, _) => a.bounds(),
+
+            // blending can only bulge the surface out by a quarter of the
+            // radius
+            Field::SmoothUnion { radius, children } => grow(union_bounds(children), radius / 4.0),
+
+            Field::Translate(offset, f) => {
+                let b = f.bounds();
+                AxisAlignedBox {
+                    lower: b.lower + *offset,
+                    upper: b.upper + *offset,
+                }
+            }
+
+            Field::Repeat(period, f) => {
+                let b = f.bounds();
+                let spread = |lo: Float, hi: Float, c: Float| {
+                    if c > 0.0 {
+                        (Float::NEG_INFINITY, Float::INFINITY)
+                    } else {
+                        (lo, hi)
+                    }
+                };
+                let (lx, ux) = spread(b.lower.x, b.upper.x, period.x);
+                let (ly, uy) = spread(b.lower.y, b.upper.y, period.y);
+                let (lz, uz) = spread(b.lower.z, b.upper.z, period.z);
+                AxisAlignedBox {
+                    lower: point(lx, ly, lz),
+                    upper: point(ux, uy, uz),
+                }
+            }
+
+            Field::Twist(_, f) => {
+                let b = f.bounds();
+                let reach = radial_extent(&b);
+                AxisAlignedBox {
+                    lower: point(-reach, b.lower.y, -reach),
+                    upper: point(reach, b.upper.y, reach),
+                }
+            }
+
+            Field::Round(radius, f) => grow(f.bounds(), *radius),
+        }
+    }
+}
+
+///
+/// Estimates the distance to the Mandelbulb, from how quickly the point
+/// escapes under iteration
+///
+fn mandelbulb(p: Vector3, power: Float, iterations: usize) -> Float {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = z.length();
+
+    for _ in 0..iterations {
+        if r > 2.0 || r == 0.0 {
+            break;
+        }
+
+        let theta = (z.y / r).acos() * power;
+        let phi = z.z.atan2(z.x) * power;
+        dr = (r.powf(power - 1.0) * power * dr) + 1.0;
+
+        let zr = r.powf(power);
+        z = (vector(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        ) * zr)
+            + p;
+        r = z.length();
+    }
+
+    if r == 0.0 {
+        0.0
+    } else {
+        0.5 * r.ln() * r / dr
+    }
+}
+
+/// The polynomial smooth minimum, which blends `a` and `b` within `k`
+fn smooth_min(a: Float, b: Float, k: Float) -> Float {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - (h * h * k / 4.0)
+}
+
+fn centred_box(half_size: Vector3) -> AxisAlignedBox {
+    AxisAlignedBox {
+        lower: Point3::from(-half_size),
+        upper: Point3::from(half_size),
+    }
+}
+
+fn grow(b: AxisAlignedBox, by: Float) -> AxisAlignedBox {
+    let pad = vector(by, by, by);
+    AxisAlignedBox {
+        lower: b.lower - pad,
+        upper: b.upper + pad,
+    }
+}
+
+fn union_bounds(children: &[Field]) -> AxisAlignedBox {
+    children
+        .iter()
+        .map(Field::bounds)
+        .fold(None, |acc: Option<AxisAlignedBox>, b| match acc {
+            Some(acc) => Some(acc.union(&b)),
+            None => Some(b),
+        })
+        .unwrap_or_default()
+}
+
+/// How far from the y axis a box reaches
+fn radial_extent(b: &AxisAlignedBox) -> Float {
+    let x = b.lower.x.abs().max(b.upper.x.abs());
+    let z = b.lower.z.abs().max(b.upper.z.abs());
+    ((x * x) + (z * z)).sqrt()
+}
+
+///
+/// A surface with no closed-form intersection, described by a signed distance
+/// `field` and found by sphere tracing: stepping along the ray by the
+/// distance to the nearest surface until the ray gets within `tolerance` of
+/// it. Rays that take more than `max_steps` steps are counted as misses.
+///
+#[derive(Debug)]
+pub struct Sdf {
+    pub field: Field,
+    pub max_steps: usize,
+    pub tolerance: Float,
+
+    /// How far along a ray to look, where the field is unbounded
+    pub max_distance: Float,
+}
+
+impl Sdf {
+    pub fn new(field: Field) -> Sdf {
+        Sdf {
+            field,
+            max_steps: 256,
+            tolerance: 1e-4,
+            max_distance: 1e3,
+        }
+    }
+
+    /// The (unit) gradient of the field at `p`, by central differences
+    fn gradient(&self, p: Vector3) -> Vector3 {
+        let h = self.tolerance;
+        let d = |offset: Vector3| self.field.distance(p + offset) - self.field.distance(p - offset);
+        vector(
+            d(vector(h, 0.0, 0.0)),
+            d(vector(0.0, h, 0.0)),
+            d(vector(0.0, 0.0, h)),
+        )
+        .normalize()
+    }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        let p = r.extend(t);
+        let n = self.gradient(Vector3::from(p));
+        let (dpdu, dpdv) = n.coordinate_system();
+
+        // the march stops anywhere within `tolerance` of the surface, and
+        // spawned rays need to start clear of it on both sides
+        let slack = 2.0 * self.tolerance;
+
+        Hit {
+            t,
+            point: p,
+            error: extension_error(r, t) + vector(slack, slack, slack),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: normal_uv(n),
+            dpdu,
+            dpdv,
+            face: 0,
+        }
+    }
+}
+
+impl Default for Sdf {
+    fn default() -> Sdf {
+        Sdf::new(Field::Sphere(1.0))
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for an SDF object
+impl cmp::PartialEq for Sdf {
+    fn eq(&self, other: &Sdf) -> bool {
+        self.field == other.field
+            && self.max_steps == other.max_steps
+            && self.tolerance == other.tolerance
+            && self.max_distance == other.max_distance
+    }
+}
+
+impl Primitive for Sdf {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let (near, far) = self.field.bounds().span(&r)?;
+        let mut t = near.max(0.0);
+        let far = far.min(t + self.max_distance);
+
+        // rays starting inside the surface look for the way out, so march
+        // by the unsigned distance
+        for _ in 0..self.max_steps {
+            let d = self.field.distance(Vector3::from(r.extend(t))).abs();
+            if d < self.tolerance {
+                return Some(self.hit(r, t));
+            }
+            t += d;
+            if t > far {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        self.field.bounds()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    fn hit(s: &Sdf, src: Point3, dir: Vector3) -> Option<Hit> {
+        let r = Ray::new(src, dir);
+        s.intersects(r)
+    }
+
+    #[test]
+    fn traced_sphere_matches_the_exact_one() {
+        let s = Sdf::new(Field::Sphere(2.0));
+        let h = hit(&s, point(0.0, 0.0, -10.0), vector(0.0, 0.0, 1.0)).unwrap();
+
+        assert!((h.t - 8.0).abs() < s.tolerance, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, -1.0)), "{:?}", h);
+        assert!(hit(&s, point(0.0, 3.0, -10.0), vector(0.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn shapes_measure_distance_correctly() {
+        let bx = Field::Box {
+            size: vector(1.0, 2.0, 3.0),
+            rounding: 0.0,
+        };
+        assert!((bx.distance(vector(3.0, 0.0, 0.0)) - 2.0).abs() < APPROX_EPSILON);
+        assert!((bx.distance(vector(0.0, 0.0, 0.0)) + 1.0).abs() < APPROX_EPSILON);
+
+        let torus = Field::Torus {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        assert!((torus.distance(vector(0.0, 0.0, 0.0)) - 1.5).abs() < APPROX_EPSILON);
+        assert!(torus.distance(vector(2.0, 0.0, 0.0)) < 0.0);
+
+        let cylinder = Field::Cylinder {
+            radius: 1.0,
+            height: 2.0,
+        };
+        assert!((cylinder.distance(vector(0.0, 3.0, 0.0)) - 2.0).abs() < APPROX_EPSILON);
+        assert!((cylinder.distance(vector(4.0, 0.0, 0.0)) - 3.0).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn combinators() {
+        let a = Field::Sphere(1.0);
+        let b = Field::Translate(vector(1.5, 0.0, 0.0), Box::new(Field::Sphere(1.0)));
+        let gap = vector(0.75, 1.0, 0.0);
+
+        let hard = Field::Union(vec![a.clone(), b.clone()]);
+        let smooth = Field::SmoothUnion {
+            radius: 0.5,
+            children: vec![a.clone(), b.clone()],
+        };
+        assert!(smooth.distance(gap) < hard.distance(gap));
+
+        let carved = Field::Difference(Box::new(a.clone()), Box::new(b.clone()));
+        assert!(carved.distance(vector(0.75, 0.0, 0.0)) > 0.0);
+        assert!(carved.distance(vector(-0.5, 0.0, 0.0)) < 0.0);
+
+        let both = Field::Intersection(vec![a, b]);
+        assert!(both.distance(vector(0.75, 0.0, 0.0)) < 0.0);
+        assert!(both.distance(vector(-0.5, 0.0, 0.0)) > 0.0);
+        let bb = both.bounds();
+        assert!(bb.lower.approx_eq(point(0.5, -1.0, -1.0)), "{:?}", bb);
+        assert!(bb.upper.approx_eq(point(1.0, 1.0, 1.0)), "{:?}", bb);
+    }
+
+    #[test]
+    fn repeated_fields_are_hit_far_away() {
+        let s = Sdf::new(Field::Repeat(
+            vector(4.0, 0.0, 4.0),
+            Box::new(Field::Sphere(1.0)),
+        ));
+        assert!(s.bounding_box().upper.x.is_infinite());
+
+        let h = hit(&s, point(40.0, 10.0, 80.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert!(h.point.approx_eq(point(40.0, 1.0, 80.0)), "{:?}", h);
+        assert!(hit(&s, point(42.0, 10.0, 80.0), vector(0.0, -1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn twisted_boxes_still_hit() {
+        let twisted = Sdf::new(Field::Twist(
+            1.0,
+            Box::new(Field::Box {
+                size: vector(1.0, 2.0, 0.25),
+                rounding: 0.0,
+            }),
+        ));
+
+        // at height 1 the box has turned a radian, so a ray down the
+        // untwisted face misses it and one along the twisted face hits
+        let (sin, cos) = (1.0 as Float).sin_cos();
+        let along = vector(cos, 0.0, sin);
+        let h = hit(&twisted, point(0.0, 1.0, 0.0) + (along * 5.0), -along).unwrap();
+        assert!((h.t - 4.0).abs() < 1e-3, "{:?}", h);
+    }
+
+    #[test]
+    fn spawned_rays_escape_the_surface() {
+        let s = Sdf::new(Field::Box {
+            size: vector(1.0, 1.0, 1.0),
+            rounding: 0.25,
+        });
+        let h = hit(&s, point(0.3, 0.2, -10.0), vector(0.0, 0.0, 1.0)).unwrap();
+
+        let bounce = raygun_math::spawn_ray(h.point, h.error, h.normal, vector(0.0, 0.0, -1.0));
+        assert!(s.intersects(bounce).is_none());
+    }
+}
@@ -1,5 +1,8 @@
-use crate::{AxisAlignedBox, Primitive};
-use raygun_math::{self as math, point, Point, Ray, Vector};
+use crate::{AxisAlignedBox, Hit, Primitive};
+use raygun_math::{
+    self as math, consts::PI, gamma, point, vector, Float, Floatx4, Normal3, Point3, Ray,
+    RayPacket, Vector3x4, LANES,
+};
 use std::cmp;
 
 ///
@@ -7,17 +10,51 @@ use std::cmp;
 ///
 #[derive(Debug)]
 pub struct Sphere {
-    pub centre: Point,
-    pub radius: f64,
+    pub centre: Point3,
+    pub radius: Float,
 }
 
 impl Sphere {
-    pub fn new(loc: Point, radius: f64) -> Sphere {
+    pub fn new(loc: Point3, radius: Float) -> Sphere {
         Sphere {
             centre: loc,
             radius: radius,
         }
     }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        // re-project the point onto the sphere, leaving only the error from
+        // the projection itself
+        let local = r.extend(t) - self.centre;
+        let local = local * (self.radius / local.length());
+        let point = self.centre + local;
+
+        // spherical coordinates about the y axis, with u running around the
+        // equator and v running from the north pole to the south
+        let (x, y, z) = (local.x, local.y, local.z);
+        let phi = z.atan2(x);
+        let theta = (y / self.radius).clamp(-1.0, 1.0).acos();
+        let rho = (x * x + z * z).sqrt();
+        let (cos_phi, sin_phi) = if rho > 0.0 {
+            (x / rho, z / rho)
+        } else {
+            (1.0, 0.0)
+        };
+
+        let n = Normal3::from(local / self.radius);
+        Hit {
+            t,
+            point,
+            error: (local.abs() * gamma(5)) + (point.abs() * gamma(1)),
+            normal: n,
+            shading_normal: n,
+            uv: ((phi + PI) / (2.0 * PI), theta / PI),
+            dpdu: vector(-z, 0.0, x) * (2.0 * PI),
+            dpdv: vector(y * cos_phi, -rho, y * sin_phi) * PI,
+            face: 0,
+        }
+    }
 }
 
 impl Default for Sphere {
@@ -43,8 +80,8 @@ impl cmp::PartialEq for Sphere {
 impl cmp::Eq for Sphere {}
 
 impl Primitive for Sphere {
-    fn intersects(&self, r: Ray) -> Option<f64> {
-        let dist = Vector::between(r.src, self.centre);
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let dist = self.centre - r.src;
         let b = r.dir.dot(dist);
         match (b * b) - dist.dot(dist) + (self.radius * self.radius) {
             n if n < 0.0 => None,
@@ -53,7 +90,7 @@ impl Primitive for Sphere {
                 let t1 = b - d;
                 let t2 = b + d;
                 if t2 > 0.0 {
-                    Some(if t1 > 0.0 { t1 } else { t2 })
+                    Some(self.hit(r, if t1 > 0.0 { t1 } else { t2 }))
                 } else {
                     None
                 }
@@ -61,6 +98,28 @@ impl Primitive for Sphere {
         }
     }
 
+    fn intersects_packet(&self, p: &RayPacket) -> [Option<Hit>; LANES] {
+        let centre = Vector3x4::splat(self.centre.x, self.centre.y, self.centre.z);
+        let dist = centre - p.src;
+        let b = p.dir.dot(&dist);
+        let d2 = (b * b) - dist.dot(&dist) + Floatx4::splat(self.radius * self.radius);
+
+        // lanes that miss compute the square root of a negative number, but
+        // the result is thrown away
+        let d = d2.sqrt();
+        let t1 = b - d;
+        let t2 = b + d;
+
+        let mut result = [None; LANES];
+        for (n, r) in result.iter_mut().enumerate() {
+            if d2.lane(n) >= 0.0 && t2.lane(n) > 0.0 {
+                let (t1, t2) = (t1.lane(n), t2.lane(n));
+                *r = Some(self.hit(p.ray(n), if t1 > 0.0 { t1 } else { t2 }));
+            }
+        }
+        result
+    }
+
     fn bounding_box(&self) -> AxisAlignedBox {
         let (min_x, max_x) = math::sort(self.centre.x - self.radius, self.centre.x + self.radius);
         let (min_y, max_y) = math::sort(self.centre.y - self.radius, self.centre.y + self.radius);
@@ -70,16 +129,12 @@ impl Primitive for Sphere {
             upper: point(max_x, max_y, max_z),
         }
     }
-
-    fn normal(&self, pt: Point) -> Vector {
-        (pt - self.centre).normalize()
-    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use raygun_math::{vector, Ray};
+    use raygun_math::{normal, Ray, Vector3, APPROX_EPSILON};
 
     #[test]
     fn default() {
@@ -96,7 +151,7 @@ mod test {
         let s = Sphere::default();
         let r = Ray::new(point(0.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
         if let Some(x) = s.intersects(r) {
-            assert!(x - 90.0 < 0.0000001)
+            assert!(x.t - 90.0 < 0.0000001)
         } else {
             panic!("Did not intersect")
         }
@@ -111,23 +166,85 @@ mod test {
         }
     }
 
+    #[test]
+    fn distant_hits_lie_on_the_surface() {
+        let s = Sphere::new(point(1.0, 2.0, 3.0), 2.0);
+        let r = Ray::new(point(1e3, 1e3, 1e3), vector(-1.0, -1.0, -1.0));
+        let hit = s.intersects(r).unwrap();
+
+        let off_surface = ((hit.point - s.centre).length() - s.radius).abs();
+        assert!(
+            off_surface <= hit.error.length(),
+            "{:?} is {} off the surface",
+            hit,
+            off_surface
+        );
+    }
+
     #[test]
     fn cardinal_normals_are_as_expected() {
         let s = Sphere::default();
+        let cases = [
+            normal(1.0, 0.0, 0.0),
+            normal(0.0, 1.0, 0.0),
+            normal(0.0, 0.0, 1.0),
+        ];
+
+        for &expected in cases.iter() {
+            let r = Ray::new(point(0.0, 0.0, 0.0) + (expected * 10.0), -expected * 1.0);
+            let hit = s.intersects(r).unwrap();
+            assert!(
+                hit.normal.approx_eq(expected),
+                "Expected {:?}, got {:?}",
+                expected,
+                hit.normal
+            );
+        }
+    }
 
-        let p1 = point(1.0, 0.0, 0.0);
-        let v1 = vector(1.0, 0.0, 0.0);
-        let n1 = s.normal(p1);
-        assert!(n1.approx_eq(v1), "Expected {:?}, got {:?}", v1, n1);
-
-        let p2 = point(0.0, 1.0, 0.0);
-        let v2 = vector(0.0, 1.0, 0.0);
-        let n2 = s.normal(p2);
-        assert!(n2.approx_eq(v2), "Expected {:?}, got {:?}", v2, n2);
+    #[test]
+    fn uv_wraps_around_the_y_axis() {
+        let s = Sphere::new(point(1.0, 2.0, 3.0), 2.0);
+        let cases = [
+            (vector(0.0, 0.0, -1.0), (0.25, 0.5)),
+            (vector(1.0, 0.0, 0.0), (0.5, 0.5)),
+            (vector(0.0, 0.0, 1.0), (0.75, 0.5)),
+            (vector(0.0, 1.0, 0.0), (0.5, 0.0)),
+            (vector(0.0, -1.0, 0.0), (0.5, 1.0)),
+        ];
+
+        for &(dir, (u, v)) in cases.iter() {
+            let r = Ray::new(s.centre + (dir * 10.0), -dir);
+            let hit = s.intersects(r).unwrap();
+            let (hu, hv) = hit.uv;
+            assert!(
+                (hu - u).abs() < APPROX_EPSILON && (hv - v).abs() < APPROX_EPSILON,
+                "Expected {:?} at {:?}, got {:?}",
+                (u, v),
+                dir,
+                hit.uv
+            );
+
+            // the partial derivatives lie in the tangent plane
+            assert!(hit.dpdu.dot(Vector3::from(hit.normal)).abs() < APPROX_EPSILON);
+            assert!(hit.dpdv.dot(Vector3::from(hit.normal)).abs() < APPROX_EPSILON);
+        }
+    }
 
-        let p3 = point(0.0, 0.0, 1.0);
-        let v3 = vector(0.0, 0.0, 1.0);
-        let n3 = s.normal(p3);
-        assert!(n3.approx_eq(v3), "Expected {:?}, got {:?}", v3, n3);
+    #[test]
+    fn packet_matches_single_rays() {
+        let s = Sphere::new(point(1.0, 2.0, 3.0), 2.0);
+        let rays = [
+            Ray::new(point(1.0, 2.0, -10.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(0.0, 0.0, 0.0), vector(1.0, 2.0, 3.0)),
+            Ray::new(point(1.0, 2.0, 3.0), vector(0.0, 1.0, 0.0)),
+            Ray::new(point(1.0, 10.0, 0.0), vector(0.0, 0.0, 1.0)),
+        ];
+
+        let actual = s.intersects_packet(&RayPacket::new(&rays));
+        assert!(actual[3].is_none());
+        for (n, r) in rays.iter().enumerate() {
+            assert_eq!(actual[n], s.intersects(*r), "lane {}", n);
+        }
     }
 }
@@ -0,0 +1,157 @@
+use raygun_math::Float;
+
+///
+/// How the points of a profile or path are joined up
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Spline {
+    /// Straight lines from each point to the next
+    Linear,
+
+    /// Quadratic Bézier curves, where every second point is a control point
+    /// that the curve bends towards but doesn't pass through. There must be
+    /// an odd number of points.
+    Quadratic,
+
+    /// A Catmull-Rom spline, which passes smoothly through every point but
+    /// the first and last. Those only set the direction the curve leaves the
+    /// second point and arrives at the second-last.
+    Cubic,
+}
+
+///
+/// One piece of a spline, as a weighted sum of some of its points. Each
+/// weight is a cubic in the parameter `s`, which runs from 0 to 1 along the
+/// segment, given lowest power first.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Segment {
+    pub terms: Vec<(usize, [Float; 4])>,
+}
+
+impl Segment {
+    /// The polynomial giving one coordinate of the segment, lowest power
+    /// first, given that coordinate of each point
+    pub fn coefficients<F: Fn(usize) -> Float>(&self, coordinate: F) -> [Float; 4] {
+        let mut c = [0.0; 4];
+        for (index, weights) in self.terms.iter() {
+            let x = coordinate(*index);
+            for (c, w) in c.iter_mut().zip(weights.iter()) {
+                *c += x * w;
+            }
+        }
+        c
+    }
+
+    /// The weight of each point at `s`
+    pub fn weights(&self, s: Float) -> impl Iterator<Item = (usize, Float)> + '_ {
+        self.terms
+            .iter()
+            .map(move |(index, w)| (*index, ((w[3] * s + w[2]) * s + w[1]) * s + w[0]))
+    }
+}
+
+impl Spline {
+    /// Can this kind of spline join up `count` points?
+    pub fn accepts(self, count: usize) -> bool {
+        self.segments(count).is_some()
+    }
+
+    /// The fewest points this kind of spline can join
+    fn min_points(self) -> usize {
+        match self {
+            Spline::Linear => 2,
+            Spline::Quadratic => 3,
+            Spline::Cubic => 4,
+        }
+    }
+
+    ///
+    /// Splits a spline through `count` points into its segments. Returns
+    /// `None` if that's the wrong number of points for this kind of spline.
+    ///
+    pub(crate) fn segments(self, count: usize) -> Option<Vec<Segment>> {
+        if count < self.min_points() {
+            return None;
+        }
+
+        let segments = match self {
+            Spline::Linear => (0..count - 1)
+                .map(|i| Segment {
+                    terms: vec![(i, [1.0, -1.0, 0.0, 0.0]), (i + 1, [0.0, 1.0, 0.0, 0.0])],
+                })
+                .collect(),
+
+            Spline::Quadratic if count % 2 == 1 => (0..count - 1)
+                .step_by(2)
+                .map(|i| Segment {
+                    terms: vec![
+                        (i, [1.0, -2.0, 1.0, 0.0]),
+                        (i + 1, [0.0, 2.0, -2.0, 0.0]),
+                        (i + 2, [0.0, 0.0, 1.0, 0.0]),
+                    ],
+                })
+                .collect(),
+
+            Spline::Quadratic => return None,
+
+            Spline::Cubic => (0..count - 3)
+                .map(|i| Segment {
+                    terms: vec![
+                        (i, [0.0, -0.5, 1.0, -0.5]),
+                        (i + 1, [1.0, 0.0, -2.5, 1.5]),
+                        (i + 2, [0.0, 0.5, 2.0, -1.5]),
+                        (i + 3, [0.0, 0.0, -0.5, 0.5]),
+                    ],
+                })
+                .collect(),
+        };
+
+        Some(segments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::APPROX_EPSILON;
+
+    #[test]
+    fn point_counts() {
+        assert_eq!(Spline::Linear.segments(1), None);
+        assert_eq!(Spline::Linear.segments(3).unwrap().len(), 2);
+        assert_eq!(Spline::Quadratic.segments(4), None);
+        assert_eq!(Spline::Quadratic.segments(5).unwrap().len(), 2);
+        assert_eq!(Spline::Cubic.segments(3), None);
+        assert_eq!(Spline::Cubic.segments(6).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn splines_join_up() {
+        let xs = [0.0, 1.0, 3.0, 2.0, 5.0];
+        for &spline in [Spline::Linear, Spline::Quadratic, Spline::Cubic].iter() {
+            let segments = spline.segments(xs.len()).unwrap();
+            let value =
+                |seg: &Segment, s: Float| seg.weights(s).map(|(i, w)| xs[i] * w).sum::<Float>();
+            for pair in segments.windows(2) {
+                let (end, start) = (value(&pair[0], 1.0), value(&pair[1], 0.0));
+                assert!((end - start).abs() < APPROX_EPSILON, "{:?}", spline);
+            }
+
+            // the polynomial form agrees with the weights
+            let c = segments[0].coefficients(|i| xs[i]);
+            let s = 0.3;
+            let poly = ((c[3] * s + c[2]) * s + c[1]) * s + c[0];
+            assert!((poly - value(&segments[0], s)).abs() < APPROX_EPSILON);
+        }
+    }
+
+    #[test]
+    fn cubic_splines_pass_through_the_inner_points() {
+        let xs = [0.0, 1.0, 3.0, 2.0];
+        let segment = &Spline::Cubic.segments(4).unwrap()[0];
+        let c = segment.coefficients(|i| xs[i]);
+        assert!((c[0] - 1.0).abs() < APPROX_EPSILON);
+        assert!((c.iter().sum::<Float>() - 3.0).abs() < APPROX_EPSILON);
+    }
+}
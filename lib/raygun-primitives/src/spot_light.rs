@@ -0,0 +1,92 @@
+use crate::{AxisAlignedBox, Hit, Light, Primitive};
+use raygun_material::Colour;
+use raygun_math::{Float, Point3, Ray};
+
+///
+/// A light at the origin shining down the z axis, in a cone that fades out
+/// between the inner and outer angles (in radians) from the axis. Move and
+/// point it with a transform.
+///
+#[derive(Debug)]
+pub struct SpotLight {
+    pub colour: Colour,
+    pub inner: Float,
+    pub outer: Float,
+}
+
+impl SpotLight {
+    pub fn new(colour: Colour, inner: Float, outer: Float) -> SpotLight {
+        SpotLight {
+            colour,
+            inner: inner.min(outer),
+            outer,
+        }
+    }
+}
+
+impl Primitive for SpotLight {
+    fn intersects(&self, _r: Ray) -> Option<Hit> {
+        None
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        AxisAlignedBox {
+            lower: Point3::default(),
+            upper: Point3::default(),
+        }
+    }
+
+    fn as_light(&self) -> Option<&dyn Light> {
+        Some(self as &dyn Light)
+    }
+}
+
+impl Light for SpotLight {
+    fn src(&self) -> Point3 {
+        Point3::default()
+    }
+
+    fn illuminates(&self, p: Point3) -> Option<Colour> {
+        let distance = (p - Point3::default()).length();
+        if distance == 0.0 {
+            return None;
+        }
+
+        // fade out smoothly with the angle from the axis, the way glTF
+        // describes it
+        let cos = p.z / distance;
+        let (cos_inner, cos_outer) = (self.inner.cos(), self.outer.cos());
+        if cos <= cos_outer {
+            return None;
+        }
+        let fade = if cos >= cos_inner {
+            1.0
+        } else {
+            let f = (cos - cos_outer) / (cos_inner - cos_outer);
+            f * f
+        };
+
+        Some(self.colour * fade)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::point;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn light_falls_within_the_cone() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let light = SpotLight::new(white, (PI / 8.0) as Float, (PI / 4.0) as Float);
+
+        assert_eq!(light.illuminates(point(0.0, 0.1, 1.0)), Some(white));
+        assert_eq!(light.illuminates(point(0.0, 1.1, 1.0)), None);
+        assert_eq!(light.illuminates(point(0.0, 0.0, -1.0)), None);
+
+        // between the angles the light fades
+        let c = light.illuminates(point(0.6, 0.0, 1.0)).unwrap();
+        assert!(c.r > 0.0 && c.r < 1.0);
+    }
+}
@@ -0,0 +1,520 @@
+use std::cmp;
+
+use crate::{
+    flat::{plane_distance, project_onto_plane, triangle_distance},
+    AxisAlignedBox, Hit, Primitive, Spline,
+};
+use raygun_math::{point, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A 2D shape extruded along a path. The shape is a closed polygon of
+/// `(x, y)` points, laid across the path so that x runs along the path's
+/// "right" and y along its "up", and carried along the path without
+/// twisting. Where a straight path turns a corner the sides are mitred, and
+/// curved paths are followed in short straight steps with their normals
+/// smoothed over. Both ends of the sweep are capped.
+///
+#[derive(Debug)]
+pub struct Sweep {
+    spline: Spline,
+    shape: Vec<(Float, Float)>,
+    path: Vec<Point3>,
+
+    // the shape as actually swept: anticlockwise, with no repeated points
+    outline: Vec<(Float, Float)>,
+
+    // the outline positioned at each step along the path
+    rings: Vec<Vec<Point3>>,
+
+    // the outward normal of each side face, and the normal averaged across
+    // neighbouring steps at each ring, for smooth shading
+    face_normals: Vec<Vec<Vector3>>,
+    ring_normals: Vec<Vec<Vector3>>,
+
+    // each end of the sweep, as the point on the path, the outward normal,
+    // and the directions of the shape's x and y
+    caps: [(Point3, Vector3, Vector3, Vector3); 2],
+
+    // the extent of the shape, for mapping textures onto the caps
+    shape_lower: (Float, Float),
+    shape_size: (Float, Float),
+
+    bounds: Vec<AxisAlignedBox>,
+}
+
+/// How many straight steps each segment of a curved path is followed in
+const STEPS: usize = 16;
+
+/// Where on the sweep a ray hits it
+#[derive(Clone, Copy, Debug)]
+enum Place {
+    /// The side face along the given path step and shape edge, and how far
+    /// the hit is along the edge and along the step
+    Side {
+        step: usize,
+        edge: usize,
+        along: Float,
+        across: Float,
+    },
+
+    /// The start (0) or end (1) cap
+    Cap(usize),
+}
+
+/// Is `(x, y)` inside `shape`? Uses the even-odd rule.
+fn contains(shape: &[(Float, Float)], x: Float, y: Float) -> bool {
+    let mut inside = false;
+    let mut j = shape.len() - 1;
+    for (i, &(xi, yi)) in shape.iter().enumerate() {
+        let (xj, yj) = shape[j];
+        if (yi > y) != (yj > y) && x < xi + (y - yi) * (xj - xi) / (yj - yi) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Rotates `v` by the rotation that takes unit vector `a` to unit vector `b`
+fn rotate(v: Vector3, a: Vector3, b: Vector3) -> Vector3 {
+    let w = a.cross(b);
+    let c = a.dot(b);
+    (v * c) + w.cross(v) + (w * (w.dot(v) / (1.0 + c)))
+}
+
+fn bounds_of<'a, I: Iterator<Item = &'a Point3>>(points: I) -> AxisAlignedBox {
+    let inf = Float::INFINITY;
+    points.fold(
+        AxisAlignedBox {
+            lower: point(inf, inf, inf),
+            upper: point(-inf, -inf, -inf),
+        },
+        |b, p| AxisAlignedBox {
+            lower: point(b.lower.x.min(p.x), b.lower.y.min(p.y), b.lower.z.min(p.z)),
+            upper: point(b.upper.x.max(p.x), b.upper.y.max(p.y), b.upper.z.max(p.z)),
+        },
+    )
+}
+
+impl Sweep {
+    ///
+    /// Creates a sweep of `shape` along the spline through the `path`
+    /// points. Returns `None` if the shape has no area, there are the wrong
+    /// number of path points for the spline, or the path doubles straight
+    /// back on itself.
+    ///
+    pub fn new(spline: Spline, shape: Vec<(Float, Float)>, path: Vec<Point3>) -> Option<Sweep> {
+        // drop repeated points, which would leave zero-length edges
+        let mut outline: Vec<(Float, Float)> = Vec::with_capacity(shape.len());
+        for &p in shape.iter() {
+            if outline.last() != Some(&p) {
+                outline.push(p);
+            }
+        }
+        while outline.len() > 1 && outline.first() == outline.last() {
+            outline.pop();
+        }
+        if outline.len() < 3 {
+            return None;
+        }
+        let area: Float = (0..outline.len())
+            .map(|i| {
+                let ((x0, y0), (x1, y1)) = (outline[i], outline[(i + 1) % outline.len()]);
+                (x0 * y1) - (x1 * y0)
+            })
+            .sum();
+        if area == 0.0 {
+            return None;
+        } else if area < 0.0 {
+            outline.reverse();
+        }
+
+        // follow the path in straight steps
+        let mut steps: Vec<Point3> = Vec::new();
+        for seg in spline.segments(path.len())? {
+            let samples = if spline == Spline::Linear { 1 } else { STEPS };
+            for k in 0..=samples {
+                let s = k as Float / samples as Float;
+                let p = seg.weights(s).fold(point(0.0, 0.0, 0.0), |acc, (i, w)| {
+                    acc + (Vector3::from(path[i]) * w)
+                });
+                if steps.last() != Some(&p) {
+                    steps.push(p);
+                }
+            }
+        }
+        if steps.len() < 2 {
+            return None;
+        }
+        let dirs: Vec<Vector3> = steps
+            .windows(2)
+            .map(|w| (w[1] - w[0]).normalize())
+            .collect();
+
+        // the shape's axes for each step, carried along from one step to the
+        // next by the smallest rotation between them
+        let first = dirs[0];
+        let up = if first.y.abs() < 0.999 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(0.0, 0.0, 1.0)
+        };
+        let y_axis = (up - (first * up.dot(first))).normalize();
+        let mut frames = vec![(y_axis.cross(first), y_axis)];
+        for w in dirs.windows(2) {
+            if 1.0 + w[0].dot(w[1]) < 1e-6 {
+                return None;
+            }
+            let (x, y) = frames[frames.len() - 1];
+            frames.push((rotate(x, w[0], w[1]), rotate(y, w[0], w[1])));
+        }
+
+        // position the shape at each step. At a corner it's laid in the
+        // plane halfway between the steps either side, which both meet
+        let mut rings = Vec::with_capacity(steps.len());
+        for j in 0..steps.len() {
+            let n = j.saturating_sub(1);
+            let ((x, y), d) = (frames[n], dirs[n]);
+            let mitre = if j > 0 && j < dirs.len() {
+                Some((d + dirs[j]).normalize())
+            } else {
+                None
+            };
+            let ring = outline
+                .iter()
+                .map(|&(sx, sy)| {
+                    let q = (x * sx) + (y * sy);
+                    let q = match mitre {
+                        Some(m) => q - (d * (q.dot(m) / d.dot(m))),
+                        None => q,
+                    };
+                    steps[j] + q
+                })
+                .collect::<Vec<_>>();
+            rings.push(ring);
+        }
+
+        let edges = outline.len();
+        let face_normals: Vec<Vec<Vector3>> = rings
+            .windows(2)
+            .map(|w| {
+                (0..edges)
+                    .map(|k| {
+                        let (a, b, d) = (w[0][k], w[0][(k + 1) % edges], w[1][k]);
+                        (b - a).cross(d - a).normalize()
+                    })
+                    .collect()
+            })
+            .collect();
+        let ring_normals = (0..rings.len())
+            .map(|j| {
+                (0..edges)
+                    .map(|k| {
+                        let before = if j > 0 {
+                            face_normals[j - 1][k]
+                        } else {
+                            Vector3::default()
+                        };
+                        let after = face_normals.get(j).map_or(Vector3::default(), |f| f[k]);
+                        (before + after).normalize()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let bounds = rings
+            .windows(2)
+            .map(|w| bounds_of(w[0].iter().chain(w[1].iter())))
+            .collect();
+
+        let last = dirs.len() - 1;
+        let caps = [
+            (steps[0], -dirs[0], frames[0].0, frames[0].1),
+            (steps[last + 1], dirs[last], frames[last].0, frames[last].1),
+        ];
+
+        let (x0, y0) = outline[0];
+        let (lower, upper) = outline
+            .iter()
+            .fold(((x0, y0), (x0, y0)), |(lo, hi), &(x, y)| {
+                ((lo.0.min(x), lo.1.min(y)), (hi.0.max(x), hi.1.max(y)))
+            });
+
+        Some(Sweep {
+            spline,
+            shape,
+            path,
+            outline,
+            rings,
+            face_normals,
+            ring_normals,
+            caps,
+            shape_lower: lower,
+            shape_size: (upper.0 - lower.0, upper.1 - lower.1),
+            bounds,
+        })
+    }
+
+    pub fn spline(&self) -> Spline {
+        self.spline
+    }
+
+    pub fn shape(&self) -> &[(Float, Float)] {
+        &self.shape
+    }
+
+    pub fn path(&self) -> &[Point3] {
+        &self.path
+    }
+
+    /// The shape's edge count, and the number of steps along the path
+    fn size(&self) -> (usize, usize) {
+        (self.rings[0].len(), self.rings.len() - 1)
+    }
+
+    /// Where a point on a cap's plane lies in the shape's coordinates
+    fn on_cap(&self, cap: usize, p: Point3) -> (Float, Float) {
+        let (origin, _, x, y) = self.caps[cap];
+        let v = p - origin;
+        (v.dot(x), v.dot(y))
+    }
+
+    fn nearest(&self, r: Ray) -> Option<(Float, Place)> {
+        let mut nearest: Option<(Float, Place)> = None;
+        let mut consider = |t: Float, place: Place| match nearest {
+            Some((best, _)) if best <= t => {}
+            _ => nearest = Some((t, place)),
+        };
+
+        let (edges, _) = self.size();
+        for (step, bounds) in self.bounds.iter().enumerate() {
+            if bounds.span(&r).is_none() {
+                continue;
+            }
+            let (here, next) = (&self.rings[step], &self.rings[step + 1]);
+            for edge in 0..edges {
+                let k = (edge + 1) % edges;
+                let (a, b, c, d) = (here[edge], here[k], next[k], next[edge]);
+                if let Some((t, b1, b2)) = triangle_distance(r, a, b, c) {
+                    let (along, across) = (b1 + b2, b2);
+                    consider(
+                        t,
+                        Place::Side {
+                            step,
+                            edge,
+                            along,
+                            across,
+                        },
+                    );
+                }
+                if let Some((t, b1, b2)) = triangle_distance(r, a, c, d) {
+                    let (along, across) = (b1, b1 + b2);
+                    consider(
+                        t,
+                        Place::Side {
+                            step,
+                            edge,
+                            along,
+                            across,
+                        },
+                    );
+                }
+            }
+        }
+
+        for cap in 0..2 {
+            let (origin, n, _, _) = self.caps[cap];
+            if let Some(t) = plane_distance(r, origin, n) {
+                let (x, y) = self.on_cap(cap, r.extend(t));
+                if contains(&self.outline, x, y) {
+                    consider(t, Place::Cap(cap));
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The hit record for an intersection `t` units along `r`, at the place
+    /// found by `nearest`
+    fn hit(&self, r: Ray, t: Float, place: Place) -> Hit {
+        let (edges, steps) = self.size();
+
+        match place {
+            Place::Side {
+                step,
+                edge,
+                along,
+                across,
+            } => {
+                let (here, next) = (&self.rings[step], &self.rings[step + 1]);
+                let a = here[edge];
+                let n = self.face_normals[step][edge];
+                let (point, error) = project_onto_plane(r, t, a, n);
+
+                let shading = if self.spline == Spline::Linear {
+                    n
+                } else {
+                    let (n0, n1) = (
+                        self.ring_normals[step][edge],
+                        self.ring_normals[step + 1][edge],
+                    );
+                    ((n0 * (1.0 - across)) + (n1 * across)).normalize()
+                };
+
+                Hit {
+                    t,
+                    point,
+                    error,
+                    normal: Normal3::from(n),
+                    shading_normal: Normal3::from(shading),
+                    uv: (
+                        (edge as Float + along) / edges as Float,
+                        (step as Float + across) / steps as Float,
+                    ),
+                    dpdu: (here[(edge + 1) % edges] - a) * edges as Float,
+                    dpdv: (next[edge] - a) * steps as Float,
+                    face: (step * edges) + edge,
+                }
+            }
+
+            Place::Cap(cap) => {
+                let (origin, n, x, y) = self.caps[cap];
+                let (point, error) = project_onto_plane(r, t, origin, n);
+                let (sx, sy) = self.on_cap(cap, point);
+                let (lower, size) = (self.shape_lower, self.shape_size);
+
+                Hit {
+                    t,
+                    point,
+                    error,
+                    normal: Normal3::from(n),
+                    shading_normal: Normal3::from(n),
+                    uv: ((sx - lower.0) / size.0, (sy - lower.1) / size.1),
+                    dpdu: x * size.0,
+                    dpdv: y * size.1,
+                    face: (steps * edges) + cap,
+                }
+            }
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a sweep object
+impl cmp::PartialEq for Sweep {
+    fn eq(&self, other: &Sweep) -> bool {
+        self.spline == other.spline && self.shape == other.shape && self.path == other.path
+    }
+}
+
+impl Primitive for Sweep {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, place)| self.hit(r, t, place))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let first = self.bounds[0].clone();
+        self.bounds
+            .iter()
+            .skip(1)
+            .fold(first, |acc, b| acc.union(b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    fn square() -> Vec<(Float, Float)> {
+        vec![(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+    }
+
+    #[test]
+    fn straight_sweeps_are_prisms() {
+        let s = Sweep::new(
+            Spline::Linear,
+            square(),
+            vec![point(0.0, 0.0, 0.0), point(0.0, 0.0, 4.0)],
+        )
+        .unwrap();
+
+        let b = s.bounding_box();
+        assert!(b.lower.approx_eq(point(-1.0, -1.0, 0.0)), "{:?}", b);
+        assert!(b.upper.approx_eq(point(1.0, 1.0, 4.0)), "{:?}", b);
+
+        // the shape's x and y line up with the world's when sweeping along z
+        let r = Ray::new(point(10.0, 0.5, 2.0), vector(-1.0, 0.0, 0.0));
+        let h = s.intersects(r).unwrap();
+        assert!((h.t - 9.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(1.0, 0.0, 0.0)), "{:?}", h);
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+
+        // and the ends are capped
+        let r = Ray::new(point(0.5, 0.5, -10.0), vector(0.0, 0.0, 1.0));
+        let h = s.intersects(r).unwrap();
+        assert!((h.t - 10.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, -1.0)), "{:?}", h);
+        assert_eq!(h.face, 4);
+
+        let r = Ray::new(point(1.5, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        assert!(s.intersects(r).is_none());
+    }
+
+    #[test]
+    fn corners_are_mitred() {
+        // an L-shaped bend, which keeps its full thickness round the corner
+        let s = Sweep::new(
+            Spline::Linear,
+            square(),
+            vec![
+                point(0.0, 0.0, 0.0),
+                point(0.0, 0.0, 4.0),
+                point(4.0, 0.0, 4.0),
+            ],
+        )
+        .unwrap();
+
+        let r = Ray::new(point(-0.8, 10.0, 4.7), vector(0.0, -1.0, 0.0));
+        let h = s.intersects(r).unwrap();
+        assert!((h.point.y - 1.0).abs() < APPROX_EPSILON, "{:?}", h);
+
+        let r = Ray::new(point(-0.5, 0.0, 10.0), vector(0.0, 0.0, -1.0));
+        let h = s.intersects(r).unwrap();
+        assert!((h.point.z - 5.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, 1.0)), "{:?}", h);
+    }
+
+    #[test]
+    fn curved_sweeps_are_smoothed() {
+        let path = vec![
+            point(0.0, 0.0, -1.0),
+            point(0.0, 0.0, 0.0),
+            point(5.0, 0.0, 5.0),
+            point(10.0, 0.0, 0.0),
+            point(10.0, 0.0, -1.0),
+        ];
+        let s = Sweep::new(Spline::Cubic, square(), path).unwrap();
+
+        let r = Ray::new(point(5.0, 10.0, 5.0), vector(0.0, -1.0, 0.0));
+        let h = s.intersects(r).unwrap();
+        assert!((h.point.y - 1.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.shading_normal.approx_eq(normal(0.0, 1.0, 0.0)), "{:?}", h);
+    }
+
+    #[test]
+    fn degenerate_sweeps() {
+        let line = vec![point(0.0, 0.0, 0.0), point(0.0, 0.0, 1.0)];
+        let flat = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)];
+        assert!(Sweep::new(Spline::Linear, flat, line.clone()).is_none());
+        assert!(Sweep::new(Spline::Cubic, square(), line).is_none());
+
+        let back = vec![
+            point(0.0, 0.0, 0.0),
+            point(0.0, 0.0, 1.0),
+            point(0.0, 0.0, 0.0),
+        ];
+        assert!(Sweep::new(Spline::Linear, square(), back).is_none());
+    }
+}
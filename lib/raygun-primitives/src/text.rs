@@ -0,0 +1,895 @@
+use std::{cmp, fmt, fs, ops::Range, path::Path};
+
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::{
+    flat::{plane_distance, project_onto_plane, triangle_distance},
+    AxisAlignedBox, Hit, Primitive,
+};
+use raygun_math::{point, Float, Normal3, Point3, Ray, Vector3};
+
+///
+/// A TrueType or OpenType font, read into memory
+///
+pub struct Font {
+    data: Vec<u8>,
+}
+
+impl Font {
+    /// Wraps the contents of a font file, checking that it can be parsed
+    pub fn new(data: Vec<u8>) -> Result<Font, String> {
+        Face::parse(&data, 0).map_err(|e| e.to_string())?;
+        Ok(Font { data })
+    }
+
+    pub fn load(path: &Path) -> Result<Font, String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Font::new(data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, 0).expect("font was checked when it was read")
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Font {{ {} bytes }}", self.data.len())
+    }
+}
+
+///
+/// Where each line of text is placed relative to the origin
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+    /// Lines start at the origin
+    Left,
+
+    /// Lines are centred on the origin
+    Centre,
+
+    /// Lines end at the origin
+    Right,
+}
+
+///
+/// A string of text set in a font and extruded into a solid. The text reads
+/// along +x with the baseline of the first line on y = 0, and each further
+/// line below the last. Sizes are in ems, so capitals stand roughly 0.7
+/// units tall, and pairs of letters are kerned as the font's `kern` table
+/// says (fonts that only kern through OpenType layout aren't). The letters'
+/// faces lie on z = 0 and they're extruded back to z = `depth`.
+///
+/// A non-zero `bevel` chamfers the edges of both faces at 45°, cutting that
+/// far into the outline and into the depth. A bevel wider than half a
+/// letter's stroke will tangle the outline, so keep it small.
+///
+#[derive(Debug)]
+pub struct Text {
+    string: String,
+    depth: Float,
+    bevel: Float,
+    alignment: Alignment,
+
+    glyphs: Vec<Glyph>,
+    walls: Vec<Wall>,
+
+    // the extent of the text, for mapping textures onto the faces
+    lower: (Float, Float),
+    size: (Float, Float),
+}
+
+/// A glyph's outline, as a list of closed polygons
+type Contours = Vec<Vec<(Float, Float)>>;
+
+/// One letter's outline, and where to find its side walls
+#[derive(Debug)]
+struct Glyph {
+    /// Which character of the string this is
+    index: usize,
+
+    /// The outline's contours, as placed in the text
+    contours: Contours,
+
+    /// The outline of the front and back faces, which the bevel shrinks
+    faces: Contours,
+
+    bounds: AxisAlignedBox,
+
+    /// Runs of walls, and the box around each
+    chunks: Vec<(AxisAlignedBox, Range<usize>)>,
+}
+
+/// One quad of a letter's sides, between two layers of the extrusion
+#[derive(Debug)]
+struct Wall {
+    /// The edge's start and end on the nearer layer, then its end and start
+    /// on the farther one
+    corners: [Point3; 4],
+
+    normal: Vector3,
+
+    /// The shading normal at the edge's start and end
+    shading: [Vector3; 2],
+
+    /// Where the wall lies along its contour, and in depth, for texturing
+    u: (Float, Float),
+    v: (Float, Float),
+
+    glyph: usize,
+}
+
+/// Where on the text a ray hits it
+#[derive(Clone, Copy, Debug)]
+enum Place {
+    /// The given wall, and how far the hit is along its edge and across
+    /// the layers
+    Wall {
+        wall: usize,
+        along: Float,
+        across: Float,
+    },
+
+    /// The given glyph's front (0) or back (1) face
+    Face(usize, usize),
+}
+
+/// How far a flattened curve may stray from the true one, in ems
+const TOLERANCE: Float = 0.001;
+
+/// The most segments any one curve is flattened into
+const MAX_SEGMENTS: usize = 32;
+
+/// How many walls share a bounding box
+const CHUNK: usize = 16;
+
+/// Neighbouring edges meeting at less than this angle are shaded smoothly
+const SMOOTH_ANGLE: Float = 0.5;
+
+/// The outline of a glyph, with its curves flattened to within `tolerance`
+#[derive(Default)]
+struct Outline {
+    tolerance: Float,
+    contours: Vec<Vec<(Float, Float)>>,
+    current: Vec<(Float, Float)>,
+}
+
+impl Outline {
+    fn new(tolerance: Float) -> Outline {
+        Outline {
+            tolerance,
+            ..Outline::default()
+        }
+    }
+
+    fn last(&self) -> (Float, Float) {
+        self.current.last().copied().unwrap_or((0.0, 0.0))
+    }
+
+    fn push(&mut self, p: (Float, Float)) {
+        if self.current.last() != Some(&p) {
+            self.current.push(p);
+        }
+    }
+
+    /// Adds `segments` points along a curve, given its position at `s`
+    fn curve<F: Fn(Float) -> (Float, Float)>(&mut self, bend: Float, at: F) {
+        // a curve with second difference `bend` strays from a chord by at
+        // most bend / 8 per segment², taking the worse of a cubic's two ends
+        let segments =
+            ((bend / (8.0 * self.tolerance)).sqrt().ceil() as usize).clamp(1, MAX_SEGMENTS);
+        for k in 1..=segments {
+            self.push(at(k as Float / segments as Float));
+        }
+    }
+
+    fn finish(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        while contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+fn length((x, y): (Float, Float)) -> Float {
+    x.hypot(y)
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish();
+        self.push((x as Float, y as Float));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push((x as Float, y as Float));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (
+            self.last(),
+            (x1 as Float, y1 as Float),
+            (x as Float, y as Float),
+        );
+        let bend = 2.0 * length((p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1));
+        self.curve(bend, |s| {
+            let (a, b, c) = ((1.0 - s) * (1.0 - s), 2.0 * s * (1.0 - s), s * s);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0,
+                a * p0.1 + b * p1.1 + c * p2.1,
+            )
+        });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (
+            self.last(),
+            (x1 as Float, y1 as Float),
+            (x2 as Float, y2 as Float),
+            (x as Float, y as Float),
+        );
+        let bend = 6.0
+            * length((p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1))
+                .max(length((p1.0 - 2.0 * p2.0 + p3.0, p1.1 - 2.0 * p2.1 + p3.1)));
+        self.curve(bend, |s| {
+            let t = 1.0 - s;
+            let (a, b, c, d) = (t * t * t, 3.0 * s * t * t, 3.0 * s * s * t, s * s * s);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+            )
+        });
+    }
+
+    fn close(&mut self) {
+        self.finish();
+    }
+}
+
+/// The winding number of `contours` around `(x, y)`
+fn winding(contours: &[Vec<(Float, Float)>], x: Float, y: Float) -> i32 {
+    let mut w = 0;
+    for contour in contours.iter() {
+        let mut j = contour.len() - 1;
+        for (i, &(xi, yi)) in contour.iter().enumerate() {
+            let (xj, yj) = contour[j];
+            let side = (xi - xj) * (y - yj) - (x - xj) * (yi - yj);
+            if yj <= y {
+                if yi > y && side > 0.0 {
+                    w += 1;
+                }
+            } else if yi <= y && side < 0.0 {
+                w -= 1;
+            }
+            j = i;
+        }
+    }
+    w
+}
+
+/// The unit normal to the right of the edge from `a` to `b`
+fn right_of(a: (Float, Float), b: (Float, Float)) -> (Float, Float) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let l = dx.hypot(dy);
+    (dy / l, -dx / l)
+}
+
+///
+/// The outward normal of each edge of `contour`, which is one of the
+/// glyph's `contours`. Fonts differ on which way round they wind their
+/// outlines, so this looks at which side of the contour's longest edge is
+/// filled.
+///
+fn outward_normals(
+    contour: &[(Float, Float)],
+    contours: &[Vec<(Float, Float)>],
+) -> Vec<(Float, Float)> {
+    let edge = |i: usize| (contour[i], contour[(i + 1) % contour.len()]);
+    let longest = (0..contour.len())
+        .max_by(|&i, &j| {
+            let ((a, b), (c, d)) = (edge(i), edge(j));
+            let (li, lj) = (
+                length((b.0 - a.0, b.1 - a.1)),
+                length((d.0 - c.0, d.1 - c.1)),
+            );
+            li.partial_cmp(&lj).unwrap_or(cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+
+    let (a, b) = edge(longest);
+    let n = right_of(a, b);
+    let (mx, my) = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    let e = 1e-4;
+    let filled_right = winding(contours, mx + n.0 * e, my + n.1 * e) != 0;
+    let filled_left = winding(contours, mx - n.0 * e, my - n.1 * e) != 0;
+    let flip = match (filled_left, filled_right) {
+        (true, false) => false,
+        (false, true) => true,
+        // an ambiguous edge: assume outer contours run anticlockwise
+        _ => signed_area(contour) < 0.0,
+    };
+
+    (0..contour.len())
+        .map(|i| {
+            let (a, b) = edge(i);
+            let (x, y) = right_of(a, b);
+            if flip {
+                (-x, -y)
+            } else {
+                (x, y)
+            }
+        })
+        .collect()
+}
+
+fn signed_area(contour: &[(Float, Float)]) -> Float {
+    (0..contour.len())
+        .map(|i| {
+            let ((x0, y0), (x1, y1)) = (contour[i], contour[(i + 1) % contour.len()]);
+            (x0 * y1) - (x1 * y0)
+        })
+        .sum()
+}
+
+/// Moves each point of `contour` inwards by `by`, keeping its edges parallel
+fn inset(contour: &[(Float, Float)], normals: &[(Float, Float)], by: Float) -> Vec<(Float, Float)> {
+    let n = contour.len();
+    (0..n)
+        .map(|i| {
+            let (n0, n1) = (normals[(i + n - 1) % n], normals[i]);
+            let m = (n0.0 + n1.0, n0.1 + n1.1);
+            let (x, y) = contour[i];
+            let cos = length(m) / 2.0;
+            if cos < 1e-3 {
+                // the outline doubles back on itself here
+                return (x - n0.0 * by, y - n0.1 * by);
+            }
+
+            // a mitre, limited so that sharp corners don't spike
+            let scale = by / (2.0 * cos * cos).max(2.0 / 9.0);
+            (x - m.0 * scale, y - m.1 * scale)
+        })
+        .collect()
+}
+
+/// The normals to shade with at each point of a contour, smoothed across
+/// gentle bends and left sharp at corners. Gives the normal at the start and
+/// end of each edge.
+fn smoothed(normals: &[(Float, Float)]) -> Vec<[(Float, Float); 2]> {
+    let n = normals.len();
+    let cos = SMOOTH_ANGLE.cos();
+    let blend = |a: (Float, Float), b: (Float, Float)| {
+        if a.0 * b.0 + a.1 * b.1 > cos {
+            let m = (a.0 + b.0, a.1 + b.1);
+            let l = length(m);
+            (m.0 / l, m.1 / l)
+        } else {
+            a
+        }
+    };
+    (0..n)
+        .map(|i| {
+            let (before, here, after) =
+                (normals[(i + n - 1) % n], normals[i], normals[(i + 1) % n]);
+            [blend(here, before), blend(here, after)]
+        })
+        .collect()
+}
+
+fn bounds_of<'a, I: Iterator<Item = &'a Point3>>(points: I) -> AxisAlignedBox {
+    let inf = Float::INFINITY;
+    points.fold(
+        AxisAlignedBox {
+            lower: point(inf, inf, inf),
+            upper: point(-inf, -inf, -inf),
+        },
+        |b, p| AxisAlignedBox {
+            lower: point(b.lower.x.min(p.x), b.lower.y.min(p.y), b.lower.z.min(p.z)),
+            upper: point(b.upper.x.max(p.x), b.upper.y.max(p.y), b.upper.z.max(p.z)),
+        },
+    )
+}
+
+/// The horizontal kerning between two glyphs, in font units
+fn kerning(face: &Face, left: GlyphId, right: GlyphId) -> Float {
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|s| s.horizontal && !s.variable && !s.has_cross_stream)
+                .find_map(|s| s.glyphs_kerning(left, right))
+        })
+        .map_or(0.0, Float::from)
+}
+
+impl Text {
+    ///
+    /// Sets `string` in `font` and extrudes it. Characters the font has no
+    /// glyph for are drawn with its "missing" glyph, and newlines start a
+    /// new line. Returns `None` if the depth isn't positive, the bevel is
+    /// negative or more than half the depth, or there's nothing to draw.
+    ///
+    pub fn new(
+        font: &Font,
+        string: &str,
+        depth: Float,
+        bevel: Float,
+        alignment: Alignment,
+    ) -> Option<Text> {
+        if depth <= 0.0 || !(0.0..=depth / 2.0).contains(&bevel) {
+            return None;
+        }
+
+        let face = font.face();
+        let em = Float::from(face.units_per_em());
+        let line_height = Float::from(face.ascender()) - Float::from(face.descender())
+            + Float::from(face.line_gap());
+
+        // lay the outlines out in font units, one line at a time
+        let mut outlines: Vec<(usize, Contours)> = Vec::new();
+        let mut index = 0;
+        for (row, line) in string.split('\n').enumerate() {
+            let mut placed = Vec::new();
+            let mut pen = 0.0;
+            let mut previous = None;
+            for c in line.chars() {
+                let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+                if let Some(previous) = previous {
+                    pen += kerning(&face, previous, glyph);
+                }
+
+                let mut outline = Outline::new(TOLERANCE * em);
+                face.outline_glyph(glyph, &mut outline);
+                outline.finish();
+                let y = -(row as Float) * line_height;
+                let contours = outline
+                    .contours
+                    .into_iter()
+                    .map(|c| {
+                        c.into_iter()
+                            .map(|(px, py)| (px + pen, py + y))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                if !contours.is_empty() {
+                    placed.push((index, contours));
+                }
+
+                pen += face.glyph_hor_advance(glyph).map_or(0.0, Float::from);
+                previous = Some(glyph);
+                index += 1;
+            }
+            // the newline counts as a character too
+            index += 1;
+
+            let shift = match alignment {
+                Alignment::Left => 0.0,
+                Alignment::Centre => -pen / 2.0,
+                Alignment::Right => -pen,
+            };
+            outlines.extend(placed.into_iter().map(|(i, contours)| {
+                let contours = contours
+                    .into_iter()
+                    .map(|c| {
+                        c.into_iter()
+                            .map(|(x, y)| ((x + shift) / em, y / em))
+                            .collect()
+                    })
+                    .collect();
+                (i, contours)
+            }));
+        }
+        if outlines.is_empty() {
+            return None;
+        }
+
+        let mut glyphs = Vec::with_capacity(outlines.len());
+        let mut walls = Vec::new();
+        for (index, contours) in outlines.into_iter() {
+            let mut faces = Vec::with_capacity(contours.len());
+            let mut chunks = Vec::new();
+            for contour in contours.iter() {
+                let normals = outward_normals(contour, &contours);
+                let shading = smoothed(&normals);
+                let shrunk = if bevel > 0.0 {
+                    inset(contour, &normals, bevel)
+                } else {
+                    contour.clone()
+                };
+
+                // the layers of the extrusion, front to back
+                let mut layers = vec![(0.0, &shrunk)];
+                if bevel > 0.0 {
+                    layers.push((bevel, contour));
+                    if depth - bevel > bevel {
+                        layers.push((depth - bevel, contour));
+                    }
+                }
+                layers.push((depth, &shrunk));
+
+                let first = walls.len();
+                let n = contour.len();
+                for edge in 0..n {
+                    let k = (edge + 1) % n;
+                    let (nx, ny) = normals[edge];
+                    for band in layers.windows(2) {
+                        let ((z0, near), (z1, far)) = (band[0], band[1]);
+                        let corners = [
+                            point(near[edge].0, near[edge].1, z0),
+                            point(near[k].0, near[k].1, z0),
+                            point(far[k].0, far[k].1, z1),
+                            point(far[edge].0, far[edge].1, z1),
+                        ];
+                        let mut normal = (corners[1] - corners[0])
+                            .cross(corners[3] - corners[0])
+                            .normalize();
+                        if normal.x * nx + normal.y * ny < 0.0 {
+                            normal = -normal;
+                        }
+
+                        // bend the smoothed outline normals to the wall's slope
+                        let slope = normal.x.hypot(normal.y);
+                        let tilt = |(sx, sy): (Float, Float)| {
+                            Vector3::new(sx * slope, sy * slope, normal.z).normalize()
+                        };
+
+                        walls.push(Wall {
+                            corners,
+                            normal,
+                            shading: [tilt(shading[edge][0]), tilt(shading[edge][1])],
+                            u: (edge as Float / n as Float, (edge + 1) as Float / n as Float),
+                            v: (z0 / depth, z1 / depth),
+                            glyph: glyphs.len(),
+                        });
+                    }
+                }
+                let mut start = first;
+                while start < walls.len() {
+                    let end = (start + CHUNK).min(walls.len());
+                    let bounds = bounds_of(walls[start..end].iter().flat_map(|w| w.corners.iter()));
+                    chunks.push((bounds, start..end));
+                    start = end;
+                }
+
+                faces.push(shrunk);
+            }
+
+            let bounds = chunks
+                .iter()
+                .skip(1)
+                .fold(chunks[0].0.clone(), |acc, (b, _)| acc.union(b));
+            glyphs.push(Glyph {
+                index,
+                contours,
+                faces,
+                bounds,
+                chunks,
+            });
+        }
+
+        let (lower, upper) = glyphs.iter().skip(1).fold(
+            (glyphs[0].bounds.lower, glyphs[0].bounds.upper),
+            |(lo, hi), g| {
+                (
+                    point(lo.x.min(g.bounds.lower.x), lo.y.min(g.bounds.lower.y), 0.0),
+                    point(hi.x.max(g.bounds.upper.x), hi.y.max(g.bounds.upper.y), 0.0),
+                )
+            },
+        );
+
+        Some(Text {
+            string: string.to_string(),
+            depth,
+            bevel,
+            alignment,
+            glyphs,
+            walls,
+            lower: (lower.x, lower.y),
+            size: (upper.x - lower.x, upper.y - lower.y),
+        })
+    }
+
+    pub fn string(&self) -> &str {
+        &self.string
+    }
+
+    pub fn depth(&self) -> Float {
+        self.depth
+    }
+
+    pub fn bevel(&self) -> Float {
+        self.bevel
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    /// The front (0) or back (1) face's plane, as a point and outward normal
+    fn face_plane(&self, end: usize) -> (Point3, Vector3) {
+        if end == 0 {
+            (point(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0))
+        } else {
+            (point(0.0, 0.0, self.depth), Vector3::new(0.0, 0.0, 1.0))
+        }
+    }
+
+    fn nearest(&self, r: Ray) -> Option<(Float, Place)> {
+        let mut nearest: Option<(Float, Place)> = None;
+        let mut consider = |t: Float, place: Place| match nearest {
+            Some((best, _)) if best <= t => {}
+            _ => nearest = Some((t, place)),
+        };
+
+        for (g, glyph) in self.glyphs.iter().enumerate() {
+            if glyph.bounds.span(&r).is_none() {
+                continue;
+            }
+
+            for (bounds, range) in glyph.chunks.iter() {
+                if bounds.span(&r).is_none() {
+                    continue;
+                }
+                for wall in range.clone() {
+                    let [a, b, c, d] = self.walls[wall].corners;
+                    if let Some((t, b1, b2)) = triangle_distance(r, a, b, c) {
+                        let (along, across) = (b1 + b2, b2);
+                        consider(
+                            t,
+                            Place::Wall {
+                                wall,
+                                along,
+                                across,
+                            },
+                        );
+                    }
+                    if let Some((t, b1, b2)) = triangle_distance(r, a, c, d) {
+                        let (along, across) = (b1, b1 + b2);
+                        consider(
+                            t,
+                            Place::Wall {
+                                wall,
+                                along,
+                                across,
+                            },
+                        );
+                    }
+                }
+            }
+
+            for end in 0..2 {
+                let (origin, n) = self.face_plane(end);
+                if let Some(t) = plane_distance(r, origin, n) {
+                    let p = r.extend(t);
+                    if winding(&glyph.faces, p.x, p.y) != 0 {
+                        consider(t, Place::Face(g, end));
+                    }
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The hit record for an intersection `t` units along `r`, at the place
+    /// found by `nearest`
+    fn hit(&self, r: Ray, t: Float, place: Place) -> Hit {
+        match place {
+            Place::Wall {
+                wall,
+                along,
+                across,
+            } => {
+                let w = &self.walls[wall];
+                let [a, b, _, d] = w.corners;
+                let (point, error) = project_onto_plane(r, t, a, w.normal);
+                let shading = ((w.shading[0] * (1.0 - along)) + (w.shading[1] * along)).normalize();
+                let (du, dv) = (w.u.1 - w.u.0, w.v.1 - w.v.0);
+
+                Hit {
+                    t,
+                    point,
+                    error,
+                    normal: Normal3::from(w.normal),
+                    shading_normal: Normal3::from(shading),
+                    uv: (w.u.0 + along * du, w.v.0 + across * dv),
+                    dpdu: (b - a) / du,
+                    dpdv: (d - a) / dv,
+                    face: self.glyphs[w.glyph].index,
+                }
+            }
+
+            Place::Face(glyph, end) => {
+                let (origin, n) = self.face_plane(end);
+                let (point, error) = project_onto_plane(r, t, origin, n);
+                let (lower, size) = (self.lower, self.size);
+
+                Hit {
+                    t,
+                    point,
+                    error,
+                    normal: Normal3::from(n),
+                    shading_normal: Normal3::from(n),
+                    uv: ((point.x - lower.0) / size.0, (point.y - lower.1) / size.1),
+                    dpdu: Vector3::new(size.0, 0.0, 0.0),
+                    dpdv: Vector3::new(0.0, size.1, 0.0),
+                    face: self.glyphs[glyph].index,
+                }
+            }
+        }
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a text object
+impl cmp::PartialEq for Text {
+    fn eq(&self, other: &Text) -> bool {
+        self.string == other.string
+            && self.depth == other.depth
+            && self.bevel == other.bevel
+            && self.alignment == other.alignment
+            && self.glyphs.len() == other.glyphs.len()
+            && self
+                .glyphs
+                .iter()
+                .zip(other.glyphs.iter())
+                .all(|(a, b)| a.contours == b.contours)
+    }
+}
+
+impl Primitive for Text {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        self.nearest(r).map(|(t, place)| self.hit(r, t, place))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let first = self.glyphs[0].bounds.clone();
+        self.glyphs
+            .iter()
+            .skip(1)
+            .fold(first, |acc, g| acc.union(&g.bounds))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, vector, APPROX_EPSILON};
+
+    /// A tiny block letter font made for the tests by `make_font.py`
+    const FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/blocks.ttf");
+
+    fn font() -> Font {
+        Font::load(Path::new(FONT)).unwrap()
+    }
+
+    #[test]
+    fn outlines_are_flattened() {
+        let mut outline = Outline::new(TOLERANCE);
+        outline.move_to(0.0, 0.0);
+        outline.line_to(1.0, 0.0);
+        outline.quad_to(1.0, 1.0, 0.0, 1.0);
+        outline.line_to(0.0, 0.0);
+        outline.close();
+
+        assert_eq!(outline.contours.len(), 1);
+        let contour = &outline.contours[0];
+        assert!(contour.len() > 4, "{:?}", contour);
+        assert_ne!(contour.first(), contour.last());
+
+        // every point of the curve lies on it
+        for &(x, y) in contour[1..].iter() {
+            let s = 1.0 - (1.0 - y).max(0.0).sqrt();
+            assert!((x - (1.0 - s * s)).abs() < APPROX_EPSILON, "{:?}", contour);
+        }
+        assert_eq!(winding(&outline.contours, 0.5, 0.5).abs(), 1);
+        assert_eq!(winding(&outline.contours, 2.0, 0.5), 0);
+    }
+
+    #[test]
+    fn insets_keep_edges_parallel() {
+        let square = vec![(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 0.0)];
+        let normals = outward_normals(&square, std::slice::from_ref(&square));
+        assert_eq!(normals[0], (-1.0, 0.0));
+        let shrunk = inset(&square, &normals, 0.25);
+        for (&(x, y), &(ex, ey)) in shrunk
+            .iter()
+            .zip([(0.25, 0.25), (0.25, 1.75), (1.75, 1.75)].iter())
+        {
+            assert!(
+                (x - ex).abs() < APPROX_EPSILON && (y - ey).abs() < APPROX_EPSILON,
+                "{:?}",
+                shrunk
+            );
+        }
+    }
+
+    #[test]
+    fn letters_are_extruded() {
+        let font = font();
+        let text = Text::new(&font, "I", 0.5, 0.0, Alignment::Centre).unwrap();
+
+        let b = text.bounding_box();
+        assert!(b.lower.x < 0.0 && b.upper.x > 0.0, "{:?}", b);
+        assert!((b.lower.y).abs() < APPROX_EPSILON, "{:?}", b);
+        assert!(b.upper.y > 0.6 && b.upper.y < 0.8, "{:?}", b);
+        assert!((b.upper.z - 0.5).abs() < APPROX_EPSILON, "{:?}", b);
+
+        let r = Ray::new(point(0.0, 0.3, -5.0), vector(0.0, 0.0, 1.0));
+        let h = text.intersects(r).unwrap();
+        assert!((h.t - 5.0).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 0.0, -1.0)), "{:?}", h);
+        assert_eq!(h.face, 0);
+
+        let r = Ray::new(point(5.0, 0.3, 0.25), vector(-1.0, 0.0, 0.0));
+        let h = text.intersects(r).unwrap();
+        assert!(h.normal.approx_eq(normal(1.0, 0.0, 0.0)), "{:?}", h);
+        assert!((h.uv.1 - 0.5).abs() < APPROX_EPSILON, "{:?}", h);
+
+        // the hole in an "O" is empty
+        let text = Text::new(&font, "O", 0.5, 0.0, Alignment::Left).unwrap();
+        let b = text.bounding_box();
+        let (x, y) = ((b.lower.x + b.upper.x) / 2.0, (b.lower.y + b.upper.y) / 2.0);
+        let r = Ray::new(point(x, y, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(text.intersects(r).is_none());
+    }
+
+    #[test]
+    fn bevels_slope_the_edges() {
+        let font = font();
+        let flat = Text::new(&font, "I", 0.5, 0.0, Alignment::Left).unwrap();
+        let bevelled = Text::new(&font, "I", 0.5, 0.02, Alignment::Left).unwrap();
+        let b = flat.bounding_box();
+
+        // just inside the edge of the stem misses the shrunken face, and
+        // lands on the chamfer
+        let x = b.lower.x + 0.01;
+        let r = Ray::new(point(x, 0.3, -5.0), vector(0.0, 0.0, 1.0));
+        let h = bevelled.intersects(r).unwrap();
+        assert!((h.point.z - 0.01).abs() < 1e-6, "{:?}", h);
+        assert!(
+            h.normal
+                .approx_eq(vector(-1.0, 0.0, -1.0).normalize().into()),
+            "{:?}",
+            h
+        );
+
+        assert!(Text::new(&font, "I", 0.5, 0.3, Alignment::Left).is_none());
+        assert!(Text::new(&font, "I", 0.0, 0.0, Alignment::Left).is_none());
+        assert!(Text::new(&font, " ", 0.5, 0.0, Alignment::Left).is_none());
+    }
+
+    #[test]
+    fn alignment_and_lines() {
+        let font = font();
+        let left = Text::new(&font, "Hi", 0.1, 0.0, Alignment::Left).unwrap();
+        let right = Text::new(&font, "Hi", 0.1, 0.0, Alignment::Right).unwrap();
+        assert!(left.bounding_box().lower.x >= 0.0);
+        assert!(right.bounding_box().upper.x <= 0.0);
+
+        let face = font.face();
+        let (a, v) = (
+            face.glyph_index('A').unwrap(),
+            face.glyph_index('V').unwrap(),
+        );
+        assert!(kerning(&face, a, v) < 0.0);
+
+        let two = Text::new(&font, "Hi\nthere", 0.1, 0.0, Alignment::Left).unwrap();
+        assert!(
+            two.bounding_box().lower.y < -0.9,
+            "{:?}",
+            two.bounding_box()
+        );
+
+        // faces count characters, including the newline
+        let h = (0..100)
+            .map(|i| Ray::new(point(i as Float * 0.005, -0.9, -5.0), vector(0.0, 0.0, 1.0)))
+            .find_map(|r| two.intersects(r))
+            .unwrap();
+        assert_eq!(h.face, 3);
+    }
+}
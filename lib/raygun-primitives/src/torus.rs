@@ -0,0 +1,181 @@
+use std::cmp;
+
+use crate::{AxisAlignedBox, Hit, Primitive};
+use raygun_math::{consts::PI, gamma, point, solve_quartic, vector, Float, Normal3, Ray, Vector3};
+
+///
+/// A torus centred on the origin and lying in the xz plane. The centre of
+/// the tube sweeps out a circle of `major_radius`, and the tube itself has a
+/// radius of `minor_radius`.
+///
+#[derive(Debug)]
+pub struct Torus {
+    pub major_radius: Float,
+    pub minor_radius: Float,
+}
+
+impl Torus {
+    pub fn new(major_radius: Float, minor_radius: Float) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    /// The hit record for an intersection `t` units along `r`
+    fn hit(&self, r: Ray, t: Float) -> Hit {
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+        let p = r.extend(t);
+
+        // the nearest point on the circle running through the middle of the
+        // tube, and the angle around the y axis to get there
+        let phi = p.z.atan2(p.x);
+        let (cos_phi, sin_phi) = (phi.cos(), phi.sin());
+        let outward = vector(cos_phi, 0.0, sin_phi);
+        let core = outward * big_r;
+
+        // the angle around the tube, measured from its outer equator
+        let rho = (p.x * p.x + p.z * p.z).sqrt();
+        let theta = p.y.atan2(rho - big_r);
+        let (cos_theta, sin_theta) = (theta.cos(), theta.sin());
+        let n = (outward * cos_theta) + vector(0.0, sin_theta, 0.0);
+
+        // rebuild the point on the surface, whatever the error in `t`
+        let tube = n * small_r;
+        let point = point(0.0, 0.0, 0.0) + core + tube;
+
+        Hit {
+            t,
+            point,
+            error: (core.abs() + tube.abs()) * gamma(7),
+            normal: Normal3::from(n),
+            shading_normal: Normal3::from(n),
+            uv: ((phi + PI) / (2.0 * PI), (theta + PI) / (2.0 * PI)),
+            dpdu: vector(-point.z, 0.0, point.x) * (2.0 * PI),
+            dpdv: ((outward * -sin_theta) + vector(0.0, cos_theta, 0.0)) * (2.0 * PI * small_r),
+            face: 0,
+        }
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Torus {
+        Torus::new(1.0, 0.25)
+    }
+}
+
+/// Implements a naive, bit-pattern-equality test for a torus object
+impl cmp::PartialEq for Torus {
+    fn eq(&self, other: &Torus) -> bool {
+        self.major_radius == other.major_radius && self.minor_radius == other.minor_radius
+    }
+}
+
+impl Primitive for Torus {
+    fn intersects(&self, r: Ray) -> Option<Hit> {
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+
+        // reject rays that miss the bounding sphere, and move the origin of
+        // the rest up to it. Starting close to the torus keeps the quartic's
+        // coefficients in a sensible range.
+        let bound = big_r + small_r;
+        let to_centre = Vector3::from(r.src);
+        let b = r.dir.dot(to_centre);
+        let discriminant = (b * b) - to_centre.dot(to_centre) + (bound * bound);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let far = -b + discriminant.sqrt();
+        if far <= 0.0 {
+            return None;
+        }
+        let near = (-b - discriminant.sqrt()).max(0.0);
+
+        // points on the surface satisfy (|p|² - R² - r²)² = 4R²(r² - y²)
+        let o = Vector3::from(r.extend(near));
+        let d = r.dir;
+        let f = o.dot(d);
+        let e = o.dot(o) - (big_r * big_r) - (small_r * small_r);
+        let four_r2 = 4.0 * big_r * big_r;
+
+        let roots = solve_quartic(
+            1.0,
+            4.0 * f,
+            (4.0 * f * f) + (2.0 * e) + (four_r2 * d.y * d.y),
+            (4.0 * f * e) + (2.0 * four_r2 * o.y * d.y),
+            (e * e) - (four_r2 * ((small_r * small_r) - (o.y * o.y))),
+        );
+
+        roots
+            .iter()
+            .map(|t| t + near)
+            .find(|&t| t > 0.0)
+            .map(|t| self.hit(r, t))
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBox {
+        let (xz, y) = (self.major_radius + self.minor_radius, self.minor_radius);
+        AxisAlignedBox {
+            lower: point(-xz, -y, -xz),
+            upper: point(xz, y, xz),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::{normal, APPROX_EPSILON};
+
+    #[test]
+    fn rays_through_the_hole_miss() {
+        let t = Torus::new(2.0, 0.5);
+        assert!(t
+            .intersects(Ray::new(point(0.0, 10.0, 0.0), vector(0.0, -1.0, 0.0)))
+            .is_none());
+        assert!(t
+            .intersects(Ray::new(point(0.0, 10.0, 0.0), vector(0.0, 1.0, 0.0)))
+            .is_none());
+    }
+
+    #[test]
+    fn rays_across_the_ring_hit_the_outside() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(point(-10.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        let h = t.intersects(r).unwrap();
+
+        assert!((h.t - 7.5).abs() < APPROX_EPSILON, "{:?}", h);
+        assert!(h.point.approx_eq(point(-2.5, 0.0, 0.0)), "{:?}", h);
+        assert!(h.normal.approx_eq(normal(-1.0, 0.0, 0.0)), "{:?}", h);
+
+        // the next hit is on the inside of the ring
+        let r = Ray::new(point(-1.5, 0.0, 0.0) + vector(0.001, 0.0, 0.0), r.dir);
+        let h = t.intersects(r).unwrap();
+        assert!(h.point.approx_eq(point(1.5, 0.0, 0.0)), "{:?}", h);
+        assert!(h.normal.approx_eq(normal(-1.0, 0.0, 0.0)), "{:?}", h);
+    }
+
+    #[test]
+    fn normals_point_away_from_the_tube() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(point(0.0, 10.0, 2.0), vector(0.0, -1.0, 0.0));
+        let h = t.intersects(r).unwrap();
+
+        assert!(h.point.approx_eq(point(0.0, 0.5, 2.0)), "{:?}", h);
+        assert!(h.normal.approx_eq(normal(0.0, 1.0, 0.0)), "{:?}", h);
+        assert!(h.normal.dot(h.dpdu).abs() < APPROX_EPSILON);
+        assert!(h.normal.dot(h.dpdv).abs() < APPROX_EPSILON);
+    }
+
+    #[test]
+    fn distant_rays_hit_accurately() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(point(1e4, 1e4, 2.0), vector(-1.0, -1.0, 0.0));
+        let p = r.extend(t.intersects(r).unwrap().t);
+
+        // distance from the surface of the tube
+        let rho = (p.x * p.x + p.z * p.z).sqrt();
+        let off_surface = (((rho - 2.0) * (rho - 2.0)) + (p.y * p.y)).sqrt() - 0.5;
+        assert!(off_surface.abs() < 1e-3, "{:?} is {} off", p, off_surface);
+    }
+}
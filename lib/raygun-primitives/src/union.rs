@@ -1,9 +1,8 @@
-use log::debug;
 use std::sync::Arc;
 
-use raygun_math::{Point, Ray, Transform, Vector};
+use raygun_math::Ray;
 
-use super::{AxisAlignedBox, Object, Primitive};
+use super::{AxisAlignedBox, Hit, Object, Primitive};
 
 #[derive(Debug)]
 pub struct Union {
@@ -17,37 +16,16 @@ impl Union {
 }
 
 impl Primitive for Union {
-    fn intersects(&self, _r: Ray) -> Option<f64> {
+    fn intersects(&self, _r: Ray) -> Option<Hit> {
         None
     }
 
-    fn normal(&self, _pt: Point) -> Vector {
-        Vector::default()
-    }
-
     fn bounding_box(&self) -> AxisAlignedBox {
         let zero = AxisAlignedBox::default();
         self.children
             .iter()
             .fold(zero, |acc, b| acc.union(&b.bounding_box()))
     }
-
-    fn accept_children(&self, obj: &Object, v: &mut dyn crate::Visitor) {
-        debug!("Union: accept_children!");
-
-        let transform = match obj.transform {
-            Some(ref t) => t.as_ref().clone(),
-            None => Transform::identity(),
-        };
-
-        v.push_transform(&transform);
-
-        for child in self.children.iter() {
-            v.visit(Arc::clone(child));
-        }
-
-        v.pop_transform();
-    }
 }
 
 impl Default for Union {
@@ -1,3 +0,0 @@
-pub trait Visitor {
-    
-}
\ No newline at end of file
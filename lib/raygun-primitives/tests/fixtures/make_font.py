@@ -0,0 +1,216 @@
+#!/usr/bin/env python3
+"""
+Writes blocks.ttf, the font the text tests are set in.
+
+It's a tiny TrueType font of straight-edged capitals and lower case letters,
+just enough for the tests: "H", "I", "O", "A", "V", "e", "h", "i", "r", "t"
+and a space, with "A" and "V" kerned together. Capitals stand 700 units tall
+in a 1000 unit em. The font and this script are dedicated to the public
+domain (CC0 1.0).
+
+Run it from this directory with no arguments.
+"""
+
+import struct
+
+EM = 1000
+ASCENDER = 800
+DESCENDER = -200
+
+# (character, advance, contours), each contour clockwise unless it's a hole
+GLYPHS = [
+    (None, 500, []),
+    (" ", 300, []),
+    ("A", 600, [[(0, 0), (250, 700), (350, 700), (600, 0), (490, 0), (300, 560), (110, 0)]]),
+    (
+        "H",
+        600,
+        [
+            [
+                (60, 0), (60, 700), (160, 700), (160, 400), (440, 400), (440, 700),
+                (540, 700), (540, 0), (440, 0), (440, 300), (160, 300), (160, 0),
+            ]
+        ],
+    ),
+    ("I", 220, [[(60, 0), (60, 700), (160, 700), (160, 0)]]),
+    (
+        "O",
+        600,
+        [
+            [(60, 0), (60, 700), (540, 700), (540, 0)],
+            [(160, 100), (440, 100), (440, 600), (160, 600)],
+        ],
+    ),
+    ("V", 600, [[(0, 700), (110, 700), (300, 140), (490, 700), (600, 700), (350, 0), (250, 0)]]),
+    (
+        "e",
+        500,
+        [
+            [(60, 0), (60, 500), (440, 500), (440, 200), (160, 200), (160, 100), (440, 100), (440, 0)],
+            [(160, 300), (340, 300), (340, 400), (160, 400)],
+        ],
+    ),
+    (
+        "h",
+        500,
+        [[(60, 0), (60, 700), (160, 700), (160, 500), (440, 500), (440, 0), (340, 0), (340, 400), (160, 400), (160, 0)]],
+    ),
+    (
+        "i",
+        220,
+        [
+            [(60, 0), (60, 500), (160, 500), (160, 0)],
+            [(60, 600), (60, 700), (160, 700), (160, 600)],
+        ],
+    ),
+    ("r", 400, [[(60, 0), (60, 500), (360, 500), (360, 400), (160, 400), (160, 0)]]),
+    (
+        "t",
+        320,
+        [
+            [
+                (110, 0), (110, 400), (30, 400), (30, 500), (110, 500), (110, 650),
+                (210, 650), (210, 500), (290, 500), (290, 400), (210, 400), (210, 0),
+            ]
+        ],
+    ),
+]
+
+KERNING = [("A", "V", -80)]
+
+NAMES = {
+    0: "Dedicated to the public domain (CC0 1.0)",
+    1: "Raygun Blocks",
+    2: "Regular",
+    4: "Raygun Blocks",
+    6: "RaygunBlocks-Regular",
+}
+
+
+def bounds(contours):
+    points = [p for c in contours for p in c]
+    if not points:
+        return (0, 0, 0, 0)
+    xs, ys = [x for x, _ in points], [y for _, y in points]
+    return (min(xs), min(ys), max(xs), max(ys))
+
+
+def glyph(contours):
+    if not contours:
+        return b""
+    data = struct.pack(">hhhhh", len(contours), *bounds(contours))
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)
+    points = [p for c in contours for p in c]
+    data += bytes([0x01] * len(points))
+    for axis in (0, 1):
+        last = 0
+        for p in points:
+            data += struct.pack(">h", p[axis] - last)
+            last = p[axis]
+    return data + b"\0" * (-len(data) % 4)
+
+
+def cmap():
+    chars = sorted((ord(c), i) for i, (c, _, _) in enumerate(GLYPHS) if c)
+    segments = [(code, code, i - code) for code, i in chars] + [(0xFFFF, 0xFFFF, 1)]
+    n = len(segments)
+    search = 2 * 2 ** (n.bit_length() - 1)
+    table = (
+        struct.pack(">HHHHHHH", 4, 16 + 8 * n, 0, n * 2, search, n.bit_length() - 1, n * 2 - search)
+        + b"".join(struct.pack(">H", end) for _, end, _ in segments)
+        + struct.pack(">H", 0)
+        + b"".join(struct.pack(">H", start) for start, _, _ in segments)
+        + b"".join(struct.pack(">H", delta % 0x10000) for _, _, delta in segments)
+        + struct.pack(">H", 0) * n
+    )
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + table
+
+
+def kern():
+    index = {c: i for i, (c, _, _) in enumerate(GLYPHS)}
+    pairs = sorted((index[l], index[r], v) for l, r, v in KERNING)
+    n = len(pairs)
+    search = 6 * 2 ** (n.bit_length() - 1)
+    sub = struct.pack(">HHHHHHH", 0, 14 + 6 * n, 0x0001, n, search, n.bit_length() - 1, n * 6 - search)
+    sub += b"".join(struct.pack(">HHh", l, r, v) for l, r, v in pairs)
+    return struct.pack(">HH", 0, 1) + sub
+
+
+def name():
+    records, strings = b"", b""
+    for name_id, text in sorted(NAMES.items()):
+        s = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(s), len(strings))
+        strings += s
+    return struct.pack(">HHH", 0, len(NAMES), 6 + 12 * len(NAMES)) + records + strings
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def font():
+    glyf, loca = b"", []
+    for _, _, contours in GLYPHS:
+        loca.append(len(glyf))
+        glyf += glyph(contours)
+    loca.append(len(glyf))
+
+    all_bounds = [bounds(c) for _, _, c in GLYPHS if c]
+    x_min, y_min = min(b[0] for b in all_bounds), min(b[1] for b in all_bounds)
+    x_max, y_max = max(b[2] for b in all_bounds), max(b[3] for b in all_bounds)
+    advances = [a for _, a, _ in GLYPHS]
+    points = max(sum(len(c) for c in contours) for _, _, contours in GLYPHS)
+    most_contours = max(len(contours) for _, _, contours in GLYPHS)
+
+    tables = {
+        b"cmap": cmap(),
+        b"glyf": glyf,
+        b"head": struct.pack(
+            ">IIIIHHqqhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, EM, 0, 0,
+            x_min, y_min, x_max, y_max, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH",
+            0x00010000, ASCENDER, DESCENDER, 0, max(advances),
+            0, 0, x_max, 1, 0, 0, 0, 0, 0, 0, 0, len(GLYPHS),
+        ),
+        b"hmtx": b"".join(
+            struct.pack(">Hh", a, bounds(c)[0]) for _, a, c in GLYPHS
+        ),
+        b"kern": kern(),
+        b"loca": b"".join(struct.pack(">I", o) for o in loca),
+        b"maxp": struct.pack(
+            ">IHHHHHHHHHHHHHH", 0x00010000, len(GLYPHS), points, most_contours,
+            0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+        ),
+        b"name": name(),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0),
+    }
+
+    n = len(tables)
+    search = 16 * 2 ** (n.bit_length() - 1)
+    header = struct.pack(">IHHHH", 0x00010000, n, search, n.bit_length() - 1, n * 16 - search)
+    offset = 12 + 16 * n
+    directory, body, head = b"", b"", 0
+    for tag, data in sorted(tables.items()):
+        if tag == b"head":
+            head = offset + len(body)
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+
+    data = bytearray(header + directory + body)
+    # the head table's checksum adjustment makes the whole file sum to a magic number
+    struct.pack_into(">I", data, head + 8, (0xB1B0AFBA - checksum(bytes(data))) & 0xFFFFFFFF)
+    return bytes(data)
+
+
+if __name__ == "__main__":
+    with open("blocks.ttf", "wb") as f:
+        f.write(font())
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
 use raygun_camera::Camera;
-use raygun_material::{Colour, COLOUR_BLACK};
+use raygun_material::{Colour, Material, COLOUR_BLACK};
 use raygun_math::{Ray, Transform};
-use raygun_primitives::{Object, Visitor};
+use raygun_primitives::{Object, Union};
 
 ///
 /// The toplevel owner of all objects and lights
@@ -30,51 +30,166 @@ impl Scene {
         self.objects.push(Arc::new(obj));
     }
 
-    pub fn visit(&self, v: &mut dyn Visitor) {
+    ///
+    /// The scene's objects with every union replaced by its children, each
+    /// carrying the union's transform along with its own. Children without
+    /// a material of their own take the union's. Only these leaves are ever
+    /// hit by a ray.
+    ///
+    pub fn leaves(&self) -> Vec<Arc<Object>> {
+        fn flatten(
+            obj: &Arc<Object>,
+            parent: Option<&Transform>,
+            inherited: Option<&Material>,
+            leaves: &mut Vec<Arc<Object>>,
+        ) {
+            let transform = match (parent, &obj.transform) {
+                (Some(p), Some(t)) => Some(t.apply(p)),
+                (Some(p), None) => Some(*p),
+                (None, t) => t.as_deref().copied(),
+            };
+
+            // an object left with the default material hasn't set one
+            let material = if obj.material == Material::default() {
+                inherited
+            } else {
+                Some(&obj.material)
+            };
+
+            match obj.as_primitive::<Union>() {
+                Some(u) => {
+                    for child in u.children.iter() {
+                        flatten(child, transform.as_ref(), material, leaves);
+                    }
+                }
+                None if parent.is_none() && inherited.is_none() => leaves.push(Arc::clone(obj)),
+                None => leaves.push(Arc::new(Object {
+                    primitive: Arc::clone(&obj.primitive),
+                    material: material.unwrap_or(&obj.material).clone(),
+                    transform: transform.map(Box::new),
+                })),
+            }
+        }
+
+        let mut leaves = Vec::new();
         for o in self.objects.iter() {
-            v.visit(Arc::clone(o));
-            o.accept(v);
+            flatten(o, None, None, &mut leaves);
         }
+        leaves
     }
 
-    pub fn lights<'a>(&'a self) -> Vec<LightInfo> {
-        struct LightVisitor {
-            transform_stack: Vec<Transform>,
-            lights: Vec<LightInfo>,
+    ///
+    /// A copy of the scene with its unions flattened away, as described by
+    /// `leaves`
+    ///
+    pub fn flattened(&self) -> Scene {
+        Scene {
+            objects: self.leaves(),
+            camera: self.camera.clone(),
         }
+    }
 
-        impl Visitor for LightVisitor {
-            fn push_transform(&mut self, t: &Transform) {
-                let head = self.transform_stack.last().unwrap().clone();
-                self.transform_stack.push(head.apply(t));
-            }
+    ///
+    /// Finds all the lights in the scene, along with the transform taking
+    /// each from its own space into the world's
+    ///
+    pub fn lights(&self) -> Vec<LightInfo> {
+        self.leaves()
+            .into_iter()
+            .filter(|obj| obj.as_light().is_some())
+            .map(|obj| LightInfo {
+                transform: obj.transform.as_deref().copied().unwrap_or_default(),
+                light: obj,
+            })
+            .collect()
+    }
 
-            fn pop_transform(&mut self) {
-                self.transform_stack.pop();
-            }
+    pub fn sky(&self, _: Ray) -> Colour {
+        COLOUR_BLACK
+    }
+}
 
-            fn visit(&mut self, obj: Arc<Object>) {
-                if obj.as_light().is_some() {
-                    let t = self.transform_stack.last().unwrap();
-                    self.lights.push(LightInfo {
-                        transform: t.clone(),
-                        light: obj,
-                    });
-                }
-            }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_material::Pigment;
+    use raygun_math::{point, Float};
+    use raygun_primitives::{PointLight, Sphere};
+
+    fn translated(obj: Object, x: Float) -> Object {
+        Object {
+            transform: Some(Box::new(Transform::for_translation(x, 0.0, 0.0))),
+            ..obj
         }
+    }
 
-        let mut visitor = LightVisitor {
-            transform_stack: vec![Transform::identity()],
-            lights: Vec::new(),
+    #[test]
+    fn unions_are_flattened() {
+        let light = Object::from(Arc::new(PointLight::default()));
+        let inner = Union {
+            children: vec![Arc::new(translated(light, 1.0))],
         };
+        let outer = Union {
+            children: vec![
+                Arc::new(Object::from(Arc::new(Sphere::default()))),
+                Arc::new(translated(Object::from(Arc::new(inner)), 2.0)),
+            ],
+        };
+
+        let mut scene = Scene::new();
+        scene.add_object(Object {
+            transform: Some(Box::new(
+                Transform::for_scale(2.0, 2.0, 2.0).translate(4.0, 0.0, 0.0),
+            )),
+            ..Object::from(Arc::new(outer))
+        });
 
-        self.visit(&mut visitor);
+        let leaves = scene.leaves();
+        assert_eq!(leaves.len(), 2);
+        let origin = point(0.0, 0.0, 0.0);
+        let t = leaves[0].transform.as_ref().unwrap();
+        assert_eq!(t.matrix * origin, point(4.0, 0.0, 0.0));
 
-        return visitor.lights;
+        // the light picks up the transforms of all the unions it's in
+        let lights = scene.lights();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].transform.matrix * origin, point(10.0, 0.0, 0.0));
     }
 
-    pub fn sky(&self, _: Ray) -> Colour {
-        COLOUR_BLACK
+    #[test]
+    fn unions_lend_their_material() {
+        let red = Material {
+            pigment: Pigment::Solid(Colour::new(1.0, 0.0, 0.0)),
+            ..Material::default()
+        };
+        let blue = Material {
+            pigment: Pigment::Solid(Colour::new(0.0, 0.0, 1.0)),
+            ..Material::default()
+        };
+
+        let sphere = || Object::from(Arc::new(Sphere::default()));
+        let inner = Union {
+            children: vec![Arc::new(sphere())],
+        };
+        let outer = Union {
+            children: vec![
+                Arc::new(sphere()),
+                Arc::new(Object {
+                    material: blue.clone(),
+                    ..sphere()
+                }),
+                Arc::new(Object::from(Arc::new(inner))),
+            ],
+        };
+
+        let mut scene = Scene::new();
+        scene.add_object(Object {
+            material: red.clone(),
+            ..Object::from(Arc::new(outer))
+        });
+        scene.add_object(sphere());
+
+        let materials: Vec<_> = scene.leaves().iter().map(|o| o.material.clone()).collect();
+        assert_eq!(materials, [red.clone(), blue, red, Material::default()]);
     }
 }
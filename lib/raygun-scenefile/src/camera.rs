@@ -1,48 +1,43 @@
-use super::constructs::*;
+use super::{constructs::*, error::ParseResult};
 use log::debug;
 
 use raygun_camera::Camera;
-use raygun_math::{degrees, point, Point, Vector};
-
-use nom::IResult;
+use raygun_math::{degrees, point, Float, Vector3};
 
 // ////////////////////////////////////////////////////////////////////////////
 // Camera
 // ////////////////////////////////////////////////////////////////////////////
 
-pub fn camera(state: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Camera> {
-    use nom::{branch::alt, multi::separated_list};
+pub fn camera(state: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Camera> {
+    use nom::branch::alt;
 
     enum Arg {
-        Loc(Point),
-        Sky(Vector),
-        LookAt(Point),
-        Fov(f64),
+        Loc(Vector3),
+        Sky(Vector3),
+        LookAt(Vector3),
+        Fov(Float),
     }
 
     move |input| {
-        let camera_block = block(separated_list(
-            comma,
-            ws(alt((
-                map_named_value("location", vector_literal, Arg::Loc),
-                map_named_value("sky", vector_literal, Arg::Sky),
-                map_named_value("look_at", vector_literal, Arg::LookAt),
-                map_named_value("field_of_view", real_number, Arg::Fov),
-            ))),
-        ));
+        let camera_block = fields(ws(alt((
+            map_named_value("location", vector(state.clone()), Arg::Loc),
+            map_named_value("sky", vector(state.clone()), Arg::Sky),
+            map_named_value("look_at", vector(state.clone()), Arg::LookAt),
+            map_named_value("field_of_view", float_value, Arg::Fov),
+        ))));
 
         named_object("camera", camera_block)(input)
             .map(|(i, args)| {
                 let mut loc = point(0.0, 0.0, 0.0);
                 let mut target = point(0.0, 0.0, 0.0);
-                let mut sky = point(0.0, 1.0, 0.0);
+                let mut sky = Vector3::new(0.0, 1.0, 0.0);
                 let mut fov = degrees(39.0).radians();
 
                 for arg in args {
                     match arg {
-                        Arg::Loc(p) => loc = p,
+                        Arg::Loc(p) => loc = p.into(),
                         Arg::Sky(s) => sky = s,
-                        Arg::LookAt(p) => target = p,
+                        Arg::LookAt(p) => target = p.into(),
                         Arg::Fov(d) => fov = degrees(d).radians(),
                     }
                 }
@@ -55,7 +50,7 @@ pub fn camera(state: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Camera> {
                 let up = dir.cross(right).normalize();
 
                 let s = state.borrow();
-                let aspect_ratio = s.width as f64 / s.height as f64;
+                let aspect_ratio = s.width as Float / s.height as Float;
                 let new_camera = Camera {
                     loc: loc,
                     dir: dir,
@@ -76,7 +71,7 @@ mod test {
     use super::*;
     use float_cmp::ApproxEqUlps;
     use nom::IResult;
-    use raygun_math::vector;
+    use raygun_math::{point, vector};
 
     #[test]
     fn parse_minimal_camera() {
@@ -86,7 +81,7 @@ mod test {
             look_at: {0.0, 0.0, 0.0}
         }"#;
 
-        let expected_loc = vector(10.0, 10.0, -10.0);
+        let expected_loc = point(10.0, 10.0, -10.0);
         let expected_dir = vector(-1.0, -1.0, 1.0).normalize();
         let expected_right = vector(1.0, 0.0, 1.0).normalize();
         let expected_up = vector(-1.0, 2.0, 1.0).normalize();
@@ -129,7 +124,7 @@ mod test {
 
     #[test]
     fn honours_fov() {
-        use std::f64::consts::PI;
+        use raygun_math::consts::PI;
         let state = SceneRef::default();
 
         let text = r#"camera {
@@ -154,7 +149,7 @@ mod test {
 
     #[test]
     fn honours_aspect_ratio() {
-        use std::f64::consts::PI;
+        use raygun_math::consts::PI;
         let state = SceneRef::new(SceneState::new(1920, 1080));
 
         let text = r#"camera {
@@ -1,24 +1,28 @@
 use super::constructs::*;
+use crate::{
+    declaration::{reference, Declaration},
+    error::ParseResult,
+};
 use raygun_material::Colour;
 
 use nom::{
+    branch::alt,
     combinator::map,
     sequence::{terminated, tuple},
-    IResult,
 };
 
 // ////////////////////////////////////////////////////////////////////////////
 // Colours
 // ////////////////////////////////////////////////////////////////////////////
 
-pub fn colour<'a>(_scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&[u8], Colour> {
-    colour_literal
+pub fn colour<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Colour> {
+    alt((colour_literal, reference(scene, Declaration::colour)))
 }
 
 /*
  * A colour literal of the form {r, g, b}
  */
-pub fn colour_literal(input: &[u8]) -> IResult<&[u8], Colour> {
+pub fn colour_literal(input: &[u8]) -> ParseResult<'_, Colour> {
     let parse_r = terminated(real_number, comma);
     let parse_g = terminated(real_number, comma);
     let parse_b = real_number;
@@ -1,19 +1,28 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use nom::{
-    bytes::complete::tag,
-    character::complete::{char as _char, multispace0},
-    combinator::{map, value},
-    error::ParseError,
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{char as _char, digit1, multispace0},
+    combinator::{cut, map, map_res, not, value},
+    error::{context, ParseError},
     sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
 use raygun_material::Material;
-use raygun_math::{Transform, Vector};
+use raygun_math::{Float, Transform, Vector3};
 use raygun_primitives::{Object, Primitive};
 
+use crate::{
+    declaration::{reference, Declaration},
+    error::{Expected, ParseResult, SyntaxError},
+    include,
+};
+
 // ////////////////////////////////////////////////////////////////////////////
 // State data
 // ////////////////////////////////////////////////////////////////////////////
@@ -24,6 +33,17 @@ use raygun_primitives::{Object, Primitive};
 pub struct SceneState {
     pub width: isize,
     pub height: isize,
+    pub declarations: HashMap<String, Declaration>,
+
+    /// The scene file being parsed, and the directories to search after the
+    /// one it's in when looking for files the scene refers to
+    pub filename: PathBuf,
+    pub include_paths: Vec<PathBuf>,
+
+    /// The scene source after include expansion, and the text the template
+    /// rendered from it, so names can be traced back to the file they're in
+    pub source: Arc<include::Source>,
+    pub rendered: String,
 }
 
 impl SceneState {
@@ -32,6 +52,11 @@ impl SceneState {
         SceneState {
             width: width,
             height: height,
+            declarations: HashMap::new(),
+            filename: PathBuf::new(),
+            include_paths: Vec::new(),
+            source: Arc::default(),
+            rendered: String::new(),
         }
     }
 }
@@ -42,10 +67,44 @@ impl Default for SceneState {
         SceneState {
             width: 1024,
             height: 768,
+            declarations: HashMap::new(),
+            filename: PathBuf::new(),
+            include_paths: Vec::new(),
+            source: Arc::default(),
+            rendered: String::new(),
         }
     }
 }
 
+impl SceneState {
+    ///
+    /// Finds a file named in the scene, in the same way as included files.
+    /// `at` is the input of the block that names the file, and relative names
+    /// are looked for alongside the file that the block came from, falling
+    /// back to the top-level scene file if that can't be worked out.
+    ///
+    pub fn find_file(&self, name: &str, at: &[u8]) -> Option<PathBuf> {
+        let including = self.origin(name, at).unwrap_or(&self.filename);
+        include::resolve(including, name, &self.include_paths)
+    }
+
+    ///
+    /// Finds the file containing the line where `name` is quoted, searching
+    /// the rendered text from the start of `at`
+    ///
+    fn origin(&self, name: &str, at: &[u8]) -> Option<&Path> {
+        let start = self.rendered.len().checked_sub(at.len())?;
+        let rest = self.rendered.get(start..)?;
+        let found = start + rest.find(&format!("\"{}\"", name))?;
+
+        let line_start = self.rendered[..found].rfind('\n').map_or(0, |n| n + 1);
+        let line_end = self.rendered[found..]
+            .find('\n')
+            .map_or(self.rendered.len(), |n| n + found);
+        self.source.find_file(&self.rendered[line_start..line_end])
+    }
+}
+
 #[derive(Clone)]
 pub struct SceneRef(Arc<RefCell<SceneState>>);
 
@@ -97,45 +156,109 @@ where
     delimited(begin, parser, end)
 }
 
-pub fn named_object<'a, T, Error: ParseError<&'a [u8]>, ParserFn>(
+/*
+ * A keyword, which must not be immediately followed by anything that could
+ * continue an identifier (so `box` doesn't match the start of `boxes`)
+ */
+pub fn keyword<'a>(name: &'static str) -> impl Fn(&'a [u8]) -> ParseResult<'a, &'a [u8]> {
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let parser = terminated(tag(name), not(take_while_m_n(1, 1, is_ident)));
+
+    move |input| {
+        parser(input).map_err(|e: nom::Err<SyntaxError>| {
+            e.map(|_| SyntaxError::expected(input, Expected::Token(name)))
+        })
+    }
+}
+
+/*
+ * An object of the form `name <body>`. Once the name has been matched the
+ * parser is committed, so any error in the body is reported rather than
+ * being backtracked over.
+ */
+pub fn named_object<'a, T, ParserFn>(
     name: &'static str,
     parser: ParserFn,
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], T, Error>
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, T>
 where
-    Error: ParseError<&'a [u8]>,
-    ParserFn: Fn(&'a [u8]) -> IResult<&'a [u8], T, Error>,
+    ParserFn: Fn(&'a [u8]) -> ParseResult<'a, T>,
 {
-    ws(preceded(ws(tag(name)), parser))
+    ws(preceded(ws(keyword(name)), cut(context(name, parser))))
 }
 
-pub fn named_value<'a, T, Error, ParserFn>(
+/*
+ * A value of the form `name: <value>`. As with named objects, the parser is
+ * committed once the name and colon have been matched.
+ */
+pub fn named_value<'a, T, ParserFn>(
     name: &'static str,
     parser: ParserFn,
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], T, Error>
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, T>
 where
-    Error: ParseError<&'a [u8]>,
-    ParserFn: Fn(&'a [u8]) -> IResult<&'a [u8], T, Error>,
+    ParserFn: Fn(&'a [u8]) -> ParseResult<'a, T>,
 {
+    let value = cut(context(name, parser));
+
     move |input| {
         let (i, _) = multispace0(input)?;
-        let (i, _) = tag(name)(i)?;
+        let (i, _) = keyword(name)(i)?;
         let (i, _) = _char(':')(i)?;
         let (i, _) = multispace0(i)?;
-        let (i, v) = parser(i)?;
+        let (i, v) = value(i)?;
         let (i, _) = multispace0(i)?;
 
         Ok((i, v))
     }
 }
 
-pub fn map_named_value<'a, T, U, Error, ParserFn, MapFn>(
+/*
+ * A braced, comma-separated list of fields, e.g. `{ radius: 1, centre: c }`.
+ * Unlike a plain `separated_list` this reports what was expected when the
+ * list can't be parsed, rather than just stopping at the first bad entry.
+ */
+pub fn fields<'a, T, ParserFn>(parser: ParserFn) -> impl Fn(&'a [u8]) -> ParseResult<'a, Vec<T>>
+where
+    ParserFn: Fn(&'a [u8]) -> ParseResult<'a, T>,
+{
+    move |input| {
+        let (mut i, _) = ws(_char('{'))(input)?;
+        let mut items = Vec::new();
+
+        if i.first() == Some(&b'}') {
+            return multispace0(&i[1..]).map(|(i, _)| (i, items));
+        }
+
+        loop {
+            let (rest, item) = match parser(i) {
+                Ok(r) => r,
+                Err(nom::Err::Error(e)) if items.is_empty() => {
+                    return Err(nom::Err::Error(e.or(SyntaxError::from_char(i, '}'))));
+                }
+                Err(e) => return Err(e),
+            };
+            items.push(item);
+
+            let (rest, _) = multispace0(rest)?;
+            match rest.first() {
+                Some(b',') => i = &rest[1..],
+                Some(b'}') => return multispace0(&rest[1..]).map(|(i, _)| (i, items)),
+                _ => {
+                    let mut e = SyntaxError::expected(rest, Expected::Char(','));
+                    e.expected.push(Expected::Char('}'));
+                    return Err(nom::Err::Error(e));
+                }
+            }
+        }
+    }
+}
+
+pub fn map_named_value<'a, T, U, ParserFn, MapFn>(
     name: &'static str,
     parser: ParserFn,
     mapfn: MapFn,
-) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], U, Error>
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, U>
 where
-    Error: ParseError<&'a [u8]>,
-    ParserFn: Fn(&'a [u8]) -> IResult<&'a [u8], T, Error>,
+    ParserFn: Fn(&'a [u8]) -> ParseResult<'a, T>,
     MapFn: Fn(T) -> U,
 {
     map(named_value(name, parser), mapfn)
@@ -144,13 +267,27 @@ where
 /*
  * A vector literal of the form {x, y, z}
  */
-pub fn vector_literal(input: &[u8]) -> IResult<&[u8], Vector> {
-    let parse_x = terminated(real_number, comma);
-    let parse_y = terminated(real_number, comma);
-    let parse_z = real_number;
+pub fn vector_literal(input: &[u8]) -> ParseResult<'_, Vector3> {
+    let parse_x = terminated(float_value, comma);
+    let parse_y = terminated(float_value, comma);
+    let parse_z = float_value;
     let parse_vector = block(tuple((parse_x, parse_y, parse_z)));
 
-    map(parse_vector, |(x, y, z)| Vector::new(x, y, z))(input)
+    map(parse_vector, |(x, y, z)| Vector3::new(x, y, z))(input)
+}
+
+/*
+ * A pair of values of the form {x, y}, as for points in 2D
+ */
+pub fn pair(input: &[u8]) -> ParseResult<'_, (Float, Float)> {
+    block(tuple((terminated(float_value, comma), float_value)))(input)
+}
+
+/*
+ * A vector literal, or a reference to a declared vector
+ */
+pub fn vector<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Vector3> {
+    alt((vector_literal, reference(scene, Declaration::vector)))
 }
 
 // ////////////////////////////////////////////////////////////////////////////
@@ -166,8 +303,60 @@ fn trace<T, E: std::fmt::Debug>(e: (T, E)) -> (T, E) {
  * Parses a real number represented as a decimal fraction (as opposed to one in
  * exponential notation)
  */
-pub fn real_number(input: &[u8]) -> IResult<&[u8], f64> {
-    nom::number::complete::double(input)
+pub fn real_number(input: &[u8]) -> ParseResult<'_, f64> {
+    nom::number::complete::double(input).map_err(|e: nom::Err<SyntaxError>| {
+        e.map(|_| SyntaxError::expected(input, Expected::Description("a number")))
+    })
+}
+
+/*
+ * Parses a real number at the precision used for geometry, which may be
+ * narrower than the one used for colours and material properties
+ */
+pub fn float_value(input: &[u8]) -> ParseResult<'_, Float> {
+    map(real_number, |n| n as Float)(input)
+}
+
+/*
+ * Parses a count of things, written as a plain whole number
+ */
+pub fn count(input: &[u8]) -> ParseResult<'_, usize> {
+    let digits = map_res(digit1, |d: &[u8]| {
+        std::str::from_utf8(d)
+            .map_err(|_| ())
+            .and_then(|d| d.parse().map_err(|_| ()))
+    });
+    digits(input).map_err(|e: nom::Err<SyntaxError>| {
+        e.map(|_| SyntaxError::expected(input, Expected::Description("a whole number")))
+    })
+}
+
+/*
+ * Parses a string, written in double quotes. There are no escapes, so the
+ * string can't itself contain a double quote.
+ */
+pub fn string(input: &[u8]) -> ParseResult<'_, String> {
+    let quoted = delimited(_char('"'), take_while(|c| c != b'"'), _char('"'));
+    map_res(quoted, |s: &[u8]| std::str::from_utf8(s).map(str::to_owned))(input).map_err(
+        |e: nom::Err<SyntaxError>| {
+            e.map(|_| SyntaxError::expected(input, Expected::Description("a quoted string")))
+        },
+    )
+}
+
+// ////////////////////////////////////////////////////////////////////////////
+// Parsing flags
+// ////////////////////////////////////////////////////////////////////////////
+
+/*
+ * Parses a boolean, written as `true` or `false`
+ */
+pub fn boolean(input: &[u8]) -> ParseResult<'_, bool> {
+    alt((value(true, keyword("true")), value(false, keyword("false"))))(input).map_err(
+        |e: nom::Err<SyntaxError>| {
+            e.map(|_| SyntaxError::expected(input, Expected::Description("true or false")))
+        },
+    )
 }
 
 pub fn as_object<PrimitiveT: Primitive>(
@@ -259,4 +448,36 @@ mod test {
         integer_with_trailing_text: "42 plus some other text", 42.0,
             " plus some other text",
     }
+
+    #[test]
+    fn parse_count() {
+        assert_eq!(count(b"128"), Ok((&b""[..], 128)));
+        assert_eq!(count(b"7, "), Ok((&b", "[..], 7)));
+        assert!(count(b"-3").is_err());
+        assert!(count(b"many").is_err());
+    }
+
+    #[test]
+    fn parse_string() {
+        assert_eq!(
+            string(br#""hills.png", "#),
+            Ok((&b", "[..], "hills.png".to_owned()))
+        );
+        assert_eq!(string(br#""""#), Ok((&b""[..], String::new())));
+        assert!(string(br#""unterminated"#).is_err());
+        assert!(string(b"bare").is_err());
+    }
+
+    #[test]
+    fn parse_pair() {
+        assert_eq!(pair(b"{ 1, -2.5 }"), Ok((&b""[..], (1.0, -2.5))));
+        assert!(pair(b"{1, 2, 3}").is_err());
+    }
+
+    #[test]
+    fn parse_boolean() {
+        assert_eq!(boolean(b"true"), Ok((&b""[..], true)));
+        assert_eq!(boolean(b"false, "), Ok((&b", "[..], false)));
+        assert!(boolean(b"truest").is_err());
+    }
 }
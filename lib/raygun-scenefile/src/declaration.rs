@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{char as _char, multispace1},
+    combinator::{cut, map, recognize},
+    error::context,
+    sequence::{pair, terminated},
+};
+
+use raygun_material::{Colour, Material};
+use raygun_math::{Transform, Vector3};
+use raygun_primitives::Object;
+
+use crate::{
+    colour::colour,
+    constructs::*,
+    error::{Expected, ParseResult, SyntaxError},
+    material::material,
+    primitive::{self, primitive},
+    transform::transform,
+};
+
+///
+/// A named value declared in the scene file, available for reference by any
+/// subsequent part of the scene.
+///
+#[derive(Clone, Debug)]
+pub enum Declaration {
+    Colour(Colour),
+    Vector(Vector3),
+    Transform(Transform),
+    Material(Material),
+    Object(Arc<Object>),
+}
+
+impl Declaration {
+    /// The kind of value declared, for use in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Declaration::Colour(_) => "colour",
+            Declaration::Vector(_) => "vector",
+            Declaration::Transform(_) => "transform",
+            Declaration::Material(_) => "material",
+            Declaration::Object(_) => "object",
+        }
+    }
+
+    pub fn colour(&self) -> Option<Colour> {
+        match self {
+            Declaration::Colour(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    pub fn vector(&self) -> Option<Vector3> {
+        match self {
+            Declaration::Vector(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn transform(&self) -> Option<Transform> {
+        match self {
+            Declaration::Transform(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    pub fn material(&self) -> Option<Material> {
+        match self {
+            Declaration::Material(m) => Some(m.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn object(&self) -> Option<Arc<Object>> {
+        match self {
+            Declaration::Object(o) => Some(Arc::clone(o)),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Words that introduce a construct in the scene language and therefore can't
+/// be used as declaration names, along with the object keywords in
+/// `primitive::KEYWORDS`.
+///
+const KEYWORDS: &[&str] = &[
+    "camera",
+    "colour",
+    "declare",
+    "include",
+    "material",
+    "transform",
+    "vector",
+];
+
+/*
+ * An identifier of the form [A-Za-z_][A-Za-z0-9_]*, excluding keywords
+ */
+pub fn identifier(input: &[u8]) -> ParseResult<'_, &str> {
+    let expected = || {
+        nom::Err::Error(SyntaxError::expected(
+            input,
+            Expected::Description("a name"),
+        ))
+    };
+
+    let first = take_while_m_n(1, 1, |c: u8| c.is_ascii_alphabetic() || c == b'_');
+    let rest = take_while(|c: u8| c.is_ascii_alphanumeric() || c == b'_');
+    let (i, id) =
+        recognize(pair(first, rest))(input).map_err(|_: nom::Err<SyntaxError>| expected())?;
+
+    // the recognised bytes are all ASCII, so this can't fail
+    let id = std::str::from_utf8(id).unwrap();
+    if KEYWORDS.contains(&id) || primitive::KEYWORDS.contains(&id) {
+        Err(expected())
+    } else {
+        Ok((i, id))
+    }
+}
+
+///
+/// Parses a reference to a previously-declared value, using `extract` to
+/// check that it is of the expected type. Referring to an undeclared name,
+/// or to a declaration of the wrong type, is a hard failure.
+///
+pub fn reference<'a, T, Extract>(
+    scene: SceneRef,
+    extract: Extract,
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, T>
+where
+    Extract: Fn(&Declaration) -> Option<T>,
+{
+    move |input| {
+        let (i, name) = identifier(input)?;
+        let state = scene.borrow();
+        let decl = state.declarations.get(name).ok_or_else(|| {
+            let msg = format!("`{}` has not been declared", name);
+            nom::Err::Failure(SyntaxError::message(input, msg))
+        })?;
+
+        extract(decl).map(|value| (i, value)).ok_or_else(|| {
+            let msg = format!("`{}` is a {} and can't be used here", name, decl.kind());
+            nom::Err::Failure(SyntaxError::message(input, msg))
+        })
+    }
+}
+
+///
+/// A declaration of the form `declare name = <value>`, where the value is one
+/// of
+///
+///  * `colour {r, g, b}`
+///  * `vector {x, y, z}`
+///  * `transform { ... }`
+///  * `material { ... }`
+///  * any object, e.g. `sphere { ... }`
+///
+/// The declared value is recorded in the scene state.
+///
+pub fn declaration<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, ()> {
+    move |input| {
+        let (i, _) = ws(terminated(tag("declare"), multispace1))(input)?;
+        let (i, name) = cut(ws(identifier))(i)?;
+        let (i, _) = cut(ws(_char('=')))(i)?;
+        let (i, value) = cut(context(
+            "declare",
+            alt((
+                map(
+                    named_object("colour", colour(scene.clone())),
+                    Declaration::Colour,
+                ),
+                map(
+                    named_object("vector", vector(scene.clone())),
+                    Declaration::Vector,
+                ),
+                map(
+                    named_object("transform", transform(scene.clone())),
+                    Declaration::Transform,
+                ),
+                map(
+                    named_object("material", material(scene.clone())),
+                    Declaration::Material,
+                ),
+                map(primitive(scene.clone()), Declaration::Object),
+            )),
+        ))(i)?;
+
+        scene
+            .borrow_mut()
+            .declarations
+            .insert(name.to_owned(), value);
+        Ok((i, ()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_material::Pigment;
+    use raygun_math::{point, vector};
+    use raygun_primitives::Sphere;
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(identifier(b"red_glass2 ="), Ok((&b" ="[..], "red_glass2")));
+        assert_eq!(identifier(b"_x"), Ok((&b""[..], "_x")));
+        assert!(identifier(b"2x").is_err());
+        assert!(identifier(b"sphere").is_err());
+    }
+
+    #[test]
+    fn keywords_cant_be_declared() {
+        for kw in ["torus", "height_field", "import_gltf", "include", "box"].iter() {
+            let text = format!("declare {} = vector {{1, 2, 3}}", kw);
+            assert!(
+                declaration(SceneRef::default())(text.as_bytes()).is_err(),
+                "`{}` was declared",
+                kw
+            );
+        }
+    }
+
+    #[test]
+    fn declare_colour() {
+        let scene = SceneRef::default();
+        declaration(scene.clone())(b"declare red = colour {1, 0, 0}").unwrap();
+
+        let state = scene.borrow();
+        let c = state.declarations["red"].colour().unwrap();
+        assert_eq!(c, Colour::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn declarations_may_refer_to_each_other() {
+        let scene = SceneRef::default();
+        declaration(scene.clone())(b"declare a = vector {1, 2, 3}").unwrap();
+        declaration(scene.clone())(b"declare b = vector a").unwrap();
+
+        let state = scene.borrow();
+        assert_eq!(
+            state.declarations["b"].vector().unwrap(),
+            vector(1.0, 2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn declared_values_are_used_by_objects() {
+        let scene = SceneRef::default();
+        let text = r#"
+            declare red_glass = material {
+                pigment: solid { colour: { 1, 0, 0 } },
+                finish: { reflection: 0.5 }
+            }
+            declare origin = vector { 1, 2, 3 }
+        "#;
+        let (rest, _) = declaration(scene.clone())(text.as_bytes()).unwrap();
+        declaration(scene.clone())(rest).unwrap();
+
+        let (_, obj) =
+            primitive(scene.clone())(b"sphere { centre: origin, material: red_glass }").unwrap();
+
+        let s = obj.as_primitive::<Sphere>().unwrap();
+        assert_eq!(s.centre, point(1.0, 2.0, 3.0));
+        assert_eq!(obj.material.finish.reflection, 0.5);
+        match obj.material.pigment {
+            Pigment::Solid(c) => assert_eq!(c, Colour::new(1.0, 0.0, 0.0)),
+            ref p => panic!("Expected a solid pigment, got {:?}", p),
+        }
+    }
+
+    #[test]
+    fn undeclared_reference_is_a_failure() {
+        let scene = SceneRef::default();
+        match primitive(scene)(b"sphere { centre: nowhere }") {
+            Err(nom::Err::Failure(_)) => {}
+            other => panic!("Expected failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reference_of_wrong_type_is_a_failure() {
+        let scene = SceneRef::default();
+        declaration(scene.clone())(b"declare red = colour {1, 0, 0}").unwrap();
+        match primitive(scene)(b"sphere { centre: red }") {
+            Err(nom::Err::Failure(_)) => {}
+            other => panic!("Expected failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
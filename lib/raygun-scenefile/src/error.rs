@@ -0,0 +1,303 @@
+//! Syntax errors produced while parsing a scene, and the diagnostics they are
+//! turned into for reporting.
+
+use std::{fmt, path::PathBuf};
+
+use nom::error::{ErrorKind, ParseError};
+
+use crate::include::Source;
+
+pub type ParseResult<'a, T> = nom::IResult<&'a [u8], T, SyntaxError<'a>>;
+
+///
+/// Something the parser was expecting to find at the point it failed.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected {
+    Char(char),
+    Token(&'static str),
+    Description(&'static str),
+    Kind(ErrorKind),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Char(c) => write!(f, "`{}`", c),
+            Expected::Token(t) => write!(f, "`{}`", t),
+            Expected::Description(d) => write!(f, "{}", d),
+            Expected::Kind(k) => write!(f, "{}", k.description().to_lowercase()),
+        }
+    }
+}
+
+///
+/// The error type used by all of the scene parsers. As well as recording
+/// where parsing failed, it collects what would have been acceptable at that
+/// point and the chain of constructs being parsed when it happened.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError<'a> {
+    pub input: &'a [u8],
+    pub expected: Vec<Expected>,
+    pub message: Option<String>,
+
+    /// Enclosing constructs, innermost first
+    pub context: Vec<&'static str>,
+}
+
+impl<'a> SyntaxError<'a> {
+    pub fn expected(input: &'a [u8], what: Expected) -> SyntaxError<'a> {
+        SyntaxError {
+            input,
+            expected: vec![what],
+            message: None,
+            context: Vec::new(),
+        }
+    }
+
+    pub fn message(input: &'a [u8], msg: String) -> SyntaxError<'a> {
+        SyntaxError {
+            input,
+            expected: Vec::new(),
+            message: Some(msg),
+            context: Vec::new(),
+        }
+    }
+
+    ///
+    /// A human readable description of the error, e.g. "expected `radius` or
+    /// `}` in sphere"
+    ///
+    pub fn description(&self) -> String {
+        let mut text = match &self.message {
+            Some(msg) => msg.clone(),
+            None if self.expected.is_empty() => "syntax error".to_owned(),
+            None => {
+                let names: Vec<String> = self.expected.iter().map(|e| e.to_string()).collect();
+                let (last, rest) = names.split_last().unwrap();
+                if rest.is_empty() {
+                    format!("expected {}", last)
+                } else {
+                    format!("expected {} or {}", rest.join(", "), last)
+                }
+            }
+        };
+
+        if !self.context.is_empty() {
+            let path: Vec<&str> = self.context.iter().rev().cloned().collect();
+            text.push_str(" in ");
+            text.push_str(&path.join("."));
+        }
+
+        text
+    }
+}
+
+impl<'a> ParseError<&'a [u8]> for SyntaxError<'a> {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        SyntaxError::expected(input, Expected::Kind(kind))
+    }
+
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a [u8], c: char) -> Self {
+        SyntaxError::expected(input, Expected::Char(c))
+    }
+
+    ///
+    /// Keeps whichever error got furthest through the input, merging the
+    /// expectations of errors that failed at the same place.
+    ///
+    fn or(mut self, other: Self) -> Self {
+        if self.input.len() != other.input.len() {
+            return if self.input.len() < other.input.len() {
+                self
+            } else {
+                other
+            };
+        }
+
+        // an explicit message is more specific than a list of alternatives
+        if self.message.is_some() {
+            return self;
+        }
+        if other.message.is_some() {
+            return other;
+        }
+
+        for e in other.expected {
+            if !self.expected.contains(&e) {
+                self.expected.push(e);
+            }
+        }
+        self
+    }
+
+    fn add_context(_: &'a [u8], ctx: &'static str, mut other: Self) -> Self {
+        other.context.push(ctx);
+        other
+    }
+}
+
+///
+/// A syntax error in a scene file, located as precisely as possible
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The file containing the error. `None` if the error could only be
+    /// located in the rendered template text.
+    pub file: Option<PathBuf>,
+
+    /// 1-based line number
+    pub line: usize,
+
+    /// 1-based column number, in characters
+    pub column: usize,
+
+    pub message: String,
+
+    /// The text of the offending line
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    ///
+    /// Locates a syntax error within the rendered template text it came from
+    ///
+    pub fn new(text: &str, err: &SyntaxError) -> Diagnostic {
+        let offset = text.len() - err.input.len();
+        Diagnostic::at_offset(text, offset, err.description())
+    }
+
+    pub fn at_offset(text: &str, mut offset: usize, message: String) -> Diagnostic {
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line_end = text[offset..]
+            .find('\n')
+            .map(|n| n + offset)
+            .unwrap_or_else(|| text.len());
+
+        Diagnostic {
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+            source_line: text[line_start..line_end].trim_end().to_owned(),
+        }
+    }
+
+    ///
+    /// Maps a diagnostic located in the rendered template back onto the
+    /// original source file, if the offending line can be identified
+    /// unambiguously.
+    ///
+    pub fn relocate(self, source: &Source) -> Diagnostic {
+        let (file, line, text) = match source.find_line(&self.source_line) {
+            Some(found) => found,
+            None => return self,
+        };
+
+        let rendered_indent = indent(&self.source_line);
+        let column = if self.column > rendered_indent {
+            self.column - rendered_indent + indent(text)
+        } else {
+            self.column
+        };
+
+        Diagnostic {
+            file: Some(file.to_path_buf()),
+            line,
+            column,
+            message: self.message,
+            source_line: text.trim_end().to_owned(),
+        }
+    }
+
+    ///
+    /// The offending line, with a caret marking the error location
+    ///
+    pub fn excerpt(&self) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{} |\n{} | {}\n{} | {}^",
+            gutter, number, self.source_line, gutter, padding
+        )
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = self
+            .file
+            .as_ref()
+            .map(|f| f.display().to_string())
+            .unwrap_or_else(|| "<rendered template>".to_owned());
+
+        write!(
+            f,
+            "{}:{}:{}: {}\n{}",
+            file,
+            self.line,
+            self.column,
+            self.message,
+            self.excerpt()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expectations_at_the_same_place_are_merged() {
+        let text = b"radus: 1 }";
+        let a = SyntaxError::expected(&text[..], Expected::Token("radius"));
+        let b = SyntaxError::expected(&text[..], Expected::Char('}'));
+        assert_eq!(a.or(b).description(), "expected `radius` or `}`");
+    }
+
+    #[test]
+    fn furthest_error_wins() {
+        let text = b"{ 1, 2 }";
+        let shallow = SyntaxError::expected(&text[..], Expected::Description("a name"));
+        let mut deep = SyntaxError::expected(&text[6..], Expected::Char(','));
+        deep.context = vec!["centre", "sphere"];
+
+        let e = shallow.or(deep);
+        assert_eq!(e.description(), "expected `,` in sphere.centre");
+    }
+
+    #[test]
+    fn diagnostics_have_line_and_column() {
+        let text = "camera {}\n  sphere { radus: 1 }\n";
+        let err = SyntaxError::expected(&text.as_bytes()[21..], Expected::Token("radius"));
+        let d = Diagnostic::new(text, &err);
+
+        assert_eq!((d.line, d.column), (2, 12));
+        assert_eq!(d.source_line, "  sphere { radus: 1 }");
+        assert_eq!(
+            d.excerpt(),
+            "  |\n2 |   sphere { radus: 1 }\n  |            ^"
+        );
+    }
+}
@@ -0,0 +1,574 @@
+//! Imports scenes from glTF 2.0 files, either `.gltf` JSON with its buffers
+//! alongside or embedded as data URIs, or self-contained `.glb` files.
+//!
+//! Each node becomes a union carrying the node's transform, holding the
+//! node's meshes, light and child nodes. glTF's axes are right-handed and
+//! raygun's are left-handed, so everything is mirrored in z on the way in,
+//! which leaves the scene looking just as it did where it was authored.
+//!
+//! Much of glTF has no counterpart here, and is approximated or skipped:
+//!
+//!  * only the factors of a metallic-roughness material are used, never its
+//!    textures, and metalness and roughness become reflection and highlight
+//!    hardness;
+//!  * raygun's lights don't fall off with distance, so only a light's colour
+//!    is kept, not its intensity or range, and directional lights are point
+//!    lights a long way off;
+//!  * points, lines, orthographic cameras, skins and animation are ignored.
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use base64::Engine;
+use gltf::{
+    buffer, camera::Projection, khr_lights_punctual::Kind, material::AlphaMode, mesh::Mode,
+};
+use log::{debug, warn};
+
+use crate::constructs::SceneState;
+use raygun_camera::Camera;
+use raygun_material::{Colour, Finish, Material, Pigment};
+use raygun_math::{point, radians, vector, Float, Matrix, Point3, Transform, Vector3, IDENTITY};
+use raygun_primitives::{AxisAlignedBox, Mesh, Object, PointLight, Primitive, SpotLight, Union};
+use raygun_scene::Scene;
+
+/// How far away directional lights are put, standing in for infinity
+const DISTANT: Float = 1e5;
+
+///
+/// What was found in a glTF file
+///
+pub struct Import {
+    /// The file's default scene, as a union of its root nodes
+    pub root: Object,
+
+    /// The first perspective camera in the scene, if it has one
+    pub camera: Option<Camera>,
+
+    /// How many lights the scene has
+    pub lights: usize,
+}
+
+///
+/// Loads the default scene from a glTF file, or the first scene if the file
+/// doesn't say which is the default.
+///
+pub fn load(path: &Path) -> Result<Import, String> {
+    let data = std::fs::read(path).map_err(|e| format!("can't read {:?}: {}", path, e))?;
+    let gltf::Gltf { document, blob } =
+        gltf::Gltf::from_slice(&data).map_err(|e| format!("{:?}: {}", path, e))?;
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let buffers = document
+        .buffers()
+        .map(|b| buffer_data(&b, dir, blob.as_deref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| format!("{:?} has no scenes in it", path))?;
+
+    let mut importer = Importer {
+        buffers,
+        meshes: HashMap::new(),
+        camera: None,
+        lights: 0,
+    };
+    let mut root = Union::new();
+    for node in scene.nodes() {
+        if let Some(obj) = importer.node(&node, &IDENTITY)? {
+            root.children.push(Arc::new(obj));
+        }
+    }
+
+    Ok(Import {
+        root: Object::from(Arc::new(root)),
+        camera: importer.camera,
+        lights: importer.lights,
+    })
+}
+
+///
+/// Loads a glTF file as a whole scene to render. Scenes without a camera are
+/// looked at from the front, far enough back to see all of them, and scenes
+/// without lights are lit from the camera.
+///
+pub fn scene(path: &Path) -> Result<Scene, String> {
+    let import = load(path)?;
+    let mut scene = Scene::new();
+    scene.add_object(import.root);
+
+    scene.camera = match import.camera {
+        Some(c) => c,
+        None => framing_camera(&scene),
+    };
+    if import.lights == 0 {
+        debug!("No lights in {:?}, adding one at the camera", path);
+        scene.add_object(Object::from(Arc::new(PointLight::new(
+            scene.camera.loc,
+            Colour::new(1.0, 1.0, 1.0),
+        ))));
+    }
+
+    Ok(scene)
+}
+
+///
+/// Fetches the contents of a buffer, from the GLB file's binary chunk, a
+/// data URI or a file alongside the glTF file
+///
+fn buffer_data(b: &buffer::Buffer, dir: &Path, blob: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let data = match b.source() {
+        buffer::Source::Bin => blob
+            .ok_or("a buffer refers to a binary chunk that isn't there")?
+            .to_vec(),
+        buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+            let encoded = uri
+                .find(";base64,")
+                .map(|at| &uri[at + 8..])
+                .ok_or("only base64 data URIs are supported")?;
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("a buffer's data URI is broken: {}", e))?
+        }
+        buffer::Source::Uri(uri) => {
+            let name = urlencoding::decode(uri).map_err(|_| format!("bad buffer URI {}", uri))?;
+            let path = dir.join(name.as_ref());
+            std::fs::read(&path).map_err(|e| format!("can't read buffer {:?}: {}", path, e))?
+        }
+    };
+
+    if data.len() < b.length() {
+        return Err(format!("buffer {} is shorter than it says", b.index()));
+    }
+    Ok(data)
+}
+
+struct Importer {
+    buffers: Vec<Vec<u8>>,
+
+    /// Each glTF mesh's primitives, shared between the nodes that use it
+    meshes: HashMap<usize, Vec<Arc<Object>>>,
+
+    camera: Option<Camera>,
+    lights: usize,
+}
+
+impl Importer {
+    ///
+    /// Converts a node and everything below it into a union, given the
+    /// transform from its parent's space into the world's
+    ///
+    fn node(&mut self, node: &gltf::Node, parent: &Matrix) -> Result<Option<Object>, String> {
+        let local = mirrored(node.transform().matrix());
+        let transform = match Transform::from_matrix(local) {
+            Some(t) => t,
+            None => {
+                debug!("Skipping node {}, which is scaled to nothing", node.index());
+                return Ok(None);
+            }
+        };
+        let world = local * *parent;
+
+        let mut union = Union::new();
+        if let Some(mesh) = node.mesh() {
+            union.children.extend(self.mesh(&mesh)?);
+        }
+        if let Some(light) = node.light() {
+            union.children.push(Arc::new(light_object(&light)));
+            self.lights += 1;
+        }
+        if let Some(camera) = node.camera() {
+            if self.camera.is_none() {
+                self.camera = scene_camera(&camera, &world);
+            }
+        }
+        for child in node.children() {
+            if let Some(obj) = self.node(&child, &world)? {
+                union.children.push(Arc::new(obj));
+            }
+        }
+
+        Ok(Some(Object {
+            transform: Some(Box::new(transform)),
+            ..Object::from(Arc::new(union))
+        }))
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Result<Vec<Arc<Object>>, String> {
+        if let Some(objs) = self.meshes.get(&mesh.index()) {
+            return Ok(objs.clone());
+        }
+
+        let mut objs = Vec::new();
+        for primitive in mesh.primitives() {
+            if let Some(obj) = self.primitive(&primitive)? {
+                objs.push(Arc::new(obj));
+            }
+        }
+
+        self.meshes.insert(mesh.index(), objs.clone());
+        Ok(objs)
+    }
+
+    fn primitive(&self, primitive: &gltf::Primitive) -> Result<Option<Object>, String> {
+        let mode = primitive.mode();
+        if ![Mode::Triangles, Mode::TriangleStrip, Mode::TriangleFan].contains(&mode) {
+            warn!(
+                "Skipping a mesh drawn as {:?}, only triangles are supported",
+                mode
+            );
+            return Ok(None);
+        }
+
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|b| buffers.get(b.index()).map(Vec::as_slice));
+        let vertices: Vec<Point3> = match reader.read_positions() {
+            Some(p) => p.map(|[x, y, z]| mirror_point(x, y, z)).collect(),
+            None => return Ok(None),
+        };
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(i) => i.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+
+        let broken = || "a mesh's attributes don't match its vertices".to_owned();
+        let mut mesh = match Mesh::new(vertices, triangles(mode, &indices)) {
+            Some(m) => m,
+            None => {
+                debug!("Skipping a mesh with no triangles to draw");
+                return Ok(None);
+            }
+        };
+        if let Some(normals) = reader.read_normals() {
+            let normals = normals.map(|[x, y, z]| mirror_vector(x, y, z)).collect();
+            mesh = mesh.with_normals(normals).ok_or_else(broken)?;
+        }
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            let uvs = uvs
+                .into_f32()
+                .map(|[u, v]| (Float::from(u), Float::from(v)))
+                .collect();
+            mesh = mesh.with_uvs(uvs).ok_or_else(broken)?;
+        }
+
+        // vertex colours are multiplied by the base colour
+        let mut material = material(&primitive.material());
+        if let Some(colours) = reader.read_colors(0) {
+            let base = match material.pigment {
+                Pigment::Solid(c) | Pigment::Vertex(c) => c,
+            };
+            let colours = colours.into_rgb_f32().map(|c| base * colour(c)).collect();
+            mesh = mesh.with_colours(colours).ok_or_else(broken)?;
+            material.pigment = Pigment::Vertex(base);
+        }
+
+        Ok(Some(Object {
+            material,
+            ..Object::from(Arc::new(mesh))
+        }))
+    }
+}
+
+///
+/// Splits a mesh primitive's indices up into triangles, swapping their
+/// winding to match the mirroring
+///
+fn triangles(mode: Mode, indices: &[usize]) -> Vec<[usize; 3]> {
+    let corners = indices.len().saturating_sub(2);
+    match mode {
+        Mode::TriangleStrip => (0..corners)
+            .map(|k| {
+                let [a, b, c] = [indices[k], indices[k + 1], indices[k + 2]];
+                // every other triangle in a strip is wound the other way
+                if k % 2 == 0 {
+                    [a, c, b]
+                } else {
+                    [a, b, c]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (1..corners + 1)
+            .map(|k| [indices[0], indices[k + 1], indices[k]])
+            .collect(),
+        _ => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[2], t[1]])
+            .collect(),
+    }
+}
+
+///
+/// Approximates a metallic-roughness material with raygun's finish
+///
+fn material(m: &gltf::Material) -> Material {
+    let pbr = m.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let metallic = f64::from(pbr.metallic_factor());
+    let roughness = f64::from(pbr.roughness_factor());
+
+    // roughness is turned into a Phong exponent by way of the usual
+    // Beckmann-like correspondence, leaving fully rough surfaces without
+    // highlights at all
+    let alpha_sq = roughness.powi(4);
+    let highlight_hardness = if roughness >= 1.0 {
+        f64::INFINITY
+    } else {
+        (2.0 / alpha_sq.max(1e-9) - 2.0).clamp(10.0, 1000.0)
+    };
+
+    // raygun has no emissive surfaces, but ambient light gets close
+    let emissive = m.emissive_factor().iter().cloned().fold(0.0, f32::max);
+
+    let defaults = Finish::default();
+    Material {
+        pigment: Pigment::Solid(colour([r, g, b])),
+        finish: Finish {
+            opacity: match m.alpha_mode() {
+                AlphaMode::Blend => f64::from(alpha),
+                _ => 1.0,
+            },
+            // reflections aren't tinted by the surface, so metals keep some
+            // of their diffuse colour
+            reflection: metallic * (1.0 - roughness),
+            ambient: (defaults.ambient + f64::from(emissive)).min(1.0),
+            diffuse: defaults.diffuse * (1.0 - 0.5 * metallic),
+            highlight_hardness,
+        },
+    }
+}
+
+///
+/// Converts a light into a light primitive, in its node's space
+///
+fn light_object(light: &gltf::khr_lights_punctual::Light) -> Object {
+    let colour = colour(light.color());
+    let primitive: Arc<dyn Primitive> = match light.kind() {
+        Kind::Point => Arc::new(PointLight::new(Point3::default(), colour)),
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => Arc::new(SpotLight::new(
+            colour,
+            Float::from(inner_cone_angle),
+            Float::from(outer_cone_angle),
+        )),
+        // directional lights shine down -z, which is +z after mirroring, so
+        // they're put the other way along it
+        Kind::Directional => Arc::new(PointLight::new(point(0.0, 0.0, -DISTANT), colour)),
+    };
+    Object::from(primitive)
+}
+
+///
+/// Converts a perspective camera, given its node's transform into the world
+///
+fn scene_camera(camera: &gltf::Camera, world: &Matrix) -> Option<Camera> {
+    let p = match camera.projection() {
+        Projection::Perspective(p) => p,
+        Projection::Orthographic(_) => {
+            warn!(
+                "Ignoring camera {}, orthographic cameras aren't supported",
+                camera.index()
+            );
+            return None;
+        }
+    };
+
+    let dir = (world * vector(0.0, 0.0, 1.0)).normalize();
+    let right = (world * vector(0.0, 1.0, 0.0)).cross(dir).normalize();
+    let up = dir.cross(right).normalize();
+
+    let default_state = SceneState::default();
+    let aspect_ratio = match p.aspect_ratio() {
+        Some(a) => Float::from(a),
+        None => default_state.width as Float / default_state.height as Float,
+    };
+    let half_vfov = Float::from(p.yfov()) / 2.0;
+    let half_hfov = (aspect_ratio * half_vfov.tan()).atan();
+
+    Some(Camera {
+        loc: world * Point3::default(),
+        dir,
+        up,
+        right,
+        hfov: radians(2.0 * half_hfov),
+        vfov: radians(2.0 * half_vfov),
+    })
+}
+
+///
+/// A camera looking at the front of the scene, far enough back to take in
+/// all of its objects
+///
+fn framing_camera(scene: &Scene) -> Camera {
+    let camera = Camera::default();
+    let bounds = scene
+        .leaves()
+        .iter()
+        .filter(|obj| obj.as_light().is_none())
+        .map(|obj| obj.bounding_box())
+        .fold(None, |acc: Option<AxisAlignedBox>, b| match acc {
+            Some(a) => Some(a.union(&b)),
+            None => Some(b),
+        });
+    let bounds = match bounds {
+        Some(b) => b,
+        None => return camera,
+    };
+
+    let centre = bounds.lower + (bounds.upper - bounds.lower) * 0.5;
+    let radius = (bounds.upper - bounds.lower).length() / 2.0;
+    let half_fov: Float = (camera.hfov.get().min(camera.vfov.get())) / 2.0;
+    let distance = radius / half_fov.sin();
+
+    Camera {
+        loc: centre - camera.dir * distance,
+        ..camera
+    }
+}
+
+///
+/// Converts a glTF matrix, which is column-major and right-handed, into a
+/// raygun one
+///
+fn mirrored(m: [[f32; 4]; 4]) -> Matrix {
+    let mut values = [0.0; 16];
+    for (row, values) in values.chunks_exact_mut(4).enumerate() {
+        for (col, v) in values.iter_mut().enumerate() {
+            // mirroring on both sides flips the sign of the entries that
+            // mix z with the other axes
+            let sign = if (row == 2) != (col == 2) { -1.0 } else { 1.0 };
+            *v = sign * Float::from(m[col][row]);
+        }
+    }
+    Matrix::new(values)
+}
+
+fn mirror_point(x: f32, y: f32, z: f32) -> Point3 {
+    point(Float::from(x), Float::from(y), -Float::from(z))
+}
+
+fn mirror_vector(x: f32, y: f32, z: f32) -> Vector3 {
+    vector(Float::from(x), Float::from(y), -Float::from(z))
+}
+
+fn colour([r, g, b]: [f32; 3]) -> Colour {
+    Colour::new(f64::from(r), f64::from(g), f64::from(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    /// A glTF file holding a single triangle in a translated node, with a
+    /// camera and a spot light. Its buffer is three vertices then three
+    /// normals, all little-endian f32s.
+    fn triangle_gltf(dir: &ScratchDir) -> std::path::PathBuf {
+        let floats: [f32; 18] = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+        ];
+        let bytes: Vec<u8> = floats
+            .iter()
+            .flat_map(|f| f.to_le_bytes().to_vec())
+            .collect();
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&bytes)
+        );
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "extensionsUsed": ["KHR_lights_punctual"],
+                "extensions": {{ "KHR_lights_punctual": {{ "lights": [
+                    {{ "type": "spot", "color": [1, 0.5, 0.25], "spot": {{ "outerConeAngle": 0.5 }} }}
+                ] }} }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0, 1] }}],
+                "nodes": [
+                    {{ "mesh": 0, "translation": [0, 0, -5],
+                       "children": [2] }},
+                    {{ "camera": 0, "translation": [0, 0, 5] }},
+                    {{ "extensions": {{ "KHR_lights_punctual": {{ "light": 0 }} }} }}
+                ],
+                "cameras": [{{ "type": "perspective",
+                    "perspective": {{ "yfov": 0.5, "aspectRatio": 2.0, "znear": 0.1 }} }}],
+                "meshes": [{{ "primitives": [{{
+                    "attributes": {{ "POSITION": 0, "NORMAL": 1 }},
+                    "material": 0
+                }}] }}],
+                "materials": [{{ "pbrMetallicRoughness": {{
+                    "baseColorFactor": [0.8, 0.2, 0.1, 1.0],
+                    "metallicFactor": 1.0,
+                    "roughnessFactor": 0.5
+                }} }}],
+                "buffers": [{{ "byteLength": 72, "uri": "{}" }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": 72 }}],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0] }},
+                    {{ "bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3,
+                       "type": "VEC3" }}
+                ]
+            }}"#,
+            uri
+        );
+
+        dir.write("triangle.gltf", json);
+        dir.join("triangle.gltf")
+    }
+
+    #[test]
+    fn nodes_become_unions() {
+        let dir = ScratchDir::new("import");
+        let import = load(&triangle_gltf(&dir)).unwrap();
+        assert_eq!(import.lights, 1);
+
+        let mut scene = Scene::new();
+        scene.add_object(import.root);
+        let leaves = scene.leaves();
+        assert_eq!(leaves.len(), 2);
+
+        // the mesh is mirrored in z, as is its node's translation, and its
+        // winding is swapped to keep it facing the same way
+        let mesh = leaves[0].as_primitive::<Mesh>().unwrap();
+        assert_eq!(mesh.triangles(), &[[0, 2, 1]]);
+        assert_eq!(mesh.normals().unwrap()[0], vector(0.0, 0.0, -1.0));
+        let t = leaves[0].transform.as_ref().unwrap();
+        assert_eq!(t.matrix * point(1.0, 0.0, 0.0), point(1.0, 0.0, 5.0));
+        assert!(matches!(leaves[0].material.pigment, Pigment::Solid(_)));
+        assert_eq!(leaves[0].material.finish.reflection, 0.5);
+
+        // the light is a child of the mesh's node, so it moves with it
+        assert!(leaves[1].as_primitive::<SpotLight>().is_some());
+        let lights = scene.lights();
+        assert_eq!(
+            lights[0].transform.matrix * Point3::default(),
+            point(0.0, 0.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn cameras_look_down_the_mirrored_axis() {
+        let dir = ScratchDir::new("import-camera");
+        let camera = load(&triangle_gltf(&dir)).unwrap().camera.unwrap();
+
+        assert_eq!(camera.loc, point(0.0, 0.0, -5.0));
+        assert!((camera.dir - vector(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert!((camera.right - vector(1.0, 0.0, 0.0)).length() < 1e-6);
+        assert!((camera.vfov.get() - 0.5).abs() < 1e-6);
+        assert!(camera.hfov.get() > camera.vfov.get());
+    }
+
+    #[test]
+    fn strips_and_fans_are_split_up() {
+        let strip = triangles(Mode::TriangleStrip, &[0, 1, 2, 3]);
+        assert_eq!(strip, vec![[0, 2, 1], [1, 2, 3]]);
+
+        let fan = triangles(Mode::TriangleFan, &[0, 1, 2, 3]);
+        assert_eq!(fan, vec![[0, 2, 1], [0, 3, 2]]);
+    }
+}
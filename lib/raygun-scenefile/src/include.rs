@@ -0,0 +1,314 @@
+//! Expands `include "file.rg"` directives in scene files.
+//!
+//! Includes are expanded textually before the scene is handed to the template
+//! engine, so included files can use (and define) template variables just
+//! like the including file can.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::SceneError;
+
+///
+/// The scene source after include expansion, remembering which file and line
+/// each line of the expanded text came from.
+///
+#[derive(Debug, Default)]
+pub struct Source {
+    pub text: String,
+    origins: Vec<(PathBuf, usize)>,
+}
+
+impl Source {
+    fn push_line(&mut self, filename: &Path, line: usize, text: &str) {
+        self.text.push_str(text);
+        self.text.push('\n');
+        self.origins.push((filename.to_path_buf(), line));
+    }
+
+    ///
+    /// Finds the original file, line number and text of the source line that
+    /// matches `rendered` (ignoring surrounding whitespace). Returns `None` if
+    /// there is no such line, or if more than one line matches.
+    ///
+    pub fn find_line(&self, rendered: &str) -> Option<(&Path, usize, &str)> {
+        let wanted = rendered.trim();
+        if wanted.is_empty() {
+            return None;
+        }
+
+        let mut matches = self
+            .text
+            .lines()
+            .zip(self.origins.iter())
+            .filter(|(text, _)| text.trim() == wanted);
+
+        match (matches.next(), matches.next()) {
+            (Some((text, (file, line))), None) => Some((file.as_path(), *line, text)),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Finds the file that the source line matching `rendered` came from.
+    /// Unlike `find_line`, the line may appear more than once, as long as
+    /// every copy is in the same file.
+    ///
+    pub fn find_file(&self, rendered: &str) -> Option<&Path> {
+        let wanted = rendered.trim();
+        if wanted.is_empty() {
+            return None;
+        }
+
+        let mut files = self
+            .text
+            .lines()
+            .zip(self.origins.iter())
+            .filter(|(text, _)| text.trim() == wanted)
+            .map(|(_, (file, _))| file.as_path());
+
+        let first = files.next()?;
+        if files.all(|file| file == first) {
+            Some(first)
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// Reads the scene file at `filename`, recursively replacing any include
+/// directives with the contents of the named file. Relative include paths are
+/// resolved against the directory of the including file first, and then
+/// against each of the `search_paths` in order.
+///
+pub fn expand_includes(filename: &Path, search_paths: &[PathBuf]) -> Result<Source, SceneError> {
+    let text = fs::read_to_string(filename).map_err(SceneError::FileError)?;
+    let mut stack = vec![canonical(filename)];
+    let mut output = Source {
+        text: String::with_capacity(text.len()),
+        origins: Vec::new(),
+    };
+
+    expand(filename, &text, search_paths, &mut stack, &mut output)?;
+    Ok(output)
+}
+
+fn expand(
+    filename: &Path,
+    source: &str,
+    search_paths: &[PathBuf],
+    stack: &mut Vec<PathBuf>,
+    output: &mut Source,
+) -> Result<(), SceneError> {
+    for (n, line) in source.lines().enumerate() {
+        let include = match include_directive(line) {
+            Some(Ok(name)) => name,
+            Some(Err(msg)) => return Err(include_error(filename, n + 1, &msg)),
+            None => {
+                output.push_line(filename, n + 1, line);
+                continue;
+            }
+        };
+
+        let path = resolve(filename, include, search_paths).ok_or_else(|| {
+            include_error(
+                filename,
+                n + 1,
+                &format!("cannot find include file \"{}\"", include),
+            )
+        })?;
+
+        let key = canonical(&path);
+        if stack.contains(&key) {
+            let chain = stack
+                .iter()
+                .chain(std::iter::once(&key))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(include_error(
+                filename,
+                n + 1,
+                &format!("include cycle detected: {}", chain),
+            ));
+        }
+
+        debug!("Including {:?} from {:?}", path, filename);
+        let text = fs::read_to_string(&path).map_err(|e| {
+            include_error(
+                filename,
+                n + 1,
+                &format!("failed to read {:?}: {}", path, e),
+            )
+        })?;
+
+        stack.push(key);
+        expand(&path, &text, search_paths, stack, output)?;
+        stack.pop();
+    }
+
+    Ok(())
+}
+
+///
+/// Recognises a line of the form `include "some/file.rg"`. Returns `None` if
+/// the line is not an include directive at all, and an error if it looks like
+/// one but is malformed.
+///
+fn include_directive(line: &str) -> Option<Result<&str, String>> {
+    let trimmed = line.trim();
+    let rest = trimmed.strip_prefix("include")?;
+    if !rest.starts_with(char::is_whitespace) && !rest.starts_with('"') {
+        // some other identifier that just happens to start with "include"
+        return None;
+    }
+
+    let rest = rest.trim();
+    let name = rest
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .filter(|name| !name.is_empty() && !name.contains('"'));
+
+    Some(name.ok_or_else(|| format!("malformed include directive: {}", trimmed)))
+}
+
+pub(crate) fn resolve(
+    including_file: &Path,
+    name: &str,
+    search_paths: &[PathBuf],
+) -> Option<PathBuf> {
+    let name = Path::new(name);
+    if name.is_absolute() {
+        return Some(name.to_path_buf()).filter(|p| p.is_file());
+    }
+
+    let local_dir = including_file.parent().map(Path::to_path_buf);
+    local_dir
+        .iter()
+        .chain(search_paths.iter())
+        .map(|dir| dir.join(name))
+        .find(|p| p.is_file())
+}
+
+fn canonical(p: &Path) -> PathBuf {
+    p.canonicalize().unwrap_or_else(|_| p.to_path_buf())
+}
+
+fn include_error(filename: &Path, line: usize, msg: &str) -> SceneError {
+    SceneError::Include(format!("{}:{}: {}", filename.display(), line, msg))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    #[test]
+    fn directive_recognition() {
+        assert_eq!(include_directive(r#"include "a.rg""#), Some(Ok("a.rg")));
+        assert_eq!(
+            include_directive(r#"   include   "lib/b.rg"  "#),
+            Some(Ok("lib/b.rg"))
+        );
+        assert_eq!(include_directive("sphere {}"), None);
+        assert_eq!(include_directive("included_thing {}"), None);
+        assert!(include_directive("include a.rg").unwrap().is_err());
+        assert!(include_directive(r#"include """#).unwrap().is_err());
+    }
+
+    #[test]
+    fn nested_includes_resolve_relative_to_including_file() {
+        let dir = ScratchDir::with_files(
+            "include-nested",
+            &[
+                ("scene.rg", "include \"lib/lights.rg\"\nsphere {}\n"),
+                ("lib/lights.rg", "include \"colours.rg\"\npoint_light {}\n"),
+                ("lib/colours.rg", "{% assign red = '{1, 0, 0}' %}\n"),
+            ],
+        );
+
+        let source = expand_includes(&dir.join("scene.rg"), &[]).unwrap();
+        assert_eq!(
+            source.text,
+            "{% assign red = '{1, 0, 0}' %}\npoint_light {}\nsphere {}\n"
+        );
+
+        let (file, line, _) = source.find_line("point_light {}").unwrap();
+        assert_eq!((file, line), (dir.join("lib/lights.rg").as_path(), 2));
+    }
+
+    #[test]
+    fn repeated_lines_are_traced_to_their_file() {
+        let dir = ScratchDir::with_files(
+            "include-repeated",
+            &[
+                ("scene.rg", "include \"lib/spheres.rg\"\nbox {}\n"),
+                ("lib/spheres.rg", "sphere {}\nsphere {}\nbox {}\n"),
+            ],
+        );
+
+        let source = expand_includes(&dir.join("scene.rg"), &[]).unwrap();
+        assert_eq!(source.find_line("sphere {}"), None);
+        assert_eq!(
+            source.find_file("sphere {}"),
+            Some(dir.join("lib/spheres.rg").as_path())
+        );
+        assert_eq!(source.find_file("box {}"), None);
+    }
+
+    #[test]
+    fn search_paths_are_used_in_order() {
+        let dir = ScratchDir::with_files(
+            "include-search",
+            &[
+                ("scenes/scene.rg", "include \"common.rg\"\n"),
+                ("first/common.rg", "box {}\n"),
+                ("second/common.rg", "plane {}\n"),
+            ],
+        );
+
+        let paths = vec![dir.join("first"), dir.join("second")];
+        let source = expand_includes(&dir.join("scenes/scene.rg"), &paths).unwrap();
+        assert_eq!(source.text, "box {}\n");
+    }
+
+    #[test]
+    fn missing_include_names_including_file() {
+        let dir = ScratchDir::with_files(
+            "include-missing",
+            &[("scene.rg", "sphere {}\ninclude \"nope.rg\"\n")],
+        );
+
+        match expand_includes(&dir.join("scene.rg"), &[]) {
+            Err(SceneError::Include(msg)) => {
+                assert!(msg.contains("scene.rg:2:"), "{}", msg);
+                assert!(msg.contains("nope.rg"), "{}", msg);
+            }
+            other => panic!("Expected include error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cycles_are_detected() {
+        let dir = ScratchDir::with_files(
+            "include-cycle",
+            &[
+                ("a.rg", "include \"b.rg\"\n"),
+                ("b.rg", "include \"a.rg\"\n"),
+            ],
+        );
+
+        match expand_includes(&dir.join("a.rg"), &[]) {
+            Err(SceneError::Include(msg)) => {
+                assert!(msg.contains("b.rg:1:"), "{}", msg);
+                assert!(msg.contains("cycle"), "{}", msg);
+            }
+            other => panic!("Expected include error, got {:?}", other),
+        }
+    }
+}
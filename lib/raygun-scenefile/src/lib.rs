@@ -4,54 +4,95 @@
 mod camera;
 mod colour;
 mod constructs;
+mod declaration;
+mod error;
+mod import;
+mod include;
 mod material;
 mod primitive;
+#[cfg(test)]
+mod scratch;
 mod transform;
 
 use std::{
-    convert::From,
-    fs::File,
-    io::{self, Read},
-    path::Path,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use liquid;
 use log::{debug, info};
-use nom::IResult;
+use nom::{character::complete::multispace0, multi::many0};
 
 use raygun_scene::Scene;
 
-use self::{camera::*, constructs::*, primitive::*};
+pub use self::error::Diagnostic;
+
+use self::{
+    camera::*,
+    constructs::*,
+    declaration::declaration,
+    error::{Expected, ParseResult, SyntaxError},
+    primitive::*,
+};
 
 // ////////////////////////////////////////////////////////////////////////////
 // top level scene file
 // ////////////////////////////////////////////////////////////////////////////
 
-fn scene_file<'a>(input: &'a [u8]) -> IResult<&'a [u8], Scene> {
-    let state = SceneRef::new(SceneState::default());
+fn scene_file<'a>(input: &'a [u8], state: SceneState) -> ParseResult<'a, Scene> {
+    let state = SceneRef::new(state);
 
-    let (text, cam) = camera(state.clone())(input)?;
-    primitives(state.clone())(text).map(|(i, objs)| {
-        let scene = Scene {
-            camera: cam,
-            objects: objs,
-        };
-        (i, scene)
-    })
+    let (text, _) = many0(declaration(state.clone()))(input)?;
+    let (text, cam) = camera(state.clone())(text)?;
+    let (text, objs) = primitives(state.clone())(text)?;
+
+    // anything left over is something we couldn't make sense of
+    let (text, _) = multispace0(text)?;
+    if !text.is_empty() {
+        let what = Expected::Description("an object or declaration");
+        return Err(nom::Err::Failure(SyntaxError::expected(text, what)));
+    }
+
+    let scene = Scene {
+        camera: cam,
+        objects: objs,
+    };
+    Ok((text, scene))
 }
 
 #[derive(Debug)]
 pub enum SceneError {
     FileError(io::Error),
     Template(String),
-    Scene(Vec<String>),
+    Include(String),
+    Import(String),
+    Scene(Vec<Diagnostic>),
+}
+
+///
+/// Controls how a scene file is loaded
+///
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    /// Values exposed to the scene template as Liquid globals (e.g. the
+    /// animation `clock`)
+    pub globals: liquid::Object,
+
+    /// Directories searched for included files that can't be found relative
+    /// to the including file
+    pub include_paths: Vec<PathBuf>,
 }
 
 fn to_template_error(e: liquid::Error) -> SceneError {
     SceneError::Template(e.to_string())
 }
 
-fn scene_template(source: &str) -> Result<Scene, SceneError> {
+fn scene_template(
+    source: &str,
+    globals: &liquid::Object,
+    mut state: SceneState,
+) -> Result<Scene, SceneError> {
     debug!("Compiling scene template...");
     liquid::ParserBuilder::with_stdlib()
         .build()
@@ -59,48 +100,80 @@ fn scene_template(source: &str) -> Result<Scene, SceneError> {
         .parse(source)
         .map_err(to_template_error)
         .and_then(|template| {
-            let mut globals = liquid::object!({});
-
             debug!("Rendering scene template...");
             template
-                .render(&mut globals)
+                .render(globals)
                 .map_err(to_template_error)
                 .and_then(|scene_text| {
                     let bytes: Vec<u8> = scene_text.as_bytes().to_vec();
+                    state.rendered = scene_text.clone();
 
                     // uncomment for debug
                     // File::create("scene.rso").unwrap().write(&bytes);
 
                     debug!("Parsing scene...");
-                    match scene_file(&bytes) {
-                        IResult::Ok((_, s)) => Ok(s),
-                        IResult::Err(nom::Err::Incomplete(_)) => Err(SceneError::Scene(vec![])),
-                        IResult::Err(nom::Err::Failure((_, err))) => {
-                            let errors = vec![String::from(err.description())];
-                            Err(SceneError::Scene(errors))
+                    let diagnostic = match scene_file(&bytes, state) {
+                        Ok((_, s)) => return Ok(s),
+                        Err(nom::Err::Incomplete(_)) => Diagnostic::at_offset(
+                            &scene_text,
+                            scene_text.len(),
+                            "unexpected end of file".to_owned(),
+                        ),
+                        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                            Diagnostic::new(&scene_text, &e)
                         }
-                        IResult::Err(_) => Err(SceneError::Scene(vec!["Unknown".to_owned()])),
-                    }
+                    };
+                    Err(SceneError::Scene(vec![diagnostic]))
                 })
         })
 }
 
 pub fn load_scene<P: AsRef<Path>>(filename: P) -> Result<Scene, SceneError> {
+    load_scene_with_options(filename, &LoadOptions::default())
+}
+
+pub fn load_scene_with_options<P: AsRef<Path>>(
+    filename: P,
+    options: &LoadOptions,
+) -> Result<Scene, SceneError> {
     info!("Loading scene from {:?}...", filename.as_ref());
 
-    File::open(filename)
-        .map_err(|e| SceneError::FileError(e))
-        .and_then(|mut f| {
-            let mut source = String::new();
-            f.read_to_string(&mut source)
-                .map_err(|e| SceneError::FileError(e))
-                .and_then(|_| scene_template(&source))
-        })
+    let source = Arc::new(include::expand_includes(
+        filename.as_ref(),
+        &options.include_paths,
+    )?);
+    let state = SceneState {
+        filename: filename.as_ref().to_path_buf(),
+        include_paths: options.include_paths.clone(),
+        source: source.clone(),
+        ..SceneState::default()
+    };
+    scene_template(&source.text, &options.globals, state).map_err(|err| match err {
+        SceneError::Scene(diagnostics) => SceneError::Scene(
+            diagnostics
+                .into_iter()
+                .map(|d| d.relocate(&source))
+                .collect(),
+        ),
+        err => err,
+    })
+}
+
+///
+/// Imports a scene from a glTF 2.0 file (`.gltf` or `.glb`), rather than
+/// from a scene file. The file's own camera is used if it has one, and
+/// otherwise the scene is framed from the front. Scenes without lights are
+/// lit from the camera.
+///
+pub fn import_gltf<P: AsRef<Path>>(filename: P) -> Result<Scene, SceneError> {
+    info!("Importing scene from {:?}...", filename.as_ref());
+    import::scene(filename.as_ref()).map_err(SceneError::Import)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::scratch::ScratchDir;
 
     #[test]
     fn no_such_file() {
@@ -112,4 +185,133 @@ mod test {
         let path = Path::new("../../scenes/example.rg").canonicalize().unwrap();
         load_scene(&path).unwrap();
     }
+
+    #[test]
+    fn included_files_are_templated() {
+        let dir = ScratchDir::with_files(
+            "include-scene",
+            &[
+                (
+                    "scene.rg",
+                    "camera { location: { 0, 0, -10 }, look_at: { 0, 0, 0 } }\ninclude \"spheres.rg\"\n",
+                ),
+                (
+                    "lib/spheres.rg",
+                    "{% for i in (1..count) %}sphere { radius: {{ i }} }\n{% endfor %}",
+                ),
+            ],
+        );
+
+        let options = LoadOptions {
+            globals: liquid::object!({ "count": 3 }),
+            include_paths: vec![dir.join("lib")],
+        };
+        let scene = load_scene_with_options(dir.join("scene.rg"), &options).unwrap();
+        assert_eq!(scene.objects.len(), 3);
+    }
+
+    #[test]
+    fn files_are_found_next_to_the_file_that_names_them() {
+        let dir = ScratchDir::with_files(
+            "include-files",
+            &[
+                (
+                    "scene.rg",
+                    "camera { location: { 0, 0, -10 }, look_at: { 0, 0, 0 } }\ninclude \"models/model.rg\"\n",
+                ),
+                ("models/model.rg", "mesh {\n    file: \"triangle.stl\"\n}\n"),
+                (
+                    "models/triangle.stl",
+                    "solid t\nfacet normal 0 0 -1\nouter loop\nvertex 0 0 0\nvertex 0 1 0\nvertex 1 0 0\nendloop\nendfacet\nendsolid t\n",
+                ),
+            ],
+        );
+
+        let scene = load_scene(dir.join("scene.rg")).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    #[test]
+    fn globals_are_visible_to_template() {
+        let source = r#"
+            camera { location: { 0, 0, -10 }, look_at: { 0, 0, 0 } }
+            sphere { radius: {{ clock | times: 2 }} }
+        "#;
+
+        let globals = liquid::object!({ "clock": 0.25 });
+        let scene = scene_template(source, &globals, SceneState::default()).unwrap();
+
+        let s = scene.objects[0]
+            .as_primitive::<raygun_primitives::Sphere>()
+            .unwrap();
+        assert_eq!(s.radius, 0.5);
+    }
+
+    fn scene_error(source: &str) -> Diagnostic {
+        match scene_template(source, &liquid::Object::new(), SceneState::default()) {
+            Err(SceneError::Scene(mut errs)) => errs.remove(0),
+            other => panic!("Expected a scene error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_reported() {
+        let d = scene_error("camera { location: { 0, 0, -10 } }\n\n  sphere { radus: 1 }\n");
+        assert_eq!((d.line, d.column), (3, 12));
+        assert_eq!(
+            d.message,
+            "expected `radius`, `centre`, `material`, `transform` or `}` in sphere"
+        );
+    }
+
+    #[test]
+    fn misplaced_commas_are_reported() {
+        let d = scene_error(
+            "camera { location: { 0, 0, -10 } }\nsphere { radius: 1,, centre: {0, 0, 0} }",
+        );
+        assert_eq!((d.line, d.column), (2, 20));
+        assert!(d.message.ends_with(" in sphere"), "{}", d.message);
+
+        let d = scene_error("camera { location: { 0, 0, -10 } }\nsphere { centre: {0, 0 0} }");
+        assert_eq!((d.line, d.column), (2, 24));
+        assert_eq!(d.message, "expected `,` in sphere.centre");
+    }
+
+    #[test]
+    fn bad_values_are_reported() {
+        let d = scene_error("camera { location: { 0, 0, -10 } }\nsphere { radius: big }");
+        assert_eq!((d.line, d.column), (2, 18));
+        assert_eq!(d.message, "expected a number in sphere.radius");
+
+        let d = scene_error("camera { location: { 0, 0, -10 } }\nsphere { centre: middle }");
+        assert_eq!((d.line, d.column), (2, 18));
+        assert_eq!(d.message, "`middle` has not been declared in sphere.centre");
+    }
+
+    #[test]
+    fn errors_are_located_in_the_original_source() {
+        let dir = ScratchDir::with_files(
+            "diagnostic-scene",
+            &[
+                (
+                    "scene.rg",
+                    "camera { location: { 0, 0, -10 } }\n{% for i in (1..3) %}\nsphere { radius: {{ i }} }\n{% endfor %}\ninclude \"lights.rg\"\n",
+                ),
+                (
+                    "lights.rg",
+                    "point_light {\n    colour: { 1, 1, 1 },\n    location { 0, 10, 0 }\n}\n",
+                ),
+            ],
+        );
+
+        let d = match load_scene(dir.join("scene.rg")) {
+            Err(SceneError::Scene(mut errs)) => errs.remove(0),
+            other => panic!("Expected a scene error, got {:?}", other.map(|_| ())),
+        };
+
+        assert_eq!(d.file, Some(dir.join("lights.rg")));
+        assert_eq!((d.line, d.column), (3, 13));
+        assert_eq!(d.message, "expected `:` in point_light");
+        assert_eq!(d.source_line, "    location { 0, 10, 0 }");
+    }
 }
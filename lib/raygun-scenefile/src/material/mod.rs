@@ -1,12 +1,16 @@
 mod pigment;
 
-use nom::{branch::alt, multi::separated_list, IResult};
+use nom::{branch::alt, combinator::map};
 
 use self::pigment::pigment;
 use super::constructs::*;
+use crate::{
+    declaration::{reference, Declaration},
+    error::ParseResult,
+};
 use raygun_material::{Finish, Material, Pigment};
 
-pub fn finish<'a>(_scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Finish> {
+pub fn finish<'a>(_scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Finish> {
     enum Arg {
         Opacity(f64),
         Reflection(f64),
@@ -16,16 +20,13 @@ pub fn finish<'a>(_scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Fi
     };
 
     move |input| {
-        let material_block = block(separated_list(
-            comma,
-            ws(alt((
-                map_named_value("opacity", real_number, Arg::Opacity),
-                map_named_value("reflection", real_number, Arg::Reflection),
-                map_named_value("ambient", real_number, Arg::Ambient),
-                map_named_value("diffuse", real_number, Arg::Diffuse),
-                map_named_value("highlight", real_number, Arg::Highlight),
-            ))),
-        ));
+        let material_block = fields(ws(alt((
+            map_named_value("opacity", real_number, Arg::Opacity),
+            map_named_value("reflection", real_number, Arg::Reflection),
+            map_named_value("ambient", real_number, Arg::Ambient),
+            map_named_value("diffuse", real_number, Arg::Diffuse),
+            map_named_value("highlight", real_number, Arg::Highlight),
+        ))));
 
         material_block(input).map(|(i, args)| {
             let mut result = Finish::default();
@@ -45,22 +46,19 @@ pub fn finish<'a>(_scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Fi
     }
 }
 
-pub fn material<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Material> {
+pub fn material<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Material> {
     enum Arg {
         Pigment(Pigment),
         Finish(Finish),
     };
 
     move |input| {
-        let material_block = block(separated_list(
-            comma,
-            ws(alt((
-                map_named_value("pigment", pigment(scene.clone()), Arg::Pigment),
-                map_named_value("finish", finish(scene.clone()), Arg::Finish),
-            ))),
-        ));
+        let material_block = fields(ws(alt((
+            map_named_value("pigment", pigment(scene.clone()), Arg::Pigment),
+            map_named_value("finish", finish(scene.clone()), Arg::Finish),
+        ))));
 
-        material_block(input).map(|(i, args)| {
+        let material_literal = map(material_block, |args| {
             let mut result = Material::default();
 
             for arg in args {
@@ -70,8 +68,13 @@ pub fn material<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], M
                 }
             }
 
-            (i, result)
-        })
+            result
+        });
+
+        alt((
+            material_literal,
+            reference(scene.clone(), Declaration::material),
+        ))(input)
     }
 }
 
@@ -79,6 +82,7 @@ pub fn material<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], M
 mod test {
     use super::*;
     use float_cmp::ApproxEqUlps;
+    use nom::IResult;
 
     #[test]
     fn parses_completely_specified_finish() {
@@ -1,24 +1,41 @@
-use nom::{bytes::complete::tag, sequence::preceded, IResult};
+use nom::{
+    branch::alt,
+    combinator::{map, opt},
+    sequence::preceded,
+};
 
-use crate::{colour::colour, constructs::*};
+use crate::{colour::colour, constructs::*, error::ParseResult};
 
-use raygun_material::Pigment;
+use raygun_material::{Colour, Pigment};
 
-fn solid_pigment<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Pigment> {
+fn solid_pigment<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Pigment> {
     let block_parser = block(map_named_value("colour", colour(scene), Pigment::Solid));
 
-    preceded(ws(tag("solid")), block_parser)
+    named_object("solid", block_parser)
 }
 
-pub fn pigment<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Pigment> {
-    //ws(alt((solid_pigment, )))(input)
-    ws(solid_pigment(scene))
+/*
+ * Parses a pigment that takes its colour from the object's vertex colours,
+ * written `vertex_colours`, or `vertex_colours { colour: ... }` to give a
+ * colour for objects without any.
+ */
+fn vertex_pigment<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Pigment> {
+    let block_parser = block(map_named_value("colour", colour(scene), |c| c));
+
+    map(
+        preceded(ws(keyword("vertex_colours")), opt(block_parser)),
+        |c: Option<Colour>| Pigment::Vertex(c.unwrap_or_default()),
+    )
+}
+
+pub fn pigment<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Pigment> {
+    ws(alt((solid_pigment(scene.clone()), vertex_pigment(scene))))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use raygun_material::Colour;
+    use nom::IResult;
 
     #[test]
     fn solid_pigment() {
@@ -28,11 +45,24 @@ mod test {
         let scene = SceneRef::default();
 
         match pigment(scene)(text.as_bytes()) {
-            IResult::Ok((_, p)) => {
-                let Pigment::Solid(c) = p;
-                assert_eq!(c, Colour::new(0.1, 0.2, 0.3))
-            }
+            IResult::Ok((_, Pigment::Solid(c))) => assert_eq!(c, Colour::new(0.1, 0.2, 0.3)),
+            IResult::Ok((_, p)) => panic!("Expected a solid pigment, got {:?}", p),
             IResult::Err(e) => assert!(false, "Parse failed: {:?}", e),
         }
     }
+
+    #[test]
+    fn vertex_pigment() {
+        let scene = SceneRef::default();
+        match pigment(scene.clone())(b"vertex_colours") {
+            IResult::Ok((_, Pigment::Vertex(c))) => assert_eq!(c, Colour::default()),
+            other => panic!("Expected a vertex pigment, got {:?}", other),
+        }
+
+        let text = b"vertex_colours { colour: { 0.1, 0.2, 0.3 } }";
+        match pigment(scene)(text) {
+            IResult::Ok((_, Pigment::Vertex(c))) => assert_eq!(c, Colour::new(0.1, 0.2, 0.3)),
+            other => panic!("Expected a vertex pigment, got {:?}", other),
+        }
+    }
 }
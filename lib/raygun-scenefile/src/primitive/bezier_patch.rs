@@ -0,0 +1,85 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Point3, Transform};
+use raygun_primitives::{BezierPatch, Object};
+
+///
+/// Parses a bicubic Bézier patch, given as its 16 control points a row at a
+/// time
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Points(Vec<Point3>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let points = fields(ws(vector(scene.clone())));
+        let parse_args = named_object(
+            "bezier_patch",
+            fields(alt((
+                map_named_value("points", points, |ps| {
+                    Arg::Points(ps.into_iter().map(Point3::from).collect())
+                }),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut points = Vec::new();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Points(p) => points = p,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if points.len() != 16 {
+            let msg = format!("a Bézier patch needs 16 points, not {}", points.len());
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+        let mut grid = [Point3::default(); 16];
+        grid.copy_from_slice(&points);
+
+        Ok((rest, as_object(BezierPatch::new(grid), mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::point;
+    use raygun_primitives::BezierPatch;
+
+    #[test]
+    fn parse_bezier_patch() {
+        let text = b"bezier_patch { points: {
+            {0, 0, 0}, {1, 0, 0}, {2, 0, 0}, {3, 0, 0},
+            {0, 0, 1}, {1, 1, 1}, {2, 1, 1}, {3, 0, 1},
+            {0, 0, 2}, {1, 1, 2}, {2, 1, 2}, {3, 0, 2},
+            {0, 0, 3}, {1, 0, 3}, {2, 0, 3}, {3, 0, 3}
+        } }";
+        let (_, obj) = super::parse(SceneRef::default())(text).unwrap();
+
+        let p = obj.as_primitive::<BezierPatch>().unwrap();
+        assert_eq!(p.points()[5], point(1.0, 1.0, 1.0));
+
+        let text = b"bezier_patch { points: { {0, 0, 0}, {1, 0, 0} } }";
+        assert!(super::parse(SceneRef::default())(text).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::Transform;
+use raygun_primitives::{BezierPatches, Object};
+
+///
+/// Parses a set of Bézier patches loaded from a file in the Utah teapot
+/// format, e.g.
+///
+/// ```text
+/// bezier_patches {
+///     file: "teapot.bpt",
+///     transform: { rotate: {-90, 0, 0} }
+/// }
+/// ```
+///
+/// The file is looked for alongside the scene file that names it, and then in
+/// the include paths.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        File(String),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "bezier_patches",
+            fields(alt((
+                map_named_value("file", string, Arg::File),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut file = None;
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::File(f) => file = Some(f),
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+
+        let file = match file {
+            Some(f) => f,
+            None => return fail("a set of Bézier patches needs a file to load".to_owned()),
+        };
+        let path = match scene.borrow().find_file(&file, input) {
+            Some(p) => p,
+            None => return fail(format!("can't find Bézier patch file \"{}\"", file)),
+        };
+        match BezierPatches::load(&path) {
+            Ok(patches) => Ok((rest, as_object(patches, mat, xform))),
+            Err(msg) => fail(msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    #[test]
+    fn parse_bezier_patches() {
+        let dir = ScratchDir::new("bezier-patches");
+        let mut text = String::from("2\n");
+        text.push_str("1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16\n");
+        text.push_str("4, 3, 2, 1, 8, 7, 6, 5, 12, 11, 10, 9, 16, 15, 14, 13\n16\n");
+        for n in 0..16 {
+            text.push_str(&format!("{}, {}, 0\n", n % 4, n / 4));
+        }
+        dir.write("square.bpt", text);
+
+        let text = br#"bezier_patches { file: "square.bpt" }"#;
+        let (_, obj) = super::parse(dir.scene())(text).unwrap();
+
+        let patches = obj.as_primitive::<BezierPatches>().unwrap();
+        assert_eq!(patches.patches().len(), 2);
+    }
+}
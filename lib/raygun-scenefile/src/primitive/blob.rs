@@ -0,0 +1,160 @@
+use nom::{branch::alt, combinator::map, multi::many0};
+
+use crate::{constructs::*, error::ParseResult, material::*, transform::*, SceneRef};
+use raygun_material::Material;
+use raygun_math::{point, Float, Point3, Transform, Vector3};
+use raygun_primitives::{blob::Component, Blob, Object};
+
+///
+/// Parses a blob and its components, e.g.
+///
+/// ```text
+/// blob {
+///     threshold: 0.6,
+///     components: {
+///         sphere { centre: {0, 0, 0}, radius: 1, strength: 1 }
+///         cylinder { base: {0, 0, 0}, cap: {0, 2, 0}, radius: 0.5, strength: -0.5 }
+///     }
+/// }
+/// ```
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Threshold(Float),
+        Components(Vec<Component>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let components = ws(block(many0(component(scene.clone()))));
+        let parse_args = named_object(
+            "blob",
+            fields(alt((
+                map_named_value("threshold", float_value, Arg::Threshold),
+                map_named_value("components", components, Arg::Components),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        parse_args(input).map(|(i, args)| {
+            let mut b = Blob::default();
+            let mut mat = Material::default();
+            let mut xform = None;
+
+            for arg in args {
+                match arg {
+                    Arg::Threshold(t) => b.threshold = t,
+                    Arg::Components(c) => b.components.extend(c),
+                    Arg::Mat(m) => mat = m,
+                    Arg::XForm(x) => xform = Some(x),
+                }
+            }
+
+            (i, as_object(b, mat, xform))
+        })
+    }
+}
+
+fn component<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Component> {
+    enum Arg {
+        Centre(Vector3),
+        Base(Vector3),
+        Cap(Vector3),
+        Radius(Float),
+        Strength(Float),
+    }
+
+    move |input| {
+        let radius = || map_named_value("radius", float_value, Arg::Radius);
+        let strength = || map_named_value("strength", float_value, Arg::Strength);
+
+        let sphere = named_object(
+            "sphere",
+            fields(alt((
+                map_named_value("centre", vector(scene.clone()), Arg::Centre),
+                radius(),
+                strength(),
+            ))),
+        );
+        let cylinder = named_object(
+            "cylinder",
+            fields(alt((
+                map_named_value("base", vector(scene.clone()), Arg::Base),
+                map_named_value("cap", vector(scene.clone()), Arg::Cap),
+                radius(),
+                strength(),
+            ))),
+        );
+
+        let build = |args: Vec<Arg>, cylinder: bool| {
+            let (mut centre, mut cap) = (Point3::default(), point(0.0, 1.0, 0.0));
+            let (mut radius, mut strength) = (1.0, 1.0);
+            for arg in args {
+                match arg {
+                    Arg::Centre(c) | Arg::Base(c) => centre = c.into(),
+                    Arg::Cap(c) => cap = c.into(),
+                    Arg::Radius(r) => radius = r,
+                    Arg::Strength(s) => strength = s,
+                }
+            }
+
+            if cylinder {
+                Component::Cylinder {
+                    base: centre,
+                    cap,
+                    radius,
+                    strength,
+                }
+            } else {
+                Component::Sphere {
+                    centre,
+                    radius,
+                    strength,
+                }
+            }
+        };
+
+        alt((
+            map(sphere, move |args| build(args, false)),
+            map(cylinder, move |args| build(args, true)),
+        ))(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_blob() {
+        let text = br#"blob {
+            threshold: 0.5,
+            components: {
+                sphere { centre: {1, 2, 3}, radius: 2 }
+                cylinder { base: {0, 0, 0}, cap: {0, 0, 4}, radius: 0.5, strength: -1 }
+            }
+        }"#;
+        let (_, obj) = super::parse(SceneRef::default())(text).unwrap();
+
+        let b = obj.as_primitive::<Blob>().unwrap();
+        assert_eq!(b.threshold, 0.5);
+        assert_eq!(
+            b.components,
+            vec![
+                Component::Sphere {
+                    centre: point(1.0, 2.0, 3.0),
+                    radius: 2.0,
+                    strength: 1.0,
+                },
+                Component::Cylinder {
+                    base: point(0.0, 0.0, 0.0),
+                    cap: point(0.0, 0.0, 4.0),
+                    radius: 0.5,
+                    strength: -1.0,
+                },
+            ]
+        );
+    }
+}
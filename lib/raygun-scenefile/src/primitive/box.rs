@@ -1,14 +1,14 @@
-use nom::{branch::alt, combinator::map, multi::separated_list, IResult};
+use nom::{branch::alt, combinator::map};
 
-use crate::{constructs::*, material::*, transform::*, SceneRef};
+use crate::{constructs::*, error::ParseResult, material::*, transform::*, SceneRef};
 use raygun_material::Material;
-use raygun_math::{Point, Transform};
+use raygun_math::{Transform, Vector3};
 use raygun_primitives::{AxisAlignedBox, Box, Object};
 
-pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
     enum Arg {
-        Upper(Point),
-        Lower(Point),
+        Upper(Vector3),
+        Lower(Vector3),
         Mat(Material),
         XForm(Transform),
     };
@@ -16,15 +16,12 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
     move |input| {
         let parse_args = named_object(
             "box",
-            block(separated_list(
-                comma,
-                alt((
-                    map_named_value("upper", vector_literal, Arg::Upper),
-                    map_named_value("lower", vector_literal, Arg::Lower),
-                    map_named_value("material", material(scene.clone()), Arg::Mat),
-                    map_named_value("transform", transform, Arg::XForm),
-                )),
-            )),
+            fields(alt((
+                map_named_value("upper", vector(scene.clone()), Arg::Upper),
+                map_named_value("lower", vector(scene.clone()), Arg::Lower),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
         );
 
         let construct_box = |args: Vec<Arg>| -> Object {
@@ -34,8 +31,8 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
 
             for arg in args {
                 match arg {
-                    Arg::Upper(r) => aab.upper = r,
-                    Arg::Lower(c) => aab.lower = c,
+                    Arg::Upper(r) => aab.upper = r.into(),
+                    Arg::Lower(c) => aab.lower = c.into(),
                     Arg::Mat(m) => mat = m,
                     Arg::XForm(x) => xform = Some(x),
                 }
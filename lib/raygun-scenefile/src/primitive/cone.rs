@@ -0,0 +1,109 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform, Vector3};
+use raygun_primitives::{Cone, Object};
+
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Base(Vector3),
+        BaseRadius(Float),
+        Cap(Vector3),
+        CapRadius(Float),
+        Open(bool),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "cone",
+            fields(alt((
+                map_named_value("base", vector(scene.clone()), Arg::Base),
+                map_named_value("base_radius", float_value, Arg::BaseRadius),
+                map_named_value("cap", vector(scene.clone()), Arg::Cap),
+                map_named_value("cap_radius", float_value, Arg::CapRadius),
+                map_named_value("open", boolean, Arg::Open),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut c = Cone::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Base(b) => c.base = b.into(),
+                Arg::BaseRadius(r) => c.base_radius = r,
+                Arg::Cap(t) => c.cap = t.into(),
+                Arg::CapRadius(r) => c.cap_radius = r,
+                Arg::Open(o) => c.open = o,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if c.base == c.cap {
+            let msg = "a cone's base and cap must be different points".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+        if c.base_radius < 0.0 || c.cap_radius < 0.0 {
+            let msg = "a cone's radii can't be negative".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+
+        Ok((rest, as_object(c, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::point;
+    use raygun_primitives::Cone;
+
+    #[test]
+    fn parse_cone() {
+        let state = SceneRef::default();
+        let text = b"cone { base: {0, 0, 0}, base_radius: 2, cap: {0, 3, 0}, cap_radius: 1 }";
+        let (_, obj) = super::parse(state)(text).unwrap();
+
+        let c = obj.as_primitive::<Cone>().unwrap();
+        assert_eq!(c.base, point(0.0, 0.0, 0.0));
+        assert_eq!(c.base_radius, 2.0);
+        assert_eq!(c.cap, point(0.0, 3.0, 0.0));
+        assert_eq!(c.cap_radius, 1.0);
+        assert!(!c.open);
+    }
+
+    #[test]
+    fn matching_base_and_cap_is_an_error() {
+        let state = SceneRef::default();
+        let text = b"cone { base: {0, 1, 0}, cap: {0, 1, 0} }";
+        match super::parse(state)(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn negative_radius_is_an_error() {
+        let state = SceneRef::default();
+        let text = b"cone { base_radius: 1, cap_radius: -0.5 }";
+        match super::parse(state)(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform, Vector3};
+use raygun_primitives::{Cylinder, Object};
+
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Base(Vector3),
+        Cap(Vector3),
+        Radius(Float),
+        Open(bool),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "cylinder",
+            fields(alt((
+                map_named_value("base", vector(scene.clone()), Arg::Base),
+                map_named_value("cap", vector(scene.clone()), Arg::Cap),
+                map_named_value("radius", float_value, Arg::Radius),
+                map_named_value("open", boolean, Arg::Open),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut c = Cylinder::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Base(b) => c.base = b.into(),
+                Arg::Cap(t) => c.cap = t.into(),
+                Arg::Radius(r) => c.radius = r,
+                Arg::Open(o) => c.open = o,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if c.base == c.cap {
+            let msg = "a cylinder's base and cap must be different points".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+        if c.radius < 0.0 {
+            let msg = "a cylinder's radius can't be negative".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+
+        Ok((rest, as_object(c, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::point;
+    use raygun_primitives::Cylinder;
+
+    #[test]
+    fn parse_default() {
+        let state = SceneRef::default();
+        let (_, obj) = super::parse(state)(b"cylinder { }").unwrap();
+
+        let c = obj.as_primitive::<Cylinder>().unwrap();
+        assert_eq!(*c, Cylinder::default());
+    }
+
+    #[test]
+    fn parse_cylinder() {
+        let state = SceneRef::default();
+        let text = b"cylinder { base: {1, 2, 3}, cap: {4, 5, 6}, radius: 0.5, open: true }";
+        let (_, obj) = super::parse(state)(text).unwrap();
+
+        let c = obj.as_primitive::<Cylinder>().unwrap();
+        assert_eq!(c.base, point(1.0, 2.0, 3.0));
+        assert_eq!(c.cap, point(4.0, 5.0, 6.0));
+        assert_eq!(c.radius, 0.5);
+        assert!(c.open);
+    }
+
+    #[test]
+    fn matching_base_and_cap_is_an_error() {
+        let state = SceneRef::default();
+        let text = b"cylinder { base: {1, 2, 3}, cap: {1, 2, 3} }";
+        match super::parse(state)(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn negative_radius_is_an_error() {
+        let state = SceneRef::default();
+        let text = b"cylinder { radius: -1 }";
+        match super::parse(state)(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform, Vector3};
+use raygun_primitives::{Disc, Object};
+
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Centre(Vector3),
+        Normal(Vector3),
+        Radius(Float),
+        InnerRadius(Float),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "disc",
+            fields(alt((
+                map_named_value("centre", vector(scene.clone()), Arg::Centre),
+                map_named_value("normal", vector(scene.clone()), Arg::Normal),
+                map_named_value("radius", float_value, Arg::Radius),
+                map_named_value("inner_radius", float_value, Arg::InnerRadius),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut d = Disc::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Centre(c) => d.centre = c.into(),
+                Arg::Normal(n) if n == Vector3::default() => {
+                    let msg = "a disc's normal can't be zero".to_owned();
+                    return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+                }
+                Arg::Normal(n) => d.normal = n.normalize().into(),
+                Arg::Radius(r) => d.radius = r,
+                Arg::InnerRadius(r) => d.inner_radius = r,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if d.radius <= 0.0 {
+            let msg = "a disc's radius must be greater than zero".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+        if d.inner_radius < 0.0 || d.inner_radius >= d.radius {
+            let msg = "a disc's inner radius must be between zero and its radius".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+
+        Ok((rest, as_object(d, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::{normal, point};
+    use raygun_primitives::Disc;
+
+    #[test]
+    fn parse_disc() {
+        let state = SceneRef::default();
+        let text = b"disc { centre: {1, 2, 3}, normal: {0, 0, 2}, radius: 4, inner_radius: 1 }";
+        let (_, obj) = super::parse(state)(text).unwrap();
+
+        let d = obj.as_primitive::<Disc>().unwrap();
+        assert_eq!(d.centre, point(1.0, 2.0, 3.0));
+        assert_eq!(d.normal, normal(0.0, 0.0, 1.0));
+        assert_eq!(d.radius, 4.0);
+        assert_eq!(d.inner_radius, 1.0);
+    }
+
+    #[test]
+    fn degenerate_discs_are_errors() {
+        for text in [
+            &b"disc { normal: {0, 0, 0} }"[..],
+            b"disc { radius: 0 }",
+            b"disc { radius: -1 }",
+            b"disc { radius: 2, inner_radius: 2 }",
+            b"disc { radius: 2, inner_radius: -1 }",
+        ]
+        .iter()
+        {
+            match super::parse(SceneRef::default())(text) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
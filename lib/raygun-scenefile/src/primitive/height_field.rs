@@ -0,0 +1,113 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::Transform;
+use raygun_primitives::{HeightField, Object};
+
+///
+/// Parses a height field loaded from a grayscale image, e.g.
+///
+/// ```text
+/// height_field {
+///     file: "hills.png",
+///     smooth: true,
+///     transform: { scale: {10, 2, 10} }
+/// }
+/// ```
+///
+/// The image is looked for alongside the scene file that names it, and then
+/// in the include paths.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        File(String),
+        Smooth(bool),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "height_field",
+            fields(alt((
+                map_named_value("file", string, Arg::File),
+                map_named_value("smooth", boolean, Arg::Smooth),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut file = None;
+        let mut smooth = false;
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::File(f) => file = Some(f),
+                Arg::Smooth(s) => smooth = s,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+
+        let file = match file {
+            Some(f) => f,
+            None => return fail("a height field needs an image file".to_owned()),
+        };
+        let path = match scene.borrow().find_file(&file, input) {
+            Some(p) => p,
+            None => return fail(format!("can't find height field image \"{}\"", file)),
+        };
+        let mut field = match HeightField::load(&path) {
+            Ok(f) => f,
+            Err(msg) => return fail(msg),
+        };
+        field.smooth = smooth;
+
+        Ok((rest, as_object(field, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::ScratchDir;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn parse_height_field() {
+        let dir = ScratchDir::new("height-field");
+        GrayImage::from_fn(4, 3, |x, y| Luma([(x * 10 + y) as u8]))
+            .save(dir.join("hills.png"))
+            .unwrap();
+
+        let text = br#"height_field { file: "hills.png", smooth: true }"#;
+        let (_, obj) = super::parse(dir.scene())(text).unwrap();
+
+        let h = obj.as_primitive::<HeightField>().unwrap();
+        assert!(h.smooth);
+        assert_eq!((h.columns(), h.rows()), (4, 3));
+    }
+
+    #[test]
+    fn missing_images_are_reported() {
+        let dir = ScratchDir::new("height-field-missing");
+        let text = br#"height_field { file: "nowhere.png" }"#;
+        match super::parse(dir.scene())(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    import,
+    transform::*,
+    SceneRef,
+};
+use raygun_math::Transform;
+use raygun_primitives::Object;
+
+///
+/// Parses a scene imported from a glTF 2.0 file, e.g.
+///
+/// ```text
+/// import_gltf {
+///     file: "room.glb",
+///     transform: { scale: {2, 2, 2} }
+/// }
+/// ```
+///
+/// The file's nodes become nested unions, with its own materials and lights.
+/// Any cameras in it are ignored, as the scene file has its own. The file is
+/// looked for alongside the scene file that names it, and then in the include
+/// paths.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        File(String),
+        XForm(Box<Transform>),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "import_gltf",
+            fields(alt((
+                map_named_value("file", string, Arg::File),
+                map_named_value("transform", transform(scene.clone()), |t| {
+                    Arg::XForm(Box::new(t))
+                }),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut file = None;
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::File(f) => file = Some(f),
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+
+        let file = match file {
+            Some(f) => f,
+            None => return fail("import_gltf needs a file".to_owned()),
+        };
+        let path = match scene.borrow().find_file(&file, input) {
+            Some(p) => p,
+            None => return fail(format!("can't find glTF file \"{}\"", file)),
+        };
+        let root = match import::load(&path) {
+            Ok(i) => i.root,
+            Err(msg) => return fail(msg),
+        };
+
+        Ok((
+            rest,
+            Object {
+                transform: xform,
+                ..root
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scratch::ScratchDir;
+    use raygun_math::point;
+    use raygun_primitives::{PointLight, Union};
+
+    #[test]
+    fn parse_import() {
+        let dir = ScratchDir::new("import-gltf");
+        dir.write(
+            "lamp.gltf",
+            r#"{
+                "asset": { "version": "2.0" },
+                "extensionsUsed": ["KHR_lights_punctual"],
+                "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "point" }] } },
+                "scenes": [{ "nodes": [0] }],
+                "nodes": [{
+                    "translation": [1, 2, 3],
+                    "extensions": { "KHR_lights_punctual": { "light": 0 } }
+                }]
+            }"#,
+        );
+
+        let text = br#"import_gltf {
+            file: "lamp.gltf",
+            transform: { translate: {10, 0, 0} }
+        }"#;
+        let (_, obj) = super::parse(dir.scene())(text).unwrap();
+
+        let root = obj.as_primitive::<Union>().unwrap();
+        assert_eq!(root.children.len(), 1);
+        let t = obj.transform.as_ref().unwrap();
+        assert_eq!(t.matrix * point(0.0, 0.0, 0.0), point(10.0, 0.0, 0.0));
+
+        let node = root.children[0].as_primitive::<Union>().unwrap();
+        let t = root.children[0].transform.as_ref().unwrap();
+        assert_eq!(t.matrix * point(0.0, 0.0, 0.0), point(1.0, 2.0, -3.0));
+        assert!(node.children[0].as_primitive::<PointLight>().is_some());
+    }
+
+    #[test]
+    fn bad_imports_are_reported() {
+        let dir =
+            ScratchDir::with_files("import-gltf-bad", &[("broken.gltf", "{ \"asset\": {} }")]);
+
+        for text in [
+            &br#"import_gltf { }"#[..],
+            br#"import_gltf { file: "nowhere.glb" }"#,
+            br#"import_gltf { file: "broken.gltf" }"#,
+        ]
+        .iter()
+        {
+            match super::parse(dir.scene())(text) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform};
+use raygun_primitives::{Lathe, Object, Spline};
+
+///
+/// Parses a surface of revolution, given as a profile of `{radius, height}`
+/// points spun around the y axis, e.g.
+///
+/// ```text
+/// lathe {
+///     spline: cubic,
+///     points: { {0, -1}, {0, 0}, {1, 0.5}, {0.4, 2}, {0.6, 3}, {0.6, 4} }
+/// }
+/// ```
+///
+/// The spline defaults to `linear`.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Spline(Spline),
+        Points(Vec<(Float, Float)>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "lathe",
+            fields(alt((
+                map_named_value("spline", super::spline, Arg::Spline),
+                map_named_value("points", fields(ws(pair)), Arg::Points),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut spline = Spline::Linear;
+        let mut points = Vec::new();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Spline(s) => spline = s,
+                Arg::Points(p) => points = p,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        match Lathe::new(spline, points) {
+            Some(l) => Ok((rest, as_object(l, mat, xform))),
+            None => {
+                let msg = super::point_count_message(spline);
+                Err(nom::Err::Failure(SyntaxError::message(input, msg)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_primitives::{Lathe, Spline};
+
+    #[test]
+    fn parse_lathe() {
+        let text = b"lathe { spline: quadratic, points: { {0, 0}, {2, 1}, {0, 2} } }";
+        let (_, obj) = super::parse(SceneRef::default())(text).unwrap();
+
+        let l = obj.as_primitive::<Lathe>().unwrap();
+        assert_eq!(l.spline(), Spline::Quadratic);
+        assert_eq!(l.points(), &[(0.0, 0.0), (2.0, 1.0), (0.0, 2.0)]);
+    }
+
+    #[test]
+    fn wrong_point_counts_are_errors() {
+        let text = b"lathe { spline: cubic, points: { {0, 0}, {2, 1}, {0, 2} } }";
+        match super::parse(SceneRef::default())(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::Transform;
+use raygun_primitives::{Mesh, Object};
+
+///
+/// Parses a triangle mesh loaded from a file, e.g.
+///
+/// ```text
+/// mesh {
+///     file: "scan.ply",
+///     material: { pigment: vertex_colours },
+///     transform: { scale: {10, 10, 10} }
+/// }
+/// ```
+///
+/// The file's format is chosen by its extension: `.ply`, `.stl` or `.obj`.
+/// It's looked for alongside the scene file that names it, and then in the
+/// include paths.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        File(String),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "mesh",
+            fields(alt((
+                map_named_value("file", string, Arg::File),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut file = None;
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::File(f) => file = Some(f),
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+
+        let file = match file {
+            Some(f) => f,
+            None => return fail("a mesh needs a file".to_owned()),
+        };
+        let path = match scene.borrow().find_file(&file, input) {
+            Some(p) => p,
+            None => return fail(format!("can't find mesh file \"{}\"", file)),
+        };
+        let mesh = match Mesh::load(&path) {
+            Ok(m) => m,
+            Err(msg) => return fail(msg),
+        };
+
+        Ok((rest, as_object(mesh, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::ScratchDir;
+    use raygun_material::Pigment;
+
+    #[test]
+    fn parse_mesh() {
+        let dir = ScratchDir::new("mesh");
+        dir.write(
+            "triangle.stl",
+            "solid t
+                facet normal 0 0 -1
+                    outer loop
+                        vertex 0 0 0
+                        vertex 0 1 0
+                        vertex 1 0 0
+                    endloop
+                endfacet
+            endsolid t",
+        );
+
+        let text = br#"mesh {
+            file: "triangle.stl",
+            material: { pigment: vertex_colours }
+        }"#;
+        let (_, obj) = super::parse(dir.scene())(text).unwrap();
+
+        let m = obj.as_primitive::<Mesh>().unwrap();
+        assert_eq!(m.triangles().len(), 1);
+        assert!(matches!(obj.material.pigment, Pigment::Vertex(_)));
+    }
+
+    #[test]
+    fn parse_obj_mesh() {
+        let dir = ScratchDir::with_files(
+            "mesh-obj",
+            &[("triangle.obj", "v 0 0 0\nv 0 1 0\nv 1 0 0\nf 1 2 3\n")],
+        );
+
+        let text = br#"mesh { file: "triangle.obj" }"#;
+        let (_, obj) = super::parse(dir.scene())(text).unwrap();
+
+        let m = obj.as_primitive::<Mesh>().unwrap();
+        assert_eq!(m.triangles(), &[[0, 1, 2]]);
+    }
+
+    #[test]
+    fn unknown_formats_are_reported() {
+        let dir = ScratchDir::with_files("mesh-unknown", &[("model.3ds", "")]);
+
+        for text in [
+            &br#"mesh { file: "model.3ds" }"#[..],
+            br#"mesh { file: "nowhere.ply" }"#,
+        ]
+        .iter()
+        {
+            match super::parse(dir.scene())(text) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
@@ -1,34 +1,167 @@
 use std::sync::Arc;
 
-use nom::{branch::alt, combinator::map, multi::many0, IResult};
+use nom::{
+    branch::alt,
+    combinator::{map, value},
+    multi::many0,
+};
 
-use raygun_primitives::Object;
+use raygun_primitives::{Object, Spline};
 
-use crate::{constructs::*, SceneRef};
+use crate::{
+    constructs::*,
+    declaration::{declaration, reference, Declaration},
+    error::{Expected, ParseResult, SyntaxError},
+    SceneRef,
+};
 
+mod bezier_patch;
+mod bezier_patches;
+mod blob;
 mod r#box;
+mod cone;
+mod cylinder;
+mod disc;
+mod height_field;
+mod import_gltf;
+mod lathe;
+mod mesh;
+mod object;
 mod plane;
 mod point_light;
+mod polygon;
+mod rectangle;
+mod sdf;
 mod sphere;
+mod sweep;
+mod text;
+mod torus;
 mod union;
 
-fn primitive<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Arc<Object>> {
+///
+/// The words that introduce each kind of object, which can't be used as
+/// declaration names. Keep this in step with the parsers in `primitive`.
+///
+pub const KEYWORDS: &[&str] = &[
+    "sphere",
+    "box",
+    "cylinder",
+    "cone",
+    "torus",
+    "blob",
+    "disc",
+    "rectangle",
+    "polygon",
+    "bezier_patches",
+    "bezier_patch",
+    "lathe",
+    "sweep",
+    "text",
+    "sdf",
+    "height_field",
+    "mesh",
+    "import_gltf",
+    "plane",
+    "point_light",
+    "spot_light",
+    "union",
+    "object",
+];
+
+///
+/// Parses a single object definition, or a reference to a declared object
+///
+pub fn primitive<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Arc<Object>> {
+    // nom's alt only takes so many parsers at a time, so they're split in two
     let p = ws(alt((
-        sphere::parse(scene.clone()),
-        r#box::parse(scene.clone()),
-        plane::parse(scene.clone()),
-        point_light::parse(scene.clone()),
-        union::parse(scene.clone()),
+        alt((
+            sphere::parse(scene.clone()),
+            r#box::parse(scene.clone()),
+            cylinder::parse(scene.clone()),
+            cone::parse(scene.clone()),
+            torus::parse(scene.clone()),
+            blob::parse(scene.clone()),
+            disc::parse(scene.clone()),
+            rectangle::parse(scene.clone()),
+            polygon::parse(scene.clone()),
+            bezier_patches::parse(scene.clone()),
+            bezier_patch::parse(scene.clone()),
+        )),
+        alt((
+            lathe::parse(scene.clone()),
+            sweep::parse(scene.clone()),
+            text::parse(scene.clone()),
+            sdf::parse(scene.clone()),
+            height_field::parse(scene.clone()),
+            mesh::parse(scene.clone()),
+            import_gltf::parse(scene.clone()),
+            plane::parse(scene.clone()),
+            point_light::parse(scene.clone()),
+            union::parse(scene.clone()),
+            object::parse(scene.clone()),
+        )),
     )));
 
-    map(p, Arc::new)
+    alt((map(p, Arc::new), ws(reference(scene, Declaration::object))))
+}
+
+///
+/// Parses a list of objects, interleaved with any number of declarations
+///
+pub fn primitives<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Vec<Arc<Object>>> {
+    let statement = alt((
+        map(declaration(scene.clone()), |_| None),
+        map(primitive(scene), Some),
+    ));
+
+    map(many0(statement), |objs| {
+        objs.into_iter().flatten().collect()
+    })
 }
 
-pub fn primitives<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Vec<Arc<Object>>> {
-    many0(primitive(scene))
+///
+/// Parses the kind of spline joining up a list of points: `linear`,
+/// `quadratic` or `cubic`
+///
+fn spline(input: &[u8]) -> ParseResult<'_, Spline> {
+    alt((
+        value(Spline::Linear, keyword("linear")),
+        value(Spline::Quadratic, keyword("quadratic")),
+        value(Spline::Cubic, keyword("cubic")),
+    ))(input)
+    .map_err(|e: nom::Err<SyntaxError>| {
+        e.map(|_| SyntaxError::expected(input, Expected::Description("linear, quadratic or cubic")))
+    })
+}
+
+///
+/// Explains how many points each kind of spline needs
+///
+fn point_count_message(spline: Spline) -> String {
+    match spline {
+        Spline::Linear => "a linear spline needs at least 2 points",
+        Spline::Quadratic => "a quadratic spline needs an odd number of points, at least 3",
+        Spline::Cubic => "a cubic spline needs at least 4 points",
+    }
+    .to_owned()
 }
 
 #[cfg(test)]
 mod test {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn keywords_introduce_objects() {
+        // spot lights only come from imported files, but the name is
+        // reserved all the same
+        for kw in KEYWORDS.iter().filter(|&&kw| kw != "spot_light") {
+            let text = format!("{} {{ }}", kw);
+            let parsed = primitive(SceneRef::default())(text.as_bytes()).map(|_| ());
+            assert!(
+                !matches!(parsed, Err(nom::Err::Error(_))),
+                "`{}` isn't parsed as an object",
+                kw
+            );
+        }
+    }
 }
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use nom::branch::alt;
+
+use raygun_material::Material;
+use raygun_math::Transform;
+use raygun_primitives::Object;
+
+use crate::{
+    constructs::*,
+    declaration::{reference, Declaration},
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+
+///
+/// Parses a new instance of a declared object, optionally overriding its
+/// material and applying an additional transform. The instance shares the
+/// underlying primitive with the declared object.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Name(Arc<Object>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let (i, args) = named_object(
+            "object",
+            fields(alt((
+                map_named_value(
+                    "name",
+                    reference(scene.clone(), Declaration::object),
+                    Arg::Name,
+                ),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        )(input)?;
+
+        let mut source = None;
+        let mut mat = None;
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Name(o) => source = Some(o),
+                Arg::Mat(m) => mat = Some(m),
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let source = source.ok_or_else(|| {
+            let msg = "an object instance needs the `name` of a declared object".to_owned();
+            nom::Err::Failure(SyntaxError::message(input, msg))
+        })?;
+        let base_xform = source.transform.as_ref().map(|t| **t);
+        let transform = match (base_xform, xform) {
+            (Some(base), Some(x)) => Some(base.apply(&x)),
+            (base, x) => base.or(x),
+        };
+
+        let instance = Object {
+            primitive: Arc::clone(&source.primitive),
+            material: mat.unwrap_or_else(|| source.material.clone()),
+            transform: transform.map(Box::new),
+        };
+
+        Ok((i, instance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::declaration::declaration;
+    use raygun_primitives::Sphere;
+
+    #[test]
+    fn instances_share_primitive() {
+        let state = SceneRef::default();
+        declaration(state.clone())(
+            b"declare ball = sphere { radius: 2, transform: { translate: {1, 0, 0} } }",
+        )
+        .unwrap();
+
+        let (_, obj) = super::parse(state.clone())(
+            b"object { name: ball, transform: { translate: {0, 1, 0} } }",
+        )
+        .unwrap();
+
+        let s = obj.as_primitive::<Sphere>().unwrap();
+        assert_eq!(s.radius, 2.0);
+
+        let ball = state.borrow().declarations["ball"].object().unwrap();
+        assert!(Arc::ptr_eq(&ball.primitive, &obj.primitive));
+
+        let expected = Transform::for_translation(1.0, 1.0, 0.0);
+        assert_eq!(**obj.transform.as_ref().unwrap(), expected);
+    }
+
+    #[test]
+    fn instance_requires_a_name() {
+        let state = SceneRef::default();
+        match super::parse(state)(b"object { transform: { translate: {0, 1, 0} } }") {
+            Err(nom::Err::Failure(_)) => {}
+            _ => panic!("Expected failure"),
+        }
+    }
+}
@@ -1,15 +1,15 @@
-use nom::{branch::alt, combinator::map, multi::separated_list, IResult};
+use nom::{branch::alt, combinator::map};
 
 use raygun_material::Material;
-use raygun_math::{Transform, Vector};
+use raygun_math::{Float, Transform, Vector3};
 use raygun_primitives::{Object, Plane};
 
-use crate::{constructs::*, material::*, transform::*, SceneRef};
+use crate::{constructs::*, error::ParseResult, material::*, transform::*, SceneRef};
 
-pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
     enum Arg {
-        Normal(Vector),
-        Offset(f64),
+        Normal(Vector3),
+        Offset(Float),
         Material(Material),
         XForm(Transform),
     };
@@ -17,15 +17,12 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
     move |input| {
         let plane_block = named_object(
             "plane",
-            block(separated_list(
-                comma,
-                alt((
-                    map_named_value("normal", vector_literal, Arg::Normal),
-                    map_named_value("offset", real_number, Arg::Offset),
-                    map_named_value("material", material(scene.clone()), Arg::Material),
-                    map_named_value("transfomr", transform, Arg::XForm),
-                )),
-            )),
+            fields(alt((
+                map_named_value("normal", vector(scene.clone()), Arg::Normal),
+                map_named_value("offset", float_value, Arg::Offset),
+                map_named_value("material", material(scene.clone()), Arg::Material),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
         );
 
         let construct_plane = |args| {
@@ -35,7 +32,7 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
 
             for arg in args {
                 match arg {
-                    Arg::Normal(n) => p.normal = n.normalize(),
+                    Arg::Normal(n) => p.normal = n.normalize().into(),
                     Arg::Offset(o) => p.offset = o,
                     Arg::Material(m) => mat = m,
                     Arg::XForm(x) => xform = Some(x),
@@ -53,7 +50,7 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
 mod test {
     use super::*;
     use float_cmp::ApproxEqUlps;
-    use raygun_math::vector;
+    use raygun_math::normal;
 
     #[test]
     fn parse_plane() {
@@ -63,7 +60,7 @@ mod test {
             super::parse(state)(b"plane { normal: {1.2, 3.4, 5.6}, offset: 7.8 }").unwrap();
 
         let p = obj.as_primitive::<Plane>().unwrap();
-        let expected = vector(0.1801712440614613, 0.5104851915074736, 0.8407991389534859);
+        let expected = normal(0.1801712440614613, 0.5104851915074736, 0.8407991389534859);
 
         assert!(
             p.normal.approx_eq(expected),
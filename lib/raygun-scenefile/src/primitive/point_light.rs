@@ -1,36 +1,31 @@
 use raygun_material::{Colour, Material};
-use raygun_math::Point;
+use raygun_math::Vector3;
 use raygun_primitives::{Object, PointLight};
 
-use nom::IResult;
+use crate::{colour::*, constructs::*, error::ParseResult, SceneRef};
 
-use crate::{colour::*, constructs::*, SceneRef};
-
-pub fn parse(_scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
-    use nom::{branch::alt, multi::separated_list};
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    use nom::branch::alt;
 
     enum Args {
         Col(Colour),
-        Loc(Point),
+        Loc(Vector3),
     };
 
     move |input| {
         let p = named_object(
             "point_light",
-            block(separated_list(
-                comma,
-                ws(alt((
-                    map_named_value("colour", colour_literal, Args::Col),
-                    map_named_value("location", vector_literal, Args::Loc),
-                ))),
-            )),
+            fields(ws(alt((
+                map_named_value("colour", colour(scene.clone()), Args::Col),
+                map_named_value("location", vector(scene.clone()), Args::Loc),
+            )))),
         );
 
         p(input).map(|(i, args)| {
             let mut result = PointLight::default();
             for arg in args {
                 match arg {
-                    Args::Loc(l) => result.loc = l,
+                    Args::Loc(l) => result.loc = l.into(),
                     Args::Col(c) => result.colour = c,
                 }
             }
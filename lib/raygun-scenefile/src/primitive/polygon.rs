@@ -0,0 +1,88 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Point3, Transform};
+use raygun_primitives::{Object, Polygon};
+
+///
+/// Parses a polygon, given as a list of at least three points in order
+/// around its edge
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Points(Vec<Point3>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let points = fields(ws(vector(scene.clone())));
+        let parse_args = named_object(
+            "polygon",
+            fields(alt((
+                map_named_value("points", points, |ps| {
+                    Arg::Points(ps.into_iter().map(Point3::from).collect())
+                }),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut points = Vec::new();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Points(p) => points = p,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        match Polygon::new(points) {
+            Some(p) => Ok((rest, as_object(p, mat, xform))),
+            None => {
+                let msg = "a polygon needs at least three points, not all in a line".to_owned();
+                Err(nom::Err::Failure(SyntaxError::message(input, msg)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::point;
+    use raygun_primitives::Polygon;
+
+    #[test]
+    fn parse_polygon() {
+        let state = SceneRef::default();
+        let text = b"polygon { points: { {0, 0, 0}, {1, 0, 0}, {1, 1, 0}, {0, 1, 0} } }";
+        let (_, obj) = super::parse(state)(text).unwrap();
+
+        let p = obj.as_primitive::<Polygon>().unwrap();
+        assert_eq!(p.points().len(), 4);
+        assert_eq!(p.points()[2], point(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn too_few_points_is_an_error() {
+        let state = SceneRef::default();
+        let text = b"polygon { points: { {0, 0, 0}, {1, 0, 0} } }";
+        match super::parse(state)(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Transform, Vector3};
+use raygun_primitives::{Object, Rectangle};
+
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Corner(Vector3),
+        U(Vector3),
+        V(Vector3),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "rectangle",
+            fields(alt((
+                map_named_value("corner", vector(scene.clone()), Arg::Corner),
+                map_named_value("u", vector(scene.clone()), Arg::U),
+                map_named_value("v", vector(scene.clone()), Arg::V),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut r = Rectangle::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Corner(c) => r.corner = c.into(),
+                Arg::U(u) => r.u = u,
+                Arg::V(v) => r.v = v,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if r.u.cross(r.v) == Vector3::default() {
+            let msg = "a rectangle's sides can't be zero or parallel".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+
+        Ok((rest, as_object(r, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::{point, vector};
+    use raygun_primitives::Rectangle;
+
+    #[test]
+    fn parse_rectangle() {
+        let state = SceneRef::default();
+        let text = b"rectangle { corner: {1, 2, 3}, u: {2, 0, 0}, v: {0, 0, 3} }";
+        let (_, obj) = super::parse(state)(text).unwrap();
+
+        let r = obj.as_primitive::<Rectangle>().unwrap();
+        assert_eq!(r.corner, point(1.0, 2.0, 3.0));
+        assert_eq!(r.u, vector(2.0, 0.0, 0.0));
+        assert_eq!(r.v, vector(0.0, 0.0, 3.0));
+    }
+
+    #[test]
+    fn degenerate_rectangles_are_errors() {
+        for text in [
+            &b"rectangle { u: {0, 0, 0}, v: {0, 0, 1} }"[..],
+            b"rectangle { u: {1, 0, 0}, v: {0, 0, 0} }",
+            b"rectangle { u: {1, 0, 0}, v: {-2, 0, 0} }",
+        ]
+        .iter()
+        {
+            match super::parse(SceneRef::default())(text) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use nom::{branch::alt, multi::many0};
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{degrees, Float, Transform, Vector3};
+use raygun_primitives::{Field, Object, Sdf};
+
+///
+/// Parses a surface defined by a signed distance field, e.g.
+///
+/// ```text
+/// sdf {
+///     field: smooth_union {
+///         radius: 0.5,
+///         fields: {
+///             sphere { radius: 1 }
+///             translate { offset: {1.5, 0, 0}, field: box { size: {1, 1, 1} } }
+///         }
+///     },
+///     tolerance: 0.001
+/// }
+/// ```
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Field(Field),
+        MaxSteps(usize),
+        Tolerance(Float),
+        MaxDistance(Float),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "sdf",
+            fields(alt((
+                map_named_value("field", field(scene.clone()), Arg::Field),
+                map_named_value("max_steps", count, Arg::MaxSteps),
+                map_named_value("tolerance", float_value, Arg::Tolerance),
+                map_named_value("max_distance", float_value, Arg::MaxDistance),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut sdf = Sdf::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Field(f) => sdf.field = f,
+                Arg::MaxSteps(n) => sdf.max_steps = n,
+                Arg::Tolerance(t) => sdf.tolerance = t,
+                Arg::MaxDistance(d) => sdf.max_distance = d,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        Ok((rest, as_object(sdf, mat, xform)))
+    }
+}
+
+///
+/// The arguments to the shapes and combinators making up a field
+///
+enum FieldArg {
+    Radius(Float),
+    MajorRadius(Float),
+    MinorRadius(Float),
+    Size(Vector3),
+    Rounding(Float),
+    Height(Float),
+    Power(Float),
+    Iterations(usize),
+    Offset(Vector3),
+    Period(Vector3),
+    Angle(Float),
+    Child(Field),
+    Children(Vec<Field>),
+}
+
+///
+/// Parses a named shape or combinator, building the field from its
+/// arguments. A builder that can't make sense of its arguments returns a
+/// message to report instead.
+///
+fn shape<'a, ArgsFn, BuildFn>(
+    name: &'static str,
+    args: ArgsFn,
+    build: BuildFn,
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, Field>
+where
+    ArgsFn: Fn(&'a [u8]) -> ParseResult<'a, Vec<FieldArg>>,
+    BuildFn: Fn(Vec<FieldArg>) -> Result<Field, &'static str>,
+{
+    let parser = named_object(name, args);
+    move |input| {
+        let (rest, args) = parser(input)?;
+        match build(args) {
+            Ok(f) => Ok((rest, f)),
+            Err(msg) => Err(nom::Err::Failure(SyntaxError::message(
+                input,
+                msg.to_owned(),
+            ))),
+        }
+    }
+}
+
+///
+/// Parses a field: one of the basic shapes, or a combinator wrapping one or
+/// more other fields
+///
+fn field<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Field> {
+    move |input| {
+        let child = || map_named_value("field", field(scene.clone()), FieldArg::Child);
+        let children = || {
+            map_named_value(
+                "fields",
+                ws(block(many0(ws(field(scene.clone()))))),
+                FieldArg::Children,
+            )
+        };
+        let radius = || map_named_value("radius", float_value, FieldArg::Radius);
+
+        ws(alt((
+            shape("sphere", fields(radius()), |args| {
+                let mut radius = 1.0;
+                for arg in args {
+                    if let FieldArg::Radius(r) = arg {
+                        radius = r;
+                    }
+                }
+                Ok(Field::Sphere(radius))
+            }),
+            shape(
+                "box",
+                fields(alt((
+                    map_named_value("size", vector(scene.clone()), FieldArg::Size),
+                    map_named_value("rounding", float_value, FieldArg::Rounding),
+                ))),
+                |args| {
+                    let (mut size, mut rounding) = (Vector3::new(1.0, 1.0, 1.0), 0.0);
+                    for arg in args {
+                        match arg {
+                            FieldArg::Size(s) => size = s,
+                            FieldArg::Rounding(r) => rounding = r,
+                            _ => {}
+                        }
+                    }
+                    Ok(Field::Box { size, rounding })
+                },
+            ),
+            shape(
+                "torus",
+                fields(alt((
+                    map_named_value("major_radius", float_value, FieldArg::MajorRadius),
+                    map_named_value("minor_radius", float_value, FieldArg::MinorRadius),
+                ))),
+                |args| {
+                    let (mut major_radius, mut minor_radius) = (1.0, 0.25);
+                    for arg in args {
+                        match arg {
+                            FieldArg::MajorRadius(r) => major_radius = r,
+                            FieldArg::MinorRadius(r) => minor_radius = r,
+                            _ => {}
+                        }
+                    }
+                    Ok(Field::Torus {
+                        major_radius,
+                        minor_radius,
+                    })
+                },
+            ),
+            shape(
+                "cylinder",
+                fields(alt((
+                    radius(),
+                    map_named_value("height", float_value, FieldArg::Height),
+                ))),
+                |args| {
+                    let (mut radius, mut height) = (1.0, 2.0);
+                    for arg in args {
+                        match arg {
+                            FieldArg::Radius(r) => radius = r,
+                            FieldArg::Height(h) => height = h,
+                            _ => {}
+                        }
+                    }
+                    Ok(Field::Cylinder { radius, height })
+                },
+            ),
+            shape(
+                "mandelbulb",
+                fields(alt((
+                    map_named_value("power", float_value, FieldArg::Power),
+                    map_named_value("iterations", count, FieldArg::Iterations),
+                ))),
+                |args| {
+                    let (mut power, mut iterations) = (8.0, 8);
+                    for arg in args {
+                        match arg {
+                            FieldArg::Power(p) => power = p,
+                            FieldArg::Iterations(n) => iterations = n,
+                            _ => {}
+                        }
+                    }
+                    Ok(Field::Mandelbulb { power, iterations })
+                },
+            ),
+            shape("union", fields(children()), |args| {
+                Ok(Field::Union(all_children(args)))
+            }),
+            shape("intersection", fields(children()), |args| {
+                Ok(Field::Intersection(all_children(args)))
+            }),
+            shape("difference", fields(children()), |args| {
+                // everything after the first field is carved out of it
+                let mut fields = all_children(args).into_iter();
+                match fields.next() {
+                    Some(first) => Ok(Field::Difference(
+                        Box::new(first),
+                        Box::new(Field::Union(fields.collect())),
+                    )),
+                    None => Err("a difference needs a field to carve from"),
+                }
+            }),
+            shape(
+                "smooth_union",
+                fields(alt((radius(), children()))),
+                |args| {
+                    let mut radius = 0.0;
+                    let mut children = Vec::new();
+                    for arg in args {
+                        match arg {
+                            FieldArg::Radius(r) => radius = r,
+                            FieldArg::Children(c) => children.extend(c),
+                            _ => {}
+                        }
+                    }
+                    Ok(Field::SmoothUnion { radius, children })
+                },
+            ),
+            wrapper(
+                "translate",
+                map_named_value("offset", vector(scene.clone()), FieldArg::Offset),
+                child(),
+            ),
+            wrapper(
+                "repeat",
+                map_named_value("period", vector(scene.clone()), FieldArg::Period),
+                child(),
+            ),
+            wrapper(
+                "twist",
+                map_named_value("angle", float_value, FieldArg::Angle),
+                child(),
+            ),
+            wrapper("round", radius(), child()),
+        )))(input)
+    }
+}
+
+fn all_children(args: Vec<FieldArg>) -> Vec<Field> {
+    let mut children = Vec::new();
+    for arg in args {
+        if let FieldArg::Children(c) = arg {
+            children.extend(c);
+        }
+    }
+    children
+}
+
+///
+/// Parses a combinator that modifies a single child field, taking one
+/// argument of its own. Twist angles are in degrees per unit of height.
+///
+fn wrapper<'a, ArgFn, ChildFn>(
+    name: &'static str,
+    arg: ArgFn,
+    child: ChildFn,
+) -> impl Fn(&'a [u8]) -> ParseResult<'a, Field>
+where
+    ArgFn: Fn(&'a [u8]) -> ParseResult<'a, FieldArg>,
+    ChildFn: Fn(&'a [u8]) -> ParseResult<'a, FieldArg>,
+{
+    shape(name, fields(alt((arg, child))), |args| {
+        let mut modifier = None;
+        let mut inner = None;
+        for arg in args {
+            match arg {
+                FieldArg::Child(f) => inner = Some(Box::new(f)),
+                other => modifier = Some(other),
+            }
+        }
+
+        let inner = inner.ok_or("expected a field to modify")?;
+        Ok(match modifier {
+            Some(FieldArg::Offset(v)) => Field::Translate(v, inner),
+            Some(FieldArg::Period(v)) => Field::Repeat(v, inner),
+            Some(FieldArg::Angle(a)) => Field::Twist(degrees(a).radians().get(), inner),
+            Some(FieldArg::Radius(r)) => Field::Round(r, inner),
+            _ => *inner,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raygun_math::vector;
+
+    fn parse_field(text: &str) -> Field {
+        let (rest, f) = field(SceneRef::default())(text.as_bytes()).unwrap();
+        assert!(rest.is_empty(), "{:?}", std::str::from_utf8(rest));
+        f
+    }
+
+    #[test]
+    fn parse_sdf() {
+        let text = b"sdf { field: sphere { radius: 2 }, max_steps: 64, tolerance: 0.01 }";
+        let (_, obj) = super::parse(SceneRef::default())(text).unwrap();
+
+        let s = obj.as_primitive::<Sdf>().unwrap();
+        assert_eq!(s.field, Field::Sphere(2.0));
+        assert_eq!(s.max_steps, 64);
+        assert_eq!(s.tolerance, 0.01);
+    }
+
+    #[test]
+    fn parse_shapes() {
+        assert_eq!(
+            parse_field("box { size: {1, 2, 3}, rounding: 0.1 }"),
+            Field::Box {
+                size: vector(1.0, 2.0, 3.0),
+                rounding: 0.1
+            }
+        );
+        assert_eq!(
+            parse_field("mandelbulb { power: 4, iterations: 12 }"),
+            Field::Mandelbulb {
+                power: 4.0,
+                iterations: 12
+            }
+        );
+    }
+
+    #[test]
+    fn parse_nested_combinators() {
+        let f = parse_field(
+            r#"difference {
+                fields: {
+                    smooth_union {
+                        radius: 0.5,
+                        fields: { sphere {} torus { major_radius: 2 } }
+                    }
+                    repeat { period: {1, 0, 0}, field: cylinder { radius: 0.1 } }
+                }
+            }"#,
+        );
+
+        let expected = Field::Difference(
+            Box::new(Field::SmoothUnion {
+                radius: 0.5,
+                children: vec![
+                    Field::Sphere(1.0),
+                    Field::Torus {
+                        major_radius: 2.0,
+                        minor_radius: 0.25,
+                    },
+                ],
+            }),
+            Box::new(Field::Union(vec![Field::Repeat(
+                vector(1.0, 0.0, 0.0),
+                Box::new(Field::Cylinder {
+                    radius: 0.1,
+                    height: 2.0,
+                }),
+            )])),
+        );
+        assert_eq!(f, expected);
+    }
+
+    #[test]
+    fn wrappers_need_a_field() {
+        let result = field(SceneRef::default())(b"twist { angle: 45 }");
+        match result {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+
+        assert_eq!(
+            parse_field("twist { angle: 180, field: sphere {} }"),
+            Field::Twist(degrees(180.0).radians().get(), Box::new(Field::Sphere(1.0)))
+        );
+    }
+}
@@ -1,15 +1,15 @@
-use nom::{branch::alt, multi::separated_list, IResult};
+use nom::branch::alt;
 
 use raygun_material::Material;
-use raygun_math::{Transform, Vector};
+use raygun_math::{Float, Transform, Vector3};
 use raygun_primitives::{Object, Sphere};
 
-use crate::{constructs::*, material::*, transform::*, SceneRef};
+use crate::{constructs::*, error::ParseResult, material::*, transform::*, SceneRef};
 
-pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
     enum Arg {
-        Radius(f64),
-        Centre(Vector),
+        Radius(Float),
+        Centre(Vector3),
         Mat(Material),
         XForm(Transform),
     }
@@ -17,15 +17,12 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
     move |input| {
         let rval = named_object(
             "sphere",
-            block(separated_list(
-                comma,
-                alt((
-                    map_named_value("radius", real_number, Arg::Radius),
-                    map_named_value("centre", vector_literal, Arg::Centre),
-                    map_named_value("material", material(scene.clone()), Arg::Mat),
-                    map_named_value("transform", transform, Arg::XForm),
-                )),
-            )),
+            fields(alt((
+                map_named_value("radius", float_value, Arg::Radius),
+                map_named_value("centre", vector(scene.clone()), Arg::Centre),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
         )(input);
 
         rval.map(|(i, args)| {
@@ -36,7 +33,7 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
             for arg in args {
                 match arg {
                     Arg::Radius(r) => result.radius = r,
-                    Arg::Centre(c) => result.centre = c,
+                    Arg::Centre(c) => result.centre = c.into(),
                     Arg::Mat(m) => mat = m,
                     Arg::XForm(x) => xform = Some(x),
                 }
@@ -0,0 +1,114 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Point3, Transform};
+use raygun_primitives::{Object, Spline, Sweep};
+
+///
+/// Parses a 2D shape swept along a path, e.g.
+///
+/// ```text
+/// sweep {
+///     shape: { {-1, -1}, {1, -1}, {1, 1}, {-1, 1} },
+///     spline: cubic,
+///     path: { {0, 0, -1}, {0, 0, 0}, {5, 0, 5}, {10, 0, 0}, {10, 0, -1} }
+/// }
+/// ```
+///
+/// The spline, which joins up the path, defaults to `linear`.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Shape(Vec<(Float, Float)>),
+        Spline(Spline),
+        Path(Vec<Point3>),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let path = fields(ws(vector(scene.clone())));
+        let parse_args = named_object(
+            "sweep",
+            fields(alt((
+                map_named_value("shape", fields(ws(pair)), Arg::Shape),
+                map_named_value("spline", super::spline, Arg::Spline),
+                map_named_value("path", path, |ps| {
+                    Arg::Path(ps.into_iter().map(Point3::from).collect())
+                }),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut shape = Vec::new();
+        let mut spline = Spline::Linear;
+        let mut path = Vec::new();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Shape(s) => shape = s,
+                Arg::Spline(s) => spline = s,
+                Arg::Path(p) => path = p,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        if !spline.accepts(path.len()) {
+            return fail(format!(
+                "the path of a sweep: {}",
+                super::point_count_message(spline)
+            ));
+        }
+        match Sweep::new(spline, shape, path) {
+            Some(s) => Ok((rest, as_object(s, mat, xform))),
+            None => fail(
+                "a sweep needs a shape with some area, on a path that doesn't double back"
+                    .to_owned(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_math::point;
+    use raygun_primitives::{Spline, Sweep};
+
+    #[test]
+    fn parse_sweep() {
+        let text = b"sweep {
+            shape: { {0, 0}, {1, 0}, {0, 1} },
+            path: { {0, 0, 0}, {0, 0, 2}, {2, 0, 2} }
+        }";
+        let (_, obj) = super::parse(SceneRef::default())(text).unwrap();
+
+        let s = obj.as_primitive::<Sweep>().unwrap();
+        assert_eq!(s.spline(), Spline::Linear);
+        assert_eq!(s.shape().len(), 3);
+        assert_eq!(s.path()[2], point(2.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn flat_shapes_are_errors() {
+        let text = b"sweep { shape: { {0, 0}, {1, 0} }, path: { {0, 0, 0}, {0, 0, 1} } }";
+        match super::parse(SceneRef::default())(text) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+        }
+    }
+}
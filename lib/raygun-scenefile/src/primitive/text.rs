@@ -0,0 +1,168 @@
+use nom::{branch::alt, combinator::value};
+
+use crate::{
+    constructs::*,
+    error::{Expected, ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform};
+use raygun_primitives::{text::Alignment, Font, Object, Text};
+
+///
+/// Parses a string of extruded text, e.g.
+///
+/// ```text
+/// text {
+///     font: "DejaVuSans.ttf",
+///     string: "Hello",
+///     depth: 0.2,
+///     bevel: 0.01,
+///     align: centre
+/// }
+/// ```
+///
+/// The font is looked for alongside the scene file that names it, and then in
+/// the include paths. The depth defaults to 0.1 ems, with no bevel, and the
+/// text is left-aligned unless told otherwise.
+///
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        Font(String),
+        String(String),
+        Depth(Float),
+        Bevel(Float),
+        Align(Alignment),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "text",
+            fields(alt((
+                map_named_value("font", string, Arg::Font),
+                map_named_value("string", string, Arg::String),
+                map_named_value("depth", float_value, Arg::Depth),
+                map_named_value("bevel", float_value, Arg::Bevel),
+                map_named_value("align", alignment, Arg::Align),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut font = None;
+        let mut text = String::new();
+        let mut depth = 0.1;
+        let mut bevel = 0.0;
+        let mut align = Alignment::Left;
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::Font(f) => font = Some(f),
+                Arg::String(s) => text = s,
+                Arg::Depth(d) => depth = d,
+                Arg::Bevel(b) => bevel = b,
+                Arg::Align(a) => align = a,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        let fail = |msg: String| Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+
+        let font = match font {
+            Some(f) => f,
+            None => return fail("text needs a font file".to_owned()),
+        };
+        let path = match scene.borrow().find_file(&font, input) {
+            Some(p) => p,
+            None => return fail(format!("can't find font \"{}\"", font)),
+        };
+        let font = match Font::load(&path) {
+            Ok(f) => f,
+            Err(msg) => return fail(msg),
+        };
+        if depth <= 0.0 || !(0.0..=depth / 2.0).contains(&bevel) {
+            return fail(
+                "text needs a positive depth, and a bevel of at most half of it".to_owned(),
+            );
+        }
+        let text = match Text::new(&font, &text, depth, bevel, align) {
+            Some(t) => t,
+            None => return fail("text needs a string with something to draw".to_owned()),
+        };
+
+        Ok((rest, as_object(text, mat, xform)))
+    }
+}
+
+///
+/// Parses how to align lines of text: `left`, `centre` or `right`
+///
+fn alignment(input: &[u8]) -> ParseResult<'_, Alignment> {
+    alt((
+        value(Alignment::Left, keyword("left")),
+        value(Alignment::Centre, keyword("centre")),
+        value(Alignment::Centre, keyword("center")),
+        value(Alignment::Right, keyword("right")),
+    ))(input)
+    .map_err(|e: nom::Err<SyntaxError>| {
+        e.map(|_| SyntaxError::expected(input, Expected::Description("left, centre or right")))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scratch::scene_in;
+
+    /// The directory holding the primitives' test font, `blocks.ttf`
+    const FONTS: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../raygun-primitives/tests/fixtures"
+    );
+
+    fn fonts() -> &'static std::path::Path {
+        std::path::Path::new(FONTS)
+    }
+
+    #[test]
+    fn parse_text() {
+        let src = br#"text {
+            font: "blocks.ttf",
+            string: "Hi",
+            depth: 0.2,
+            bevel: 0.01,
+            align: centre
+        }"#;
+        let (_, obj) = super::parse(scene_in(fonts()))(src).unwrap();
+
+        let t = obj.as_primitive::<Text>().unwrap();
+        assert_eq!(t.string(), "Hi");
+        assert_eq!((t.depth(), t.bevel()), (0.2, 0.01));
+        assert_eq!(t.alignment(), Alignment::Centre);
+    }
+
+    #[test]
+    fn bad_text_is_reported() {
+        let dir = fonts();
+        let sources: [&[u8]; 3] = [
+            br#"text { string: "Hi" }"#,
+            br#"text { font: "nowhere.ttf", string: "Hi" }"#,
+            br#"text { font: "blocks.ttf", string: "Hi", depth: 0.1, bevel: 0.1 }"#,
+        ];
+        for src in sources.iter() {
+            match super::parse(scene_in(dir))(src) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
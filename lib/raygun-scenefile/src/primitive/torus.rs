@@ -0,0 +1,86 @@
+use nom::branch::alt;
+
+use crate::{
+    constructs::*,
+    error::{ParseResult, SyntaxError},
+    material::*,
+    transform::*,
+    SceneRef,
+};
+use raygun_material::Material;
+use raygun_math::{Float, Transform};
+use raygun_primitives::{Object, Torus};
+
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
+    enum Arg {
+        MajorRadius(Float),
+        MinorRadius(Float),
+        Mat(Material),
+        XForm(Transform),
+    }
+
+    move |input| {
+        let parse_args = named_object(
+            "torus",
+            fields(alt((
+                map_named_value("major_radius", float_value, Arg::MajorRadius),
+                map_named_value("minor_radius", float_value, Arg::MinorRadius),
+                map_named_value("material", material(scene.clone()), Arg::Mat),
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+            ))),
+        );
+
+        let (rest, args) = parse_args(input)?;
+
+        let mut t = Torus::default();
+        let mut mat = Material::default();
+        let mut xform = None;
+
+        for arg in args {
+            match arg {
+                Arg::MajorRadius(r) => t.major_radius = r,
+                Arg::MinorRadius(r) => t.minor_radius = r,
+                Arg::Mat(m) => mat = m,
+                Arg::XForm(x) => xform = Some(x),
+            }
+        }
+
+        if t.major_radius <= 0.0 || t.minor_radius <= 0.0 {
+            let msg = "a torus's radii must be greater than zero".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(input, msg)));
+        }
+
+        Ok((rest, as_object(t, mat, xform)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SceneRef;
+    use raygun_primitives::Torus;
+
+    #[test]
+    fn parse_torus() {
+        let state = SceneRef::default();
+        let (_, obj) =
+            super::parse(state)(b"torus { major_radius: 3, minor_radius: 0.5 }").unwrap();
+
+        let t = obj.as_primitive::<Torus>().unwrap();
+        assert_eq!(*t, Torus::new(3.0, 0.5));
+    }
+
+    #[test]
+    fn radii_must_be_positive() {
+        for text in [
+            &b"torus { major_radius: 0, minor_radius: 0.5 }"[..],
+            b"torus { major_radius: 3, minor_radius: -0.5 }",
+        ]
+        .iter()
+        {
+            match super::parse(SceneRef::default())(text) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected a failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
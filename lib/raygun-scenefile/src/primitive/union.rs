@@ -1,17 +1,18 @@
-use nom::{branch::alt, multi::separated_list, IResult};
+use nom::branch::alt;
 
 use raygun_material::Material;
 use raygun_math::Transform;
 use raygun_primitives::{Object, ObjectList, Union};
 
 use super::primitives;
-use crate::{constructs::*, material::*, transform::*, SceneRef};
+use crate::{constructs::*, error::ParseResult, material::*, transform::*, SceneRef};
 
 ///
 /// Parses a group of objects, arbitrarily transformed. Transforms are applied in the order
-/// they're encountered, and nested groups are allowed.
+/// they're encountered, and nested groups are allowed. A material given to the group is used
+/// by any of its objects that don't have one of their own.
 ///
-pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
+pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> ParseResult<Object> {
     enum Arg {
         XForm(Transform),
         Material(Material),
@@ -23,14 +24,11 @@ pub fn parse(scene: SceneRef) -> impl Fn(&[u8]) -> IResult<&[u8], Object> {
 
         let union_block = named_object(
             "union",
-            block(separated_list(
-                comma,
-                alt((
-                    map_named_value("transform", transform, Arg::XForm),
-                    map_named_value("material", material(scene.clone()), Arg::Material),
-                    map_named_value("objects", children, Arg::Children),
-                )),
-            )),
+            fields(alt((
+                map_named_value("transform", transform(scene.clone()), Arg::XForm),
+                map_named_value("material", material(scene.clone()), Arg::Material),
+                map_named_value("objects", children, Arg::Children),
+            ))),
         );
 
         union_block(input).map(|(i, args)| {
@@ -0,0 +1,73 @@
+//! Scratch directories for tests that need files on disk.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{constructs::SceneState, SceneRef};
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// An empty temporary directory, unique to the test that made it, which is
+/// removed along with its contents when dropped
+///
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub fn new(name: &str) -> ScratchDir {
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("raygun-{}-{}-{}", name, process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+
+    ///
+    /// Creates a scratch directory holding the given files, which may be in
+    /// subdirectories
+    ///
+    pub fn with_files(name: &str, files: &[(&str, &str)]) -> ScratchDir {
+        let dir = ScratchDir::new(name);
+        for (filename, text) in files {
+            dir.write(filename, text);
+        }
+        dir
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.0.join(path)
+    }
+
+    pub fn write<C: AsRef<[u8]>>(&self, filename: &str, contents: C) {
+        let path = self.join(filename);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    ///
+    /// Scene state for parsing as though from a scene file in this directory
+    ///
+    pub fn scene(&self) -> SceneRef {
+        scene_in(&self.0)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+///
+/// Scene state for parsing as though from a scene file in `dir`
+///
+pub fn scene_in(dir: &Path) -> SceneRef {
+    SceneRef::new(SceneState {
+        filename: dir.join("scene.rg"),
+        ..SceneState::default()
+    })
+}
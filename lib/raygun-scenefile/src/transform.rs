@@ -1,53 +1,164 @@
-use nom::{branch::alt, multi::separated_list, IResult};
+use nom::{branch::alt, combinator::map};
 
-use raygun_math::{degrees, Transform, Vector};
+use raygun_math::{degrees, Float, Matrix, Quaternion, Transform, Vector3};
 
 use super::constructs::*;
+use crate::{
+    declaration::{reference, Declaration},
+    error::{ParseResult, SyntaxError},
+};
 
-fn translate<'a>(input: &'a [u8]) -> IResult<&'a [u8], Transform> {
-    map_named_value("translate", vector_literal, |Vector { x, y, z }| {
+fn translate<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Transform> {
+    map_named_value("translate", vector(scene), |Vector3 { x, y, z }| {
         Transform::identity().translate(x, y, z)
-    })(input)
+    })
 }
 
-fn rotate<'a>(input: &'a [u8]) -> IResult<&'a [u8], Transform> {
-    map_named_value("rotate", vector_literal, |Vector { x, y, z }| {
+fn rotate<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Transform> {
+    map_named_value("rotate", vector(scene), |Vector3 { x, y, z }| {
         Transform::identity().rotate(
             degrees(x).radians(),
             degrees(y).radians(),
             degrees(z).radians(),
         )
-    })(input)
+    })
 }
 
-fn scale<'a>(input: &'a [u8]) -> IResult<&'a [u8], Transform> {
-    map_named_value("scale", vector_literal, |Vector { x, y, z }| {
-        Transform::identity().scale(x, y, z)
-    })(input)
+///
+/// A rotation about an arbitrary axis, e.g.
+/// `rotate_axis: { axis: {1, 1, 0}, angle: 45 }`, with the angle in degrees
+///
+fn rotate_axis<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Transform> {
+    enum Arg {
+        Axis(Vector3),
+        Angle(Float),
+    }
+
+    let args = fields(alt((
+        map_named_value("axis", vector(scene), Arg::Axis),
+        map_named_value("angle", float_value, Arg::Angle),
+    )));
+
+    let rotation = move |i| {
+        let (rest, args) = args(i)?;
+
+        let mut axis = Vector3::default();
+        let mut angle = 0.0;
+        for arg in args {
+            match arg {
+                Arg::Axis(a) => axis = a,
+                Arg::Angle(a) => angle = a,
+            }
+        }
+
+        if axis.length() == 0.0 {
+            let msg = "the rotation axis must not be zero".to_owned();
+            return Err(nom::Err::Failure(SyntaxError::message(i, msg)));
+        }
+        Ok((
+            rest,
+            Transform::identity().rotate_axis(axis, degrees(angle).radians()),
+        ))
+    };
+
+    named_value("rotate_axis", rotation)
 }
 
-pub fn transform<'a>(input: &'a [u8]) -> IResult<&'a [u8], Transform> {
-    let xform = alt((translate, rotate, scale));
-    let transform_list = block(separated_list(comma, ws(xform)));
+///
+/// A rotation given as a quaternion `{w, x, y, z}`, which is normalised
+/// before use
+///
+fn quaternion(input: &[u8]) -> ParseResult<'_, Transform> {
+    let values = |i| {
+        let (rest, values) = fields(ws(float_value))(i)?;
+        match values[..] {
+            [w, x, y, z] if w != 0.0 || x != 0.0 || y != 0.0 || z != 0.0 => {
+                let q = Quaternion::new(w, x, y, z);
+                Ok((rest, Transform::identity().rotate_quaternion(q)))
+            }
+            [_, _, _, _] => {
+                let msg = "the quaternion must not be zero".to_owned();
+                Err(nom::Err::Failure(SyntaxError::message(i, msg)))
+            }
+            _ => {
+                let msg = format!("expected 4 values, found {}", values.len());
+                Err(nom::Err::Failure(SyntaxError::message(i, msg)))
+            }
+        }
+    };
+
+    named_value("quaternion", values)(input)
+}
 
-    transform_list(input).map(|(i, txs)| {
-        let result = txs
-            .iter()
-            .fold(Transform::identity(), |xform, t| xform.apply(t));
-        (i, result)
+fn scale<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Transform> {
+    map_named_value("scale", vector(scene), |Vector3 { x, y, z }| {
+        Transform::identity().scale(x, y, z)
     })
 }
 
+///
+/// An arbitrary transformation matrix, given as 16 values in row-major order.
+/// The matrix must be invertible.
+///
+fn matrix(input: &[u8]) -> ParseResult<'_, Transform> {
+    let values = |i| {
+        let (rest, values) = fields(ws(float_value))(i)?;
+        if values.len() != 16 {
+            let msg = format!("expected 16 values, found {}", values.len());
+            return Err(nom::Err::Failure(SyntaxError::message(i, msg)));
+        }
+
+        let mut m = [0.0; 16];
+        m.copy_from_slice(&values);
+        match Transform::from_matrix(Matrix::new(m)) {
+            Some(t) => Ok((rest, t)),
+            None => {
+                let msg = "the matrix can't be inverted".to_owned();
+                Err(nom::Err::Failure(SyntaxError::message(i, msg)))
+            }
+        }
+    };
+
+    named_value("matrix", values)(input)
+}
+
+///
+/// Parses a transform block, or a reference to a declared transform. Declared
+/// transforms may also be used as an element within a transform block.
+///
+pub fn transform<'a>(scene: SceneRef) -> impl Fn(&'a [u8]) -> ParseResult<'a, Transform> {
+    move |input| {
+        let xform = alt((
+            translate(scene.clone()),
+            rotate(scene.clone()),
+            rotate_axis(scene.clone()),
+            quaternion,
+            scale(scene.clone()),
+            matrix,
+            reference(scene.clone(), Declaration::transform),
+        ));
+        let transform_list = map(fields(ws(xform)), |txs| {
+            txs.iter()
+                .fold(Transform::identity(), |xform, t| xform.apply(t))
+        });
+
+        alt((
+            transform_list,
+            reference(scene.clone(), Declaration::transform),
+        ))(input)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use raygun_math::degrees;
+    use raygun_math::{degrees, point};
 
     #[test]
     fn parse_translate() {
         let text = "translate: { 1.2, 3, -4 }";
 
-        let (_, t) = translate(text.as_bytes()).unwrap();
+        let (_, t) = translate(SceneRef::default())(text.as_bytes()).unwrap();
         let expected = Transform::identity().translate(1.2, 3.0, -4.0);
         assert_eq!(t, expected, "Expected: {:?}\nActual {:?}", expected, t);
     }
@@ -56,7 +167,7 @@ mod test {
     fn parse_rotate() {
         let text = "rotate: { 5, -6.7, 8 }";
 
-        let (_, t) = rotate(text.as_bytes()).unwrap();
+        let (_, t) = rotate(SceneRef::default())(text.as_bytes()).unwrap();
         let expected = Transform::identity().rotate(
             degrees(5.0).radians(),
             degrees(-6.7).radians(),
@@ -65,15 +176,74 @@ mod test {
         assert_eq!(t, expected, "Expected: {:?}\nActual {:?}", expected, t);
     }
 
+    #[test]
+    fn parse_rotate_axis() {
+        let text = "rotate_axis: { axis: {0, 0, 2}, angle: 90 }";
+
+        let (_, t) = rotate_axis(SceneRef::default())(text.as_bytes()).unwrap();
+        let p = t.matrix * point(1.0, 0.0, 0.0);
+        assert!(p.approx_eq(point(0.0, 1.0, 0.0)), "got {:?}", p);
+
+        let text = "rotate_axis: { angle: 90 }";
+        match rotate_axis(SceneRef::default())(text.as_bytes()) {
+            Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+            other => panic!("Expected failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn parse_quaternion() {
+        // a half turn about z, unnormalised
+        let text = "quaternion: { 0, 0, 0, 2 }";
+
+        let (_, t) = quaternion(text.as_bytes()).unwrap();
+        let p = t.matrix * point(1.0, 0.0, 0.0);
+        assert!(p.approx_eq(point(-1.0, 0.0, 0.0)), "got {:?}", p);
+
+        for text in ["quaternion: { 0, 0, 0, 0 }", "quaternion: { 1, 0, 0 }"].iter() {
+            match quaternion(text.as_bytes()) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
     #[test]
     fn parse_scale() {
         let text = "scale: { 3, 2, 1 }";
 
-        let (_, t) = scale(text.as_bytes()).unwrap();
+        let (_, t) = scale(SceneRef::default())(text.as_bytes()).unwrap();
         let expected = Transform::identity().scale(3.0, 2.0, 1.0);
         assert_eq!(t, expected, "Expected: {:?}\nActual {:?}", expected, t);
     }
 
+    #[test]
+    fn parse_matrix() {
+        let text = "matrix: { 1, 0, 0, 4,  0, 2, 0, 5,  0, 0, 3, 6,  0, 0, 0, 1 }";
+
+        let (_, t) = matrix(text.as_bytes()).unwrap();
+        let expected = Transform::identity()
+            .scale(1.0, 2.0, 3.0)
+            .translate(4.0, 5.0, 6.0);
+
+        let p = point(1.0, 1.0, 1.0);
+        assert!((t.matrix * p).approx_eq(expected.matrix * p));
+        assert!((t.inverse * p).approx_eq(expected.inverse * p));
+    }
+
+    #[test]
+    fn bad_matrices_are_failures() {
+        let short = "matrix: { 1, 0, 0, 0 }";
+        let singular = "matrix: { 1, 0, 0, 0,  0, 0, 0, 0,  0, 0, 1, 0,  0, 0, 0, 1 }";
+
+        for text in [short, singular].iter() {
+            match matrix(text.as_bytes()) {
+                Err(nom::Err::Failure(e)) => assert!(e.message.is_some()),
+                other => panic!("Expected failure, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
     #[test]
     fn parse_transform_block() {
         let text = r#"{
@@ -84,7 +254,7 @@ mod test {
             translate: {6, 7, 8}
         }"#;
 
-        let (_, t) = transform(text.as_bytes())
+        let (_, t) = transform(SceneRef::default())(text.as_bytes())
             .map_err(|e| {
                 println!("error: {:?}", e);
                 e